@@ -0,0 +1,222 @@
+# English strings. This is the fallback for every other locale, so every key used by the
+# game must be present here.
+
+language-name = English
+language-changed = Language: { $language }
+
+window-title = Snake!
+window-title-playing = { $title } — Score { $score }
+window-title-paused = { $title } — Score { $score } — Paused
+
+paused = Paused (O for options, F11 edits the HUD, H shows the last run's heatmap, L edits the level, B browses levels and campaigns, C opens the campaign map, T shows telemetry)
+score = Score { $score }
+game-over-wall = Game over, wall collision
+game-over-tail = Game over, tail collision
+game-over-head-on = Game over, head-on collision
+game-over-idle = Run ended, nobody was playing
+game-over-level-complete = Level complete
+
+palette-changed = Palette: { $palette }
+palette-standard = standard
+palette-deuteranopia = deuteranopia
+palette-protanopia = protanopia
+palette-tritanopia = tritanopia
+palette-high-contrast = high contrast
+
+reduced-motion-on = Reduced motion: on
+reduced-motion-off = Reduced motion: off
+
+verbosity-changed = Announcements: { $verbosity }
+verbosity-off = off
+verbosity-essential = essential
+verbosity-verbose = verbose
+
+controls-changed = Controls: { $controls }
+controls-arrows = arrow keys
+controls-wasd = W A S D
+controls-ijkl = I J K L
+controls-numpad = numpad
+controls-one-button = one button, space turns clockwise
+
+config-reloaded = Config reloaded
+
+crash-found = The last run crashed. Press F9 to restore it or F10 to discard it.
+
+profile-title = Choose a profile
+profile-entry = { $name } (best { $best })
+profile-new = New profile: { $name }
+profile-hint = Up and Down choose, type to name a new profile, Enter starts, Tab for a tournament, F8 for replays
+
+power-up-hydra = Hydra! You now control two snakes
+hydra-ended = Hydra over, one snake left
+power-up-split = Split! Your tail is collecting food for you
+ally-expired = Your ally is done: +{ $points } points
+power-up-lantern = Lantern! You can see all food at night
+night-falls = Night falls
+day-breaks = Day breaks
+
+event-active = The { $event } event is on!
+event-collected = { $event }: { $count } collected
+event-result = { $event }: { $count } collected this run, best { $best }
+danger-bonus = Danger bonus!
+close-call = Close call!
+popup-merged = +{ $points } (x{ $count })
+popup-merged-combo = +{ $points } (x{ $count }, combo x{ $multiplier })
+objective = { $objective } ({ $progress }/{ $target })
+objective-complete = Objective complete: +{ $points }
+zone-timer = Zone moves in { $seconds }s
+relay-picked-up = Got the relay food, bring it home
+relay-dropped = Relay food dropped
+relay-delivered = Relay food delivered: +{ $points }
+tournament-title = Tournament
+tournament-new-player = Add player: { $name }
+tournament-entry-hint = Enter adds a player, Enter on an empty name starts with { $min } to { $max } players, Esc cancels
+tournament-bye = (bye)
+tournament-round = Round { $round }
+tournament-next = Up next: { $name }, press Enter
+tournament-champion = { $name } is the champion! Press Enter
+tournament-format-knockout = Format: knockout bracket (Tab for hot seat)
+tournament-format-hot-seat = Format: hot seat, take turns as long as you like (Tab for knockout)
+hot-seat-title = Hot seat
+hot-seat-entry = { $name }: { $runs } runs, best { $best }, total { $total }
+hot-seat-hint = Esc ends the session
+replays-title = Replays
+replays-empty = No replays yet
+replays-entry = { $date }  { $mode }  score { $score }
+replays-hint = Up and Down choose, Enter plays, Esc goes back
+replay-playing = Move { $tick }/{ $ticks } { $bar } { $speed }x
+replay-paused = Move { $tick }/{ $ticks } { $bar } paused
+replay-controls = Space pauses, 1 2 4 and -/= set speed, Left/Right step, PgUp/PgDn jump, WASD and the wheel move the camera, Esc stops
+hud-score = Score
+hud-danger = Danger bonus
+hud-objective = Objective
+hud-zone = Zone timer
+hud-hidden = [{ $key }] { $widget } (hidden)
+options-title = Options
+options-hint = Up and Down choose, Enter changes, O closes
+dpad-on = On-screen d-pad: on
+dpad-off = On-screen d-pad: off
+mouse-steering-on = Mouse steering: on
+mouse-steering-off = Mouse steering: off
+new-best = New high score!
+new-best-badge = NEW BEST
+hud-best = New best badge
+idle-paused = Paused, nobody was playing
+event-log-title = Event log (E hides)
+event-log-on = Event log: on
+event-log-off = Event log: off
+log-food-eaten = Food eaten at ({ $x }, { $y }): +{ $points }
+log-power-up-spawned = { $name } appeared at ({ $x }, { $y })
+log-power-up-collected = { $name } collected
+log-power-up-expired = { $name } ran out
+power-up-name-hydra = Hydra
+power-up-name-split = Split
+power-up-name-lantern = Lantern
+danger-preview-on = Danger preview: on
+danger-preview-off = Danger preview: off
+heatmap-empty = No finished run to show yet
+latency-title = Input latency in ms (min / avg / max)
+latency-turn = Key to turn: { $min } / { $avg } / { $max }
+latency-move = Key to move: { $min } / { $avg } / { $max }
+hud-speed = Speed
+speed = Speed { $speed }x
+editor-title = Level editor
+editor-controls = Left click paints, right click erases, arrows turn conveyors, M makes a puzzle, [ and ] set its moves, S saves, P playtests, L leaves
+brush-wall = Wall
+brush-ice = Ice
+brush-speed-pad = Speed pad
+brush-mud = Mud
+brush-conveyor = Conveyor
+brush-portal = Portal
+brush-spawn = Spawn
+brush-exit = Exit
+level-saved = Level saved
+level-error-title = Can't play { $path }
+level-error-unreadable = The file couldn't be read: { $reason }
+level-error-outside = { $count } cells are placed outside the arena.
+level-error-spawn = The spawn point and the cell below it must be open floor.
+level-error-portal = The portal at { $x }, { $y } has no partner.
+level-error-no-food = There is no free cell the snake can reach for food.
+dialog-close = Press Enter to close
+difficulty-easy = Easy
+difficulty-medium = Medium
+difficulty-hard = Hard
+levels-title = Levels
+levels-open-arena = Open arena
+levels-entry = { $name } by { $author } — { $difficulty } — best { $best }
+levels-entry-broken = { $file } (can't be played)
+levels-leaderboard = Your best on { $name }
+levels-leaderboard-entry = { $rank }. { $score } on { $date }
+levels-leaderboard-empty = No runs yet
+levels-hint = Enter to play, R to look for new levels, B to go back
+levels-downloading = Downloading levels…
+levels-downloaded = { $count } new levels downloaded
+levels-download-failed = Couldn't download levels
+levels-pack = Campaign: { $name } by { $author } — { $count } levels
+levels-pack-broken = Campaign: { $name } (can't be played)
+levels-pack-unreadable = A pack that couldn't be read, see the log
+levels-pack-loading = Loading a pack…
+levels-checking = Checking levels…
+pack-error-empty = The pack has no levels.
+pack-error-level = Level { $number }, { $name }: { $error }
+campaign-level = Level { $number } of { $count }: { $name }
+campaign-complete = { $name } complete!
+level-error-stars = Each star must need at least the score, and allow at most the time, of the one before it.
+campaign-title = Campaign — { $stars } ★
+campaign-empty = There is no campaign to play.
+campaign-chapter = Chapter { $number }: { $name }
+campaign-chapter-loading = Chapter { $number } is loading…
+campaign-level-info = { $name } — { $difficulty } — best medal { $medal }
+campaign-locked = Complete the level before this one to open it.
+campaign-hint = Arrow keys to pick a level, Enter to play, C to go back
+campaign-locked-stars = This chapter opens at { $needed } stars. You have { $stars }.
+medal-bronze = Bronze
+medal-silver = Silver
+medal-gold = Gold
+results-title = { $name } complete
+results-score = Score { $score } in { $seconds } seconds
+results-medal = { $medal } medal
+results-new-best = New best!
+results-next-star = Next star: { $score } points
+results-next-star-timed = Next star: { $score } points within { $seconds } seconds
+brush-checkpoint = Checkpoint
+checkpoint-reached = Checkpoint reached
+game-over-spikes = Game over, spikes with no tail left to lose
+brush-spikes = Spikes
+game-over-out-of-moves = Out of moves
+brush-food = Food
+editor-puzzle = Puzzle: { $food } food in { $moves } moves
+editor-checking = Checking the level…
+hud-moves = Moves left
+puzzle-moves = Moves left: { $moves }, hints: { $hints } (U undoes, H hints)
+level-error-puzzle-food = The puzzle has no food to eat.
+level-error-puzzle-unsupported = Puzzles can't have tiles, obstacles, doors or exits.
+level-error-puzzle-unsolvable = The puzzle's food can't all be eaten within { $moves } moves.
+level-error-puzzle-too-open = The puzzle is too open to check that it can be solved.
+puzzle-hint = Hint shown, { $hints } left for this level
+puzzle-no-hints = No hints left for this level
+puzzle-hint-stuck = There's no way to finish from here, try undoing with U
+puzzle-hint-gave-up = Couldn't work out a hint from here
+loading = Loading… { $done } of { $total }
+asset-error-title = Some of the game's files are missing or damaged:
+asset-missing = { $path } is missing
+asset-broken = { $path } can't be read: { $error }
+asset-load-failed = it failed to load
+asset-error-hint = Put these files back or reinstall the game, then start it again. Escape quits.
+error-title = Something went wrong
+error-snake-broken = Part of the snake went missing, so it can't move properly.
+error-hint = The game is paused. Restarting the run should fix it, and the log has the details.
+mods-loaded = { $count } mods loaded
+mod-conflict = { $by } replaces { $id } from { $replaced } ({ $file })
+mod-conflict-game = the game
+telemetry-title = Telemetry: what would be sent
+telemetry-on = Telemetry: on
+telemetry-off = Telemetry: off, nothing is collected
+telemetry-destination = Sent to { $url } every { $every } runs
+telemetry-local = Not sent anywhere, only kept on this computer
+telemetry-hint = T closes. Turn telemetry on or off in the options.
+session-summary = { $player } played { $runs } runs in { $minutes } minutes, best score { $best }
+session-anonymous = Someone
+hud-difficulty = Adaptive difficulty
+difficulty-easier = Easier after some quick runs: { $speed }% speed
+difficulty-harder = Harder after some long runs: { $speed }% speed
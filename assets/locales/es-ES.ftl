@@ -0,0 +1,220 @@
+# Spanish strings. Missing keys fall back to English.
+
+language-name = Español
+language-changed = Idioma: { $language }
+
+window-title-playing = { $title } — Puntuación { $score }
+window-title-paused = { $title } — Puntuación { $score } — En pausa
+
+paused = En pausa (O para opciones, F11 edita el HUD, H muestra el mapa de calor de la última partida, L edita el nivel, B abre los niveles y campañas, C abre el mapa de la campaña, T muestra la telemetría)
+score = Puntuación { $score }
+game-over-wall = Fin de la partida, choque contra la pared
+game-over-tail = Fin de la partida, choque contra la cola
+game-over-head-on = Fin de la partida, choque de frente
+game-over-idle = Partida terminada, nadie estaba jugando
+game-over-level-complete = Nivel completado
+
+palette-changed = Paleta: { $palette }
+palette-standard = estándar
+palette-deuteranopia = deuteranopía
+palette-protanopia = protanopía
+palette-tritanopia = tritanopía
+palette-high-contrast = alto contraste
+
+reduced-motion-on = Movimiento reducido: activado
+reduced-motion-off = Movimiento reducido: desactivado
+
+verbosity-changed = Anuncios: { $verbosity }
+verbosity-off = desactivados
+verbosity-essential = esenciales
+verbosity-verbose = detallados
+
+controls-changed = Controles: { $controls }
+controls-arrows = flechas
+controls-wasd = W A S D
+controls-ijkl = I J K L
+controls-numpad = teclado numérico
+controls-one-button = un botón, la barra espaciadora gira en sentido horario
+
+config-reloaded = Configuración recargada
+
+crash-found = La última partida falló. Pulsa F9 para recuperarla o F10 para descartarla.
+
+profile-title = Elige un perfil
+profile-entry = { $name } (récord { $best })
+profile-new = Perfil nuevo: { $name }
+profile-hint = Arriba y Abajo eligen, escribe para nombrar un perfil nuevo, Intro empieza, Tab para un torneo, F8 para repeticiones
+
+power-up-hydra = ¡Hidra! Ahora controlas dos serpientes
+hydra-ended = Se acabó la hidra, queda una serpiente
+power-up-split = ¡División! Tu cola recoge comida por ti
+ally-expired = Tu aliada ha terminado: +{ $points } puntos
+power-up-lantern = ¡Farol! Ves toda la comida de noche
+night-falls = Cae la noche
+day-breaks = Amanece
+
+event-active = ¡El evento { $event } está en marcha!
+event-collected = { $event }: { $count } recogidos
+event-result = { $event }: { $count } recogidos en esta partida, récord { $best }
+danger-bonus = ¡Bonus de peligro!
+close-call = ¡Por los pelos!
+popup-merged = +{ $points } (x{ $count })
+popup-merged-combo = +{ $points } (x{ $count }, combo x{ $multiplier })
+objective = { $objective } ({ $progress }/{ $target })
+objective-complete = Objetivo cumplido: +{ $points }
+zone-timer = La zona se mueve en { $seconds } s
+relay-picked-up = Tienes la comida de relevo, llévala a casa
+relay-dropped = Comida de relevo perdida
+relay-delivered = Comida de relevo entregada: +{ $points }
+tournament-title = Torneo
+tournament-new-player = Añadir jugador: { $name }
+tournament-entry-hint = Intro añade un jugador, Intro sin nombre empieza con { $min } a { $max } jugadores, Esc cancela
+tournament-bye = (exento)
+tournament-round = Ronda { $round }
+tournament-next = Siguiente: { $name }, pulsa Intro
+tournament-champion = ¡{ $name } es el campeón! Pulsa Intro
+tournament-format-knockout = Formato: eliminatoria (Tab para turnos)
+tournament-format-hot-seat = Formato: turnos, jugad tanto como queráis (Tab para eliminatoria)
+hot-seat-title = Por turnos
+hot-seat-entry = { $name }: { $runs } partidas, mejor { $best }, total { $total }
+hot-seat-hint = Esc termina la sesión
+replays-title = Repeticiones
+replays-empty = Aún no hay repeticiones
+replays-entry = { $date }  { $mode }  puntos { $score }
+replays-hint = Arriba y Abajo eligen, Intro reproduce, Esc vuelve
+replay-playing = Movimiento { $tick }/{ $ticks } { $bar } { $speed }x
+replay-paused = Movimiento { $tick }/{ $ticks } { $bar } en pausa
+replay-controls = Espacio pausa, 1 2 4 y -/= cambian la velocidad, Izquierda/Derecha avanzan, RePág/AvPág saltan, WASD y la rueda mueven la cámara, Esc para
+hud-score = Puntos
+hud-danger = Bonus de peligro
+hud-objective = Objetivo
+hud-zone = Temporizador de zona
+hud-hidden = [{ $key }] { $widget } (oculto)
+options-title = Opciones
+options-hint = Arriba y Abajo eligen, Intro cambia, O cierra
+dpad-on = Cruceta en pantalla: activada
+dpad-off = Cruceta en pantalla: desactivada
+mouse-steering-on = Dirección con el ratón: activada
+mouse-steering-off = Dirección con el ratón: desactivada
+new-best = ¡Nuevo récord!
+new-best-badge = NUEVO RÉCORD
+hud-best = Insignia de récord
+idle-paused = En pausa, nadie estaba jugando
+event-log-title = Registro de eventos (E lo oculta)
+event-log-on = Registro de eventos: activado
+event-log-off = Registro de eventos: desactivado
+log-food-eaten = Comida en ({ $x }, { $y }): +{ $points }
+log-power-up-spawned = Apareció { $name } en ({ $x }, { $y })
+log-power-up-collected = Recogido: { $name }
+log-power-up-expired = Se agotó: { $name }
+power-up-name-hydra = Hidra
+power-up-name-split = División
+power-up-name-lantern = Farol
+danger-preview-on = Aviso de peligro: activado
+danger-preview-off = Aviso de peligro: desactivado
+heatmap-empty = Todavía no hay ninguna partida terminada
+latency-title = Latencia de entrada en ms (mín / media / máx)
+latency-turn = Tecla a giro: { $min } / { $avg } / { $max }
+latency-move = Tecla a movimiento: { $min } / { $avg } / { $max }
+hud-speed = Velocidad
+speed = Velocidad { $speed }x
+editor-title = Editor de niveles
+editor-controls = Clic izquierdo pinta, clic derecho borra, las flechas giran las cintas, M crea un puzle, [ y ] fijan sus movimientos, S guarda, P prueba, L sale
+brush-wall = Muro
+brush-ice = Hielo
+brush-speed-pad = Acelerador
+brush-mud = Barro
+brush-conveyor = Cinta
+brush-portal = Portal
+brush-spawn = Salida de la serpiente
+brush-exit = Meta
+level-saved = Nivel guardado
+level-error-title = No se puede jugar { $path }
+level-error-unreadable = No se pudo leer el archivo: { $reason }
+level-error-outside = Hay { $count } casillas fuera de la arena.
+level-error-spawn = El punto de salida y la casilla de debajo deben estar libres.
+level-error-portal = El portal en { $x }, { $y } no tiene pareja.
+level-error-no-food = No hay ninguna casilla libre a la que la serpiente pueda llegar a comer.
+dialog-close = Pulsa Intro para cerrar
+difficulty-easy = Fácil
+difficulty-medium = Normal
+difficulty-hard = Difícil
+levels-title = Niveles
+levels-open-arena = Arena abierta
+levels-entry = { $name } de { $author } — { $difficulty } — mejor { $best }
+levels-entry-broken = { $file } (no se puede jugar)
+levels-leaderboard = Tus mejores partidas en { $name }
+levels-leaderboard-entry = { $rank }. { $score } el { $date }
+levels-leaderboard-empty = Aún no hay partidas
+levels-hint = Intro para jugar, R para buscar niveles nuevos, B para volver
+levels-downloading = Descargando niveles…
+levels-downloaded = { $count } niveles nuevos descargados
+levels-download-failed = No se pudieron descargar los niveles
+levels-pack = Campaña: { $name } de { $author } — { $count } niveles
+levels-pack-broken = Campaña: { $name } (no se puede jugar)
+levels-pack-unreadable = Un paquete que no se pudo leer, mira el registro
+levels-pack-loading = Cargando un paquete…
+levels-checking = Comprobando niveles…
+pack-error-empty = El paquete no tiene niveles.
+pack-error-level = Nivel { $number }, { $name }: { $error }
+campaign-level = Nivel { $number } de { $count }: { $name }
+campaign-complete = ¡{ $name } completada!
+level-error-stars = Cada estrella debe pedir al menos la puntuación, y dar como mucho el tiempo, de la anterior.
+campaign-title = Campaña — { $stars } ★
+campaign-empty = No hay ninguna campaña para jugar.
+campaign-chapter = Capítulo { $number }: { $name }
+campaign-chapter-loading = Cargando el capítulo { $number }…
+campaign-level-info = { $name } — { $difficulty } — mejor medalla { $medal }
+campaign-locked = Completa el nivel anterior para abrir este.
+campaign-hint = Flechas para elegir un nivel, Intro para jugar, C para volver
+campaign-locked-stars = Este capítulo se abre con { $needed } estrellas. Tienes { $stars }.
+medal-bronze = Bronce
+medal-silver = Plata
+medal-gold = Oro
+results-title = { $name } completado
+results-score = { $score } puntos en { $seconds } segundos
+results-medal = Medalla de { $medal }
+results-new-best = ¡Nuevo récord!
+results-next-star = Siguiente estrella: { $score } puntos
+results-next-star-timed = Siguiente estrella: { $score } puntos en { $seconds } segundos
+brush-checkpoint = Punto de control
+checkpoint-reached = Punto de control alcanzado
+game-over-spikes = Fin de la partida, pinchos sin cola que perder
+brush-spikes = Pinchos
+game-over-out-of-moves = Sin movimientos
+brush-food = Comida
+editor-puzzle = Puzle: { $food } comidas en { $moves } movimientos
+editor-checking = Comprobando el nivel…
+hud-moves = Movimientos restantes
+puzzle-moves = Movimientos restantes: { $moves }, pistas: { $hints } (U deshace, H da una pista)
+level-error-puzzle-food = El puzle no tiene comida.
+level-error-puzzle-unsupported = Los puzles no pueden tener casillas especiales, obstáculos, puertas ni salidas.
+level-error-puzzle-unsolvable = No se puede comer toda la comida del puzle en { $moves } movimientos.
+level-error-puzzle-too-open = El puzle es demasiado abierto para comprobar que tiene solución.
+puzzle-hint = Pista mostrada, quedan { $hints } en este nivel
+puzzle-no-hints = No quedan pistas en este nivel
+puzzle-hint-stuck = No hay forma de terminar desde aquí, prueba a deshacer con U
+puzzle-hint-gave-up = No se ha podido encontrar una pista desde aquí
+loading = Cargando… { $done } de { $total }
+asset-error-title = Faltan archivos del juego o están dañados:
+asset-missing = Falta { $path }
+asset-broken = No se puede leer { $path }: { $error }
+asset-load-failed = no se ha podido cargar
+asset-error-hint = Recupera estos archivos o reinstala el juego y vuelve a abrirlo. Escape para salir.
+error-title = Algo ha salido mal
+error-snake-broken = Ha desaparecido una parte de la serpiente, así que no puede moverse bien.
+error-hint = El juego está en pausa. Reiniciar la partida debería arreglarlo; el registro tiene los detalles.
+mods-loaded = { $count } mods cargados
+mod-conflict = { $by } sustituye { $id } de { $replaced } ({ $file })
+mod-conflict-game = el juego
+telemetry-title = Telemetría: lo que se enviaría
+telemetry-on = Telemetría: activada
+telemetry-off = Telemetría: desactivada, no se recoge nada
+telemetry-destination = Se envía a { $url } cada { $every } partidas
+telemetry-local = No se envía a ningún sitio, solo se guarda en este ordenador
+telemetry-hint = T para cerrar. Activa o desactiva la telemetría en las opciones.
+session-summary = { $player } ha jugado { $runs } partidas en { $minutes } minutos, mejor puntuación { $best }
+session-anonymous = Alguien
+hud-difficulty = Dificultad adaptativa
+difficulty-easier = Más fácil tras unas partidas cortas: { $speed }% de velocidad
+difficulty-harder = Más difícil tras unas partidas largas: { $speed }% de velocidad
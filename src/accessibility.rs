@@ -0,0 +1,359 @@
+use bevy::{prelude::*, render::pass::ClearColor};
+use serde::Deserialize;
+
+use crate::{
+    game_event::GameEvent, locale::Locale, scoring::ScoreReason, FoodMaterial, GameOverCause,
+    HeadMaterial, PowerUpMaterial, Score, SegmentMaterial,
+};
+
+/// Player-facing accessibility options, kept separate from cosmetic choices so they can be
+/// combined freely.
+#[derive(Default)]
+pub struct AccessibilitySettings {
+    pub palette: Palette,
+    /// Disables screen shake, particle bursts, interpolated movement and flashing. Every
+    /// visual effect system is expected to check this before animating anything.
+    pub reduced_motion: bool,
+    pub verbosity: Verbosity,
+    /// Marks the cell the head moves into next in red when moving there would end the run.
+    pub danger_preview: bool,
+}
+
+/// How much of the game state is read out to the player.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Deserialize)]
+pub enum Verbosity {
+    Off,
+    /// Game over and settings changes only.
+    Essential,
+    /// Everything, including every score change.
+    Verbose,
+}
+
+impl Default for Verbosity {
+    fn default() -> Self {
+        Self::Essential
+    }
+}
+
+impl Verbosity {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Off => Self::Essential,
+            Self::Essential => Self::Verbose,
+            Self::Verbose => Self::Off,
+        }
+    }
+
+    pub fn name_key(self) -> &'static str {
+        match self {
+            Self::Off => "verbosity-off",
+            Self::Essential => "verbosity-essential",
+            Self::Verbose => "verbosity-verbose",
+        }
+    }
+}
+
+/// A line of text to be read out to the player. It is dropped if the player's verbosity
+/// setting is below `verbosity`, so it should never be `Off`.
+pub struct Announcement {
+    pub text: String,
+    pub verbosity: Verbosity,
+}
+
+impl Announcement {
+    pub fn essential(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            verbosity: Verbosity::Essential,
+        }
+    }
+
+    pub fn verbose(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            verbosity: Verbosity::Verbose,
+        }
+    }
+}
+
+/// Color palettes for the arena. Everything other than `Standard` keeps the snake and food
+/// distinguishable for players with color vision deficiencies.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize)]
+pub enum Palette {
+    Standard,
+    Deuteranopia,
+    Protanopia,
+    Tritanopia,
+    HighContrast,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::Standard
+    }
+}
+
+pub struct PaletteColors {
+    pub head: Color,
+    pub segment: Color,
+    pub food: Color,
+    pub power_up: Color,
+    /// Tiles sit under everything else, so they're kept dim.
+    pub ice: Color,
+    pub speed_pad: Color,
+    pub mud: Color,
+    pub conveyor: Color,
+    pub spikes: Color,
+    /// Drawn over the tiles, so it's see-through.
+    pub zone: Color,
+    pub background: Color,
+}
+
+impl Palette {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Standard => Self::Deuteranopia,
+            Self::Deuteranopia => Self::Protanopia,
+            Self::Protanopia => Self::Tritanopia,
+            Self::Tritanopia => Self::HighContrast,
+            Self::HighContrast => Self::Standard,
+        }
+    }
+
+    pub fn name_key(self) -> &'static str {
+        match self {
+            Self::Standard => "palette-standard",
+            Self::Deuteranopia => "palette-deuteranopia",
+            Self::Protanopia => "palette-protanopia",
+            Self::Tritanopia => "palette-tritanopia",
+            Self::HighContrast => "palette-high-contrast",
+        }
+    }
+
+    // The colorblind palettes are picked from the Okabe-Ito set, avoiding the pairs each
+    // deficiency confuses.
+    pub fn colors(self) -> PaletteColors {
+        match self {
+            Self::Standard => PaletteColors {
+                head: Color::rgb(0.7, 0.7, 0.7),
+                segment: Color::rgb(0.3, 0.3, 0.3),
+                food: Color::rgb(1.0, 0.0, 1.0),
+                power_up: Color::rgb(0.0, 0.9, 0.9),
+                ice: Color::rgb(0.15, 0.3, 0.4),
+                speed_pad: Color::rgb(0.45, 0.4, 0.0),
+                mud: Color::rgb(0.3, 0.2, 0.1),
+                conveyor: Color::rgb(0.3, 0.45, 0.3),
+                spikes: Color::rgb(0.5, 0.15, 0.15),
+                zone: Color::rgba(0.35, 0.3, 0.1, 0.5),
+                background: Color::rgb(0.04, 0.04, 0.04),
+            },
+            Self::Deuteranopia => PaletteColors {
+                head: Color::rgb(0.34, 0.71, 0.91),
+                segment: Color::rgb(0.0, 0.45, 0.7),
+                food: Color::rgb(0.9, 0.62, 0.0),
+                power_up: Color::rgb(0.94, 0.89, 0.26),
+                ice: Color::rgb(0.1, 0.25, 0.35),
+                speed_pad: Color::rgb(0.4, 0.35, 0.0),
+                mud: Color::rgb(0.25, 0.2, 0.15),
+                conveyor: Color::rgb(0.2, 0.35, 0.45),
+                spikes: Color::rgb(0.45, 0.3, 0.0),
+                zone: Color::rgba(0.35, 0.25, 0.05, 0.5),
+                background: Color::rgb(0.04, 0.04, 0.04),
+            },
+            Self::Protanopia => PaletteColors {
+                head: Color::rgb(0.34, 0.71, 0.91),
+                segment: Color::rgb(0.0, 0.45, 0.7),
+                food: Color::rgb(0.94, 0.89, 0.26),
+                power_up: Color::rgb(0.8, 0.47, 0.65),
+                ice: Color::rgb(0.1, 0.25, 0.35),
+                speed_pad: Color::rgb(0.4, 0.35, 0.0),
+                mud: Color::rgb(0.25, 0.2, 0.15),
+                conveyor: Color::rgb(0.2, 0.35, 0.45),
+                spikes: Color::rgb(0.45, 0.3, 0.0),
+                zone: Color::rgba(0.35, 0.3, 0.1, 0.5),
+                background: Color::rgb(0.04, 0.04, 0.04),
+            },
+            Self::Tritanopia => PaletteColors {
+                head: Color::rgb(0.8, 0.8, 0.8),
+                segment: Color::rgb(0.0, 0.62, 0.45),
+                food: Color::rgb(0.84, 0.37, 0.0),
+                power_up: Color::rgb(0.8, 0.47, 0.65),
+                ice: Color::rgb(0.25, 0.25, 0.25),
+                speed_pad: Color::rgb(0.45, 0.15, 0.15),
+                mud: Color::rgb(0.2, 0.3, 0.25),
+                conveyor: Color::rgb(0.35, 0.3, 0.4),
+                spikes: Color::rgb(0.5, 0.1, 0.25),
+                zone: Color::rgba(0.35, 0.1, 0.2, 0.5),
+                background: Color::rgb(0.04, 0.04, 0.04),
+            },
+            Self::HighContrast => PaletteColors {
+                head: Color::rgb(1.0, 1.0, 1.0),
+                segment: Color::rgb(0.75, 0.75, 0.75),
+                food: Color::rgb(1.0, 0.9, 0.0),
+                power_up: Color::rgb(0.0, 1.0, 1.0),
+                ice: Color::rgb(0.0, 0.2, 0.5),
+                speed_pad: Color::rgb(0.5, 0.5, 0.0),
+                mud: Color::rgb(0.35, 0.2, 0.0),
+                conveyor: Color::rgb(0.6, 0.6, 0.6),
+                spikes: Color::rgb(0.8, 0.0, 0.0),
+                zone: Color::rgba(0.4, 0.0, 0.4, 0.5),
+                background: Color::rgb(0.0, 0.0, 0.0),
+            },
+        }
+    }
+}
+
+pub struct AccessibilityPlugin;
+
+impl Plugin for AccessibilityPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<AccessibilitySettings>()
+            .add_event::<Announcement>()
+            .add_system(palette_selection.system())
+            .add_system(reduced_motion_toggle.system())
+            .add_system(verbosity_selection.system())
+            .add_system(apply_palette.system())
+            .add_system(announce_game_state.system())
+            .add_system(speak_announcements.system());
+    }
+}
+
+fn palette_selection(
+    keyboard_input: Res<Input<KeyCode>>,
+    locale: Res<Locale>,
+    mut settings: ResMut<AccessibilitySettings>,
+    mut announcements: ResMut<Events<Announcement>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F1) {
+        settings.palette = settings.palette.next();
+        announcements.send(Announcement::essential(locale.format(
+            "palette-changed",
+            &[("palette", locale.text(settings.palette.name_key()))],
+        )));
+    }
+}
+
+fn reduced_motion_toggle(
+    keyboard_input: Res<Input<KeyCode>>,
+    locale: Res<Locale>,
+    mut settings: ResMut<AccessibilitySettings>,
+    mut announcements: ResMut<Events<Announcement>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F2) {
+        settings.reduced_motion = !settings.reduced_motion;
+        announcements.send(Announcement::essential(locale.text(
+            if settings.reduced_motion {
+                "reduced-motion-on"
+            } else {
+                "reduced-motion-off"
+            },
+        )));
+    }
+}
+
+fn verbosity_selection(
+    keyboard_input: Res<Input<KeyCode>>,
+    locale: Res<Locale>,
+    mut settings: ResMut<AccessibilitySettings>,
+    mut announcements: ResMut<Events<Announcement>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F3) {
+        settings.verbosity = settings.verbosity.next();
+        // Sent as essential so that switching back on is confirmed too.
+        announcements.send(Announcement::essential(locale.format(
+            "verbosity-changed",
+            &[("verbosity", locale.text(settings.verbosity.name_key()))],
+        )));
+    }
+}
+
+fn apply_palette(
+    mut applied: Local<Option<Palette>>,
+    settings: Res<AccessibilitySettings>,
+    head_material: Res<HeadMaterial>,
+    segment_material: Res<SegmentMaterial>,
+    food_material: Res<FoodMaterial>,
+    power_up_material: Res<PowerUpMaterial>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut clear_color: ResMut<ClearColor>,
+) {
+    if *applied == Some(settings.palette) {
+        return;
+    }
+    *applied = Some(settings.palette);
+
+    let colors = settings.palette.colors();
+    for (handle, color) in &[
+        (head_material.0, colors.head),
+        (segment_material.0, colors.segment),
+        (food_material.0, colors.food),
+        (power_up_material.0, colors.power_up),
+    ] {
+        if let Some(material) = materials.get_mut(handle) {
+            material.color = *color;
+        }
+    }
+    clear_color.0 = colors.background;
+}
+
+/// Reads out the end of the run, and the score after food, deliveries, objectives and allies
+/// running out. Points that can come every move, like the danger bonus and the hill, wait for
+/// the next of those rather than drowning them out.
+fn announce_game_state(
+    mut reader: Local<EventReader<GameEvent>>,
+    locale: Res<Locale>,
+    score: Res<Score>,
+    game_events: Res<Events<GameEvent>>,
+    mut announcements: ResMut<Events<Announcement>>,
+) {
+    let mut scored = false;
+    let mut game_over = None;
+    for event in reader.iter(&game_events) {
+        match *event {
+            GameEvent::Scored { reason, .. } => {
+                scored |= matches!(
+                    reason,
+                    ScoreReason::Food
+                        | ScoreReason::EventFood
+                        | ScoreReason::Objective
+                        | ScoreReason::Relay
+                        | ScoreReason::AllyExpired
+                );
+            }
+            // Only the first collision of a tick is worth reading out.
+            GameEvent::GameOver { cause } if game_over.is_none() => game_over = Some(cause),
+            _ => {}
+        }
+    }
+    if scored {
+        announcements.send(Announcement::verbose(
+            locale.format("score", &[("score", score.0.to_string())]),
+        ));
+    }
+
+    if let Some(cause) = game_over {
+        announcements.send(Announcement::essential(locale.text(match cause {
+            GameOverCause::Wall => "game-over-wall",
+            GameOverCause::Tail => "game-over-tail",
+            GameOverCause::HeadOn => "game-over-head-on",
+            GameOverCause::Spikes => "game-over-spikes",
+            GameOverCause::OutOfMoves => "game-over-out-of-moves",
+            GameOverCause::Idle => "game-over-idle",
+            GameOverCause::LevelComplete => "game-over-level-complete",
+        })));
+    }
+}
+
+/// Writes announcements to stdout, where terminal screen readers and TTS front ends pick them
+/// up.
+fn speak_announcements(
+    mut reader: Local<EventReader<Announcement>>,
+    settings: Res<AccessibilitySettings>,
+    announcements: Res<Events<Announcement>>,
+) {
+    for announcement in reader.iter(&announcements) {
+        if announcement.verbosity <= settings.verbosity {
+            println!("{}", announcement.text);
+        }
+    }
+}
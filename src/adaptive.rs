@@ -0,0 +1,112 @@
+use bevy::prelude::*;
+
+use crate::{
+    config::ZenMode,
+    effects::{ActiveEffect, EffectKind, Effects},
+    hud_layout::{HudLayout, HudWidget},
+    locale::Locale,
+    profile::ActiveProfile,
+    rules::GameRules,
+    GameState,
+};
+
+/// A run shorter than this, in seconds, counts as an early death.
+const EARLY_DEATH: f32 = 30.0;
+
+/// A run longer than this, in seconds, counts as a long one.
+const LONG_RUN: f32 = 180.0;
+
+/// How many runs in a row it takes before the speed changes at all.
+const STREAK: usize = 2;
+
+/// How much each further run in the streak changes the speed by.
+const STEP: f32 = 0.05;
+
+/// The most steps the speed can change by, either way.
+const MAX_STEPS: usize = 3;
+
+/// The HUD's note that adaptive difficulty has changed the speed.
+pub struct DifficultyLabel;
+
+pub struct AdaptivePlugin;
+
+impl Plugin for AdaptivePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(adapt_speed.system())
+            .add_system(difficulty_label.system());
+    }
+}
+
+/// What the move interval is multiplied by, given how long the latest runs lasted, oldest
+/// first. A streak of early deaths at the end slows the game down by `STEP` for each run in it
+/// from the `STREAK`th on, and a streak of long runs speeds it up the same way. Anything else
+/// leaves it alone.
+fn interval_factor(recent_runs: &[f32]) -> f32 {
+    let streak = |ends: &dyn Fn(f32) -> bool| {
+        recent_runs
+            .iter()
+            .rev()
+            .take_while(|&&seconds| ends(seconds))
+            .count()
+    };
+    let steps = |streak: usize| (streak + 1).saturating_sub(STREAK).min(MAX_STEPS) as f32;
+    let early = streak(&|seconds| seconds < EARLY_DEATH);
+    let long = streak(&|seconds| seconds > LONG_RUN);
+    if early >= STREAK {
+        1.0 + STEP * steps(early)
+    } else if long >= STREAK {
+        1.0 / (1.0 + STEP * steps(long))
+    } else {
+        1.0
+    }
+}
+
+/// The active profile's factor with the `adaptive_difficulty` rule, or 1.
+fn current_factor(rules: &GameRules, profile: &ActiveProfile) -> f32 {
+    match &profile.0 {
+        Some(profile) if rules.adaptive_difficulty => interval_factor(&profile.stats.recent_runs),
+        _ => 1.0,
+    }
+}
+
+/// Keeps the speed change going as an effect, so it combines with the other speed modifiers.
+fn adapt_speed(rules: Res<GameRules>, profile: Res<ActiveProfile>, mut effects: ResMut<Effects>) {
+    let factor = current_factor(&rules, &profile);
+    if (factor - 1.0).abs() > f32::EPSILON {
+        // Only lasts the frame, like a tile's effect.
+        effects.add(ActiveEffect::new(EffectKind::Adaptive, 0.0, factor));
+    }
+}
+
+/// Says whenever the game's been made easier or harder, and by how much.
+fn difficulty_label(
+    state: Res<GameState>,
+    locale: Res<Locale>,
+    rules: Res<GameRules>,
+    profile: Res<ActiveProfile>,
+    zen_mode: Res<ZenMode>,
+    layout: Res<HudLayout>,
+    mut labels: Query<(&DifficultyLabel, &mut Text)>,
+) {
+    // While the layout is edited every widget shows its name instead.
+    if layout.editing {
+        return;
+    }
+    let playing = matches!(*state, GameState::Playing | GameState::Paused) && !zen_mode.0;
+    let factor = current_factor(&rules, &profile);
+    let speed = ((100.0 / factor).round() as u32).to_string();
+    let value = if !playing || !layout.shows(HudWidget::Difficulty) {
+        String::new()
+    } else if factor > 1.0 + f32::EPSILON {
+        locale.format("difficulty-easier", &[("speed", speed)])
+    } else if factor < 1.0 - f32::EPSILON {
+        locale.format("difficulty-harder", &[("speed", speed)])
+    } else {
+        String::new()
+    };
+    for (_label, mut text) in &mut labels.iter() {
+        if text.value != value {
+            text.value = value.clone();
+        }
+    }
+}
@@ -0,0 +1,341 @@
+use std::collections::{HashSet, VecDeque};
+
+use bevy::prelude::*;
+
+use crate::{
+    spawn_segment, Armor, Direction, Position, SegmentMaterial, Size, SnakeHead, SnakeSegment,
+    ARMORED_SEGMENT_SIZE,
+};
+
+/// Runs after the update stages, so every system there sees the snakes as they were at the
+/// start of the frame.
+const BODY_CHANGES: &str = "body_changes";
+
+/// A change to a snake's body. Systems send these instead of spawning, despawning or relinking
+/// segments themselves, and they're applied together once the frame's update is done. Until
+/// then the body stays whole, so no system follows a link to a segment that's already gone or
+/// not there yet, and a segment two systems cut on the same frame is only despawned once.
+///
+/// Clearing the whole arena still despawns everything directly, since nothing's left to link.
+#[derive(Clone, Copy, Debug)]
+pub enum BodyChange {
+    /// Adds a segment behind `tail` at `position`, armored if `armored` is set.
+    Grow {
+        tail: Entity,
+        position: Position,
+        armored: bool,
+    },
+    /// Cuts off `first_cut`, which is just behind `new_tail`, and everything after it.
+    CutBehind { new_tail: Entity, first_cut: Entity },
+    /// Removes a whole snake, head and segments.
+    Remove { head: Entity },
+}
+
+/// The cells a snake covers, head first, and how many segments it still has to grow. It's
+/// plain data, so the rules for how a snake moves, grows and gets cut live here once, for the
+/// snakes in the arena and for the puzzle solver alike. There's always a head and at least one
+/// segment behind it.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct SnakeBody {
+    cells: VecDeque<Position>,
+    growth: u32,
+}
+
+impl SnakeBody {
+    /// Panics without any segments, since no snake is ever just a head.
+    pub fn new(head: Position, segments: impl IntoIterator<Item = Position>, growth: u32) -> Self {
+        let cells: VecDeque<Position> = Some(head).into_iter().chain(segments).collect();
+        assert!(cells.len() > 1, "a snake needs at least one segment");
+        Self { cells, growth }
+    }
+
+    pub fn head(&self) -> Position {
+        self.cells[0]
+    }
+
+    pub fn tail(&self) -> Position {
+        self.cells[self.cells.len() - 1]
+    }
+
+    /// The segments, from the one just behind the head to the tail.
+    pub fn segments(&self) -> impl Iterator<Item = Position> + '_ {
+        self.cells.iter().skip(1).copied()
+    }
+
+    /// The head and every segment.
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Segments still to grow, one per move.
+    pub fn growth(&self) -> u32 {
+        self.growth
+    }
+
+    /// Whether the head or any segment is at `position`.
+    pub fn contains(&self, position: Position) -> bool {
+        self.cells.contains(&position)
+    }
+
+    /// Which segment is at `position`, counting from 0 just behind the head.
+    pub fn segment_at(&self, position: Position) -> Option<usize> {
+        self.segments().position(|segment| segment == position)
+    }
+
+    pub fn grow(&mut self, segments: u32) {
+        self.growth += segments;
+    }
+
+    /// Moves the head to `head`, each segment taking the place of the one in front of it. While
+    /// there's growth left the tail stays put, and the body is one segment longer. Returns the
+    /// cell the tail left, if it moved.
+    pub fn advance(&mut self, head: Position) -> Option<Position> {
+        self.cells.push_front(head);
+        if self.growth > 0 {
+            self.growth -= 1;
+            None
+        } else {
+            self.cells.pop_back()
+        }
+    }
+
+    /// Cuts off the segment at `position` and everything behind it, returning how many segments
+    /// went. Nothing's cut if no segment is there, or if it's the first one, since that would
+    /// leave only the head.
+    pub fn truncate_at(&mut self, position: Position) -> Option<usize> {
+        let index = self.segment_at(position).filter(|&index| index > 0)?;
+        let kept = index + 1;
+        let cut = self.cells.len() - kept;
+        self.cells.truncate(kept);
+        Some(cut)
+    }
+
+    /// Moves the whole body one cell towards `direction`, bringing every part that leaves the
+    /// arena back in on the far side if `wrap` is set. Returns whether any part is left outside.
+    pub fn shift(&mut self, direction: Direction, wrap: bool) -> bool {
+        let mut outside = false;
+        for cell in self.cells.iter_mut() {
+            *cell = cell.moved(direction);
+            if wrap {
+                *cell = cell.wrapped();
+            }
+            outside |= cell.outside_arena();
+        }
+        outside
+    }
+}
+
+/// Reads a snake's body by following the links from its first segment, or `None` if one of
+/// them is gone.
+pub fn read_body(
+    head: Position,
+    first_segment: Entity,
+    segments: &Query<(&SnakeSegment, &Position)>,
+) -> Option<SnakeBody> {
+    let mut cells = Vec::new();
+    let mut next = Some(first_segment);
+    while let Some(ent) = next {
+        cells.push(*segments.get::<Position>(ent).ok()?);
+        next = segments.get::<SnakeSegment>(ent).ok()?.next_segment;
+    }
+    Some(SnakeBody::new(head, cells, 0))
+}
+
+pub struct BodyPlugin;
+
+impl Plugin for BodyPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<BodyChange>()
+            .add_stage_after(stage::POST_UPDATE, BODY_CHANGES)
+            .add_system_to_stage(BODY_CHANGES, apply_body_changes.system());
+    }
+}
+
+/// Applies the frame's body changes, cuts and removals first. Growth onto a part that was cut
+/// off on the same frame goes with it, rather than being left in the arena on its own.
+fn apply_body_changes(
+    mut commands: Commands,
+    mut reader: Local<EventReader<BodyChange>>,
+    body_changes: Res<Events<BodyChange>>,
+    segment_material: Res<SegmentMaterial>,
+    heads: Query<&SnakeHead>,
+    segments: Query<&mut SnakeSegment>,
+) {
+    let changes: Vec<BodyChange> = reader.iter(&body_changes).copied().collect();
+    let mut despawned = HashSet::new();
+    for change in &changes {
+        match *change {
+            BodyChange::CutBehind {
+                new_tail,
+                first_cut,
+            } => {
+                if let Ok(mut segment) = segments.get_mut::<SnakeSegment>(new_tail) {
+                    if segment.next_segment == Some(first_cut) {
+                        segment.next_segment = None;
+                    }
+                }
+                despawn_chain(&mut commands, &mut despawned, first_cut, &segments);
+            }
+            BodyChange::Remove { head } => {
+                let first_segment = match heads.get::<SnakeHead>(head) {
+                    Ok(snake) => snake.next_segment,
+                    Err(_) => continue,
+                };
+                if despawned.insert(head) {
+                    commands.despawn(head);
+                }
+                despawn_chain(&mut commands, &mut despawned, first_segment, &segments);
+            }
+            BodyChange::Grow { .. } => {}
+        }
+    }
+
+    for change in &changes {
+        if let BodyChange::Grow {
+            tail,
+            position,
+            armored,
+        } = *change
+        {
+            if despawned.contains(&tail) {
+                continue;
+            }
+            let mut tail_segment = match segments.get_mut::<SnakeSegment>(tail) {
+                Ok(segment) => segment,
+                Err(_) => continue,
+            };
+            let new_segment = spawn_segment(
+                &mut commands,
+                segment_material.0,
+                position,
+                tail_segment.next_segment,
+            );
+            tail_segment.next_segment = Some(new_segment);
+            if armored {
+                commands.insert_one(new_segment, Armor);
+                commands.insert_one(new_segment, Size::square(ARMORED_SEGMENT_SIZE));
+            }
+        }
+    }
+}
+
+/// Despawns `first` and every segment after it that's still there.
+fn despawn_chain(
+    commands: &mut Commands,
+    despawned: &mut HashSet<Entity>,
+    first: Entity,
+    segments: &Query<&mut SnakeSegment>,
+) {
+    let mut next = Some(first);
+    while let Some(ent) = next {
+        next = match segments.get::<SnakeSegment>(ent) {
+            Ok(segment) => segment.next_segment,
+            Err(_) => return,
+        };
+        if !despawned.insert(ent) {
+            return;
+        }
+        commands.despawn(ent);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ARENA_WIDTH;
+
+    fn at(x: i32, y: i32) -> Position {
+        Position { x, y }
+    }
+
+    /// A snake lying along the bottom row, head at `x = 3` and its tail at `x = 0`.
+    fn straight(growth: u32) -> SnakeBody {
+        SnakeBody::new(at(3, 0), vec![at(2, 0), at(1, 0), at(0, 0)], growth)
+    }
+
+    #[test]
+    #[should_panic]
+    fn new_needs_a_segment() {
+        SnakeBody::new(at(3, 0), Vec::new(), 0);
+    }
+
+    #[test]
+    fn advance_moves_the_tail() {
+        let mut body = straight(0);
+        assert_eq!(body.advance(at(4, 0)), Some(at(0, 0)));
+        assert_eq!(body.head(), at(4, 0));
+        assert_eq!(body.tail(), at(1, 0));
+        assert_eq!(body.len(), 4);
+    }
+
+    #[test]
+    fn advance_grows_while_there_is_growth() {
+        let mut body = straight(1);
+        assert_eq!(body.advance(at(4, 0)), None);
+        assert_eq!(body.tail(), at(0, 0));
+        assert_eq!(body.len(), 5);
+        assert_eq!(body.growth(), 0);
+        assert_eq!(body.advance(at(5, 0)), Some(at(0, 0)));
+        assert_eq!(body.len(), 5);
+    }
+
+    #[test]
+    fn grow_adds_up() {
+        let mut body = straight(1);
+        body.grow(2);
+        body.grow(3);
+        assert_eq!(body.growth(), 6);
+    }
+
+    #[test]
+    fn truncate_at_keeps_the_first_segment() {
+        let mut body = straight(0);
+        assert_eq!(body.truncate_at(at(2, 0)), None);
+        assert_eq!(body.len(), 4);
+    }
+
+    #[test]
+    fn truncate_at_cuts_from_a_middle_segment() {
+        let mut body = straight(0);
+        assert_eq!(body.truncate_at(at(1, 0)), Some(2));
+        assert_eq!(body.len(), 2);
+        assert_eq!(body.tail(), at(2, 0));
+    }
+
+    #[test]
+    fn truncate_at_needs_a_segment_there() {
+        let mut body = straight(0);
+        assert_eq!(body.truncate_at(at(3, 0)), None);
+        assert_eq!(body.truncate_at(at(9, 9)), None);
+        assert_eq!(body.len(), 4);
+    }
+
+    #[test]
+    fn contains_the_head_but_segment_at_does_not() {
+        let body = straight(0);
+        assert!(body.contains(at(3, 0)));
+        assert!(body.contains(at(0, 0)));
+        assert!(!body.contains(at(4, 0)));
+        assert_eq!(body.segment_at(at(3, 0)), None);
+        assert_eq!(body.segment_at(at(2, 0)), Some(0));
+        assert_eq!(body.segment_at(at(0, 0)), Some(2));
+    }
+
+    #[test]
+    fn shift_without_wrap_leaves_parts_outside() {
+        let mut body = straight(0);
+        assert!(body.shift(Direction::Down, false));
+        assert_eq!(body.head(), at(3, -1));
+        assert!(!body.shift(Direction::Up, false));
+        assert_eq!(body.head(), at(3, 0));
+    }
+
+    #[test]
+    fn shift_with_wrap_brings_parts_back() {
+        let mut body = straight(0);
+        assert!(!body.shift(Direction::Left, true));
+        assert_eq!(body.head(), at(2, 0));
+        assert_eq!(body.tail(), at(ARENA_WIDTH as i32, 0));
+    }
+}
@@ -0,0 +1,84 @@
+use bevy::{input::mouse::MouseWheel, prelude::*};
+
+use crate::GameState;
+
+/// Pan speed of the free camera, in pixels per second at normal zoom.
+const PAN_SPEED: f32 = 600.0;
+
+/// How much one notch of the mouse wheel zooms.
+const ZOOM_STEP: f32 = 0.1;
+
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 2.0;
+
+/// The camera that draws the arena, as opposed to the UI camera.
+pub struct ArenaCamera;
+
+/// A camera detached from gameplay, for watching replays. WASD pans and the mouse wheel zooms.
+/// It snaps back to the normal view as soon as the replays are left.
+struct FreeCamera {
+    offset: Vec2,
+    /// Scale of the view; below 1 zooms in.
+    zoom: f32,
+}
+
+impl Default for FreeCamera {
+    fn default() -> Self {
+        Self {
+            offset: Vec2::zero(),
+            zoom: 1.0,
+        }
+    }
+}
+
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<FreeCamera>()
+            .add_system(free_camera.system());
+    }
+}
+
+fn free_camera(
+    mut wheel_reader: Local<EventReader<MouseWheel>>,
+    wheel_events: Res<Events<MouseWheel>>,
+    time: Res<Time>,
+    keyboard_input: Res<Input<KeyCode>>,
+    state: Res<GameState>,
+    mut camera: ResMut<FreeCamera>,
+    mut cameras: Query<(&ArenaCamera, &mut Transform)>,
+) {
+    let scrolled: f32 = wheel_reader.iter(&wheel_events).map(|event| event.y).sum();
+    if *state == GameState::Replays {
+        let mut direction = Vec2::zero();
+        for &(key, step) in &[
+            (KeyCode::A, Vec2::new(-1.0, 0.0)),
+            (KeyCode::D, Vec2::new(1.0, 0.0)),
+            (KeyCode::W, Vec2::new(0.0, 1.0)),
+            (KeyCode::S, Vec2::new(0.0, -1.0)),
+        ] {
+            if keyboard_input.pressed(key) {
+                direction += step;
+            }
+        }
+        // Pan at the same speed on screen however far in the view is zoomed.
+        camera.offset += direction * PAN_SPEED * camera.zoom * time.delta_seconds;
+        camera.zoom = (camera.zoom - scrolled * ZOOM_STEP)
+            .max(MIN_ZOOM)
+            .min(MAX_ZOOM);
+    } else {
+        *camera = FreeCamera::default();
+    }
+
+    for (_camera, mut transform) in &mut cameras.iter() {
+        let depth = transform.translation().z();
+        let translation = Vec3::new(camera.offset.x(), camera.offset.y(), depth);
+        if transform.translation() != translation {
+            transform.set_translation(translation);
+        }
+        if (transform.scale().x() - camera.zoom).abs() > f32::EPSILON {
+            transform.set_scale(camera.zoom);
+        }
+    }
+}
@@ -0,0 +1,504 @@
+use std::fs;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::{
+    dialog::Dialog,
+    game_event::GameEvent,
+    level::{ActiveLevel, RestartRun},
+    loading::LoadingAssets,
+    locale::Locale,
+    pack::{ActiveCampaign, Campaign, Pack},
+    profile::{ActiveProfile, ProfileStats},
+    ui::UiFonts,
+    GameOverCause, GameState, Run, Score,
+};
+
+/// The chapters of the campaign, in order.
+const MANIFEST_PATH: &str = "assets/campaign.ron";
+
+const MAP_FONT_SIZE: f32 = 24.0;
+
+const NODE_SIZE: f32 = 70.0;
+
+/// Space between neighbouring nodes, center to center.
+const NODE_SPACING: f32 = 110.0;
+
+/// Chapters longer than this carry on in another row.
+const NODES_PER_ROW: usize = 8;
+
+/// What a level's stars are worth: a bronze medal for one, silver for two and gold for three.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Medal {
+    Bronze,
+    Silver,
+    Gold,
+}
+
+impl Medal {
+    pub fn for_stars(stars: u8) -> Option<Self> {
+        match stars {
+            0 => None,
+            1 => Some(Self::Bronze),
+            2 => Some(Self::Silver),
+            _ => Some(Self::Gold),
+        }
+    }
+
+    pub fn name_key(self) -> &'static str {
+        match self {
+            Self::Bronze => "medal-bronze",
+            Self::Silver => "medal-silver",
+            Self::Gold => "medal-gold",
+        }
+    }
+}
+
+/// Stars earned out of three, e.g. ★★☆.
+pub fn star_text(stars: u8) -> String {
+    let stars = stars.min(3) as usize;
+    format!("{}{}", "★".repeat(stars), "☆".repeat(3 - stars))
+}
+
+/// A chapter as listed in the manifest.
+#[derive(Deserialize)]
+struct ChapterEntry {
+    /// Path of the chapter's pack.
+    pack: String,
+    /// Stars from the whole campaign needed to open the chapter.
+    #[serde(default)]
+    stars_needed: u32,
+}
+
+struct Chapter {
+    pack: Handle<Pack>,
+    stars_needed: u32,
+}
+
+/// The campaign: chapters played one after another, each a pack of levels played in order. A
+/// level opens once the one before it is completed, and a chapter once the last level of the
+/// chapter before it is and enough stars have been earned across the campaign.
+#[derive(Default)]
+struct CampaignMap {
+    chapters: Vec<Chapter>,
+    /// Chapter and level picked on the map.
+    selected: (usize, usize),
+    /// Set when the map changed and needs to be redrawn.
+    dirty: bool,
+}
+
+impl CampaignMap {
+    /// Stars earned on every level of the campaign.
+    fn total_stars(&self, packs: &Assets<Pack>, stats: &ProfileStats) -> u32 {
+        self.chapters
+            .iter()
+            .filter_map(|chapter| packs.get(&chapter.pack))
+            .flat_map(|pack| &pack.levels)
+            .filter_map(|level| stats.level_stars.get(&level.name))
+            .map(|&stars| u32::from(stars))
+            .sum()
+    }
+
+    fn chapter_unlocked(&self, packs: &Assets<Pack>, stats: &ProfileStats, chapter: usize) -> bool {
+        let previous = match chapter.checked_sub(1) {
+            Some(previous) => previous,
+            None => return true,
+        };
+        self.total_stars(packs, stats) >= self.chapters[chapter].stars_needed
+            && self.chapter_unlocked(packs, stats, previous)
+            && packs
+                .get(&self.chapters[previous].pack)
+                .and_then(|pack| pack.levels.last())
+                .map_or(false, |last| stats.completed(&last.name))
+    }
+
+    fn level_unlocked(
+        &self,
+        packs: &Assets<Pack>,
+        stats: &ProfileStats,
+        chapter: usize,
+        level: usize,
+    ) -> bool {
+        let pack = match packs.get(&self.chapters[chapter].pack) {
+            Some(pack) => pack,
+            None => return false,
+        };
+        self.chapter_unlocked(packs, stats, chapter)
+            && (level == 0 || stats.completed(&pack.levels[level - 1].name))
+    }
+}
+
+struct MapMaterials {
+    locked: Handle<ColorMaterial>,
+    open: Handle<ColorMaterial>,
+    completed: Handle<ColorMaterial>,
+    selected: Handle<ColorMaterial>,
+}
+
+struct MapPart;
+
+pub struct CampaignPlugin;
+
+impl Plugin for CampaignPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<CampaignMap>()
+            .add_startup_system(setup_campaign.system())
+            .add_system(record_stars.system())
+            .add_system(map_input.system())
+            .add_system(draw_map.system());
+    }
+}
+
+fn setup_campaign(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut loading: ResMut<LoadingAssets>,
+    mut map: ResMut<CampaignMap>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.insert_resource(MapMaterials {
+        locked: materials.add(Color::rgb(0.25, 0.25, 0.3).into()),
+        open: materials.add(Color::rgb(0.2, 0.45, 0.8).into()),
+        completed: materials.add(Color::rgb(0.85, 0.7, 0.2).into()),
+        selected: materials.add(Color::rgb(1.0, 1.0, 1.0).into()),
+    });
+
+    let text = match fs::read_to_string(MANIFEST_PATH) {
+        Ok(text) => text,
+        Err(_) => return,
+    };
+    let entries: Vec<ChapterEntry> = ron::de::from_str(&text).unwrap_or_else(|err| {
+        warn!("ignoring {}: {}", MANIFEST_PATH, err);
+        Vec::new()
+    });
+    for entry in entries {
+        match asset_server.load(entry.pack.as_str()) {
+            Ok(pack) => {
+                loading.track(entry.pack, pack);
+                map.chapters.push(Chapter {
+                    pack,
+                    stars_needed: entry.stars_needed,
+                });
+            }
+            Err(err) => warn!("skipping chapter {}: {:?}", entry.pack, err),
+        }
+    }
+}
+
+/// Awards stars for every level completed, whether it was played from the campaign, a pack or
+/// on its own, keeps the most earned in the profile, and pauses on the results.
+fn record_stars(
+    mut reader: Local<EventReader<GameEvent>>,
+    game_events: Res<Events<GameEvent>>,
+    level: Res<ActiveLevel>,
+    score: Res<Score>,
+    run: Res<Run>,
+    locale: Res<Locale>,
+    mut profile: ResMut<ActiveProfile>,
+    mut state: ResMut<GameState>,
+    mut dialogs: ResMut<Events<Dialog>>,
+) {
+    let completed = reader
+        .iter(&game_events)
+        .filter_map(GameEvent::game_over)
+        .any(|cause| matches!(cause, GameOverCause::LevelComplete));
+    let level = match (completed, &level.0) {
+        (true, Some(level)) => level,
+        _ => return,
+    };
+    let stars = level.stars_for(score.0, run.elapsed);
+    info!(level = %level.name, stars, "stars earned");
+    let mut lines = vec![
+        star_text(stars),
+        locale.format(
+            "results-score",
+            &[
+                ("score", score.0.to_string()),
+                ("seconds", format!("{:.0}", run.elapsed)),
+            ],
+        ),
+    ];
+    if let Some(medal) = Medal::for_stars(stars) {
+        lines.push(locale.format("results-medal", &[("medal", locale.text(medal.name_key()))]));
+    }
+    if let Some(profile) = &mut profile.0 {
+        let best = profile.stats.level_stars.get(&level.name).copied();
+        if best.map_or(true, |best| stars > best) {
+            lines.push(locale.text("results-new-best"));
+        }
+        profile.record_completion(&level.name, stars);
+    }
+    // What the next star would take.
+    if let Some(&needed) = level.star_scores.get(stars as usize) {
+        lines.push(match level.star_times {
+            Some(times) => locale.format(
+                "results-next-star-timed",
+                &[
+                    ("score", needed.to_string()),
+                    ("seconds", format!("{:.0}", times[stars as usize])),
+                ],
+            ),
+            None => locale.format("results-next-star", &[("score", needed.to_string())]),
+        });
+    }
+    dialogs.send(Dialog::new(
+        locale.format("results-title", &[("name", level.name.clone())]),
+        lines,
+    ));
+    *state = GameState::Paused;
+}
+
+/// C while paused opens the map, and closes it again. The arrow keys pick a level and Enter
+/// plays it, and the rest of its chapter after it, if it's open.
+fn map_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    packs: Res<Assets<Pack>>,
+    profile: Res<ActiveProfile>,
+    mut map: ResMut<CampaignMap>,
+    mut state: ResMut<GameState>,
+    mut level: ResMut<ActiveLevel>,
+    mut campaign: ResMut<ActiveCampaign>,
+    mut restart: ResMut<RestartRun>,
+) {
+    let pressed = |key| keyboard_input.just_pressed(key);
+    match *state {
+        GameState::Paused if pressed(KeyCode::C) => {
+            *state = GameState::Campaign;
+            return;
+        }
+        GameState::Campaign if pressed(KeyCode::C) => {
+            *state = GameState::Paused;
+            return;
+        }
+        GameState::Campaign => {}
+        _ => return,
+    }
+    if map.chapters.is_empty() {
+        return;
+    }
+
+    let levels_in = |chapter: usize| {
+        packs
+            .get(&map.chapters[chapter].pack)
+            .map_or(1, |pack| pack.levels.len().max(1))
+    };
+    let (mut chapter, mut index) = map.selected;
+    if pressed(KeyCode::Up) {
+        chapter = chapter.saturating_sub(1);
+    }
+    if pressed(KeyCode::Down) {
+        chapter = (chapter + 1).min(map.chapters.len() - 1);
+    }
+    if pressed(KeyCode::Left) {
+        index = index.saturating_sub(1);
+    }
+    if pressed(KeyCode::Right) {
+        index += 1;
+    }
+    index = index.min(levels_in(chapter) - 1);
+    if (chapter, index) != map.selected {
+        map.selected = (chapter, index);
+        map.dirty = true;
+    }
+    if !pressed(KeyCode::Return) {
+        return;
+    }
+
+    let default_stats = ProfileStats::default();
+    let stats = profile
+        .0
+        .as_ref()
+        .map_or(&default_stats, |profile| &profile.stats);
+    if !map.level_unlocked(&packs, stats, chapter, index) {
+        return;
+    }
+    let pack = match packs.get(&map.chapters[chapter].pack) {
+        Some(pack) if pack.validate().is_ok() => pack,
+        _ => return,
+    };
+    info!(pack = %pack.name, level = index, "campaign level chosen");
+    level.0 = Some(pack.levels[index].clone());
+    campaign.0 = Some(Campaign {
+        pack: map.chapters[chapter].pack,
+        level: index,
+        from_map: true,
+    });
+    restart.0 = true;
+    *state = GameState::Playing;
+}
+
+/// Redraws the map whenever it changes, a chapter finishes loading, or the game comes back to
+/// it with more levels completed.
+fn draw_map(
+    mut drawn_for: Local<Option<GameState>>,
+    mut pack_reader: Local<EventReader<AssetEvent<Pack>>>,
+    mut commands: Commands,
+    pack_events: Res<Events<AssetEvent<Pack>>>,
+    packs: Res<Assets<Pack>>,
+    state: Res<GameState>,
+    fonts: Res<UiFonts>,
+    locale: Res<Locale>,
+    profile: Res<ActiveProfile>,
+    materials: Res<MapMaterials>,
+    mut map: ResMut<CampaignMap>,
+    mut parts: Query<(Entity, &MapPart)>,
+) {
+    let packs_changed = pack_reader.iter(&pack_events).next().is_some();
+    if !map.dirty && !packs_changed && *drawn_for == Some(*state) {
+        return;
+    }
+    map.dirty = false;
+    *drawn_for = Some(*state);
+
+    for (ent, _part) in &mut parts.iter() {
+        commands.despawn(ent);
+    }
+    if *state != GameState::Campaign {
+        return;
+    }
+
+    let spawn_text = |commands: &mut Commands, text: String, left: f32, top: f32| {
+        commands
+            .spawn(TextComponents {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        left: Val::Px(left),
+                        top: Val::Px(top),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                text: fonts.text(text, MAP_FONT_SIZE),
+                ..Default::default()
+            })
+            .with(MapPart);
+    };
+    let node = |commands: &mut Commands, material, left: f32, top: f32, size: f32| {
+        commands
+            .spawn(NodeComponents {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        left: Val::Px(left),
+                        top: Val::Px(top),
+                        ..Default::default()
+                    },
+                    size: Size::new(Val::Px(size), Val::Px(size)),
+                    ..Default::default()
+                },
+                material,
+                ..Default::default()
+            })
+            .with(MapPart);
+    };
+
+    let default_stats = ProfileStats::default();
+    let stats = profile
+        .0
+        .as_ref()
+        .map_or(&default_stats, |profile| &profile.stats);
+    let total_stars = map.total_stars(&packs, stats);
+    let title = locale.format("campaign-title", &[("stars", total_stars.to_string())]);
+    spawn_text(&mut commands, title, 40.0, 20.0);
+    if map.chapters.is_empty() {
+        spawn_text(&mut commands, locale.text("campaign-empty"), 40.0, 60.0);
+        return;
+    }
+    let mut top = 70.0;
+    for (chapter, entry) in map.chapters.iter().enumerate() {
+        let pack = match packs.get(&entry.pack) {
+            Some(pack) => pack,
+            None => {
+                let text = locale.format(
+                    "campaign-chapter-loading",
+                    &[("number", (chapter + 1).to_string())],
+                );
+                spawn_text(&mut commands, text, 40.0, top);
+                top += MAP_FONT_SIZE + 20.0;
+                continue;
+            }
+        };
+        let title = locale.format(
+            "campaign-chapter",
+            &[
+                ("number", (chapter + 1).to_string()),
+                ("name", pack.name.clone()),
+            ],
+        );
+        spawn_text(&mut commands, title, 40.0, top);
+        top += MAP_FONT_SIZE + 16.0;
+
+        for (index, level) in pack.levels.iter().enumerate() {
+            let left = 60.0 + (index % NODES_PER_ROW) as f32 * NODE_SPACING;
+            let node_top = top + (index / NODES_PER_ROW) as f32 * NODE_SPACING;
+            if map.selected == (chapter, index) {
+                node(
+                    &mut commands,
+                    materials.selected,
+                    left - 5.0,
+                    node_top - 5.0,
+                    NODE_SIZE + 10.0,
+                );
+            }
+            let unlocked = map.level_unlocked(&packs, stats, chapter, index);
+            let stars = stats.level_stars.get(&level.name).copied();
+            let material = match (unlocked, stars) {
+                (false, _) => materials.locked,
+                (true, None) => materials.open,
+                (true, Some(_)) => materials.completed,
+            };
+            node(&mut commands, material, left, node_top, NODE_SIZE);
+            spawn_text(
+                &mut commands,
+                (index + 1).to_string(),
+                left + 8.0,
+                node_top + 6.0,
+            );
+            if let Some(stars) = stars {
+                let shown = star_text(stars);
+                spawn_text(&mut commands, shown, left + 2.0, node_top + NODE_SIZE + 4.0);
+            }
+        }
+        let rows = (pack.levels.len() + NODES_PER_ROW - 1) / NODES_PER_ROW;
+        top += rows as f32 * NODE_SPACING + 20.0;
+    }
+
+    // What the picked level is, or what it takes to open it.
+    let (chapter, index) = map.selected;
+    let picked = packs
+        .get(&map.chapters[chapter].pack)
+        .and_then(|pack| pack.levels.get(index));
+    if let Some(level) = picked {
+        let stars_needed = map.chapters[chapter].stars_needed;
+        let text = if map.level_unlocked(&packs, stats, chapter, index) {
+            let best = stats.level_stars.get(&level.name).copied();
+            let medal = best
+                .and_then(Medal::for_stars)
+                .map_or_else(|| "-".to_string(), |medal| locale.text(medal.name_key()));
+            locale.format(
+                "campaign-level-info",
+                &[
+                    ("name", level.name.clone()),
+                    ("difficulty", locale.text(level.difficulty.name_key())),
+                    ("medal", medal),
+                ],
+            )
+        } else if total_stars < stars_needed {
+            locale.format(
+                "campaign-locked-stars",
+                &[
+                    ("needed", stars_needed.to_string()),
+                    ("stars", total_stars.to_string()),
+                ],
+            )
+        } else {
+            locale.text("campaign-locked")
+        };
+        spawn_text(&mut commands, text, 40.0, top);
+        top += MAP_FONT_SIZE + 8.0;
+    }
+    spawn_text(&mut commands, locale.text("campaign-hint"), 40.0, top);
+}
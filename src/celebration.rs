@@ -0,0 +1,213 @@
+use bevy::{prelude::*, sprite::SpriteResizeMode};
+use rand::Rng;
+use tracing::info;
+
+use crate::{
+    accessibility::{AccessibilitySettings, Announcement},
+    config::KidMode,
+    game_event::GameEvent,
+    locale::Locale,
+    primary_window,
+    profile::ActiveProfile,
+    time_scale::TimeScale,
+    toast::Toast,
+    Position, Run, Score, ARENA_HEIGHT, ARENA_WIDTH,
+};
+
+const CONFETTI_PIECES: usize = 60;
+
+/// Confetti for every food in kid mode, so much less of it.
+const FOOD_CONFETTI_PIECES: usize = 12;
+
+/// How long each piece of confetti falls for, in seconds.
+const CONFETTI_DURATION: f32 = 2.5;
+
+/// Pull on falling confetti, in pixels per second squared.
+const CONFETTI_GRAVITY: f32 = 300.0;
+
+const CONFETTI_SIZE: f32 = 8.0;
+
+/// Above the arena sprites, which sit at 0.
+const CONFETTI_DEPTH: f32 = 0.5;
+
+/// Whether this run has beaten the active profile's high score. Set once per run, the first
+/// time the score passes the old best, and read by the HUD's "new best" badge.
+#[derive(Default)]
+pub struct NewBest(pub bool);
+
+struct Confetti {
+    velocity: Vec2,
+    timer: Timer,
+}
+
+struct ConfettiMaterials(Vec<Handle<ColorMaterial>>);
+
+pub struct CelebrationPlugin;
+
+impl Plugin for CelebrationPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<NewBest>()
+            .add_startup_system(setup_confetti.system())
+            .add_system(detect_new_best.system())
+            .add_system(food_confetti.system())
+            .add_system(animate_confetti.system());
+    }
+}
+
+fn setup_confetti(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+    let colors = [
+        Color::rgb(0.9, 0.62, 0.0),
+        Color::rgb(0.34, 0.71, 0.91),
+        Color::rgb(0.0, 0.62, 0.45),
+        Color::rgb(0.94, 0.89, 0.26),
+        Color::rgb(0.8, 0.47, 0.65),
+    ];
+    let materials = colors
+        .iter()
+        .map(|&color| materials.add(color.into()))
+        .collect();
+    commands.insert_resource(ConfettiMaterials(materials));
+}
+
+/// Celebrates the first time a run's score passes the old high score. Profiles without a high
+/// score yet don't get one, or every first run would trigger it.
+fn detect_new_best(
+    mut seen_seed: Local<Option<u64>>,
+    mut commands: Commands,
+    run: Res<Run>,
+    score: Res<Score>,
+    profile: Res<ActiveProfile>,
+    locale: Res<Locale>,
+    settings: Res<AccessibilitySettings>,
+    windows: Res<Windows>,
+    materials: Res<ConfettiMaterials>,
+    mut new_best: ResMut<NewBest>,
+    mut toasts: ResMut<Events<Toast>>,
+    mut announcements: ResMut<Events<Announcement>>,
+) {
+    if *seen_seed != Some(run.seed) {
+        *seen_seed = Some(run.seed);
+        new_best.0 = false;
+    }
+    let best = match &profile.0 {
+        Some(profile) => profile.stats.high_score,
+        None => return,
+    };
+    if new_best.0 || best == 0 || score.0 <= best {
+        return;
+    }
+    new_best.0 = true;
+    info!(score = score.0, previous = best, "new high score");
+    let text = locale.text("new-best");
+    toasts.send(Toast::new(text.clone()));
+    announcements.send(Announcement::essential(text));
+
+    if settings.reduced_motion {
+        return;
+    }
+    // Cosmetic, so it doesn't draw from the run's seeded generator.
+    let mut rng = rand::thread_rng();
+    let window = match primary_window(&windows) {
+        Some(window) => window,
+        None => return,
+    };
+    let half_width = window.width as f32 / 2.0;
+    let top = window.height as f32 / 2.0;
+    for i in 0..CONFETTI_PIECES {
+        let x = rng.gen_range(-half_width, half_width);
+        let velocity = Vec2::new(rng.gen_range(-80.0, 80.0), rng.gen_range(-150.0, 50.0));
+        spawn_confetti(
+            &mut commands,
+            materials.0[i % materials.0.len()],
+            Vec2::new(x, top),
+            velocity,
+        );
+    }
+}
+
+/// In kid mode, every food eaten bursts into confetti.
+fn food_confetti(
+    mut reader: Local<EventReader<GameEvent>>,
+    mut commands: Commands,
+    game_events: Res<Events<GameEvent>>,
+    kid_mode: Res<KidMode>,
+    settings: Res<AccessibilitySettings>,
+    windows: Res<Windows>,
+    materials: Res<ConfettiMaterials>,
+) {
+    let window = match primary_window(&windows) {
+        Some(window) => window,
+        None => return,
+    };
+    let mut rng = rand::thread_rng();
+    for event in reader.iter(&game_events) {
+        let position = match *event {
+            GameEvent::FoodEaten { position, .. } => position,
+            _ => continue,
+        };
+        if !kid_mode.0 || settings.reduced_motion {
+            continue;
+        }
+        let origin = cell_center(position, window);
+        for i in 0..FOOD_CONFETTI_PIECES {
+            let velocity = Vec2::new(rng.gen_range(-120.0, 120.0), rng.gen_range(100.0, 250.0));
+            spawn_confetti(
+                &mut commands,
+                materials.0[i % materials.0.len()],
+                origin,
+                velocity,
+            );
+        }
+    }
+}
+
+fn cell_center(position: Position, window: &Window) -> Vec2 {
+    let width = window.width as f32;
+    let height = window.height as f32;
+    Vec2::new(
+        position.x as f32 / ARENA_WIDTH as f32 * width - width / 2.0,
+        position.y as f32 / ARENA_HEIGHT as f32 * height - height / 2.0,
+    )
+}
+
+fn spawn_confetti(
+    commands: &mut Commands,
+    material: Handle<ColorMaterial>,
+    start: Vec2,
+    velocity: Vec2,
+) {
+    commands
+        .spawn(SpriteComponents {
+            material,
+            sprite: Sprite {
+                size: Vec2::new(CONFETTI_SIZE, CONFETTI_SIZE),
+                resize_mode: SpriteResizeMode::Manual,
+            },
+            transform: Transform::from_translation(Vec3::new(start.x(), start.y(), CONFETTI_DEPTH)),
+            ..Default::default()
+        })
+        .with(Confetti {
+            velocity,
+            timer: Timer::from_seconds(CONFETTI_DURATION, false),
+        });
+}
+
+fn animate_confetti(
+    mut commands: Commands,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    mut confetti: Query<(Entity, &mut Confetti, &mut Transform)>,
+) {
+    let delta = time_scale.delta(&time);
+    for (ent, mut piece, mut transform) in &mut confetti.iter() {
+        piece.timer.tick(delta);
+        if piece.timer.finished {
+            commands.despawn(ent);
+            continue;
+        }
+        *piece.velocity.y_mut() -= CONFETTI_GRAVITY * delta;
+        let step = piece.velocity * delta;
+        let translation = transform.translation() + Vec3::new(step.x(), step.y(), 0.0);
+        transform.set_translation(translation);
+    }
+}
@@ -0,0 +1,276 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use bevy::prelude::*;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::{
+    accessibility::{AccessibilitySettings, Palette, Verbosity},
+    heatmap::HeatmapExport,
+    input::{ControlPreset, InputSettings, SwapKey},
+    latency::LatencyOverlay,
+    level_browser::LevelIndexUrl,
+    locale::Locale,
+    profile::{ActiveProfile, Profile},
+    rules::GameRules,
+    session::SessionWebhook,
+    slow_motion::{SlowMotion, SlowMotionSettings},
+    telemetry::TelemetrySettings,
+    toast::Toast,
+    MoveInterval, SNAKE_MOVE_INTERVAL,
+};
+
+/// Used when the active profile has no config of its own.
+const CONFIG_PATH: &str = "config.ron";
+
+/// How often the config file is checked for changes, in seconds.
+const POLL_INTERVAL: f32 = 1.0;
+
+const MIN_MOVE_INTERVAL_MS: u64 = 30;
+const MAX_MOVE_INTERVAL_MS: u64 = 2000;
+
+/// Kid mode never moves faster than this.
+const KID_MOVE_INTERVAL_MS: u64 = 300;
+
+/// Kid mode always has at least this much food out.
+const KID_MIN_FOOD: u32 = 3;
+
+/// Whether kid mode is on, for the effects it adds beyond other settings.
+#[derive(Default)]
+pub struct KidMode(pub bool);
+
+/// Whether zen mode is on, for the effects it adds beyond other settings.
+#[derive(Default)]
+pub struct ZenMode(pub bool);
+
+/// Settings read from `config.ron`. Only settings that are safe to change mid-run belong here,
+/// since the file is re-applied whenever it changes. Missing fields keep their defaults.
+#[derive(Deserialize)]
+#[serde(default)]
+struct Config {
+    palette: Palette,
+    reduced_motion: bool,
+    verbosity: Verbosity,
+    danger_preview: bool,
+    /// Slows the game down for a moment when the snake is about to crash. `None` turns it off.
+    slow_motion: Option<SlowMotion>,
+    controls: ControlPreset,
+    /// Preset that `swap_controls_key` switches to and back.
+    alternate_controls: Option<ControlPreset>,
+    swap_controls_key: SwapKey,
+    /// Two players steer one snake, one along each axis.
+    co_op: bool,
+    /// Time between snake moves, in milliseconds.
+    move_interval_ms: u64,
+    rules: GameRules,
+    /// Slows the snake down, turns on the forgiving rules and the high-contrast palette, and
+    /// celebrates every food. Overrides the settings it covers.
+    kid_mode: bool,
+    /// Takes away the score, the HUD and every way to die, and tints the background. Overrides
+    /// the rules it covers.
+    zen_mode: bool,
+    /// Turns off the assists that warn about or slow down a crash, and the slow start.
+    hardcore: bool,
+    /// Saves a PNG of where the snake went at the end of every run.
+    export_heatmap: bool,
+    /// Shows how long turns take to go from key press to the screen, for tuning input.
+    latency_overlay: bool,
+    /// URL of a list of levels the level browser downloads from.
+    level_index: Option<String>,
+    /// Counts modes, run lengths and how runs end, with nothing that says who played.
+    telemetry: bool,
+    /// Where the counts are sent every few runs while telemetry is on. Without one they're
+    /// only kept on this computer.
+    telemetry_url: Option<String>,
+    /// Gets a summary of each session as JSON, e.g. for a family scoreboard in a chat channel.
+    session_webhook: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            palette: Palette::default(),
+            reduced_motion: false,
+            verbosity: Verbosity::default(),
+            danger_preview: false,
+            slow_motion: Some(SlowMotion::default()),
+            controls: ControlPreset::default(),
+            alternate_controls: None,
+            swap_controls_key: SwapKey::default(),
+            co_op: false,
+            move_interval_ms: SNAKE_MOVE_INTERVAL.as_millis() as u64,
+            rules: GameRules::default(),
+            kid_mode: false,
+            zen_mode: false,
+            hardcore: false,
+            export_heatmap: false,
+            latency_overlay: false,
+            level_index: None,
+            telemetry: false,
+            telemetry_url: None,
+            session_webhook: None,
+        }
+    }
+}
+
+impl Config {
+    fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let config: Self = ron::de::from_str(&text).map_err(|err| err.to_string())?;
+        config.validate()?;
+        Ok(config.with_modes())
+    }
+
+    /// Applies the settings kid mode, zen mode and hardcore override.
+    fn with_modes(mut self) -> Self {
+        if self.kid_mode {
+            self.palette = Palette::HighContrast;
+            self.move_interval_ms = self.move_interval_ms.max(KID_MOVE_INTERVAL_MS);
+            self.rules.wall_bounce = true;
+            self.rules.tail_cut = true;
+            self.rules.slow_start = true;
+            let pacing = &mut self.rules.food_pacing;
+            pacing.min_food = pacing.min_food.max(KID_MIN_FOOD);
+            pacing.max_food = pacing.max_food.max(KID_MIN_FOOD);
+        }
+        if self.zen_mode {
+            self.rules.wrap_walls = true;
+            self.rules.tail_cut = true;
+        }
+        if self.hardcore {
+            self.danger_preview = false;
+            self.slow_motion = None;
+            self.rules.slow_start = false;
+        }
+        self
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.move_interval_ms < MIN_MOVE_INTERVAL_MS
+            || self.move_interval_ms > MAX_MOVE_INTERVAL_MS
+        {
+            return Err(format!(
+                "move_interval_ms must be between {} and {}, got {}",
+                MIN_MOVE_INTERVAL_MS, MAX_MOVE_INTERVAL_MS, self.move_interval_ms
+            ));
+        }
+        if let Some(slow_motion) = &self.slow_motion {
+            slow_motion.validate()?;
+        }
+        self.rules.food_pacing.validate()
+    }
+}
+
+struct ConfigWatch {
+    poll_timer: Timer,
+    checked: bool,
+    /// Config path of the profile the watch was last checked for.
+    profile_path: Option<PathBuf>,
+    modified: Option<(PathBuf, SystemTime)>,
+}
+
+impl Default for ConfigWatch {
+    fn default() -> Self {
+        Self {
+            poll_timer: Timer::from_seconds(POLL_INTERVAL, true),
+            checked: false,
+            profile_path: None,
+            modified: None,
+        }
+    }
+}
+
+pub struct ConfigPlugin;
+
+impl Plugin for ConfigPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<KidMode>()
+            .init_resource::<ZenMode>()
+            .init_resource::<ConfigWatch>()
+            .add_system(poll_config.system())
+            .add_system(watch_config.system());
+    }
+}
+
+/// Runs the watch's poll timer. It's kept out of `watch_config`, which takes as many
+/// resources as a system can.
+fn poll_config(time: Res<Time>, mut watch: ResMut<ConfigWatch>) {
+    watch.poll_timer.tick(time.delta_seconds);
+}
+
+/// Applies the config file on the first frame, then again whenever its modification time
+/// changes or a profile is picked. The active profile's own config takes precedence over the
+/// shared one. Invalid files are reported and ignored, leaving the current settings in place.
+fn watch_config(
+    mut watch: ResMut<ConfigWatch>,
+    locale: Res<Locale>,
+    profile: Res<ActiveProfile>,
+    mut accessibility: ResMut<AccessibilitySettings>,
+    mut input: ResMut<InputSettings>,
+    mut move_interval: ResMut<MoveInterval>,
+    mut rules: ResMut<GameRules>,
+    mut kid_mode: ResMut<KidMode>,
+    mut zen_mode: ResMut<ZenMode>,
+    mut slow_motion: ResMut<SlowMotionSettings>,
+    mut heatmap_export: ResMut<HeatmapExport>,
+    mut latency: ResMut<LatencyOverlay>,
+    mut level_index: ResMut<LevelIndexUrl>,
+    mut telemetry: ResMut<TelemetrySettings>,
+    mut session_webhook: ResMut<SessionWebhook>,
+    mut toasts: ResMut<Events<Toast>>,
+) {
+    let profile_path = profile.0.as_ref().map(Profile::config_path);
+    // Switching profiles applies the new config right away and silently, like startup.
+    let first_check = !watch.checked || watch.profile_path != profile_path;
+    if !first_check && !watch.poll_timer.finished {
+        return;
+    }
+    watch.checked = true;
+    watch.profile_path = profile_path.clone();
+
+    let path = profile_path
+        .filter(|path| path.exists())
+        .unwrap_or_else(|| PathBuf::from(CONFIG_PATH));
+    let modified = match fs::metadata(&path).and_then(|metadata| metadata.modified()) {
+        Ok(modified) => (path, modified),
+        Err(_) => return,
+    };
+    if watch.modified.as_ref() == Some(&modified) {
+        return;
+    }
+    let path = modified.0.clone();
+    watch.modified = Some(modified);
+
+    match Config::load(&path) {
+        Ok(config) => {
+            accessibility.palette = config.palette;
+            accessibility.reduced_motion = config.reduced_motion;
+            accessibility.verbosity = config.verbosity;
+            accessibility.danger_preview = config.danger_preview;
+            slow_motion.0 = config.slow_motion;
+            input.preset = config.controls;
+            input.alternate_preset = config.alternate_controls;
+            input.swap_key = config.swap_controls_key;
+            input.co_op = config.co_op;
+            move_interval.0 = config.move_interval_ms as f32 / 1000.0;
+            *rules = config.rules;
+            kid_mode.0 = config.kid_mode;
+            zen_mode.0 = config.zen_mode;
+            heatmap_export.0 = config.export_heatmap;
+            latency.open = config.latency_overlay;
+            level_index.0 = config.level_index;
+            telemetry.enabled = config.telemetry;
+            telemetry.upload_url = config.telemetry_url;
+            session_webhook.0 = config.session_webhook;
+            info!("applied {}", path.display());
+            if !first_check {
+                toasts.send(Toast::new(locale.text("config-reloaded")));
+            }
+        }
+        Err(err) => warn!("ignoring {}: {}", path.display(), err),
+    }
+}
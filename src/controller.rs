@@ -0,0 +1,108 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+
+use crate::{
+    input::Action, split::Ally, Dead, Direction, Food, GameState, Position, SnakeHead, SnakeSegment,
+};
+
+/// What a controller sees of the arena when it's asked how to steer.
+pub struct ControlFrame<'a> {
+    pub head: Position,
+    pub direction: Direction,
+    /// Turns the player asked for this frame, from any device.
+    pub player_turns: &'a [Direction],
+    pub food: &'a [Position],
+    /// Every cell a segment is on.
+    pub blocked: &'a HashSet<Position>,
+    /// Which way each of the player's heads is going, and where it is.
+    pub players: &'a [(Direction, Position)],
+}
+
+/// Whatever steers a snake: the player, through the keyboard, the d-pad, swipes or the mouse,
+/// or the computer. Something like a replay or a remote player would be one more of these.
+/// Controllers only ever ask for turns, and `snake_movement` checks and applies them the same
+/// way whoever asked.
+pub trait Controller: Send + Sync + 'static {
+    /// Adds the turns wanted on this frame to `queue`.
+    fn steer(&mut self, frame: &ControlFrame, queue: &mut InputQueue);
+}
+
+/// The controller steering a snake. Every head has one.
+pub struct Controlled(pub Box<dyn Controller>);
+
+/// Turns a snake's controller asked for that its head hasn't queued yet. `snake_movement` takes
+/// its turns from here and nowhere else.
+#[derive(Default)]
+pub struct InputQueue(Vec<Direction>);
+
+impl InputQueue {
+    pub fn push(&mut self, direction: Direction) {
+        self.0.push(direction);
+    }
+
+    pub fn take(&mut self) -> Vec<Direction> {
+        std::mem::take(&mut self.0)
+    }
+}
+
+/// Steers with the player's turn actions, whichever device they came from.
+pub struct PlayerController;
+
+impl Controller for PlayerController {
+    fn steer(&mut self, frame: &ControlFrame, queue: &mut InputQueue) {
+        for &direction in frame.player_turns {
+            queue.push(direction);
+        }
+    }
+}
+
+pub struct ControllerPlugin;
+
+impl Plugin for ControllerPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(drive_controllers.system());
+    }
+}
+
+/// Asks every snake's controller for its turns.
+fn drive_controllers(
+    mut action_reader: Local<EventReader<Action>>,
+    actions: Res<Events<Action>>,
+    state: Res<GameState>,
+    mut heads: Query<Without<Dead, (&SnakeHead, &Position, &mut Controlled, &mut InputQueue)>>,
+    mut players: Query<Without<Ally, (&SnakeHead, &Position)>>,
+    mut food: Query<(&Food, &Position)>,
+    mut segments: Query<(&SnakeSegment, &Position)>,
+) {
+    // Drain turns even when not playing so they don't apply all at once on resume.
+    let player_turns: Vec<Direction> = action_reader
+        .iter(&actions)
+        .filter_map(|action| match action {
+            Action::Turn(dir) => Some(*dir),
+            _ => None,
+        })
+        .collect();
+    if *state != GameState::Playing {
+        return;
+    }
+
+    let food: Vec<Position> = food.iter().iter().map(|(_food, pos)| *pos).collect();
+    let blocked: HashSet<Position> = segments.iter().iter().map(|(_seg, pos)| *pos).collect();
+    let players: Vec<(Direction, Position)> = players
+        .iter()
+        .iter()
+        .map(|(head, pos)| (head.direction, *pos))
+        .collect();
+    for (head, position, mut controlled, mut queue) in &mut heads.iter() {
+        let frame = ControlFrame {
+            head: *position,
+            direction: head.direction,
+            player_turns: &player_turns,
+            food: &food,
+            blocked: &blocked,
+            players: &players,
+        };
+        controlled.0.steer(&frame, &mut queue);
+    }
+}
@@ -0,0 +1,225 @@
+use std::{
+    collections::VecDeque,
+    fs, panic,
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError, TryLockError},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    accessibility::Announcement,
+    despawn_arena,
+    input::Action,
+    locale::Locale,
+    power_up::PowerUp,
+    save_format::{self, Versioned},
+    snapshot::Snapshot,
+    split::Ally,
+    toast::Toast,
+    Food, FoodMaterial, HeadMaterial, Position, Run, Score, SegmentMaterial, SnakeHead,
+    SnakeSegment,
+};
+
+const CRASH_DIR: &str = "crash";
+
+/// Number of recent actions kept for the crash dump.
+const INPUT_HISTORY: usize = 128;
+
+/// What gets written to the crash folder when the game panics.
+#[derive(Default, Serialize, Deserialize)]
+struct CrashDump {
+    snapshot: Option<Snapshot>,
+    /// Most recent actions, tagged with the tick they arrived on.
+    inputs: VecDeque<(u64, Action)>,
+}
+
+impl Versioned for CrashDump {
+    const KIND: &'static str = "crash-dump";
+    const VERSION: u32 = 1;
+
+    fn migrate(version: u32, body: &str) -> Result<Self, String> {
+        match version {
+            // Dumps from before the header have the same layout.
+            0 => ron::de::from_str(body).map_err(|err| err.to_string()),
+            _ => Err(format!("can't read crash dump version {}", version)),
+        }
+    }
+}
+
+/// Kept outside the ECS so the panic hook can reach it.
+static LATEST: Mutex<Option<CrashDump>> = Mutex::new(None);
+
+/// A dump from an earlier session's crash, waiting for the player to restore or discard it.
+#[derive(Default)]
+struct PendingRestore(Option<(PathBuf, Snapshot)>);
+
+/// Chains onto the default panic hook to write the latest snapshot and input history to the
+/// crash folder.
+pub fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        match write_dump() {
+            Ok(Some(path)) => eprintln!("crash dump written to {}", path.display()),
+            Ok(None) => {}
+            Err(err) => eprintln!("failed to write crash dump: {}", err),
+        }
+    }));
+}
+
+fn write_dump() -> Result<Option<PathBuf>, String> {
+    // The panicking thread may be the one holding the lock, so don't wait for it.
+    let latest = match LATEST.try_lock() {
+        Ok(latest) => latest,
+        Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner(),
+        Err(TryLockError::WouldBlock) => return Ok(None),
+    };
+    let dump = match &*latest {
+        Some(dump) => dump,
+        None => return Ok(None),
+    };
+
+    let text = save_format::encode(dump)?;
+    fs::create_dir_all(CRASH_DIR).map_err(|err| err.to_string())?;
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let path = Path::new(CRASH_DIR).join(format!("crash-{}.ron", timestamp));
+    fs::write(&path, text).map_err(|err| err.to_string())?;
+    Ok(Some(path))
+}
+
+/// Finds the newest dump that hasn't been restored or discarded yet.
+fn find_pending_dump() -> Option<(PathBuf, Snapshot)> {
+    let mut newest: Option<(SystemTime, PathBuf)> = None;
+    for entry in fs::read_dir(CRASH_DIR).ok()?.flatten() {
+        let path = entry.path();
+        if path.extension().map_or(true, |ext| ext != "ron") {
+            continue;
+        }
+        if let Ok(modified) = entry.metadata().and_then(|metadata| metadata.modified()) {
+            if newest
+                .as_ref()
+                .map_or(true, |(time, _path)| modified > *time)
+            {
+                newest = Some((modified, path));
+            }
+        }
+    }
+
+    let (_modified, path) = newest?;
+    let text = fs::read_to_string(&path).ok()?;
+    match save_format::decode::<CrashDump>(&text) {
+        Ok(dump) => dump.snapshot.map(|snapshot| (path, snapshot)),
+        Err(err) => {
+            warn!("unreadable crash dump {}: {}", path.display(), err);
+            None
+        }
+    }
+}
+
+pub struct CrashPlugin;
+
+impl Plugin for CrashPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<PendingRestore>()
+            .add_startup_system(offer_restore.system())
+            .add_system(record_crash_state.system())
+            .add_system(handle_restore.system());
+    }
+}
+
+fn offer_restore(
+    locale: Res<Locale>,
+    mut pending: ResMut<PendingRestore>,
+    mut toasts: ResMut<Events<Toast>>,
+    mut announcements: ResMut<Events<Announcement>>,
+) {
+    pending.0 = find_pending_dump();
+    if pending.0.is_some() {
+        let text = locale.text("crash-found");
+        toasts.send(Toast::new(text.clone()));
+        announcements.send(Announcement::essential(text));
+    }
+}
+
+fn record_crash_state(
+    mut reader: Local<EventReader<Action>>,
+    actions: Res<Events<Action>>,
+    run: Res<Run>,
+    score: Res<Score>,
+    mut heads: Query<Without<Ally, (&SnakeHead, &Position)>>,
+    segments: Query<(&SnakeSegment, &Position)>,
+    mut food: Query<(&Food, &Position)>,
+) {
+    let mut latest = LATEST.lock().unwrap_or_else(PoisonError::into_inner);
+    let dump = latest.get_or_insert_with(CrashDump::default);
+
+    // Held keys repeat every frame, so only keep actions that change something.
+    for &action in reader.iter(&actions) {
+        if dump.inputs.back().map(|&(_tick, last)| last) == Some(action) {
+            continue;
+        }
+        if dump.inputs.len() == INPUT_HISTORY {
+            dump.inputs.pop_front();
+        }
+        dump.inputs.push_back((run.tick, action));
+    }
+
+    if let Some(snapshot) = Snapshot::capture(&run, &score, &mut heads, &segments, &mut food) {
+        dump.snapshot = Some(snapshot);
+    }
+}
+
+/// F9 restores the pending crashed run and F10 discards it.
+fn handle_restore(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut pending: ResMut<PendingRestore>,
+    head_material: Res<HeadMaterial>,
+    segment_material: Res<SegmentMaterial>,
+    food_material: Res<FoodMaterial>,
+    mut run: ResMut<Run>,
+    mut score: ResMut<Score>,
+    mut segments: Query<(Entity, &SnakeSegment)>,
+    mut food: Query<(Entity, &Food)>,
+    mut power_ups: Query<(Entity, &PowerUp)>,
+    mut heads: Query<(Entity, &SnakeHead)>,
+) {
+    let restore = keyboard_input.just_pressed(KeyCode::F9);
+    if !restore && !keyboard_input.just_pressed(KeyCode::F10) {
+        return;
+    }
+    let (path, snapshot) = match pending.0.take() {
+        Some(pending) => pending,
+        None => return,
+    };
+
+    if restore {
+        despawn_arena(
+            &mut commands,
+            &mut segments,
+            &mut food,
+            &mut power_ups,
+            &mut heads,
+        );
+        snapshot.restore(
+            &mut commands,
+            &head_material,
+            &segment_material,
+            &food_material,
+            &mut run,
+            &mut score,
+        );
+        info!(seed = run.seed, tick = run.tick, "restored crashed run");
+    }
+
+    // Keep the dump around for bug reports, but don't offer it again.
+    if let Err(err) = fs::rename(&path, path.with_extension("ron.handled")) {
+        warn!("could not retire crash dump {}: {}", path.display(), err);
+    }
+}
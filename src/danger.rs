@@ -0,0 +1,172 @@
+use bevy::prelude::*;
+
+use crate::{
+    accessibility::AccessibilitySettings,
+    level::ActiveLevel,
+    obstacle::SwitchState,
+    sized_sprite, snake_length,
+    split::Ally,
+    tiles::{TileKind, Tiles},
+    turn_allowed, Armor, Dead, Direction, GameState, Position, Run, Size, SnakeHead, SnakeSegment,
+    ARENA_HEIGHT, ARENA_WIDTH, BOUNCE_WINDOW, MAX_BOUNCES,
+};
+
+/// Above the arena sprites, which sit at 0, and below confetti.
+const MARKER_DEPTH: f32 = 0.3;
+
+/// Cells the player's heads would die in on the next move if nothing changes, worked out
+/// every frame. Empty when every head is safe.
+#[derive(Default)]
+pub struct Lookahead {
+    pub fatal_cells: Vec<Position>,
+}
+
+/// Marks a fatal cell when the danger preview assist is on.
+struct DangerMarker;
+
+struct DangerMaterial(Handle<ColorMaterial>);
+
+pub struct DangerPlugin;
+
+impl Plugin for DangerPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Lookahead>()
+            .add_startup_system(setup_danger_material.system())
+            .add_system(look_ahead.system())
+            .add_system(show_danger.system());
+    }
+}
+
+fn setup_danger_material(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+    commands.insert_resource(DangerMaterial(
+        materials.add(Color::rgba(0.9, 0.1, 0.1, 0.6).into()),
+    ));
+}
+
+/// The direction a head will move in next, after any turns due by then. Mirrors how
+/// `snake_movement` applies turns, including ice dropping them.
+fn next_direction(
+    head: &SnakeHead,
+    position: Position,
+    length: u32,
+    tick: u64,
+    tiles: &Tiles,
+) -> Direction {
+    if tiles.get(&position) == Some(TileKind::Ice) {
+        return head.direction;
+    }
+    let mut direction = head.direction;
+    for &(due, turn) in &head.pending_turns {
+        if due > tick + 1 {
+            break;
+        }
+        if turn_allowed(direction, turn, length) {
+            direction = turn;
+        }
+    }
+    direction
+}
+
+/// Finds the cell each of the player's heads moves into next and whether it would end the
+/// run there, on a wall, an obstacle, a closed door or the body. The tail moves out of the way
+/// unless the snake is growing, armor absorbs a hit, and the forgiving rules make walls or the
+/// tail safe.
+fn look_ahead(
+    run: Res<Run>,
+    tiles: Res<Tiles>,
+    level: Res<ActiveLevel>,
+    switch_state: Res<SwitchState>,
+    mut lookahead: ResMut<Lookahead>,
+    mut heads: Query<Without<Ally, Without<Dead, (&SnakeHead, &Position)>>>,
+    segments: Query<(&SnakeSegment, &Position, Option<&Armor>)>,
+    links: Query<&SnakeSegment>,
+) {
+    lookahead.fatal_cells.clear();
+    for (head, position) in &mut heads.iter() {
+        let length = snake_length(head.next_segment, &links);
+        let direction = next_direction(head, *position, length, run.tick, &tiles);
+        let mut next = position.moved(direction);
+        if head.wraps_at_walls {
+            next = next.wrapped();
+        }
+        // Obstacles are checked where they'll be once the snake has moved.
+        let tick = run.tick + 1;
+        let blocked = next.outside_arena()
+            || level.0.as_ref().map_or(false, |level| {
+                level.is_wall(next) || switch_state.is_blocked(level, next, tick)
+            });
+        if blocked {
+            let bounces = head
+                .recent_bounces
+                .iter()
+                .filter(|&&bounce| bounce + BOUNCE_WINDOW > tick)
+                .count();
+            if !head.bounces_off_walls || bounces >= MAX_BOUNCES {
+                lookahead.fatal_cells.push(next);
+            }
+            continue;
+        }
+        if head.bites_own_tail {
+            continue;
+        }
+        let mut segment = Some(head.next_segment);
+        while let Some(ent) = segment {
+            let following = segments
+                .get::<SnakeSegment>(ent)
+                .ok()
+                .and_then(|segment| segment.next_segment);
+            let is_tail = following.is_none();
+            let occupied = segments
+                .get::<Position>(ent)
+                .map_or(false, |pos| *pos == next);
+            if occupied && (!is_tail || head.pending_growth > 0) {
+                if segments.get::<Armor>(ent).is_err() {
+                    lookahead.fatal_cells.push(next);
+                }
+                break;
+            }
+            segment = following;
+        }
+    }
+}
+
+/// Keeps one marker on every fatal cell while the assist is on and a run is going.
+fn show_danger(
+    mut shown: Local<Vec<Position>>,
+    mut commands: Commands,
+    state: Res<GameState>,
+    settings: Res<AccessibilitySettings>,
+    lookahead: Res<Lookahead>,
+    material: Res<DangerMaterial>,
+    mut markers: Query<(Entity, &DangerMarker)>,
+) {
+    let cells =
+        if settings.danger_preview && matches!(*state, GameState::Playing | GameState::Paused) {
+            lookahead.fatal_cells.clone()
+        } else {
+            Vec::new()
+        };
+    if *shown == cells {
+        return;
+    }
+    *shown = cells.clone();
+
+    for (ent, _marker) in &mut markers.iter() {
+        commands.despawn(ent);
+    }
+    for cell in cells {
+        // Walls are just outside the arena, so mark the edge the head is about to leave.
+        let cell = Position {
+            x: cell.x.max(0).min(ARENA_WIDTH as i32),
+            y: cell.y.max(0).min(ARENA_HEIGHT as i32),
+        };
+        commands
+            .spawn(SpriteComponents {
+                transform: Transform::from_translation(Vec3::new(0.0, 0.0, MARKER_DEPTH)),
+                ..sized_sprite(material.0)
+            })
+            .with(DangerMarker)
+            .with(cell)
+            .with(Size::square(1.0));
+    }
+}
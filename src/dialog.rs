@@ -0,0 +1,144 @@
+use bevy::prelude::*;
+
+use crate::{locale::Locale, ui::UiFonts};
+
+const DIALOG_FONT_SIZE: f32 = 24.0;
+
+const DIALOG_WIDTH: f32 = 760.0;
+
+/// Above everything in the arena.
+const DIALOG_TOP: f32 = 300.0;
+
+/// A message that stays on screen until the player closes it with Enter, for problems they
+/// need to read rather than glance at, like why a level won't load. Any system can send one;
+/// a newer dialog replaces the one showing.
+#[derive(Clone, PartialEq)]
+pub struct Dialog {
+    pub title: String,
+    pub lines: Vec<String>,
+}
+
+impl Dialog {
+    pub fn new(title: impl Into<String>, lines: Vec<String>) -> Self {
+        Self {
+            title: title.into(),
+            lines,
+        }
+    }
+}
+
+/// The dialog showing, if any. Menus that also use Enter check `is_open` first, so the key
+/// that closes a dialog doesn't go on to pick something behind it.
+#[derive(Default)]
+pub struct OpenDialog(Option<Dialog>);
+
+impl OpenDialog {
+    pub fn is_open(&self) -> bool {
+        self.0.is_some()
+    }
+}
+
+struct DialogPart;
+
+struct DialogMaterial(Handle<ColorMaterial>);
+
+pub struct DialogPlugin;
+
+impl Plugin for DialogPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<Dialog>()
+            .init_resource::<OpenDialog>()
+            .add_startup_system(setup_dialog_material.system())
+            .add_system(open_dialogs.system())
+            .add_system(draw_dialog.system())
+            // After every other system has seen this frame's Enter as meant for the dialog.
+            .add_system_to_stage(stage::POST_UPDATE, close_dialog.system());
+    }
+}
+
+fn setup_dialog_material(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+    commands.insert_resource(DialogMaterial(
+        materials.add(Color::rgba(0.05, 0.05, 0.1, 0.9).into()),
+    ));
+}
+
+fn open_dialogs(
+    mut reader: Local<EventReader<Dialog>>,
+    dialogs: Res<Events<Dialog>>,
+    mut open: ResMut<OpenDialog>,
+) {
+    if let Some(dialog) = reader.iter(&dialogs).last() {
+        open.0 = Some(dialog.clone());
+    }
+}
+
+fn close_dialog(keyboard_input: Res<Input<KeyCode>>, mut open: ResMut<OpenDialog>) {
+    if keyboard_input.just_pressed(KeyCode::Return) {
+        open.0 = None;
+    }
+}
+
+/// Redraws the dialog whenever it opens, closes or is replaced.
+fn draw_dialog(
+    mut drawn: Local<Option<Dialog>>,
+    mut commands: Commands,
+    fonts: Res<UiFonts>,
+    locale: Res<Locale>,
+    material: Res<DialogMaterial>,
+    open: Res<OpenDialog>,
+    mut parts: Query<(Entity, &DialogPart)>,
+) {
+    if *drawn == open.0 {
+        return;
+    }
+    *drawn = open.0.clone();
+
+    for (ent, _part) in &mut parts.iter() {
+        commands.despawn_recursive(ent);
+    }
+    let dialog = match &open.0 {
+        Some(dialog) => dialog,
+        None => return,
+    };
+    let mut text = vec![dialog.title.clone()];
+    text.extend(dialog.lines.iter().cloned());
+    text.push(locale.text("dialog-close"));
+
+    let row_height = DIALOG_FONT_SIZE + 8.0;
+    commands
+        .spawn(NodeComponents {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(120.0),
+                    top: Val::Px(DIALOG_TOP),
+                    ..Default::default()
+                },
+                size: Size::new(
+                    Val::Px(DIALOG_WIDTH),
+                    Val::Px(20.0 + text.len() as f32 * row_height),
+                ),
+                ..Default::default()
+            },
+            material: material.0,
+            ..Default::default()
+        })
+        .with(DialogPart);
+    for (row, line) in text.into_iter().enumerate() {
+        commands
+            .spawn(TextComponents {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        left: Val::Px(140.0),
+                        top: Val::Px(DIALOG_TOP + 10.0 + row as f32 * row_height),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                text: fonts.text(line, DIALOG_FONT_SIZE),
+                ..Default::default()
+            })
+            .with(DialogPart);
+    }
+}
@@ -0,0 +1,326 @@
+use std::collections::{HashSet, VecDeque};
+
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::{
+    game_event::GameEvent, level::ActiveLevel, rules::GameRules, spawn_food, split::Ally,
+    time_scale::TimeScale, Armor, Direction, Food, FoodMaterial, GameState, Position, Run, Size,
+    SnakeHead, SnakeSegment, SuperGrowth, ARENA_HEIGHT, ARENA_WIDTH, ARMORED_FOOD_SIZE,
+    ARMOR_FOOD_CHANCE, SUPER_GROWTH_FOOD_CHANCE,
+};
+
+/// How far back, in seconds of play, the director looks when judging how fast food is eaten.
+const APPETITE_WINDOW: f32 = 10.0;
+
+/// Random cells fair placement tries before settling for the best of them.
+const PLACEMENT_CANDIDATES: usize = 12;
+
+/// Food this close to the head, on the side it's coming from, would need a U-turn to reach.
+const BEHIND_HEAD_DISTANCE: i32 = 3;
+
+/// How the food director paces spawns. Part of the rules, so each mode can set its own pace.
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct FoodPacing {
+    /// Seconds between spawns for a short snake on an empty board.
+    pub interval: f32,
+    /// The board is topped up to this much food right away.
+    pub min_food: u32,
+    /// No more food spawns while this much is on the board.
+    pub max_food: u32,
+    /// How much sooner food comes for every part of the snake, e.g. 0.02 for 2% per part.
+    pub length_speedup: f32,
+    /// How much sooner food comes for every food eaten in the last ten seconds.
+    pub appetite_speedup: f32,
+    /// Places food anywhere at random, even behind the head or inside a coil the snake can't
+    /// get into, for players who want the classic game.
+    pub pure_random: bool,
+}
+
+impl Default for FoodPacing {
+    fn default() -> Self {
+        Self {
+            interval: 1.0,
+            min_food: 1,
+            max_food: 8,
+            length_speedup: 0.02,
+            appetite_speedup: 0.1,
+            pure_random: false,
+        }
+    }
+}
+
+impl FoodPacing {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.interval <= 0.0 {
+            return Err(format!(
+                "food_pacing.interval must be positive, got {}",
+                self.interval
+            ));
+        }
+        if self.max_food == 0 || self.min_food > self.max_food {
+            return Err(format!(
+                "food_pacing needs 1 <= max_food and min_food <= max_food, got {} and {}",
+                self.max_food, self.min_food
+            ));
+        }
+        Ok(())
+    }
+
+    /// Seconds between spawns right now. Long snakes and quick eaters get food sooner, and
+    /// the more food is already out, the longer the next one takes.
+    fn interval(&self, length: u32, recent_meals: usize, on_board: u32) -> f32 {
+        let eagerness =
+            1.0 + self.length_speedup * length as f32 + self.appetite_speedup * recent_meals as f32;
+        let crowding = 1.0 + on_board as f32 / self.max_food as f32;
+        self.interval * crowding / eagerness
+    }
+}
+
+/// Pacing state for the current run.
+#[derive(Default)]
+struct FoodDirector {
+    seed: u64,
+    /// Seconds of play since food last spawned.
+    since_spawn: f32,
+    /// When food was eaten recently, in seconds into the run.
+    recent_meals: VecDeque<f32>,
+}
+
+pub struct DirectorPlugin;
+
+impl Plugin for DirectorPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(direct_food_spawns.system());
+    }
+}
+
+/// Spawns food when the pacing rules say it's due, any time the board is short of
+/// `min_food`, and never while it holds `max_food`.
+fn direct_food_spawns(
+    mut director: Local<FoodDirector>,
+    mut reader: Local<EventReader<GameEvent>>,
+    mut commands: Commands,
+    mut game_events: ResMut<Events<GameEvent>>,
+    food_material: Res<FoodMaterial>,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    state: Res<GameState>,
+    rules: Res<GameRules>,
+    level: Res<ActiveLevel>,
+    mut run: ResMut<Run>,
+    mut food: Query<(&Food, &Position)>,
+    mut heads: Query<Without<Ally, (&SnakeHead, &Position)>>,
+    mut segments: Query<(&SnakeSegment, &Position)>,
+) {
+    if director.seed != run.seed {
+        *director = FoodDirector {
+            seed: run.seed,
+            ..FoodDirector::default()
+        };
+    }
+    let now = run.elapsed;
+    for event in reader.iter(&game_events) {
+        if let GameEvent::FoodEaten { .. } = event {
+            director.recent_meals.push_back(now);
+        }
+    }
+    while director
+        .recent_meals
+        .front()
+        .map_or(false, |&eaten| eaten + APPETITE_WINDOW < now)
+    {
+        director.recent_meals.pop_front();
+    }
+    // Puzzles bring their own food.
+    if *state != GameState::Playing || level.is_puzzle() {
+        return;
+    }
+
+    let pacing = &rules.food_pacing;
+    let on_board = food.iter().iter().count() as u32;
+    if on_board >= pacing.max_food {
+        // Start counting once there's room again, so eating doesn't bring in food at once.
+        director.since_spawn = 0.0;
+        return;
+    }
+    director.since_spawn += time_scale.delta(&time);
+    let length = segments.iter().iter().count() as u32 + 1;
+    let interval = pacing.interval(length, director.recent_meals.len(), on_board);
+    if on_board >= pacing.min_food && director.since_spawn < interval {
+        return;
+    }
+
+    // Food never goes on the level's blocked cells, however it's placed. Without a free cell
+    // among the ones tried, the spawn waits for the next frame.
+    let walls = level_blocked_cells(&level);
+    let position = if pacing.pure_random {
+        random_free_cell(&mut run, &walls)
+    } else {
+        let mut blocked = walls;
+        blocked.extend(segments.iter().iter().map(|(_seg, pos)| *pos));
+        blocked.extend(food.iter().iter().map(|(_food, pos)| *pos));
+        let head = (&mut heads.iter())
+            .into_iter()
+            .next()
+            .map(|(head, pos)| (*pos, head.direction));
+        if let Some((head, _direction)) = head {
+            blocked.insert(head);
+        }
+        fair_cell(&mut run, head, &blocked, rules.wrap_walls)
+    };
+    let position = match position {
+        Some(position) => position,
+        None => return,
+    };
+    director.since_spawn = 0.0;
+    let armored = run.rng.gen::<f32>() < ARMOR_FOOD_CHANCE;
+    let super_growth = run.rng.gen::<f32>() < SUPER_GROWTH_FOOD_CHANCE;
+    run.span().in_scope(|| {
+        debug!(
+            x = position.x,
+            y = position.y,
+            armored,
+            super_growth,
+            on_board,
+            interval,
+            "food spawned"
+        )
+    });
+    let food = spawn_food(&mut commands, food_material.0, position);
+    if armored {
+        commands.insert_one(food, Armor);
+        commands.insert_one(food, Size::square(ARMORED_FOOD_SIZE));
+    }
+    if super_growth {
+        commands.insert_one(food, SuperGrowth);
+    }
+    game_events.send(GameEvent::FoodSpawned { position });
+}
+
+fn random_cell(run: &mut Run) -> Position {
+    Position {
+        x: (run.rng.gen::<f32>() * ARENA_WIDTH as f32) as i32,
+        y: (run.rng.gen::<f32>() * ARENA_HEIGHT as f32) as i32,
+    }
+}
+
+/// The level's cells nothing is ever placed on: walls, portals, exits and anywhere an obstacle
+/// or a door can block. The level's validation makes sure some other cell is left.
+pub fn level_blocked_cells(level: &ActiveLevel) -> HashSet<Position> {
+    match &level.0 {
+        Some(level) => level
+            .walls
+            .iter()
+            .chain(&level.portals)
+            .chain(&level.exits)
+            .copied()
+            .chain(level.obstacle_cells())
+            .collect(),
+        None => HashSet::new(),
+    }
+}
+
+/// The first of a few random cells that isn't `blocked`, or `None` if none of them was free.
+pub fn random_free_cell(run: &mut Run, blocked: &HashSet<Position>) -> Option<Position> {
+    (0..PLACEMENT_CANDIDATES)
+        .map(|_| random_cell(run))
+        .find(|cell| !blocked.contains(cell))
+}
+
+/// Picks a cell the snake can actually get to: free, reachable from the head without
+/// crossing the snake, not a dead-end pocket, and not just behind the head. Tries a few random
+/// cells and keeps the best of the free ones, or `None` if every one was blocked.
+fn fair_cell(
+    run: &mut Run,
+    head: Option<(Position, Direction)>,
+    blocked: &HashSet<Position>,
+    wraps: bool,
+) -> Option<Position> {
+    let candidates: Vec<Position> = (0..PLACEMENT_CANDIDATES)
+        .map(|_| random_cell(run))
+        .filter(|cell| !blocked.contains(cell))
+        .collect();
+    let (head, direction) = match head {
+        Some(head) => head,
+        None => return candidates.first().copied(),
+    };
+    let reachable = reachable_cells(head, blocked, wraps);
+    // `max_by_key` keeps the last of equal scores, so go backwards to prefer earlier cells.
+    candidates
+        .iter()
+        .rev()
+        .copied()
+        .max_by_key(|&cell| placement_score(cell, head, direction, &reachable, blocked, wraps))
+}
+
+/// Higher is fairer. Unreachable cells score lowest, then cells just behind the head, then
+/// dead ends; anything else is fine.
+fn placement_score(
+    cell: Position,
+    head: Position,
+    direction: Direction,
+    reachable: &HashSet<Position>,
+    blocked: &HashSet<Position>,
+    wraps: bool,
+) -> u32 {
+    if !reachable.contains(&cell) {
+        return 0;
+    }
+    let (dx, dy) = (cell.x - head.x, cell.y - head.y);
+    let ahead = match direction {
+        Direction::Left => -dx,
+        Direction::Right => dx,
+        Direction::Up => dy,
+        Direction::Down => -dy,
+    };
+    if ahead < 0 && dx.abs() + dy.abs() <= BEHIND_HEAD_DISTANCE {
+        return 1;
+    }
+    let open_sides = neighbors(cell, wraps)
+        .iter()
+        .filter(|next| !next.outside_arena() && !blocked.contains(*next))
+        .count();
+    if open_sides < 2 {
+        return 2;
+    }
+    3
+}
+
+fn neighbors(cell: Position, wraps: bool) -> [Position; 4] {
+    let mut cells = [
+        cell.moved(Direction::Left),
+        cell.moved(Direction::Up),
+        cell.moved(Direction::Right),
+        cell.moved(Direction::Down),
+    ];
+    if wraps {
+        for cell in &mut cells {
+            *cell = cell.wrapped();
+        }
+    }
+    cells
+}
+
+/// Every free cell the head can get to, treating the snake as it is now as walls.
+pub fn reachable_cells(
+    head: Position,
+    blocked: &HashSet<Position>,
+    wraps: bool,
+) -> HashSet<Position> {
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::new();
+    seen.insert(head);
+    queue.push_back(head);
+    while let Some(cell) = queue.pop_front() {
+        for &next in &neighbors(cell, wraps) {
+            if !next.outside_arena() && !blocked.contains(&next) && seen.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+    seen
+}
@@ -0,0 +1,486 @@
+use std::path::PathBuf;
+
+use bevy::{prelude::*, window::CursorMoved};
+use tracing::{info, warn};
+
+use crate::{
+    despawn_arena,
+    dialog::Dialog,
+    level::{level_error_dialog, ActiveLevel, Level, LevelError, LEVELS_DIR},
+    locale::Locale,
+    power_up::PowerUp,
+    primary_window,
+    profile::ActiveProfile,
+    puzzle::Puzzle,
+    spawn_initial_snake,
+    tasks::{Task, TaskPool},
+    tiles::TileKind,
+    toast::Toast,
+    ui::UiFonts,
+    Direction, Food, GameState, HeadMaterial, Position, Run, Score, SegmentMaterial, SnakeHead,
+    SnakeSegment, ARENA_HEIGHT, ARENA_WIDTH,
+};
+
+/// The level the editor opens and saves.
+const EDITOR_LEVEL: &str = "custom.ron";
+
+const EDITOR_FONT_SIZE: f32 = 22.0;
+
+/// What a click paints.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum Brush {
+    Wall,
+    Ice,
+    SpeedPad,
+    Mud,
+    Conveyor,
+    Portal,
+    Spawn,
+    Exit,
+    Checkpoint,
+    Spikes,
+    /// A puzzle's food. Placing some makes the level a puzzle.
+    Food,
+}
+
+impl Brush {
+    const ALL: [Brush; 11] = [
+        Self::Wall,
+        Self::Ice,
+        Self::SpeedPad,
+        Self::Mud,
+        Self::Conveyor,
+        Self::Portal,
+        Self::Spawn,
+        Self::Exit,
+        Self::Checkpoint,
+        Self::Spikes,
+        Self::Food,
+    ];
+
+    /// The key picking each brush in `ALL`, and its label.
+    const KEYS: [(KeyCode, &'static str); 11] = [
+        (KeyCode::Key1, "1"),
+        (KeyCode::Key2, "2"),
+        (KeyCode::Key3, "3"),
+        (KeyCode::Key4, "4"),
+        (KeyCode::Key5, "5"),
+        (KeyCode::Key6, "6"),
+        (KeyCode::Key7, "7"),
+        (KeyCode::Key8, "8"),
+        (KeyCode::Key9, "9"),
+        (KeyCode::Key0, "0"),
+        (KeyCode::F, "F"),
+    ];
+
+    fn name_key(self) -> &'static str {
+        match self {
+            Self::Wall => "brush-wall",
+            Self::Ice => "brush-ice",
+            Self::SpeedPad => "brush-speed-pad",
+            Self::Mud => "brush-mud",
+            Self::Conveyor => "brush-conveyor",
+            Self::Portal => "brush-portal",
+            Self::Spawn => "brush-spawn",
+            Self::Exit => "brush-exit",
+            Self::Checkpoint => "brush-checkpoint",
+            Self::Spikes => "brush-spikes",
+            Self::Food => "brush-food",
+        }
+    }
+
+    /// Portals, the spawn point and food are placed one per click rather than painted, so
+    /// dragging doesn't lay a trail of them.
+    fn paints_while_held(self) -> bool {
+        !matches!(self, Self::Portal | Self::Spawn | Self::Food)
+    }
+}
+
+/// What a check of the level is for, which decides what happens once it's done.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum CheckFor {
+    /// Only to show the level's problems, if it has any.
+    Opening,
+    /// To start a playtest if the level can be played.
+    Playtest,
+}
+
+/// A check of the level on the task pool, since solving a puzzle can take a while.
+struct LevelCheck {
+    purpose: CheckFor,
+    /// The level as it was checked, so a playtest never starts on changes made since.
+    level: Level,
+    task: Task<Result<(), Vec<LevelError>>>,
+}
+
+impl LevelCheck {
+    fn start(tasks: &TaskPool, level: &Level, purpose: CheckFor) -> Self {
+        let checked = level.clone();
+        Self {
+            purpose,
+            level: level.clone(),
+            task: tasks.spawn("level check", move || checked.validate()),
+        }
+    }
+}
+
+struct Editor {
+    brush: Brush,
+    /// Which way newly painted conveyors push.
+    conveyor: Direction,
+    cursor: Vec2,
+    checking: Option<LevelCheck>,
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self {
+            brush: Brush::Wall,
+            conveyor: Direction::Right,
+            cursor: Vec2::zero(),
+            checking: None,
+        }
+    }
+}
+
+struct EditorLine;
+
+pub struct EditorPlugin;
+
+impl Plugin for EditorPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Editor>()
+            .add_system(switch_editor.system())
+            .add_system(paint_level.system())
+            .add_system(draw_editor.system());
+    }
+}
+
+fn editor_path() -> PathBuf {
+    PathBuf::from(LEVELS_DIR).join(EDITOR_LEVEL)
+}
+
+/// The cell under a cursor position, which is measured in pixels from the bottom-left corner
+/// of the window. Mirrors `position_translation`, which puts each cell's center on a multiple
+/// of the cell size.
+fn cursor_cell(cursor: Vec2, window: &Window) -> Position {
+    Position {
+        x: (cursor.x() / window.width as f32 * ARENA_WIDTH as f32).round() as i32,
+        y: (cursor.y() / window.height as f32 * ARENA_HEIGHT as f32).round() as i32,
+    }
+}
+
+/// L while paused clears the arena and opens the editor on the level being played, or on the
+/// saved level if none is. In the editor P playtests the level from its spawn point, and L
+/// leaves it for a normal run. Pausing a playtest and pressing L comes back to the editor.
+/// Problems with the level are shown in a dialog, both when it's opened and when playtesting
+/// is refused because of them. The level is checked in the background, so the playtest starts
+/// once the check is done.
+fn switch_editor(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    locale: Res<Locale>,
+    tasks: Res<TaskPool>,
+    mut dialogs: ResMut<Events<Dialog>>,
+    head_material: Res<HeadMaterial>,
+    segment_material: Res<SegmentMaterial>,
+    mut editor: ResMut<Editor>,
+    mut state: ResMut<GameState>,
+    mut level: ResMut<ActiveLevel>,
+    mut run: ResMut<Run>,
+    mut score: ResMut<Score>,
+    mut segments: Query<(Entity, &SnakeSegment)>,
+    mut food: Query<(Entity, &Food)>,
+    mut power_ups: Query<(Entity, &PowerUp)>,
+    mut heads: Query<(Entity, &SnakeHead)>,
+) {
+    let next = match *state {
+        GameState::Paused if keyboard_input.just_pressed(KeyCode::L) => {
+            if level.0.is_none() {
+                let path = editor_path();
+                level.0 = Some(if path.exists() {
+                    // A level that reads but can't be played still opens, so it can be fixed.
+                    match Level::read(&path) {
+                        Ok(loaded) => {
+                            editor.checking =
+                                Some(LevelCheck::start(&tasks, &loaded, CheckFor::Opening));
+                            loaded
+                        }
+                        Err(err) => {
+                            warn!("starting a new level over {}", path.display());
+                            level_error_dialog(&path, &[err], &locale, &mut dialogs);
+                            Level::default()
+                        }
+                    }
+                } else {
+                    Level::default()
+                });
+            }
+            GameState::Editor
+        }
+        GameState::Editor if keyboard_input.just_pressed(KeyCode::P) => {
+            let checking = editor.checking.as_ref().map(|check| check.purpose);
+            match &level.0 {
+                Some(_level) if checking == Some(CheckFor::Playtest) => return,
+                Some(level) => {
+                    editor.checking = Some(LevelCheck::start(&tasks, level, CheckFor::Playtest));
+                    return;
+                }
+                None => GameState::Playing,
+            }
+        }
+        GameState::Editor if keyboard_input.just_pressed(KeyCode::L) => {
+            editor.checking = None;
+            level.0 = None;
+            GameState::Playing
+        }
+        GameState::Editor => {
+            let result = match editor.checking.as_ref().and_then(|check| check.task.poll()) {
+                Some(result) => result,
+                None => return,
+            };
+            let check = editor.checking.take().unwrap();
+            match result {
+                Ok(Err(errors)) => {
+                    level_error_dialog(&editor_path(), &errors, &locale, &mut dialogs);
+                    return;
+                }
+                Err(_lost) => {
+                    warn!("checking the level stopped");
+                    return;
+                }
+                Ok(Ok(())) if check.purpose == CheckFor::Opening => return,
+                Ok(Ok(())) => match &level.0 {
+                    Some(level) if *level == check.level => GameState::Playing,
+                    // Painted over while it was checked, so check it again as it is now.
+                    Some(level) => {
+                        editor.checking =
+                            Some(LevelCheck::start(&tasks, level, CheckFor::Playtest));
+                        return;
+                    }
+                    None => return,
+                },
+            }
+        }
+        _ => return,
+    };
+    *state = next;
+
+    // Every switch starts from an empty arena, and a fresh run when leaving the editor.
+    despawn_arena(
+        &mut commands,
+        &mut segments,
+        &mut food,
+        &mut power_ups,
+        &mut heads,
+    );
+    *run = Run::default();
+    score.0 = 0;
+    if next == GameState::Playing {
+        spawn_initial_snake(&mut commands, head_material, segment_material);
+    }
+}
+
+/// 1 to 9, 0 and F pick a brush and the arrow keys turn conveyors. The left mouse button
+/// paints, the right one erases, and S saves the level, signed with the profile's name if it
+/// has no author yet.
+fn paint_level(
+    mut cursor_reader: Local<EventReader<CursorMoved>>,
+    cursor_moved_events: Res<Events<CursorMoved>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_input: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    state: Res<GameState>,
+    locale: Res<Locale>,
+    profile: Res<ActiveProfile>,
+    mut editor: ResMut<Editor>,
+    mut level: ResMut<ActiveLevel>,
+    mut toasts: ResMut<Events<Toast>>,
+) {
+    for event in cursor_reader.iter(&cursor_moved_events) {
+        editor.cursor = event.position;
+    }
+    let level = match (&mut level.0, *state) {
+        (Some(level), GameState::Editor) => level,
+        _ => return,
+    };
+
+    for (&(key, _label), &brush) in Brush::KEYS.iter().zip(Brush::ALL.iter()) {
+        if keyboard_input.just_pressed(key) {
+            editor.brush = brush;
+        }
+    }
+
+    // M turns the puzzle off and on, and the brackets set its moves.
+    if keyboard_input.just_pressed(KeyCode::M) {
+        level.puzzle = match level.puzzle {
+            Some(_) => None,
+            None => Some(Puzzle::default()),
+        };
+    }
+    if let Some(puzzle) = &mut level.puzzle {
+        if keyboard_input.just_pressed(KeyCode::LBracket) {
+            puzzle.moves = puzzle.moves.saturating_sub(1).max(1);
+        }
+        if keyboard_input.just_pressed(KeyCode::RBracket) {
+            puzzle.moves += 1;
+        }
+    }
+    for &(key, direction) in &[
+        (KeyCode::Left, Direction::Left),
+        (KeyCode::Up, Direction::Up),
+        (KeyCode::Right, Direction::Right),
+        (KeyCode::Down, Direction::Down),
+    ] {
+        if keyboard_input.just_pressed(key) {
+            editor.conveyor = direction;
+        }
+    }
+
+    if keyboard_input.just_pressed(KeyCode::S) {
+        if let (true, Some(profile)) = (level.author.is_empty(), &profile.0) {
+            level.author = profile.name.clone();
+        }
+        let path = editor_path();
+        match level.save(&path) {
+            Ok(()) => {
+                info!(path = %path.display(), "level saved");
+                toasts.send(Toast::new(locale.text("level-saved")));
+            }
+            Err(err) => warn!("could not save level {}: {}", path.display(), err),
+        }
+    }
+
+    let window = match primary_window(&windows) {
+        Some(window) => window,
+        None => return,
+    };
+    let cell = cursor_cell(editor.cursor, window);
+    if cell.outside_arena() {
+        return;
+    }
+    if mouse_input.pressed(MouseButton::Right) {
+        level.erase(cell);
+        return;
+    }
+    let brush = editor.brush;
+    let painting = if brush.paints_while_held() {
+        mouse_input.pressed(MouseButton::Left)
+    } else {
+        mouse_input.just_pressed(MouseButton::Left)
+    };
+    if !painting {
+        return;
+    }
+    if brush == Brush::Spawn {
+        level.spawn = cell;
+        return;
+    }
+    if brush == Brush::Food {
+        let placed = level
+            .puzzle
+            .as_ref()
+            .map_or(false, |puzzle| puzzle.food.contains(&cell));
+        if !placed {
+            level.erase(cell);
+            level
+                .puzzle
+                .get_or_insert_with(Puzzle::default)
+                .food
+                .push(cell);
+        }
+        return;
+    }
+    let tile = match brush {
+        Brush::Ice => Some(TileKind::Ice),
+        Brush::SpeedPad => Some(TileKind::SpeedPad),
+        Brush::Mud => Some(TileKind::Mud),
+        Brush::Conveyor => Some(TileKind::Conveyor(editor.conveyor)),
+        Brush::Spikes => Some(TileKind::Spikes),
+        _ => None,
+    };
+    let unchanged = match (brush, tile) {
+        (_, Some(kind)) => level.tiles.contains(&(cell, kind)),
+        (Brush::Wall, None) => level.walls.contains(&cell),
+        (Brush::Portal, None) => level.portals.contains(&cell),
+        (Brush::Checkpoint, None) => level.checkpoints.contains(&cell),
+        _ => level.exits.contains(&cell),
+    };
+    if unchanged {
+        return;
+    }
+    // One thing per cell, so painting replaces whatever was there.
+    level.erase(cell);
+    match (brush, tile) {
+        (_, Some(kind)) => level.tiles.push((cell, kind)),
+        (Brush::Wall, None) => level.walls.push(cell),
+        (Brush::Portal, None) => level.portals.push(cell),
+        (Brush::Checkpoint, None) => level.checkpoints.push(cell),
+        _ => level.exits.push(cell),
+    }
+}
+
+/// Redraws the editor's help text whenever it changes.
+fn draw_editor(
+    mut drawn: Local<Vec<String>>,
+    mut commands: Commands,
+    fonts: Res<UiFonts>,
+    locale: Res<Locale>,
+    state: Res<GameState>,
+    editor: Res<Editor>,
+    level: Res<ActiveLevel>,
+    mut lines: Query<(Entity, &EditorLine)>,
+) {
+    let mut text = Vec::new();
+    if *state == GameState::Editor {
+        let brushes: Vec<String> = Brush::ALL
+            .iter()
+            .enumerate()
+            .map(|(i, &brush)| {
+                let marker = if brush == editor.brush { ">" } else { "" };
+                let (_key, label) = Brush::KEYS[i];
+                format!("{}{} {}", marker, label, locale.text(brush.name_key()))
+            })
+            .collect();
+        text.push(locale.text("editor-title"));
+        text.push(brushes.join("  "));
+        text.push(locale.text("editor-controls"));
+        if let Some(puzzle) = level.0.as_ref().and_then(|level| level.puzzle.as_ref()) {
+            text.push(locale.format(
+                "editor-puzzle",
+                &[
+                    ("moves", puzzle.moves.to_string()),
+                    ("food", puzzle.food.len().to_string()),
+                ],
+            ));
+        }
+        if editor.checking.is_some() {
+            text.push(locale.text("editor-checking"));
+        }
+    }
+    if *drawn == text {
+        return;
+    }
+    *drawn = text.clone();
+
+    for (ent, _line) in &mut lines.iter() {
+        commands.despawn(ent);
+    }
+    for (row, line) in text.into_iter().enumerate() {
+        commands
+            .spawn(TextComponents {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        left: Val::Px(10.0),
+                        top: Val::Px(10.0 + row as f32 * (EDITOR_FONT_SIZE + 6.0)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                text: fonts.text(line, EDITOR_FONT_SIZE),
+                ..Default::default()
+            })
+            .with(EditorLine);
+    }
+}
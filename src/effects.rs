@@ -0,0 +1,143 @@
+use bevy::prelude::*;
+
+use crate::{game_event::GameEvent, power_up::PowerUpKind, time_scale::TimeScale, GameState, Run};
+
+/// What happens when an effect is added while one of the same kind is already active.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Stacking {
+    /// The active effect starts its time over and takes the new magnitude.
+    Refresh,
+    /// Both stay active, so their magnitudes multiply.
+    Stack,
+    /// The new effect is dropped.
+    Ignore,
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum EffectKind {
+    /// From speed pads. Multiplies the move interval by its magnitude.
+    Haste,
+    /// From mud. Multiplies the move interval by its magnitude.
+    Slow,
+    /// Shows all food at night.
+    Lantern,
+    /// From the `slow_start` rule. Multiplies the move interval by its magnitude.
+    SlowStart,
+    /// From the `adaptive_difficulty` rule. Multiplies the move interval by its magnitude.
+    Adaptive,
+}
+
+impl EffectKind {
+    pub fn stacking(self) -> Stacking {
+        match self {
+            // Two heads on speed pads at once, e.g. with the hydra, are still only twice as
+            // fast, but every head stuck in mud slows the snake down further.
+            Self::Haste => Stacking::Ignore,
+            Self::Slow => Stacking::Stack,
+            Self::Lantern | Self::SlowStart | Self::Adaptive => Stacking::Refresh,
+        }
+    }
+
+    fn changes_speed(self) -> bool {
+        matches!(
+            self,
+            Self::Haste | Self::Slow | Self::SlowStart | Self::Adaptive
+        )
+    }
+}
+
+/// A temporary change to the game, like a speed pad's haste or a burning lantern.
+#[derive(Copy, Clone, Debug)]
+pub struct ActiveEffect {
+    pub kind: EffectKind,
+    /// Seconds of play left. Effects that only last while their source does, like tiles, are
+    /// added every frame with no time left and gone by the next.
+    pub remaining: f32,
+    pub magnitude: f32,
+}
+
+impl ActiveEffect {
+    pub fn new(kind: EffectKind, remaining: f32, magnitude: f32) -> Self {
+        Self {
+            kind,
+            remaining,
+            magnitude,
+        }
+    }
+}
+
+/// Every effect active in the current run. Sources add effects here rather than changing the
+/// timers and resources they affect, so effects from several sources combine by the rules in
+/// `EffectKind::stacking`.
+#[derive(Default)]
+pub struct Effects {
+    active: Vec<ActiveEffect>,
+    seed: u64,
+}
+
+impl Effects {
+    pub fn add(&mut self, effect: ActiveEffect) {
+        let existing = self
+            .active
+            .iter_mut()
+            .find(|active| active.kind == effect.kind);
+        match (existing, effect.kind.stacking()) {
+            (Some(active), Stacking::Refresh) => *active = effect,
+            (Some(_active), Stacking::Ignore) => {}
+            (Some(_active), Stacking::Stack) | (None, _) => self.active.push(effect),
+        }
+    }
+
+    pub fn is_active(&self, kind: EffectKind) -> bool {
+        self.active.iter().any(|active| active.kind == kind)
+    }
+
+    /// What the move interval is multiplied by, 1 with no speed effects.
+    pub fn interval_factor(&self) -> f32 {
+        self.active
+            .iter()
+            .filter(|active| active.kind.changes_speed())
+            .map(|active| active.magnitude)
+            .product()
+    }
+}
+
+pub struct EffectsPlugin;
+
+impl Plugin for EffectsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Effects>()
+            .add_system(tick_effects.system());
+    }
+}
+
+/// Runs effects down during play and drops them once they're out of time. Nothing carries
+/// over into the next run.
+fn tick_effects(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    state: Res<GameState>,
+    run: Res<Run>,
+    mut effects: ResMut<Effects>,
+    mut game_events: ResMut<Events<GameEvent>>,
+) {
+    if effects.seed != run.seed {
+        effects.seed = run.seed;
+        effects.active.clear();
+    }
+    let delta = if *state == GameState::Playing {
+        time_scale.delta(&time)
+    } else {
+        0.0
+    };
+    for active in &mut effects.active {
+        active.remaining -= delta;
+    }
+    let lantern_was_lit = effects.is_active(EffectKind::Lantern);
+    effects.active.retain(|active| active.remaining > 0.0);
+    if lantern_was_lit && !effects.is_active(EffectKind::Lantern) {
+        game_events.send(GameEvent::PowerUpExpired {
+            kind: PowerUpKind::Lantern,
+        });
+    }
+}
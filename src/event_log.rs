@@ -0,0 +1,152 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::{
+    game_event::GameEvent, locale::Locale, ui::UiFonts, GameOverCause, GameState, Position, Run,
+};
+
+/// How many events the panel lists before the oldest scroll off.
+const MAX_ENTRIES: usize = 12;
+
+const LOG_FONT_SIZE: f32 = 22.0;
+
+/// A panel listing the latest game events in plain words, as a debugging aid and for players
+/// who can't easily follow what happens on the board.
+#[derive(Default)]
+pub struct EventLog {
+    pub open: bool,
+    entries: VecDeque<String>,
+}
+
+struct EventLogLine;
+
+pub struct EventLogPlugin;
+
+impl Plugin for EventLogPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<EventLog>()
+            .add_system(toggle_event_log.system())
+            .add_system(record_game_events.system())
+            .add_system(draw_event_log.system());
+    }
+}
+
+/// Describes an event, e.g. "Food eaten at (3, 7): +10". Events that happen too often to
+/// follow, like food appearing every second, are left out.
+fn describe(event: &GameEvent, locale: &Locale) -> Option<String> {
+    let at = |key: &str, position: Position, extra: &[(&str, String)]| {
+        let mut args = vec![("x", position.x.to_string()), ("y", position.y.to_string())];
+        args.extend_from_slice(extra);
+        locale.format(key, &args)
+    };
+    let text = match *event {
+        GameEvent::FoodSpawned { .. } | GameEvent::Grew { .. } | GameEvent::Scored { .. } => {
+            return None
+        }
+        GameEvent::FoodEaten {
+            position, points, ..
+        } => at(
+            "log-food-eaten",
+            position,
+            &[("points", points.to_string())],
+        ),
+        GameEvent::PowerUpSpawned { kind, position } => at(
+            "log-power-up-spawned",
+            position,
+            &[("name", locale.text(kind.name_key()))],
+        ),
+        GameEvent::PowerUpCollected { kind } => locale.format(
+            "log-power-up-collected",
+            &[("name", locale.text(kind.name_key()))],
+        ),
+        GameEvent::PowerUpExpired { kind } => locale.format(
+            "log-power-up-expired",
+            &[("name", locale.text(kind.name_key()))],
+        ),
+        GameEvent::GameOver { cause } => locale.text(match cause {
+            GameOverCause::Wall => "game-over-wall",
+            GameOverCause::Tail => "game-over-tail",
+            GameOverCause::HeadOn => "game-over-head-on",
+            GameOverCause::Spikes => "game-over-spikes",
+            GameOverCause::OutOfMoves => "game-over-out-of-moves",
+            GameOverCause::Idle => "game-over-idle",
+            GameOverCause::LevelComplete => "game-over-level-complete",
+        }),
+    };
+    Some(text)
+}
+
+/// E during a run shows or hides the log. It's in the options menu too.
+fn toggle_event_log(
+    keyboard_input: Res<Input<KeyCode>>,
+    state: Res<GameState>,
+    mut log: ResMut<EventLog>,
+) {
+    if matches!(*state, GameState::Playing | GameState::Paused)
+        && keyboard_input.just_pressed(KeyCode::E)
+    {
+        log.open = !log.open;
+    }
+}
+
+/// Keeps recording while the panel is hidden, so opening it shows what just happened.
+fn record_game_events(
+    mut reader: Local<EventReader<GameEvent>>,
+    game_events: Res<Events<GameEvent>>,
+    locale: Res<Locale>,
+    run: Res<Run>,
+    mut log: ResMut<EventLog>,
+) {
+    for event in reader.iter(&game_events) {
+        let text = match describe(event, &locale) {
+            Some(text) => text,
+            None => continue,
+        };
+        log.entries.push_back(format!("[{}] {}", run.tick, text));
+        if log.entries.len() > MAX_ENTRIES {
+            log.entries.pop_front();
+        }
+    }
+}
+
+/// Redraws the panel whenever its text changes, newest entry at the bottom.
+fn draw_event_log(
+    mut drawn: Local<Vec<String>>,
+    mut commands: Commands,
+    fonts: Res<UiFonts>,
+    locale: Res<Locale>,
+    log: Res<EventLog>,
+    mut lines: Query<(Entity, &EventLogLine)>,
+) {
+    let mut text = Vec::new();
+    if log.open {
+        text.push(locale.text("event-log-title"));
+        text.extend(log.entries.iter().cloned());
+    }
+    if *drawn == text {
+        return;
+    }
+    *drawn = text.clone();
+
+    for (ent, _line) in &mut lines.iter() {
+        commands.despawn(ent);
+    }
+    for (row, line) in text.into_iter().enumerate() {
+        commands
+            .spawn(TextComponents {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        right: Val::Px(10.0),
+                        top: Val::Px(10.0 + row as f32 * (LOG_FONT_SIZE + 6.0)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                text: fonts.text(line, LOG_FONT_SIZE),
+                ..Default::default()
+            })
+            .with(EventLogLine);
+    }
+}
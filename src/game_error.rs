@@ -0,0 +1,60 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use tracing::error;
+
+use crate::{dialog::Dialog, locale::Locale, GameState};
+
+/// Something a system can't carry on from, sent instead of panicking. The system skips what it
+/// was doing, and the run is paused with a dialog explaining what happened, so the player can
+/// decide whether to keep going.
+#[derive(Clone, Debug)]
+pub struct FatalError {
+    /// The locale key of the message shown to the player.
+    pub key: &'static str,
+    /// What went wrong, for the log.
+    pub detail: String,
+}
+
+impl FatalError {
+    pub fn new(key: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            key,
+            detail: detail.into(),
+        }
+    }
+}
+
+pub struct GameErrorPlugin;
+
+impl Plugin for GameErrorPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<FatalError>()
+            .add_system(report_fatal_errors.system());
+    }
+}
+
+/// Logs every fatal error, and shows each kind in a dialog the first time it happens, since
+/// one that keeps happening would otherwise reopen the dialog every frame.
+fn report_fatal_errors(
+    mut shown: Local<HashSet<&'static str>>,
+    mut reader: Local<EventReader<FatalError>>,
+    errors: Res<Events<FatalError>>,
+    locale: Res<Locale>,
+    mut state: ResMut<GameState>,
+    mut dialogs: ResMut<Events<Dialog>>,
+) {
+    for fatal in reader.iter(&errors) {
+        error!(key = fatal.key, "{}", fatal.detail);
+        if !shown.insert(fatal.key) {
+            continue;
+        }
+        if *state == GameState::Playing {
+            *state = GameState::Paused;
+        }
+        dialogs.send(Dialog::new(
+            locale.text("error-title"),
+            vec![locale.text(fatal.key), locale.text("error-hint")],
+        ));
+    }
+}
@@ -0,0 +1,57 @@
+use crate::{power_up::PowerUpKind, scoring::ScoreReason, GameOverCause, Position};
+
+/// Everything that happens in a run that other parts of the game might care about. Systems
+/// that make things happen send these, and the HUD, announcements, stats and the like read
+/// them, so a new feature subscribes here instead of being wired into the system it reacts to.
+///
+/// Points are still awarded by sending a `ScoreEvent`, which scoring turns into `Scored` once
+/// bonuses are applied.
+#[derive(Copy, Clone, Debug)]
+pub enum GameEvent {
+    FoodSpawned {
+        position: Position,
+    },
+    FoodEaten {
+        position: Position,
+        points: u32,
+        /// Eaten by an ally rather than a player's snake.
+        by_ally: bool,
+    },
+    /// A segment emerged from the tail.
+    Grew {
+        position: Position,
+    },
+    /// The score changed. `points` are after the combo multiplier, and taken away for
+    /// penalties.
+    Scored {
+        points: u32,
+        reason: ScoreReason,
+    },
+    PowerUpSpawned {
+        kind: PowerUpKind,
+        position: Position,
+    },
+    PowerUpCollected {
+        kind: PowerUpKind,
+    },
+    /// A power-up's effect ran out, or ended early like the hydra losing a snake.
+    PowerUpExpired {
+        kind: PowerUpKind,
+    },
+    /// The run is over, or goes back to the level's last checkpoint, and the arena is about to
+    /// be reset.
+    GameOver {
+        cause: GameOverCause,
+    },
+}
+
+impl GameEvent {
+    /// The cause, if this is a game over. Handy with `find_map` for systems that only care
+    /// about the end of a run.
+    pub fn game_over(&self) -> Option<GameOverCause> {
+        match *self {
+            Self::GameOver { cause } => Some(cause),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+use tracing::info;
+
+use crate::{
+    rules::GameRules, snake_length, Dead, GameOverCause, Position, Run, SnakeHead, SnakeSegment,
+};
+
+/// What happens when two heads meet, either by moving into the same cell or by swapping
+/// cells, on the same move.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize)]
+pub enum HeadOnRule {
+    BothDie,
+    /// The longer snake survives. Both die if they're the same length.
+    LongerSurvives,
+    /// One of them survives at random.
+    CoinFlip,
+}
+
+pub struct HeadOnPlugin;
+
+impl Plugin for HeadOnPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        // Runs after the move so it sees where every head ended up.
+        app.add_system_to_stage(stage::POST_UPDATE, head_on_collisions.system());
+    }
+}
+
+/// Where a head was before the last move and where it is now.
+struct HeadMove {
+    entity: Entity,
+    from: Position,
+    to: Position,
+    length: u32,
+}
+
+/// Applies the head-on rule from `GameRules` to every pair of heads that met on the last
+/// move. The outcome only depends on the run seed, the tick and where the snakes are, so every
+/// copy of a game resolves it the same way.
+fn head_on_collisions(
+    mut commands: Commands,
+    mut last_positions: Local<HashMap<Entity, Position>>,
+    mut last_tick: Local<u64>,
+    run: Res<Run>,
+    rules: Res<GameRules>,
+    mut heads: Query<Without<Dead, (Entity, &SnakeHead, &Position)>>,
+    segments: Query<&SnakeSegment>,
+) {
+    if run.tick == *last_tick {
+        return;
+    }
+    *last_tick = run.tick;
+
+    let mut moves = Vec::new();
+    for (ent, head, position) in &mut heads.iter() {
+        if let Some(&from) = last_positions.get(&ent) {
+            moves.push(HeadMove {
+                entity: ent,
+                from,
+                to: *position,
+                length: snake_length(head.next_segment, &segments),
+            });
+        }
+    }
+    *last_positions = heads
+        .iter()
+        .iter()
+        .map(|(ent, _head, position)| (ent, *position))
+        .collect();
+    let rule = match rules.head_on {
+        Some(rule) => rule,
+        None => return,
+    };
+    // Entity ids can differ between copies of a game, so order by position instead.
+    moves.sort_by_key(|head| (head.from.x, head.from.y));
+
+    for (i, a) in moves.iter().enumerate() {
+        for b in &moves[i + 1..] {
+            let same_cell = a.to == b.to;
+            let swapped = a.to == b.from && b.to == a.from;
+            if !same_cell && !swapped {
+                continue;
+            }
+            let losers: Vec<Entity> = match rule {
+                HeadOnRule::BothDie => vec![a.entity, b.entity],
+                HeadOnRule::LongerSurvives if a.length > b.length => vec![b.entity],
+                HeadOnRule::LongerSurvives if b.length > a.length => vec![a.entity],
+                HeadOnRule::LongerSurvives => vec![a.entity, b.entity],
+                HeadOnRule::CoinFlip => {
+                    let flip = (run.seed ^ run.tick ^ (a.from.x * 31 + a.from.y) as u64) & 1;
+                    if flip == 0 {
+                        vec![a.entity]
+                    } else {
+                        vec![b.entity]
+                    }
+                }
+            };
+            info!(x = a.to.x, y = a.to.y, ?rule, "head-on collision");
+            for loser in losers {
+                commands.insert_one(
+                    loser,
+                    Dead {
+                        cause: GameOverCause::HeadOn,
+                    },
+                );
+            }
+        }
+    }
+}
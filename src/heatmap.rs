@@ -0,0 +1,262 @@
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+use tracing::{info, warn};
+
+use crate::{
+    locale::Locale, sized_sprite, split::Ally, toast::Toast, GameState, Position, Run, Size,
+    SnakeHead, ARENA_HEIGHT, ARENA_WIDTH,
+};
+
+const HEATMAPS_DIR: &str = "heatmaps";
+
+/// How many shades the heatmap is drawn in.
+const HEAT_LEVELS: usize = 5;
+
+/// Above the arena sprites and the danger markers, below confetti.
+const HEATMAP_DEPTH: f32 = 0.4;
+
+const HEATMAP_ALPHA: f32 = 0.7;
+
+/// Size of one cell in exported images, in pixels.
+const EXPORT_CELL_PIXELS: u32 = 10;
+
+/// How often heads entered each cell over a run, row by row from the bottom.
+#[derive(Clone)]
+struct Visits(Vec<u32>);
+
+impl Default for Visits {
+    fn default() -> Self {
+        Self(vec![0; (ARENA_WIDTH * ARENA_HEIGHT) as usize])
+    }
+}
+
+impl Visits {
+    fn index(position: Position) -> Option<usize> {
+        if position.x < 0
+            || position.y < 0
+            || position.x >= ARENA_WIDTH as i32
+            || position.y >= ARENA_HEIGHT as i32
+        {
+            return None;
+        }
+        Some((position.y as u32 * ARENA_WIDTH + position.x as u32) as usize)
+    }
+
+    fn record(&mut self, position: Position) {
+        if let Some(index) = Self::index(position) {
+            self.0[index] += 1;
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.0.iter().all(|&count| count == 0)
+    }
+
+    /// Every visited cell with its visits as a share of the busiest cell's, from 0 to 1.
+    fn heat(&self) -> impl Iterator<Item = (Position, f32)> + '_ {
+        let busiest = self.0.iter().copied().max().unwrap_or(0).max(1) as f32;
+        self.0
+            .iter()
+            .enumerate()
+            .filter(|(_, &count)| count > 0)
+            .map(move |(index, &count)| {
+                let position = Position {
+                    x: (index as u32 % ARENA_WIDTH) as i32,
+                    y: (index as u32 / ARENA_WIDTH) as i32,
+                };
+                (position, count as f32 / busiest)
+            })
+    }
+}
+
+/// Which shade a cell with this much heat is drawn in.
+fn heat_level(heat: f32) -> usize {
+    ((heat * HEAT_LEVELS as f32).ceil() as usize)
+        .max(1)
+        .min(HEAT_LEVELS)
+        - 1
+}
+
+/// Blue for the quietest cells, through to red for the busiest.
+fn level_color(level: usize) -> Color {
+    let t = level as f32 / (HEAT_LEVELS - 1) as f32;
+    Color::rgb(0.15 + 0.8 * t, 0.35 - 0.15 * t, 0.85 - 0.75 * t)
+}
+
+/// Where the heads went in the current run and in the last one to end. The last run's heatmap
+/// can be brought up over the arena while paused.
+#[derive(Default)]
+pub struct Heatmap {
+    seed: u64,
+    last_tick: u64,
+    current: Visits,
+    finished: Option<Visits>,
+    shown: bool,
+}
+
+/// Whether each finished run's heatmap is saved as a PNG in the heatmaps folder.
+#[derive(Default)]
+pub struct HeatmapExport(pub bool);
+
+struct HeatCell;
+
+struct HeatMaterials(Vec<Handle<ColorMaterial>>);
+
+pub struct HeatmapPlugin;
+
+impl Plugin for HeatmapPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Heatmap>()
+            .init_resource::<HeatmapExport>()
+            .add_startup_system(setup_heat_materials.system())
+            .add_system(record_visits.system())
+            .add_system(toggle_heatmap.system())
+            .add_system(draw_heatmap.system());
+    }
+}
+
+fn setup_heat_materials(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+    let levels = (0..HEAT_LEVELS)
+        .map(|level| {
+            let mut color = level_color(level);
+            color.a = HEATMAP_ALPHA;
+            materials.add(color.into())
+        })
+        .collect();
+    commands.insert_resource(HeatMaterials(levels));
+}
+
+/// Counts the cells heads move into. When a new run starts, the old run's counts become the
+/// heatmap to show, and are exported if that's turned on.
+fn record_visits(
+    state: Res<GameState>,
+    run: Res<Run>,
+    export: Res<HeatmapExport>,
+    mut heatmap: ResMut<Heatmap>,
+    mut heads: Query<Without<Ally, (&SnakeHead, &Position)>>,
+) {
+    if heatmap.seed != run.seed {
+        let finished = std::mem::take(&mut heatmap.current);
+        if !finished.is_empty() {
+            if export.0 {
+                save_png(&finished, heatmap.seed);
+            }
+            heatmap.finished = Some(finished);
+        }
+        heatmap.seed = run.seed;
+        heatmap.last_tick = run.tick;
+    }
+    if *state != GameState::Playing || run.tick == heatmap.last_tick {
+        return;
+    }
+    heatmap.last_tick = run.tick;
+    for (_head, &position) in &mut heads.iter() {
+        heatmap.current.record(position);
+    }
+}
+
+fn save_png(visits: &Visits, seed: u64) {
+    let width = ARENA_WIDTH * EXPORT_CELL_PIXELS;
+    let height = ARENA_HEIGHT * EXPORT_CELL_PIXELS;
+    let mut cells = vec![Color::rgb(0.1, 0.1, 0.1); visits.0.len()];
+    for (position, heat) in visits.heat() {
+        cells[Visits::index(position).unwrap()] = level_color(heat_level(heat));
+    }
+    // Images are stored top row first, the arena counts rows from the bottom.
+    let mut pixels = Vec::with_capacity((width * height * 3) as usize);
+    for row in 0..height {
+        let y = ARENA_HEIGHT - 1 - row / EXPORT_CELL_PIXELS;
+        for column in 0..width {
+            let color = cells[(y * ARENA_WIDTH + column / EXPORT_CELL_PIXELS) as usize];
+            pixels.extend_from_slice(&[
+                (color.r * 255.0) as u8,
+                (color.g * 255.0) as u8,
+                (color.b * 255.0) as u8,
+            ]);
+        }
+    }
+
+    let recorded_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs());
+    let path = Path::new(HEATMAPS_DIR).join(format!("heatmap-{}-{}.png", recorded_at, seed));
+    let result = fs::create_dir_all(HEATMAPS_DIR)
+        .and_then(|_| File::create(&path))
+        .map_err(|err| err.to_string())
+        .and_then(|file| {
+            let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+            encoder.set_color(png::ColorType::RGB);
+            encoder.set_depth(png::BitDepth::Eight);
+            encoder
+                .write_header()
+                .and_then(|mut writer| writer.write_image_data(&pixels))
+                .map_err(|err| err.to_string())
+        });
+    match result {
+        Ok(()) => info!(path = %path.display(), "heatmap saved"),
+        Err(err) => warn!("could not save heatmap {}: {}", path.display(), err),
+    }
+}
+
+/// H while paused shows or hides the last run's heatmap. Resuming hides it.
+fn toggle_heatmap(
+    keyboard_input: Res<Input<KeyCode>>,
+    state: Res<GameState>,
+    locale: Res<Locale>,
+    mut heatmap: ResMut<Heatmap>,
+    mut toasts: ResMut<Events<Toast>>,
+) {
+    if *state != GameState::Paused {
+        heatmap.shown = false;
+        return;
+    }
+    if !keyboard_input.just_pressed(KeyCode::H) {
+        return;
+    }
+    if heatmap.finished.is_none() {
+        toasts.send(Toast::new(locale.text("heatmap-empty")));
+        return;
+    }
+    heatmap.shown = !heatmap.shown;
+}
+
+fn draw_heatmap(
+    mut drawn: Local<bool>,
+    mut commands: Commands,
+    heatmap: Res<Heatmap>,
+    materials: Res<HeatMaterials>,
+    mut cells: Query<(Entity, &HeatCell)>,
+) {
+    if heatmap.shown == *drawn {
+        return;
+    }
+    *drawn = heatmap.shown;
+
+    for (ent, _cell) in &mut cells.iter() {
+        commands.despawn(ent);
+    }
+    let visits = match (&heatmap.finished, heatmap.shown) {
+        (Some(visits), true) => visits,
+        _ => return,
+    };
+    for (position, heat) in visits.heat() {
+        commands
+            .spawn(SpriteComponents {
+                transform: Transform::from_translation(Vec3::new(0.0, 0.0, HEATMAP_DEPTH)),
+                draw: Draw {
+                    is_transparent: true,
+                    ..Default::default()
+                },
+                ..sized_sprite(materials.0[heat_level(heat)])
+            })
+            .with(HeatCell)
+            .with(position)
+            .with(Size::square(1.0));
+    }
+}
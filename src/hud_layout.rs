@@ -0,0 +1,274 @@
+use std::{collections::HashMap, fs};
+
+use bevy::{prelude::*, window::CursorMoved};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    primary_window,
+    profile::ActiveProfile,
+    save_format::{self, Versioned},
+    GameState,
+};
+
+/// Rough size of a widget on screen, in pixels, for picking it up with the mouse.
+const WIDGET_WIDTH: f32 = 320.0;
+const WIDGET_HEIGHT: f32 = 36.0;
+
+/// The parts of the HUD that can be moved and hidden.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum HudWidget {
+    Score,
+    Danger,
+    Objective,
+    Zone,
+    Best,
+    Speed,
+    Moves,
+    Difficulty,
+}
+
+impl HudWidget {
+    pub const ALL: [HudWidget; 8] = [
+        Self::Score,
+        Self::Danger,
+        Self::Objective,
+        Self::Zone,
+        Self::Best,
+        Self::Speed,
+        Self::Moves,
+        Self::Difficulty,
+    ];
+
+    pub fn name_key(self) -> &'static str {
+        match self {
+            Self::Score => "hud-score",
+            Self::Danger => "hud-danger",
+            Self::Objective => "hud-objective",
+            Self::Zone => "hud-zone",
+            Self::Best => "hud-best",
+            Self::Speed => "hud-speed",
+            Self::Moves => "hud-moves",
+            Self::Difficulty => "hud-difficulty",
+        }
+    }
+}
+
+/// Where a widget sits, in pixels from the top-left corner of the window.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Placement {
+    pub visible: bool,
+    pub left: f32,
+    pub top: f32,
+}
+
+/// Placement of every HUD widget, saved per profile.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HudLayout {
+    widgets: HashMap<HudWidget, Placement>,
+    /// Whether the layout is being edited. Not saved.
+    #[serde(skip)]
+    pub editing: bool,
+}
+
+impl Default for HudLayout {
+    /// Stacks the widgets down the top-left corner, out of the way of toasts and the d-pad.
+    fn default() -> Self {
+        let widgets = HudWidget::ALL
+            .iter()
+            .enumerate()
+            .map(|(row, &widget)| {
+                let placement = Placement {
+                    visible: true,
+                    left: 10.0,
+                    top: 10.0 + row as f32 * WIDGET_HEIGHT,
+                };
+                (widget, placement)
+            })
+            .collect();
+        Self {
+            widgets,
+            editing: false,
+        }
+    }
+}
+
+impl Versioned for HudLayout {
+    const KIND: &'static str = "hud-layout";
+    const VERSION: u32 = 1;
+}
+
+impl HudLayout {
+    pub fn placement(&self, widget: HudWidget) -> Placement {
+        self.widgets
+            .get(&widget)
+            .copied()
+            .unwrap_or_else(|| HudLayout::default().widgets[&widget])
+    }
+
+    pub fn shows(&self, widget: HudWidget) -> bool {
+        self.placement(widget).visible
+    }
+
+    fn widget_at(&self, left: f32, top: f32) -> Option<HudWidget> {
+        HudWidget::ALL.iter().copied().find(|&widget| {
+            let placement = self.placement(widget);
+            left >= placement.left
+                && left <= placement.left + WIDGET_WIDTH
+                && top >= placement.top
+                && top <= placement.top + WIDGET_HEIGHT
+        })
+    }
+}
+
+/// The widget being dragged and the cursor's offset from its corner.
+#[derive(Default)]
+struct Drag {
+    cursor: Vec2,
+    held: Option<(HudWidget, Vec2)>,
+}
+
+pub struct HudLayoutPlugin;
+
+impl Plugin for HudLayoutPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<HudLayout>()
+            .add_system(load_layout.system())
+            .add_system(edit_layout.system())
+            .add_system(apply_layout.system());
+    }
+}
+
+/// Loads the profile's layout when a profile is picked.
+fn load_layout(
+    mut loaded_for: Local<Option<String>>,
+    profile: Res<ActiveProfile>,
+    mut layout: ResMut<HudLayout>,
+) {
+    let profile = match &profile.0 {
+        Some(profile) => profile,
+        None => return,
+    };
+    if loaded_for.as_deref() == Some(profile.name.as_str()) {
+        return;
+    }
+    *loaded_for = Some(profile.name.clone());
+
+    let path = profile.hud_layout_path();
+    *layout = match fs::read_to_string(&path) {
+        Ok(text) => save_format::decode(&text).unwrap_or_else(|err| {
+            warn!(
+                "resetting unreadable HUD layout {}: {}",
+                path.display(),
+                err
+            );
+            HudLayout::default()
+        }),
+        Err(_) => HudLayout::default(),
+    };
+}
+
+/// F11 while paused toggles edit mode. In edit mode widgets can be dragged with the mouse and
+/// 1 to 7 show or hide them. Leaving edit mode saves the layout to the profile.
+fn edit_layout(
+    mut drag: Local<Drag>,
+    mut cursor_reader: Local<EventReader<CursorMoved>>,
+    cursor_moved_events: Res<Events<CursorMoved>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_input: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    state: Res<GameState>,
+    profile: Res<ActiveProfile>,
+    mut layout: ResMut<HudLayout>,
+) {
+    for event in cursor_reader.iter(&cursor_moved_events) {
+        drag.cursor = event.position;
+    }
+
+    let toggled = keyboard_input.just_pressed(KeyCode::F11);
+    if !layout.editing {
+        if toggled && *state == GameState::Paused {
+            layout.editing = true;
+        }
+        return;
+    }
+    if toggled || *state != GameState::Paused {
+        layout.editing = false;
+        drag.held = None;
+        if let Some(profile) = &profile.0 {
+            let path = profile.hud_layout_path();
+            let result = save_format::encode(&*layout)
+                .and_then(|text| fs::write(&path, text).map_err(|err| err.to_string()));
+            match result {
+                Ok(()) => info!(profile = %profile.name, "HUD layout saved"),
+                Err(err) => warn!("could not save HUD layout {}: {}", path.display(), err),
+            }
+        }
+        return;
+    }
+
+    for (&key, &widget) in [
+        KeyCode::Key1,
+        KeyCode::Key2,
+        KeyCode::Key3,
+        KeyCode::Key4,
+        KeyCode::Key5,
+        KeyCode::Key6,
+        KeyCode::Key7,
+        KeyCode::Key8,
+    ]
+    .iter()
+    .zip(HudWidget::ALL.iter())
+    {
+        if keyboard_input.just_pressed(key) {
+            let mut placement = layout.placement(widget);
+            placement.visible = !placement.visible;
+            layout.widgets.insert(widget, placement);
+        }
+    }
+
+    // The cursor is measured from the bottom-left corner, the HUD from the top-left.
+    let window = match primary_window(&windows) {
+        Some(window) => window,
+        None => return,
+    };
+    let left = drag.cursor.x();
+    let top = window.height as f32 - drag.cursor.y();
+    if mouse_input.just_pressed(MouseButton::Left) {
+        drag.held = layout.widget_at(left, top).map(|widget| {
+            let placement = layout.placement(widget);
+            (
+                widget,
+                Vec2::new(left - placement.left, top - placement.top),
+            )
+        });
+    }
+    if mouse_input.just_released(MouseButton::Left) {
+        drag.held = None;
+    }
+    if let Some((widget, grab)) = drag.held {
+        let mut placement = layout.placement(widget);
+        placement.left = (left - grab.x())
+            .max(0.0)
+            .min(window.width as f32 - WIDGET_WIDTH);
+        placement.top = (top - grab.y())
+            .max(0.0)
+            .min(window.height as f32 - WIDGET_HEIGHT);
+        layout.widgets.insert(widget, placement);
+    }
+}
+
+fn apply_layout(layout: Res<HudLayout>, mut widgets: Query<(&HudWidget, &mut Style)>) {
+    for (&widget, mut style) in &mut widgets.iter() {
+        let placement = layout.placement(widget);
+        let position = Rect {
+            left: Val::Px(placement.left),
+            top: Val::Px(placement.top),
+            ..Default::default()
+        };
+        if style.position != position {
+            style.position = position;
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use bevy::prelude::*;
+use tracing::info;
+
+use crate::{
+    game_event::GameEvent, input::LastInput, locale::Locale, toast::Toast, GameOverCause, GameState,
+};
+
+/// Seconds without input before a run is paused.
+const PAUSE_AFTER: f64 = 60.0;
+
+/// Seconds without input on the pause screen before the run is ended and the profile picker
+/// comes back.
+const MENU_AFTER: f64 = 120.0;
+
+pub struct IdlePlugin;
+
+impl Plugin for IdlePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(idle_timeout.system());
+    }
+}
+
+/// Pauses a run nobody is playing, and later gives the game back to the profile picker so the
+/// next player doesn't inherit the run or the profile. Counting restarts on every state change,
+/// so the pause screen gets its full time.
+fn idle_timeout(
+    mut counting: Local<Option<(GameState, f64)>>,
+    time: Res<Time>,
+    last_input: Res<LastInput>,
+    locale: Res<Locale>,
+    mut state: ResMut<GameState>,
+    mut game_events: ResMut<Events<GameEvent>>,
+    mut toasts: ResMut<Events<Toast>>,
+) {
+    let now = time.seconds_since_startup;
+    let since = match *counting {
+        Some((counted_state, since)) if counted_state == *state => since.max(last_input.0),
+        _ => now,
+    };
+    *counting = Some((*state, since));
+
+    let idle = now - since;
+    match *state {
+        GameState::Playing if idle >= PAUSE_AFTER => {
+            info!("paused after no input");
+            *state = GameState::Paused;
+            toasts.send(Toast::new(locale.text("idle-paused")));
+        }
+        GameState::Paused if idle >= MENU_AFTER => {
+            info!("run ended after no input");
+            game_events.send(GameEvent::GameOver {
+                cause: GameOverCause::Idle,
+            });
+            *state = GameState::ProfileSelect;
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,491 @@
+use bevy::{input::mouse::MouseWheel, prelude::*, window::CursorMoved};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    accessibility::Announcement, locale::Locale, Direction, GameState, Position, Run, SnakeHead,
+    ARENA_HEIGHT, ARENA_WIDTH,
+};
+
+/// Something the player asked for, independent of the device they used. Gameplay systems read
+/// these instead of polling devices directly.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Action {
+    Turn(Direction),
+    TogglePause,
+}
+
+/// Minimum drag distance, in pixels, for a press to count as a swipe rather than a tap.
+const SWIPE_THRESHOLD: f32 = 30.0;
+
+const DPAD_BUTTON_SIZE: f32 = 60.0;
+
+/// In co-op, one player steers left and right with A and D, the other up and down with the
+/// arrow keys.
+const CO_OP_HORIZONTAL_KEYS: [(KeyCode, Direction); 2] = [
+    (KeyCode::A, Direction::Left),
+    (KeyCode::D, Direction::Right),
+];
+const CO_OP_VERTICAL_KEYS: [(KeyCode, Direction); 2] = [
+    (KeyCode::Up, Direction::Up),
+    (KeyCode::Down, Direction::Down),
+];
+
+/// Keyboard layouts the player can pick from, including layouts usable with one hand.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize)]
+pub enum ControlPreset {
+    Arrows,
+    Wasd,
+    Ijkl,
+    Numpad,
+    /// A single key turns the snake clockwise.
+    OneButton,
+}
+
+impl Default for ControlPreset {
+    fn default() -> Self {
+        Self::Arrows
+    }
+}
+
+impl ControlPreset {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Arrows => Self::Wasd,
+            Self::Wasd => Self::Ijkl,
+            Self::Ijkl => Self::Numpad,
+            Self::Numpad => Self::OneButton,
+            Self::OneButton => Self::Arrows,
+        }
+    }
+
+    pub fn name_key(self) -> &'static str {
+        match self {
+            Self::Arrows => "controls-arrows",
+            Self::Wasd => "controls-wasd",
+            Self::Ijkl => "controls-ijkl",
+            Self::Numpad => "controls-numpad",
+            Self::OneButton => "controls-one-button",
+        }
+    }
+
+    /// Keys for left, up, right and down, or `None` if the preset doesn't steer by direction.
+    fn direction_keys(self) -> Option<[KeyCode; 4]> {
+        match self {
+            Self::Arrows => Some([KeyCode::Left, KeyCode::Up, KeyCode::Right, KeyCode::Down]),
+            Self::Wasd => Some([KeyCode::A, KeyCode::W, KeyCode::D, KeyCode::S]),
+            Self::Ijkl => Some([KeyCode::J, KeyCode::I, KeyCode::L, KeyCode::K]),
+            Self::Numpad => Some([
+                KeyCode::Numpad4,
+                KeyCode::Numpad8,
+                KeyCode::Numpad6,
+                KeyCode::Numpad2,
+            ]),
+            Self::OneButton => None,
+        }
+    }
+
+    fn pause_key(self) -> KeyCode {
+        match self {
+            // Space is the turn key in one-button mode.
+            Self::OneButton => KeyCode::Escape,
+            _ => KeyCode::Space,
+        }
+    }
+}
+
+/// Keys the control swap can be bound to. None of them steers or pauses in any preset.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize)]
+pub enum SwapKey {
+    Tab,
+    Grave,
+    Insert,
+    Delete,
+    F12,
+}
+
+impl Default for SwapKey {
+    fn default() -> Self {
+        Self::Tab
+    }
+}
+
+impl SwapKey {
+    fn key_code(self) -> KeyCode {
+        match self {
+            Self::Tab => KeyCode::Tab,
+            Self::Grave => KeyCode::Grave,
+            Self::Insert => KeyCode::Insert,
+            Self::Delete => KeyCode::Delete,
+            Self::F12 => KeyCode::F12,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct InputSettings {
+    pub preset: ControlPreset,
+    /// A second preset that `swap_key` switches to and back, e.g. for two players sharing a
+    /// keyboard.
+    pub alternate_preset: Option<ControlPreset>,
+    pub swap_key: SwapKey,
+    pub show_dpad: bool,
+    /// Steer toward the mouse cursor instead of using the keyboard.
+    pub mouse_steering: bool,
+    /// Two players share one snake, each steering along one axis. Replaces the preset's keys.
+    pub co_op: bool,
+}
+
+/// When the player last touched any input device, in seconds since startup.
+#[derive(Default)]
+pub struct LastInput(pub f64);
+
+#[derive(Default)]
+struct PointerState {
+    cursor: Vec2,
+    press_start: Option<Vec2>,
+}
+
+struct DpadMaterials {
+    background: Handle<ColorMaterial>,
+    button: Handle<ColorMaterial>,
+}
+
+struct Dpad;
+
+struct DpadButton(Direction);
+
+pub struct InputPlugin;
+
+impl Plugin for InputPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<InputSettings>()
+            .init_resource::<LastInput>()
+            .add_event::<Action>()
+            .add_startup_system(setup_dpad_materials.system())
+            .add_system(track_last_input.system())
+            .add_system(keyboard_actions.system())
+            .add_system(preset_selection.system())
+            .add_system(preset_swap.system())
+            .add_system(pointer_actions.system())
+            .add_system(dpad_actions.system())
+            .add_system(dpad_toggle.system())
+            .add_system(sync_dpad.system())
+            .add_system(mouse_steering.system())
+            .add_system(mouse_steering_toggle.system());
+    }
+}
+
+fn setup_dpad_materials(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+    commands.insert_resource(DpadMaterials {
+        background: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.0).into()),
+        button: materials.add(Color::rgba(1.0, 1.0, 1.0, 0.2).into()),
+    });
+}
+
+/// Watches the devices themselves rather than actions, so keys that don't map to an action
+/// and menus that read keys directly still count.
+fn track_last_input(
+    mut cursor_reader: Local<EventReader<CursorMoved>>,
+    mut wheel_reader: Local<EventReader<MouseWheel>>,
+    time: Res<Time>,
+    cursor_moved_events: Res<Events<CursorMoved>>,
+    wheel_events: Res<Events<MouseWheel>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_input: Res<Input<MouseButton>>,
+    mut last_input: ResMut<LastInput>,
+) {
+    let moved = cursor_reader.iter(&cursor_moved_events).count() > 0;
+    let scrolled = wheel_reader.iter(&wheel_events).count() > 0;
+    let pressed =
+        keyboard_input.get_pressed().next().is_some() || mouse_input.get_pressed().next().is_some();
+    if moved || scrolled || pressed {
+        last_input.0 = time.seconds_since_startup;
+    }
+}
+
+fn keyboard_actions(
+    mut last_co_op_turn: Local<Option<u64>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    settings: Res<InputSettings>,
+    run: Res<Run>,
+    mut actions: ResMut<Events<Action>>,
+    mut heads: Query<&SnakeHead>,
+) {
+    if settings.co_op {
+        let direction = (&mut heads.iter())
+            .into_iter()
+            .next()
+            .map(|head| head.direction);
+        if let Some(dir) = co_op_turn(&keyboard_input, direction) {
+            // One turn per move, so the players can't undo each other's turn before the snake
+            // has moved on it.
+            if *last_co_op_turn != Some(run.tick) {
+                *last_co_op_turn = Some(run.tick);
+                actions.send(Action::Turn(dir));
+            }
+        }
+    } else if let Some([left, up, right, down]) = settings.preset.direction_keys() {
+        // Later keys win when several are held, matching the old polling order.
+        let mut turn = None;
+        for &(key, dir) in &[
+            (left, Direction::Left),
+            (right, Direction::Right),
+            (down, Direction::Down),
+            (up, Direction::Up),
+        ] {
+            if keyboard_input.pressed(key) {
+                turn = Some(dir);
+            }
+        }
+        if let Some(dir) = turn {
+            actions.send(Action::Turn(dir));
+        }
+    } else if keyboard_input.just_pressed(KeyCode::Space) {
+        if let Some(head) = (&mut heads.iter()).into_iter().next() {
+            actions.send(Action::Turn(head.direction.clockwise()));
+        }
+    }
+
+    if keyboard_input.just_pressed(settings.preset.pause_key()) {
+        actions.send(Action::TogglePause);
+    }
+}
+
+/// Merges both co-op players' keys into at most one turn. Only the player whose axis crosses the
+/// snake's heading can turn it, so when both press at once that player wins, and the other gets
+/// their turn once the snake is heading their way.
+fn co_op_turn(keyboard_input: &Input<KeyCode>, heading: Option<Direction>) -> Option<Direction> {
+    let pressed = |keys: &[(KeyCode, Direction)]| {
+        keys.iter()
+            .filter(|&&(key, _)| keyboard_input.pressed(key))
+            .map(|&(_, dir)| dir)
+            .last()
+    };
+    let horizontal = pressed(&CO_OP_HORIZONTAL_KEYS);
+    let vertical = pressed(&CO_OP_VERTICAL_KEYS);
+    match heading {
+        Some(Direction::Left) | Some(Direction::Right) => vertical,
+        Some(Direction::Up) | Some(Direction::Down) => horizontal,
+        None => None,
+    }
+}
+
+fn preset_selection(
+    keyboard_input: Res<Input<KeyCode>>,
+    locale: Res<Locale>,
+    mut settings: ResMut<InputSettings>,
+    mut announcements: ResMut<Events<Announcement>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F7) {
+        settings.preset = settings.preset.next();
+        announcements.send(Announcement::essential(locale.format(
+            "controls-changed",
+            &[("controls", locale.text(settings.preset.name_key()))],
+        )));
+    }
+}
+
+/// Swaps between the two configured presets during a run.
+fn preset_swap(
+    keyboard_input: Res<Input<KeyCode>>,
+    state: Res<GameState>,
+    locale: Res<Locale>,
+    mut settings: ResMut<InputSettings>,
+    mut announcements: ResMut<Events<Announcement>>,
+) {
+    // On the menus the swap key may mean something else, like Tab for a tournament.
+    if !matches!(*state, GameState::Playing | GameState::Paused)
+        || !keyboard_input.just_pressed(settings.swap_key.key_code())
+    {
+        return;
+    }
+    if let Some(alternate) = settings.alternate_preset {
+        settings.alternate_preset = Some(settings.preset);
+        settings.preset = alternate;
+        announcements.send(Announcement::essential(locale.format(
+            "controls-changed",
+            &[("controls", locale.text(alternate.name_key()))],
+        )));
+    }
+}
+
+/// Turns pointer gestures into actions: a swipe turns the snake and a tap toggles pause. Bevy
+/// doesn't expose touch events yet, so this reads the primary pointer, which is what platforms
+/// that emulate a mouse from touch input report.
+fn pointer_actions(
+    mut state: Local<PointerState>,
+    mut cursor_reader: Local<EventReader<CursorMoved>>,
+    cursor_moved_events: Res<Events<CursorMoved>>,
+    mouse_input: Res<Input<MouseButton>>,
+    mut actions: ResMut<Events<Action>>,
+    mut dpad_buttons: Query<(&DpadButton, &Interaction)>,
+) {
+    for event in cursor_reader.iter(&cursor_moved_events) {
+        state.cursor = event.position;
+    }
+
+    if mouse_input.just_pressed(MouseButton::Left) {
+        // Presses on the d-pad are handled by the buttons themselves.
+        let mut on_dpad = false;
+        for (_button, interaction) in &mut dpad_buttons.iter() {
+            on_dpad |= *interaction != Interaction::None;
+        }
+        state.press_start = if on_dpad { None } else { Some(state.cursor) };
+    }
+
+    if mouse_input.just_released(MouseButton::Left) {
+        if let Some(start) = state.press_start.take() {
+            let delta = state.cursor - start;
+            let action = if delta.length() < SWIPE_THRESHOLD {
+                Action::TogglePause
+            } else if delta.x().abs() > delta.y().abs() {
+                if delta.x() > 0.0 {
+                    Action::Turn(Direction::Right)
+                } else {
+                    Action::Turn(Direction::Left)
+                }
+            } else if delta.y() > 0.0 {
+                Action::Turn(Direction::Up)
+            } else {
+                Action::Turn(Direction::Down)
+            };
+            actions.send(action);
+        }
+    }
+}
+
+/// Turns the snake toward whichever side of the head the cursor is furthest along, so it keeps
+/// chasing the cursor. Turns away from the cursor are illegal reversals and get dropped by
+/// movement.
+fn mouse_steering(
+    mut cursor: Local<Option<Vec2>>,
+    mut cursor_reader: Local<EventReader<CursorMoved>>,
+    cursor_moved_events: Res<Events<CursorMoved>>,
+    settings: Res<InputSettings>,
+    windows: Res<Windows>,
+    mut actions: ResMut<Events<Action>>,
+    mut heads: Query<(&SnakeHead, &Position)>,
+) {
+    for event in cursor_reader.iter(&cursor_moved_events) {
+        *cursor = Some(event.position);
+    }
+    if !settings.mouse_steering {
+        return;
+    }
+    let (cursor, window) = match (*cursor, windows.get_primary()) {
+        (Some(cursor), Some(window)) => (cursor, window),
+        _ => return,
+    };
+
+    let target_x = cursor.x() / window.width as f32 * ARENA_WIDTH as f32;
+    let target_y = cursor.y() / window.height as f32 * ARENA_HEIGHT as f32;
+    if let Some((_head, head_pos)) = (&mut heads.iter()).into_iter().next() {
+        let dx = target_x - head_pos.x as f32;
+        let dy = target_y - head_pos.y as f32;
+        let dir = if dx.abs() > dy.abs() {
+            if dx > 0.0 {
+                Direction::Right
+            } else {
+                Direction::Left
+            }
+        } else if dy > 0.0 {
+            Direction::Up
+        } else {
+            Direction::Down
+        };
+        actions.send(Action::Turn(dir));
+    }
+}
+
+fn mouse_steering_toggle(keyboard_input: Res<Input<KeyCode>>, mut settings: ResMut<InputSettings>) {
+    if keyboard_input.just_pressed(KeyCode::F6) {
+        settings.mouse_steering = !settings.mouse_steering;
+    }
+}
+
+fn dpad_actions(
+    mut actions: ResMut<Events<Action>>,
+    mut interactions: Query<(&DpadButton, Mutated<Interaction>)>,
+) {
+    for (button, interaction) in &mut interactions.iter() {
+        if *interaction == Interaction::Clicked {
+            actions.send(Action::Turn(button.0));
+        }
+    }
+}
+
+fn dpad_toggle(keyboard_input: Res<Input<KeyCode>>, mut settings: ResMut<InputSettings>) {
+    if keyboard_input.just_pressed(KeyCode::F5) {
+        settings.show_dpad = !settings.show_dpad;
+    }
+}
+
+/// Spawns or removes the d-pad whenever `show_dpad` changes, however it was changed.
+fn sync_dpad(
+    mut shown: Local<bool>,
+    mut commands: Commands,
+    materials: Res<DpadMaterials>,
+    settings: Res<InputSettings>,
+    mut dpads: Query<(Entity, &Dpad)>,
+) {
+    if *shown == settings.show_dpad {
+        return;
+    }
+    *shown = settings.show_dpad;
+    if settings.show_dpad {
+        spawn_dpad(&mut commands, &materials);
+    } else {
+        for (ent, _dpad) in &mut dpads.iter() {
+            commands.despawn_recursive(ent);
+        }
+    }
+}
+
+fn spawn_dpad(commands: &mut Commands, materials: &DpadMaterials) {
+    let button_style = |left: f32, bottom: f32| Style {
+        position_type: PositionType::Absolute,
+        position: Rect {
+            left: Val::Px(left),
+            bottom: Val::Px(bottom),
+            ..Default::default()
+        },
+        size: Size::new(Val::Px(DPAD_BUTTON_SIZE), Val::Px(DPAD_BUTTON_SIZE)),
+        ..Default::default()
+    };
+
+    commands
+        .spawn(NodeComponents {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    right: Val::Px(20.0),
+                    bottom: Val::Px(20.0),
+                    ..Default::default()
+                },
+                size: Size::new(
+                    Val::Px(DPAD_BUTTON_SIZE * 3.0),
+                    Val::Px(DPAD_BUTTON_SIZE * 3.0),
+                ),
+                ..Default::default()
+            },
+            material: materials.background,
+            ..Default::default()
+        })
+        .with(Dpad)
+        .with_children(|parent| {
+            for &(direction, column, row) in &[
+                (Direction::Up, 1.0, 2.0),
+                (Direction::Left, 0.0, 1.0),
+                (Direction::Right, 2.0, 1.0),
+                (Direction::Down, 1.0, 0.0),
+            ] {
+                parent
+                    .spawn(ButtonComponents {
+                        style: button_style(column * DPAD_BUTTON_SIZE, row * DPAD_BUTTON_SIZE),
+                        material: materials.button,
+                        ..Default::default()
+                    })
+                    .with(DpadButton(direction));
+            }
+        });
+}
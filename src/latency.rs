@@ -0,0 +1,170 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use crate::{
+    input::Action, locale::Locale, split::Ally, ui::UiFonts, Direction, GameState, Run, SnakeHead,
+};
+
+/// Presses that haven't turned the snake after this long were dropped, e.g. by ice or by
+/// trying to reverse, and stop being waited on.
+const GIVE_UP_AFTER: f64 = 2.0;
+
+/// How many recent presses the stats cover.
+const MAX_SAMPLES: usize = 100;
+
+const LATENCY_FONT_SIZE: f32 = 20.0;
+
+/// Recent latencies, in seconds.
+#[derive(Default)]
+struct Samples(VecDeque<f64>);
+
+impl Samples {
+    fn push(&mut self, seconds: f64) {
+        self.0.push_back(seconds);
+        if self.0.len() > MAX_SAMPLES {
+            self.0.pop_front();
+        }
+    }
+
+    /// Min, average and max in whole milliseconds, or dashes before the first sample.
+    fn summary(&self) -> [(&'static str, String); 3] {
+        let ms = |seconds: f64| format!("{:.0}", seconds * 1000.0);
+        if self.0.is_empty() {
+            return [
+                ("min", "-".to_string()),
+                ("avg", "-".to_string()),
+                ("max", "-".to_string()),
+            ];
+        }
+        let min = self.0.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = self.0.iter().copied().fold(0.0, f64::max);
+        let avg = self.0.iter().sum::<f64>() / self.0.len() as f64;
+        [("min", ms(min)), ("avg", ms(avg)), ("max", ms(max))]
+    }
+}
+
+/// A turn the player asked for that hasn't been fully measured yet.
+struct Press {
+    direction: Direction,
+    /// Seconds since startup of the frame the press arrived in.
+    at: f64,
+    tick: u64,
+    turned: bool,
+    moved: bool,
+}
+
+/// A debug overlay timing each turn from the frame its key press arrives to the frame the
+/// head faces the new way, and to the frame of the next move. Times are only as fine as the
+/// frame rate.
+#[derive(Default)]
+pub struct LatencyOverlay {
+    pub open: bool,
+    pending: VecDeque<Press>,
+    to_turn: Samples,
+    to_move: Samples,
+}
+
+struct LatencyLine;
+
+pub struct LatencyPlugin;
+
+impl Plugin for LatencyPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<LatencyOverlay>()
+            .add_system(measure_latency.system())
+            .add_system(draw_latency.system());
+    }
+}
+
+fn measure_latency(
+    mut reader: Local<EventReader<Action>>,
+    time: Res<Time>,
+    actions: Res<Events<Action>>,
+    state: Res<GameState>,
+    run: Res<Run>,
+    mut overlay: ResMut<LatencyOverlay>,
+    mut heads: Query<Without<Ally, &SnakeHead>>,
+) {
+    let now = time.seconds_since_startup;
+    let facing: Vec<Direction> = heads.iter().iter().map(|head| head.direction).collect();
+    for action in reader.iter(&actions) {
+        let direction = match *action {
+            Action::Turn(direction) => direction,
+            _ => continue,
+        };
+        // Pressing the way the snake already goes turns nothing, so there'd be nothing to time.
+        if !overlay.open || *state != GameState::Playing || facing.contains(&direction) {
+            continue;
+        }
+        overlay.pending.push_back(Press {
+            direction,
+            at: now,
+            tick: run.tick,
+            turned: false,
+            moved: false,
+        });
+    }
+
+    let LatencyOverlay {
+        pending,
+        to_turn,
+        to_move,
+        ..
+    } = &mut *overlay;
+    for press in pending.iter_mut() {
+        if !press.turned && facing.contains(&press.direction) {
+            press.turned = true;
+            to_turn.push(now - press.at);
+        }
+        if !press.moved && run.tick > press.tick {
+            press.moved = true;
+            to_move.push(now - press.at);
+        }
+    }
+    pending.retain(|press| !(press.turned && press.moved) && now - press.at < GIVE_UP_AFTER);
+}
+
+/// Redraws the overlay in the bottom-right corner whenever its text changes.
+fn draw_latency(
+    mut drawn: Local<Vec<String>>,
+    mut commands: Commands,
+    fonts: Res<UiFonts>,
+    locale: Res<Locale>,
+    overlay: Res<LatencyOverlay>,
+    mut lines: Query<(Entity, &LatencyLine)>,
+) {
+    let mut text = Vec::new();
+    if overlay.open {
+        text.push(locale.text("latency-title"));
+        text.push(locale.format("latency-turn", &overlay.to_turn.summary()));
+        text.push(locale.format("latency-move", &overlay.to_move.summary()));
+    }
+    if *drawn == text {
+        return;
+    }
+    *drawn = text.clone();
+
+    for (ent, _line) in &mut lines.iter() {
+        commands.despawn(ent);
+    }
+    let rows = text.len();
+    for (row, line) in text.into_iter().enumerate() {
+        let bottom = 10.0 + (rows - 1 - row) as f32 * (LATENCY_FONT_SIZE + 6.0);
+        commands
+            .spawn(TextComponents {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        right: Val::Px(10.0),
+                        bottom: Val::Px(bottom),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                text: fonts.text(line, LATENCY_FONT_SIZE),
+                ..Default::default()
+            })
+            .with(LatencyLine);
+    }
+}
@@ -0,0 +1,654 @@
+use std::{
+    collections::HashSet,
+    env,
+    f32::consts::FRAC_PI_4,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    despawn_arena,
+    dialog::Dialog,
+    director::reachable_cells,
+    game_event::GameEvent,
+    locale::Locale,
+    obstacle::{Obstacle, Switch},
+    power_up::PowerUp,
+    puzzle::{self, Puzzle, Solution},
+    save_format::{self, Versioned},
+    sized_sprite,
+    snapshot::Snapshot,
+    spawn_initial_snake,
+    split::Ally,
+    tiles::TileKind,
+    toast::Toast,
+    Dead, Direction, Food, GameOverCause, GameState, HeadMaterial, Position, Run, Score,
+    SegmentMaterial, Size, SnakeHead, SnakeSegment, START_POSITION,
+};
+
+pub const LEVELS_DIR: &str = "levels";
+
+/// Above the floor tiles, below the night overlay and the snake. The overlay's depth is worked
+/// out from this one so the two never end up level.
+pub const LEVEL_DEPTH: f32 = -0.03;
+
+/// How hard a level's author says it is.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl Difficulty {
+    pub fn name_key(self) -> &'static str {
+        match self {
+            Self::Easy => "difficulty-easy",
+            Self::Medium => "difficulty-medium",
+            Self::Hard => "difficulty-hard",
+        }
+    }
+}
+
+/// A hand-made arena, as saved by the editor. Anything it doesn't mention is open floor.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Level {
+    /// Also what scores on the level are recorded under in the profile.
+    pub name: String,
+    pub author: String,
+    pub difficulty: Difficulty,
+    /// Where the snake's head starts. It faces up, with its one segment just below.
+    pub spawn: Position,
+    /// Cells that end the run like the arena's edge.
+    pub walls: Vec<Position>,
+    pub tiles: Vec<(Position, TileKind)>,
+    /// Portals link in pairs in the order they were placed: the first with the second, the
+    /// third with the fourth, and so on. A head entering one comes out of its partner.
+    pub portals: Vec<Position>,
+    /// Reaching any of these completes the level.
+    pub exits: Vec<Position>,
+    /// Reaching one saves the run there, so a death afterwards carries on from it with the
+    /// length and score the snake had, instead of starting the level over.
+    pub checkpoints: Vec<Position>,
+    /// Patrols and timed gates, which block like walls wherever they are at the time.
+    pub obstacles: Vec<Obstacle>,
+    /// Pressure plates, each with the doors it opens.
+    pub switches: Vec<Switch>,
+    /// Makes the level a puzzle, played a move at a time with hand-placed food.
+    pub puzzle: Option<Puzzle>,
+    /// The scores that earn one, two and three stars when the level is completed.
+    pub star_scores: [u32; 3],
+    /// If set, each star also has to be earned within this many seconds of play.
+    pub star_times: Option<[f32; 3]>,
+}
+
+impl Default for Level {
+    fn default() -> Self {
+        Self {
+            name: "custom".to_string(),
+            author: String::new(),
+            difficulty: Difficulty::Medium,
+            spawn: START_POSITION,
+            walls: Vec::new(),
+            tiles: Vec::new(),
+            portals: Vec::new(),
+            exits: Vec::new(),
+            checkpoints: Vec::new(),
+            obstacles: Vec::new(),
+            switches: Vec::new(),
+            puzzle: None,
+            star_scores: [0, 0, 0],
+            star_times: None,
+        }
+    }
+}
+
+impl Versioned for Level {
+    const KIND: &'static str = "level";
+    const VERSION: u32 = 1;
+}
+
+/// Why a level can't be played.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LevelError {
+    /// The file couldn't be read or parsed.
+    Unreadable(String),
+    /// Walls, tiles, portals, exits, checkpoints, obstacles, plates or doors placed outside the
+    /// arena, which is fixed at `ARENA_WIDTH` by `ARENA_HEIGHT`.
+    OutsideArena { count: usize },
+    /// The spawn point or the cell just below it, where the first segment goes, isn't open
+    /// floor inside the arena.
+    SpawnBlocked,
+    /// The last portal placed has no partner.
+    UnpairedPortal(Position),
+    /// No free cell is reachable from the spawn point, so food has nowhere to go.
+    NoFoodCell,
+    /// A star needs a lower score, or allows more time, than the one before it.
+    StarsOutOfOrder,
+    /// A puzzle with no food to eat.
+    PuzzleWithoutFood,
+    /// A puzzle with tiles, obstacles, doors or exits, which the solver can't account for.
+    PuzzleUnsupported,
+    /// No way was found to eat all of a puzzle's food within its moves.
+    PuzzleUnsolvable { moves: u32 },
+    /// The solver gave up before finding out whether the puzzle can be done.
+    PuzzleTooOpen,
+}
+
+impl LevelError {
+    /// A sentence the player can act on.
+    pub fn describe(&self, locale: &Locale) -> String {
+        match self {
+            Self::Unreadable(reason) => {
+                locale.format("level-error-unreadable", &[("reason", reason.clone())])
+            }
+            Self::OutsideArena { count } => {
+                locale.format("level-error-outside", &[("count", count.to_string())])
+            }
+            Self::SpawnBlocked => locale.text("level-error-spawn"),
+            Self::UnpairedPortal(position) => locale.format(
+                "level-error-portal",
+                &[("x", position.x.to_string()), ("y", position.y.to_string())],
+            ),
+            Self::NoFoodCell => locale.text("level-error-no-food"),
+            Self::StarsOutOfOrder => locale.text("level-error-stars"),
+            Self::PuzzleWithoutFood => locale.text("level-error-puzzle-food"),
+            Self::PuzzleUnsupported => locale.text("level-error-puzzle-unsupported"),
+            Self::PuzzleUnsolvable { moves } => locale.format(
+                "level-error-puzzle-unsolvable",
+                &[("moves", moves.to_string())],
+            ),
+            Self::PuzzleTooOpen => locale.text("level-error-puzzle-too-open"),
+        }
+    }
+}
+
+impl Level {
+    /// Reads a level without checking it can be played; see `validate`.
+    pub fn read(path: &Path) -> Result<Self, LevelError> {
+        let text =
+            fs::read_to_string(path).map_err(|err| LevelError::Unreadable(err.to_string()))?;
+        save_format::decode(&text).map_err(LevelError::Unreadable)
+    }
+
+    /// Reads a level and checks it can be played.
+    pub fn load(path: &Path) -> Result<Self, Vec<LevelError>> {
+        let level = Self::read(path).map_err(|err| vec![err])?;
+        level.validate()?;
+        Ok(level)
+    }
+
+    /// Every reason the level can't be played, or none.
+    pub fn validate(&self) -> Result<(), Vec<LevelError>> {
+        let mut errors = Vec::new();
+
+        let obstacle_cells = self.obstacle_cells();
+        let outside = self
+            .walls
+            .iter()
+            .chain(self.tiles.iter().map(|(position, _kind)| position))
+            .chain(&self.portals)
+            .chain(&self.exits)
+            .chain(&self.checkpoints)
+            .chain(&obstacle_cells)
+            .chain(self.switches.iter().map(|switch| &switch.plate))
+            .chain(self.puzzle.iter().flat_map(|puzzle| &puzzle.food))
+            .filter(|position| position.outside_arena())
+            .count();
+        if outside > 0 {
+            errors.push(LevelError::OutsideArena { count: outside });
+        }
+
+        let occupied = |position: Position| {
+            position.outside_arena()
+                || self.is_wall(position)
+                || self.portals.contains(&position)
+                || self.exits.contains(&position)
+                || obstacle_cells.contains(&position)
+        };
+        let tail = self.spawn.moved(Direction::Down);
+        let spawn_blocked = occupied(self.spawn) || occupied(tail);
+        if spawn_blocked {
+            errors.push(LevelError::SpawnBlocked);
+        }
+
+        if self.portals.len() % 2 == 1 {
+            errors.push(LevelError::UnpairedPortal(*self.portals.last().unwrap()));
+        }
+
+        // Food never lands on the snake, a portal or an exit, so one other cell the snake can
+        // walk to has to be left. Portals aren't followed, which only ever undercounts.
+        if !spawn_blocked {
+            let walls: HashSet<Position> = self.walls.iter().copied().collect();
+            let has_food_cell = reachable_cells(self.spawn, &walls, false)
+                .into_iter()
+                .any(|cell| cell != self.spawn && cell != tail && !occupied(cell));
+            if !has_food_cell {
+                errors.push(LevelError::NoFoodCell);
+            }
+        }
+
+        let times_out_of_order = self.star_times.map_or(false, |times| {
+            times.windows(2).any(|pair| pair[0] < pair[1])
+        });
+        if self.star_scores.windows(2).any(|pair| pair[0] > pair[1]) || times_out_of_order {
+            errors.push(LevelError::StarsOutOfOrder);
+        }
+
+        // Searching is slow, so only a level that's otherwise fine gets solved. The solver only
+        // knows walls and portals, so it can't vouch for a puzzle with anything else in it.
+        let unsupported = !self.tiles.is_empty()
+            || !self.obstacles.is_empty()
+            || !self.switches.is_empty()
+            || !self.exits.is_empty();
+        match &self.puzzle {
+            Some(puzzle) if puzzle.food.is_empty() => errors.push(LevelError::PuzzleWithoutFood),
+            Some(_puzzle) if unsupported => errors.push(LevelError::PuzzleUnsupported),
+            Some(puzzle) if errors.is_empty() => match puzzle::check_solvable(self, puzzle) {
+                Solution::Moves(_) => {}
+                Solution::Impossible => errors.push(LevelError::PuzzleUnsolvable {
+                    moves: puzzle.moves,
+                }),
+                Solution::GaveUp => errors.push(LevelError::PuzzleTooOpen),
+            },
+            _ => {}
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// How many stars completing the level with `score` after `seconds` of play earns.
+    pub fn stars_for(&self, score: u32, seconds: f32) -> u8 {
+        (0..3)
+            .take_while(|&star| {
+                score >= self.star_scores[star]
+                    && self.star_times.map_or(true, |times| seconds <= times[star])
+            })
+            .count() as u8
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let text = save_format::encode(self)?;
+        fs::create_dir_all(LEVELS_DIR)
+            .and_then(|_| fs::write(path, text))
+            .map_err(|err| err.to_string())
+    }
+
+    pub fn is_wall(&self, position: Position) -> bool {
+        self.walls.contains(&position)
+    }
+
+    /// Every cell an obstacle or a door can ever block.
+    pub fn obstacle_cells(&self) -> Vec<Position> {
+        let doors = self
+            .switches
+            .iter()
+            .flat_map(|switch| switch.doors.iter().copied());
+        self.obstacles
+            .iter()
+            .flat_map(Obstacle::cells)
+            .chain(doors)
+            .collect()
+    }
+
+    /// Where a head entering `position` comes out, if it's a portal with a partner.
+    pub fn portal_exit(&self, position: Position) -> Option<Position> {
+        let index = self.portals.iter().position(|&portal| portal == position)?;
+        self.portals.get(index ^ 1).copied()
+    }
+
+    /// Clears whatever is at `position`, apart from the spawn point.
+    pub fn erase(&mut self, position: Position) {
+        self.walls.retain(|&wall| wall != position);
+        self.tiles.retain(|&(tile, _kind)| tile != position);
+        self.portals.retain(|&portal| portal != position);
+        self.exits.retain(|&exit| exit != position);
+        self.checkpoints
+            .retain(|&checkpoint| checkpoint != position);
+        self.obstacles
+            .retain(|obstacle| !obstacle.cells().contains(&position));
+        self.switches
+            .retain(|switch| switch.plate != position && !switch.doors.contains(&position));
+        if let Some(puzzle) = &mut self.puzzle {
+            puzzle.food.retain(|&food| food != position);
+        }
+    }
+}
+
+/// The level being played or edited. Without one the arena is open and its floor is laid out
+/// by the tile rules.
+#[derive(Default)]
+pub struct ActiveLevel(pub Option<Level>);
+
+impl ActiveLevel {
+    pub fn is_puzzle(&self) -> bool {
+        self.0
+            .as_ref()
+            .map_or(false, |level| level.puzzle.is_some())
+    }
+}
+
+/// Set by screens that pick a level to play, to start a fresh run on it.
+#[derive(Default)]
+pub struct RestartRun(pub bool);
+
+/// The run as it was at the last checkpoint reached. It only belongs to the run with the same
+/// seed, so any fresh run leaves it behind.
+#[derive(Default)]
+pub struct Checkpoint(pub Option<Snapshot>);
+
+/// Shows why a level can't be played.
+pub fn level_error_dialog(
+    path: &Path,
+    errors: &[LevelError],
+    locale: &Locale,
+    dialogs: &mut Events<Dialog>,
+) {
+    for err in errors {
+        warn!("level {}: {:?}", path.display(), err);
+    }
+    dialogs.send(Dialog::new(
+        locale.format("level-error-title", &[("path", path.display().to_string())]),
+        errors.iter().map(|err| err.describe(locale)).collect(),
+    ));
+}
+
+/// The level named on the command line with `--level <path>`, if any.
+fn level_arg() -> Option<PathBuf> {
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--level" {
+            return args.next().map(PathBuf::from);
+        }
+    }
+    None
+}
+
+/// Starts on the level given with `--level`. One that can't be played leaves the arena open
+/// and says why rather than stopping the game.
+fn load_level_arg(
+    locale: Res<Locale>,
+    mut level: ResMut<ActiveLevel>,
+    mut dialogs: ResMut<Events<Dialog>>,
+) {
+    let path = match level_arg() {
+        Some(path) => path,
+        None => return,
+    };
+    match Level::load(&path) {
+        Ok(loaded) => {
+            info!(path = %path.display(), "level loaded");
+            level.0 = Some(loaded);
+        }
+        Err(errors) => level_error_dialog(&path, &errors, &locale, &mut dialogs),
+    }
+}
+
+/// Any sprite drawn for the level.
+struct LevelMarker;
+
+pub const WALL_COLOR: Color = Color::rgb(0.45, 0.45, 0.5);
+
+pub struct LevelMaterials {
+    pub wall: Handle<ColorMaterial>,
+    portal: Handle<ColorMaterial>,
+    exit: Handle<ColorMaterial>,
+    checkpoint: Handle<ColorMaterial>,
+    spawn: Handle<ColorMaterial>,
+    puzzle_food: Handle<ColorMaterial>,
+}
+
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<ActiveLevel>()
+            .init_resource::<RestartRun>()
+            .init_resource::<Checkpoint>()
+            .add_startup_system(setup_level_materials.system())
+            .add_startup_system(load_level_arg.system())
+            .add_system(draw_level.system())
+            .add_system(restart_run.system())
+            .add_system(move_to_spawn.system())
+            .add_system(level_hazards.system())
+            .add_system(reach_checkpoint.system());
+    }
+}
+
+fn setup_level_materials(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+    commands.insert_resource(LevelMaterials {
+        wall: materials.add(WALL_COLOR.into()),
+        portal: materials.add(Color::rgb(0.6, 0.3, 0.9).into()),
+        exit: materials.add(Color::rgb(0.2, 0.85, 0.4).into()),
+        checkpoint: materials.add(Color::rgb(0.95, 0.75, 0.2).into()),
+        spawn: materials.add(Color::rgba(1.0, 1.0, 1.0, 0.5).into()),
+        puzzle_food: materials.add(Color::rgba(1.0, 0.0, 1.0, 0.6).into()),
+    });
+}
+
+/// Redraws the walls, portals, exits and checkpoints whenever the level changes. The spawn
+/// point and a puzzle's food are only marked in the editor, since the food is real once the
+/// level is played. Floor tiles are drawn by `tiles`.
+fn draw_level(
+    mut drawn: Local<Option<(Level, bool)>>,
+    mut commands: Commands,
+    level: Res<ActiveLevel>,
+    state: Res<GameState>,
+    materials: Res<LevelMaterials>,
+    mut markers: Query<(Entity, &LevelMarker)>,
+) {
+    let editing = *state == GameState::Editor;
+    let wanted = level.0.clone().map(|level| (level, editing));
+    if *drawn == wanted {
+        return;
+    }
+    *drawn = wanted;
+
+    for (ent, _marker) in &mut markers.iter() {
+        commands.despawn(ent);
+    }
+    let level = match &level.0 {
+        Some(level) => level,
+        None => return,
+    };
+    // Portals are diamonds, exits small squares and checkpoints smaller ones, so none of them
+    // relies on color alone.
+    let mut sprites: Vec<(Position, Handle<ColorMaterial>, f32, f32)> = Vec::new();
+    sprites.extend(
+        level
+            .walls
+            .iter()
+            .map(|&wall| (wall, materials.wall, 0.0, 1.0)),
+    );
+    sprites.extend(
+        level
+            .portals
+            .iter()
+            .map(|&portal| (portal, materials.portal, FRAC_PI_4, 0.7)),
+    );
+    sprites.extend(
+        level
+            .exits
+            .iter()
+            .map(|&exit| (exit, materials.exit, 0.0, 0.6)),
+    );
+    sprites.extend(
+        level
+            .checkpoints
+            .iter()
+            .map(|&checkpoint| (checkpoint, materials.checkpoint, 0.0, 0.35)),
+    );
+    if editing {
+        sprites.push((level.spawn, materials.spawn, 0.0, 0.9));
+        if let Some(puzzle) = &level.puzzle {
+            sprites.extend(
+                puzzle
+                    .food
+                    .iter()
+                    .map(|&food| (food, materials.puzzle_food, FRAC_PI_4, 0.6)),
+            );
+        }
+    }
+    for (position, material, rotation, size) in sprites {
+        let mut transform = Transform::from_rotation(Quat::from_rotation_z(rotation));
+        transform.set_translation(Vec3::new(0.0, 0.0, LEVEL_DEPTH));
+        commands
+            .spawn(SpriteComponents {
+                transform,
+                draw: Draw {
+                    is_transparent: true,
+                    ..Default::default()
+                },
+                ..sized_sprite(material)
+            })
+            .with(LevelMarker)
+            .with(position)
+            .with(Size::square(size));
+    }
+}
+
+fn restart_run(
+    mut commands: Commands,
+    head_material: Res<HeadMaterial>,
+    segment_material: Res<SegmentMaterial>,
+    mut restart: ResMut<RestartRun>,
+    mut run: ResMut<Run>,
+    mut score: ResMut<Score>,
+    mut segments: Query<(Entity, &SnakeSegment)>,
+    mut food: Query<(Entity, &Food)>,
+    mut power_ups: Query<(Entity, &PowerUp)>,
+    mut heads: Query<(Entity, &SnakeHead)>,
+) {
+    if !restart.0 {
+        return;
+    }
+    restart.0 = false;
+    despawn_arena(
+        &mut commands,
+        &mut segments,
+        &mut food,
+        &mut power_ups,
+        &mut heads,
+    );
+    *run = Run::default();
+    score.0 = 0;
+    spawn_initial_snake(&mut commands, head_material, segment_material);
+}
+
+/// Moves the snake of every new run to the level's spawn point, and again if the level changes
+/// before the run's first move.
+fn move_to_spawn(
+    mut placed: Local<Option<(u64, Position)>>,
+    run: Res<Run>,
+    level: Res<ActiveLevel>,
+    mut heads: Query<Without<Ally, (&SnakeHead, &mut Position)>>,
+    segments: Query<(&SnakeSegment, &mut Position)>,
+) {
+    let level = match &level.0 {
+        Some(level) => level,
+        None => return,
+    };
+    if *placed == Some((run.seed, level.spawn)) || run.tick > 0 {
+        return;
+    }
+    for (head, mut position) in &mut heads.iter() {
+        *position = level.spawn;
+        if let Ok(mut segment) = segments.get_mut::<Position>(head.next_segment) {
+            *segment = level.spawn.moved(Direction::Down);
+        }
+        *placed = Some((run.seed, level.spawn));
+    }
+}
+
+/// Walls crash the snake, portals carry any head through to their partner, and a player's head
+/// on an exit completes the level.
+fn level_hazards(
+    mut checked_tick: Local<u64>,
+    mut commands: Commands,
+    state: Res<GameState>,
+    run: Res<Run>,
+    level: Res<ActiveLevel>,
+    mut game_events: ResMut<Events<GameEvent>>,
+    mut heads: Query<Without<Dead, (Entity, &SnakeHead, &mut Position, Option<&Ally>)>>,
+) {
+    let level = match &level.0 {
+        Some(level) => level,
+        None => return,
+    };
+    if *state != GameState::Playing || run.tick == *checked_tick {
+        return;
+    }
+    *checked_tick = run.tick;
+
+    for (ent, _head, mut position, ally) in &mut heads.iter() {
+        if level.is_wall(*position) {
+            commands.insert_one(
+                ent,
+                Dead {
+                    cause: GameOverCause::Wall,
+                },
+            );
+        } else if let Some(exit) = level.portal_exit(*position) {
+            *position = exit;
+        } else if ally.is_none() && level.exits.contains(&*position) {
+            info!(level = %level.name, tick = run.tick, "level complete");
+            game_events.send(GameEvent::GameOver {
+                cause: GameOverCause::LevelComplete,
+            });
+            return;
+        }
+    }
+}
+
+/// Saves the run when the player's head reaches a checkpoint it isn't already saved at.
+fn reach_checkpoint(
+    mut checked_tick: Local<u64>,
+    state: Res<GameState>,
+    run: Res<Run>,
+    score: Res<Score>,
+    level: Res<ActiveLevel>,
+    locale: Res<Locale>,
+    mut checkpoint: ResMut<Checkpoint>,
+    mut toasts: ResMut<Events<Toast>>,
+    mut heads: Query<Without<Ally, (&SnakeHead, &Position)>>,
+    segments: Query<(&SnakeSegment, &Position)>,
+    mut food: Query<(&Food, &Position)>,
+) {
+    let level = match &level.0 {
+        Some(level) if !level.checkpoints.is_empty() => level,
+        _ => return,
+    };
+    if *state != GameState::Playing || run.tick == *checked_tick {
+        return;
+    }
+    *checked_tick = run.tick;
+
+    let saved_at = checkpoint
+        .0
+        .as_ref()
+        .filter(|snapshot| snapshot.seed == run.seed)
+        .map(|snapshot| snapshot.head);
+    let mut reached = None;
+    for (_head, &position) in &mut heads.iter() {
+        if level.checkpoints.contains(&position) && saved_at != Some(position) {
+            reached = Some(position);
+        }
+    }
+    if reached.is_none() {
+        return;
+    }
+    if let Some(snapshot) = Snapshot::capture(&run, &score, &mut heads, &segments, &mut food) {
+        info!(level = %level.name, tick = run.tick, "checkpoint reached");
+        checkpoint.0 = Some(snapshot);
+        toasts.send(Toast::new(locale.text("checkpoint-reached")));
+    }
+}
@@ -0,0 +1,468 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::{asset::LoadState, prelude::*};
+use tracing::{info, warn};
+
+use crate::{
+    dialog::OpenDialog,
+    level::{ActiveLevel, Level, LevelError, RestartRun, LEVELS_DIR},
+    locale::Locale,
+    mods::Mods,
+    net::fetch,
+    pack::{load_packs, ActiveCampaign, Campaign, Pack},
+    profile::ActiveProfile,
+    save_format,
+    seasonal::civil_date,
+    tasks::{Task, TaskPool},
+    toast::Toast,
+    ui::UiFonts,
+    GameState,
+};
+
+const BROWSER_FONT_SIZE: f32 = 26.0;
+
+/// Where to download more levels from: a RON list of level file URLs, like
+/// `["https://example.com/maze.ron"]`. Set from `config.ron`.
+#[derive(Default)]
+pub struct LevelIndexUrl(pub Option<String>);
+
+/// A level file found in the levels folder, which may not be playable.
+struct Entry {
+    path: PathBuf,
+    level: Result<Level, Vec<LevelError>>,
+}
+
+/// What a row of the browser plays.
+enum Row<'a> {
+    OpenArena,
+    Pack(&'a Handle<Pack>),
+    Level(&'a Entry),
+}
+
+/// The level browser. The open arena comes first, then the packs, then the loose levels.
+#[derive(Default)]
+struct LevelBrowser {
+    packs: Vec<Handle<Pack>>,
+    entries: Vec<Entry>,
+    selected: usize,
+    /// The index download running in the background, which reports how many levels it added.
+    download: Option<Task<Result<usize, String>>>,
+    /// The levels folders being read in the background. The entries from the last read stay
+    /// listed until it's done.
+    reading: Option<Task<Vec<Entry>>>,
+    /// Set when the browser changed and needs to be redrawn.
+    dirty: bool,
+}
+
+impl LevelBrowser {
+    fn rows(&self) -> usize {
+        1 + self.packs.len() + self.entries.len()
+    }
+
+    fn row(&self, index: usize) -> Row {
+        match index.checked_sub(1) {
+            None => Row::OpenArena,
+            Some(index) if index < self.packs.len() => Row::Pack(&self.packs[index]),
+            Some(index) => Row::Level(&self.entries[index - self.packs.len()]),
+        }
+    }
+
+    /// Looks for packs, which check themselves as they load, and reads the loose levels on the
+    /// task pool, since checking a puzzle level means solving it.
+    fn scan(&mut self, asset_server: &AssetServer, mods: &Mods, tasks: &TaskPool) {
+        self.packs = load_packs(asset_server, mods);
+        let dirs: Vec<PathBuf> = Some(PathBuf::from(LEVELS_DIR))
+            .into_iter()
+            .chain(mods.dirs(LEVELS_DIR))
+            .collect();
+        self.reading = Some(tasks.spawn("level scan", move || read_entries(&dirs)));
+        self.selected = self.selected.min(self.rows() - 1);
+        self.dirty = true;
+    }
+}
+
+/// Every level file in `dirs`, checked, in path order.
+fn read_entries(dirs: &[PathBuf]) -> Vec<Entry> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    for dir in dirs {
+        if let Ok(entries) = fs::read_dir(dir) {
+            paths.extend(
+                entries
+                    .flatten()
+                    .map(|entry| entry.path())
+                    .filter(|path| path.extension().map_or(false, |ext| ext == "ron")),
+            );
+        }
+    }
+    paths.sort();
+    paths
+        .into_iter()
+        .map(|path| Entry {
+            level: Level::load(&path),
+            path,
+        })
+        .collect()
+}
+
+struct BrowserLine;
+
+pub struct LevelBrowserPlugin;
+
+impl Plugin for LevelBrowserPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<LevelIndexUrl>()
+            .init_resource::<LevelBrowser>()
+            .add_system(browser_input.system())
+            .add_system(finish_download.system())
+            .add_system(finish_reading.system())
+            .add_system(draw_browser.system());
+    }
+}
+
+/// The name a downloaded level is saved under, which is the last part of its URL. Only plain
+/// `.ron` names are trusted with the filesystem.
+fn level_file_name(url: &str) -> Option<&str> {
+    let name = url.rsplit('/').next()?;
+    let plain = name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c));
+    if plain && name.ends_with(".ron") && !name.starts_with('.') {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+/// Saves every playable level on the index that isn't in the levels folder yet. Runs on the task
+/// pool, since it waits on the network.
+fn download_index(url: &str) -> Result<usize, String> {
+    let urls: Vec<String> = ron::de::from_str(&fetch(url)?).map_err(|err| err.to_string())?;
+    fs::create_dir_all(LEVELS_DIR).map_err(|err| err.to_string())?;
+    let mut added = 0;
+    for level_url in urls {
+        let name = match level_file_name(&level_url) {
+            Some(name) => name,
+            None => {
+                warn!("skipping level {}: not a .ron file name", level_url);
+                continue;
+            }
+        };
+        let path = Path::new(LEVELS_DIR).join(name);
+        if path.exists() {
+            continue;
+        }
+        let text = match fetch(&level_url) {
+            Ok(text) => text,
+            Err(err) => {
+                warn!("could not download level {}: {}", level_url, err);
+                continue;
+            }
+        };
+        match save_format::decode::<Level>(&text) {
+            Ok(level) if level.validate().is_ok() => {}
+            _ => {
+                warn!("skipping level {}: not playable", level_url);
+                continue;
+            }
+        }
+        fs::write(&path, text).map_err(|err| err.to_string())?;
+        info!(path = %path.display(), "level downloaded");
+        added += 1;
+    }
+    Ok(added)
+}
+
+/// B while paused opens the browser, and closes it again. Up and Down pick a row, Enter plays
+/// it from the start if it can be played, and R looks for new levels and packs, downloading
+/// levels from the index if one is configured. Opening the browser looks too. A pack plays its
+/// levels in order as a campaign.
+fn browser_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    asset_server: Res<AssetServer>,
+    index_url: Res<LevelIndexUrl>,
+    open_dialog: Res<OpenDialog>,
+    tasks: Res<TaskPool>,
+    packs: Res<Assets<Pack>>,
+    mut state: ResMut<GameState>,
+    mut browser: ResMut<LevelBrowser>,
+    mut level: ResMut<ActiveLevel>,
+    mut campaign: ResMut<ActiveCampaign>,
+    mut restart: ResMut<RestartRun>,
+    mods: Res<Mods>,
+) {
+    let pressed = |key| keyboard_input.just_pressed(key);
+    let refresh = match *state {
+        GameState::Paused if pressed(KeyCode::B) => {
+            *state = GameState::Levels;
+            browser.selected = 0;
+            true
+        }
+        GameState::Levels if pressed(KeyCode::B) => {
+            *state = GameState::Paused;
+            browser.dirty = true;
+            return;
+        }
+        GameState::Levels => pressed(KeyCode::R),
+        _ => return,
+    };
+    if refresh {
+        browser.scan(&asset_server, &mods, &tasks);
+        if let (Some(url), None) = (index_url.0.clone(), &browser.download) {
+            browser.download =
+                Some(tasks.spawn("level index download", move || download_index(&url)));
+        }
+        return;
+    }
+
+    if pressed(KeyCode::Up) && browser.selected > 0 {
+        browser.selected -= 1;
+        browser.dirty = true;
+    }
+    if pressed(KeyCode::Down) && browser.selected + 1 < browser.rows() {
+        browser.selected += 1;
+        browser.dirty = true;
+    }
+    if !pressed(KeyCode::Return) || open_dialog.is_open() {
+        return;
+    }
+
+    // The problems with a level or pack that can't be played are listed under it instead.
+    let (chosen, chosen_campaign) = match browser.row(browser.selected) {
+        Row::OpenArena => (None, None),
+        Row::Pack(handle) => match packs.get(handle) {
+            Some(pack) if pack.validate().is_ok() => (
+                Some(pack.levels[0].clone()),
+                Some(Campaign {
+                    pack: *handle,
+                    level: 0,
+                    from_map: false,
+                }),
+            ),
+            _ => return,
+        },
+        Row::Level(entry) => match &entry.level {
+            Ok(chosen) => (Some(chosen.clone()), None),
+            Err(_errors) => return,
+        },
+    };
+    info!(
+        level = chosen
+            .as_ref()
+            .map_or("open arena", |level| level.name.as_str()),
+        campaign = chosen_campaign.is_some(),
+        "level chosen"
+    );
+    level.0 = chosen;
+    campaign.0 = chosen_campaign;
+    *state = GameState::Playing;
+    restart.0 = true;
+    browser.dirty = true;
+}
+
+/// Lists the levels once they've been read and checked.
+fn finish_reading(mut browser: ResMut<LevelBrowser>) {
+    let entries = match browser.reading.as_ref().and_then(Task::poll) {
+        Some(Ok(entries)) => entries,
+        Some(Err(_lost)) => {
+            warn!("could not read the levels folder");
+            Vec::new()
+        }
+        None => return,
+    };
+    browser.reading = None;
+    browser.entries = entries;
+    browser.selected = browser.selected.min(browser.rows() - 1);
+    browser.dirty = true;
+}
+
+/// Picks up the result of an index download and lists the new levels.
+fn finish_download(
+    locale: Res<Locale>,
+    asset_server: Res<AssetServer>,
+    mods: Res<Mods>,
+    tasks: Res<TaskPool>,
+    mut browser: ResMut<LevelBrowser>,
+    mut toasts: ResMut<Events<Toast>>,
+) {
+    let result = match browser.download.as_ref().and_then(Task::poll) {
+        Some(Ok(result)) => result,
+        Some(Err(_lost)) => Err("download stopped".to_string()),
+        None => return,
+    };
+    browser.download = None;
+    browser.dirty = true;
+    match result {
+        Ok(0) => {}
+        Ok(added) => {
+            browser.scan(&asset_server, &mods, &tasks);
+            toasts.send(Toast::new(
+                locale.format("levels-downloaded", &[("count", added.to_string())]),
+            ));
+        }
+        Err(err) => {
+            warn!("could not download the level index: {}", err);
+            toasts.send(Toast::new(locale.text("levels-download-failed")));
+        }
+    }
+}
+
+/// Redraws the browser whenever it changes, including when a pack finishes loading.
+fn draw_browser(
+    mut drawn_for: Local<Option<GameState>>,
+    mut pack_reader: Local<EventReader<AssetEvent<Pack>>>,
+    mut commands: Commands,
+    pack_events: Res<Events<AssetEvent<Pack>>>,
+    asset_server: Res<AssetServer>,
+    packs: Res<Assets<Pack>>,
+    state: Res<GameState>,
+    fonts: Res<UiFonts>,
+    locale: Res<Locale>,
+    profile: Res<ActiveProfile>,
+    mut browser: ResMut<LevelBrowser>,
+    mut lines: Query<(Entity, &BrowserLine)>,
+) {
+    let packs_changed = pack_reader.iter(&pack_events).next().is_some();
+    if !browser.dirty && !packs_changed && *drawn_for == Some(*state) {
+        return;
+    }
+    browser.dirty = false;
+    *drawn_for = Some(*state);
+
+    for (ent, _line) in &mut lines.iter() {
+        commands.despawn(ent);
+    }
+    if *state != GameState::Levels {
+        return;
+    }
+
+    let stats = profile.0.as_ref().map(|profile| &profile.stats);
+    let marker = |row: usize| if row == browser.selected { "> " } else { "  " };
+    let mut text = vec![
+        locale.text("levels-title"),
+        format!("{}{}", marker(0), locale.text("levels-open-arena")),
+    ];
+    for (index, handle) in browser.packs.iter().enumerate() {
+        let line = match packs.get(handle) {
+            Some(pack) if pack.validate().is_ok() => locale.format(
+                "levels-pack",
+                &[
+                    ("name", pack.name.clone()),
+                    ("author", pack.author.clone()),
+                    ("count", pack.levels.len().to_string()),
+                ],
+            ),
+            Some(pack) => locale.format("levels-pack-broken", &[("name", pack.name.clone())]),
+            None if matches!(
+                asset_server.get_load_state(*handle),
+                Some(LoadState::Failed(_))
+            ) =>
+            {
+                locale.text("levels-pack-unreadable")
+            }
+            None => locale.text("levels-pack-loading"),
+        };
+        text.push(format!("{}{}", marker(index + 1), line));
+    }
+    let first_level_row = 1 + browser.packs.len();
+    for (index, entry) in browser.entries.iter().enumerate() {
+        let line = match &entry.level {
+            Ok(level) => {
+                let best = stats
+                    .and_then(|stats| stats.level_best(&level.name))
+                    .map_or_else(|| "-".to_string(), |best| best.to_string());
+                locale.format(
+                    "levels-entry",
+                    &[
+                        ("name", level.name.clone()),
+                        ("author", level.author.clone()),
+                        ("difficulty", locale.text(level.difficulty.name_key())),
+                        ("best", best),
+                    ],
+                )
+            }
+            Err(_errors) => locale.format(
+                "levels-entry-broken",
+                &[("file", entry.path.display().to_string())],
+            ),
+        };
+        text.push(format!("{}{}", marker(first_level_row + index), line));
+    }
+    if browser.reading.is_some() {
+        text.push(format!("  {}", locale.text("levels-checking")));
+    }
+
+    // The levels of the pack picked, the leaderboard of the level picked, or why either can't
+    // be played.
+    let selected = match browser.row(browser.selected) {
+        Row::OpenArena => None,
+        Row::Pack(handle) => {
+            if let Some(pack) = packs.get(handle) {
+                text.push(String::new());
+                match pack.validate() {
+                    Ok(()) => text.extend(
+                        pack.levels
+                            .iter()
+                            .enumerate()
+                            .map(|(index, level)| format!("{}. {}", index + 1, level.name)),
+                    ),
+                    Err(errors) => {
+                        text.extend(errors.iter().map(|err| err.describe(pack, &locale)))
+                    }
+                }
+            }
+            None
+        }
+        Row::Level(entry) => Some(&entry.level),
+    };
+    if let Some(Err(errors)) = selected {
+        text.push(String::new());
+        text.extend(errors.iter().map(|err| err.describe(&locale)));
+    }
+    if let Some(Ok(level)) = selected {
+        text.push(String::new());
+        text.push(locale.format("levels-leaderboard", &[("name", level.name.clone())]));
+        let scores = stats.and_then(|stats| stats.level_scores.get(&level.name));
+        match scores {
+            Some(scores) if !scores.is_empty() => {
+                for (rank, entry) in scores.iter().enumerate() {
+                    let (year, month, day) = civil_date(entry.recorded_at);
+                    text.push(locale.format(
+                        "levels-leaderboard-entry",
+                        &[
+                            ("rank", (rank + 1).to_string()),
+                            ("score", entry.score.to_string()),
+                            ("date", format!("{}-{:02}-{:02}", year, month, day)),
+                        ],
+                    ));
+                }
+            }
+            _ => text.push(locale.text("levels-leaderboard-empty")),
+        }
+    }
+    text.push(locale.text(if browser.download.is_some() {
+        "levels-downloading"
+    } else {
+        "levels-hint"
+    }));
+
+    for (row, line) in text.into_iter().enumerate() {
+        commands
+            .spawn(TextComponents {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        left: Val::Px(40.0),
+                        bottom: Val::Px(900.0 - row as f32 * (BROWSER_FONT_SIZE + 8.0)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                text: fonts.text(line, BROWSER_FONT_SIZE),
+                ..Default::default()
+            })
+            .with(BrowserLine);
+    }
+}
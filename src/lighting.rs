@@ -0,0 +1,140 @@
+use bevy::{prelude::*, sprite::SpriteResizeMode};
+
+use crate::{
+    accessibility::Announcement,
+    effects::{EffectKind, Effects},
+    level::LEVEL_DEPTH,
+    locale::Locale,
+    primary_window,
+    rules::GameRules,
+    split::Ally,
+    Food, Position, Run, SnakeHead,
+};
+
+/// Length of a full day and night, in seconds of play.
+const CYCLE_LENGTH: f32 = 60.0;
+
+/// When dusk starts, in seconds into the cycle. Dawn is mirrored at the end of the cycle.
+const DUSK_START: f32 = 40.0;
+
+/// How long dusk and dawn take, in seconds.
+const TWILIGHT: f32 = 5.0;
+
+/// How much the arena is darkened at the dead of night.
+const NIGHT_DIMMING: f32 = 0.7;
+
+/// At night, food further than this many cells from the player's head is hidden.
+const SIGHT_RADIUS: i32 = 8;
+
+/// How long a lantern lasts, in seconds.
+pub const LANTERN_DURATION: f32 = 30.0;
+
+/// Sits just above the floor tiles and the level's walls, so it dims the arena but not the
+/// snake.
+const OVERLAY_DEPTH: f32 = LEVEL_DEPTH + 0.01;
+
+struct NightOverlay;
+
+#[derive(Default)]
+struct LightingState {
+    night: bool,
+}
+
+pub struct LightingPlugin;
+
+impl Plugin for LightingPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_startup_system(setup_overlay.system())
+            .add_system(update_lighting.system())
+            .add_system(hide_distant_food.system());
+    }
+}
+
+/// How dark it is, from 0 during the day to 1 at night, `elapsed` seconds into a run.
+fn darkness(elapsed: f32) -> f32 {
+    let t = elapsed % CYCLE_LENGTH;
+    if t < DUSK_START {
+        0.0
+    } else if t < DUSK_START + TWILIGHT {
+        (t - DUSK_START) / TWILIGHT
+    } else if t < CYCLE_LENGTH - TWILIGHT {
+        1.0
+    } else {
+        (CYCLE_LENGTH - t) / TWILIGHT
+    }
+}
+
+fn setup_overlay(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+    commands
+        .spawn(SpriteComponents {
+            material: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.0).into()),
+            sprite: Sprite {
+                resize_mode: SpriteResizeMode::Manual,
+                ..Default::default()
+            },
+            transform: Transform::from_translation(Vec3::new(0.0, 0.0, OVERLAY_DEPTH)),
+            ..Default::default()
+        })
+        .with(NightOverlay);
+}
+
+/// Dims the arena as night falls and announces dusk and dawn.
+fn update_lighting(
+    mut state: Local<LightingState>,
+    windows: Res<Windows>,
+    run: Res<Run>,
+    rules: Res<GameRules>,
+    locale: Res<Locale>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut announcements: ResMut<Events<Announcement>>,
+    mut overlays: Query<(&NightOverlay, &Handle<ColorMaterial>, &mut Sprite)>,
+) {
+    let darkness = if rules.day_night {
+        darkness(run.elapsed)
+    } else {
+        0.0
+    };
+    let night = darkness > 0.5;
+    if night != state.night {
+        state.night = night;
+        let key = if night { "night-falls" } else { "day-breaks" };
+        announcements.send(Announcement::verbose(locale.text(key)));
+    }
+
+    let window = match primary_window(&windows) {
+        Some(window) => window,
+        None => return,
+    };
+    for (_overlay, handle, mut sprite) in &mut overlays.iter() {
+        sprite.size = Vec2::new(window.width as f32, window.height as f32);
+        // Only touch the material when it changes, since borrowing it mutably marks it as
+        // modified.
+        let alpha = darkness * NIGHT_DIMMING;
+        if materials.get(handle).map_or(false, |material| {
+            (material.color.a - alpha).abs() > f32::EPSILON
+        }) {
+            materials.get_mut(handle).unwrap().color.a = alpha;
+        }
+    }
+}
+
+fn hide_distant_food(
+    run: Res<Run>,
+    rules: Res<GameRules>,
+    effects: Res<Effects>,
+    mut heads: Query<Without<Ally, (&SnakeHead, &Position)>>,
+    mut food: Query<(&Food, &Position, &mut Draw)>,
+) {
+    let night =
+        rules.day_night && darkness(run.elapsed) > 0.5 && !effects.is_active(EffectKind::Lantern);
+    let heads: Vec<Position> = heads.iter().iter().map(|(_head, pos)| *pos).collect();
+    for (_food, food_pos, mut draw) in &mut food.iter() {
+        let visible = !night
+            || heads.iter().any(|head| {
+                (head.x - food_pos.x).abs() + (head.y - food_pos.y).abs() <= SIGHT_RADIUS
+            });
+        if draw.is_visible != visible {
+            draw.is_visible = visible;
+        }
+    }
+}
@@ -0,0 +1,326 @@
+use std::{fs, fs::File, path::Path};
+
+use bevy::{
+    app::{startup_stage, AppExit},
+    asset::{HandleId, LoadState},
+    prelude::*,
+};
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::{locale::Locale, ui::UiFonts, GameState};
+
+/// Lists the files the game can't start without.
+const MANIFEST_PATH: &str = "assets/manifest.ron";
+
+const LOADING_FONT_SIZE: f32 = 32.0;
+
+const ERROR_FONT_SIZE: f32 = 24.0;
+
+const BAR_WIDTH: f32 = 400.0;
+
+const BAR_HEIGHT: f32 = 24.0;
+
+/// The required files, by kind. Sounds are only checked to be there.
+#[derive(Default, Deserialize)]
+#[serde(default)]
+struct AssetManifest {
+    textures: Vec<String>,
+    fonts: Vec<String>,
+    sounds: Vec<String>,
+}
+
+#[derive(Clone, Copy)]
+enum AssetKind {
+    Texture,
+    Font,
+    Sound,
+}
+
+/// Why a required file can't be used.
+#[derive(Clone, Debug, PartialEq)]
+enum AssetProblem {
+    Missing(String),
+    Broken { path: String, error: String },
+}
+
+impl AssetProblem {
+    fn describe(&self, locale: &Locale) -> String {
+        match self {
+            Self::Missing(path) => locale.format("asset-missing", &[("path", path.clone())]),
+            Self::Broken { path, error } => locale.format(
+                "asset-broken",
+                &[("path", path.clone()), ("error", error.clone())],
+            ),
+        }
+    }
+}
+
+/// Checks a texture or font by decoding its header, so a damaged file is caught before the
+/// renderer gets to it.
+fn check_file(path: &str, kind: AssetKind) -> Option<AssetProblem> {
+    if !Path::new(path).is_file() {
+        return Some(AssetProblem::Missing(path.to_string()));
+    }
+    let checked = match kind {
+        AssetKind::Texture => File::open(path)
+            .map_err(|err| err.to_string())
+            .and_then(|file| {
+                png::Decoder::new(file)
+                    .read_info()
+                    .map(|_info| ())
+                    .map_err(|err| err.to_string())
+            }),
+        AssetKind::Font => fs::read(path)
+            .map_err(|err| err.to_string())
+            .and_then(|bytes| {
+                Font::try_from_bytes(bytes)
+                    .map(|_font| ())
+                    .map_err(|err| format!("{:?}", err))
+            }),
+        AssetKind::Sound => Ok(()),
+    };
+    checked.err().map(|error| AssetProblem::Broken {
+        path: path.to_string(),
+        error,
+    })
+}
+
+/// Files the game needs before its first menu: textures, fonts and campaign packs. Whatever
+/// loads one at startup tracks it here, and the loading screen stays up until they're all in.
+/// If one listed in the manifest is missing or broken, an error screen lists them instead.
+#[derive(Default)]
+pub struct LoadingAssets {
+    tracked: Vec<(String, HandleId)>,
+    /// The manifest's files.
+    required: Vec<String>,
+    problems: Vec<AssetProblem>,
+    /// Set once the error screen is up.
+    failed: bool,
+}
+
+impl LoadingAssets {
+    pub fn track(&mut self, path: impl Into<String>, handle: impl Into<HandleId>) {
+        self.tracked.push((path.into(), handle.into()));
+    }
+}
+
+/// A part of the loading screen, removed once loading is done.
+struct LoadingScreen;
+
+struct LoadingLabel;
+
+/// The filled part of the progress bar.
+struct LoadingBar;
+
+struct AssetErrorLine;
+
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<LoadingAssets>()
+            .add_startup_system(check_manifest.system())
+            // After the UI fonts are set up.
+            .add_startup_system_to_stage(startup_stage::POST_STARTUP, setup_loading_screen.system())
+            .add_system(track_loading.system())
+            .add_system(quit_on_asset_error.system());
+    }
+}
+
+/// Makes sure every file in the manifest is there and readable. A missing manifest leaves
+/// nothing required, since the game falls back where it can without one.
+fn check_manifest(mut loading: ResMut<LoadingAssets>) {
+    let text = match fs::read_to_string(MANIFEST_PATH) {
+        Ok(text) => text,
+        Err(err) => {
+            warn!("could not read {}: {}", MANIFEST_PATH, err);
+            return;
+        }
+    };
+    let manifest: AssetManifest = match ron::de::from_str(&text) {
+        Ok(manifest) => manifest,
+        Err(err) => {
+            loading.problems.push(AssetProblem::Broken {
+                path: MANIFEST_PATH.to_string(),
+                error: err.to_string(),
+            });
+            return;
+        }
+    };
+    let textures = manifest
+        .textures
+        .iter()
+        .map(|path| (path, AssetKind::Texture));
+    let fonts = manifest.fonts.iter().map(|path| (path, AssetKind::Font));
+    let sounds = manifest.sounds.iter().map(|path| (path, AssetKind::Sound));
+    for (path, kind) in textures.chain(fonts).chain(sounds) {
+        loading.required.push(path.clone());
+        if let Some(problem) = check_file(path, kind) {
+            warn!("required asset problem: {:?}", problem);
+            loading.problems.push(problem);
+        }
+    }
+}
+
+fn setup_loading_screen(
+    mut commands: Commands,
+    fonts: Res<UiFonts>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let filled = materials.add(Color::rgb(0.3, 0.8, 0.4).into());
+    commands
+        .spawn(TextComponents {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(40.0),
+                    bottom: Val::Px(800.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: fonts.text("", LOADING_FONT_SIZE),
+            ..Default::default()
+        })
+        .with(LoadingScreen)
+        .with(LoadingLabel)
+        .spawn(NodeComponents {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    left: Val::Px(40.0),
+                    bottom: Val::Px(760.0),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Px(BAR_WIDTH), Val::Px(BAR_HEIGHT)),
+                ..Default::default()
+            },
+            material: materials.add(Color::rgb(0.2, 0.2, 0.25).into()),
+            ..Default::default()
+        })
+        .with(LoadingScreen)
+        .with_children(|parent| {
+            parent
+                .spawn(NodeComponents {
+                    style: Style {
+                        size: Size::new(Val::Percent(0.0), Val::Percent(100.0)),
+                        ..Default::default()
+                    },
+                    material: filled,
+                    ..Default::default()
+                })
+                .with(LoadingBar);
+        });
+}
+
+/// Fills the bar as the tracked files come in, and moves on to the profile picker once every
+/// one has loaded or failed to. Nothing is played before then, and nothing at all if a
+/// required file is missing or broken.
+fn track_loading(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    locale: Res<Locale>,
+    fonts: Res<UiFonts>,
+    mut loading: ResMut<LoadingAssets>,
+    mut state: ResMut<GameState>,
+    mut labels: Query<(&LoadingLabel, &mut Text)>,
+    mut bars: Query<(&LoadingBar, &mut Style)>,
+    mut screens: Query<(Entity, &LoadingScreen)>,
+) {
+    if *state != GameState::Loading || loading.failed {
+        return;
+    }
+    let states: Vec<Option<LoadState>> = loading
+        .tracked
+        .iter()
+        .map(|(_path, handle)| asset_server.get_load_state(*handle))
+        .collect();
+    let done = states
+        .iter()
+        .filter(|load_state| !matches!(load_state, Some(LoadState::Loading)))
+        .count();
+
+    let total = loading.tracked.len();
+    let value = locale.format(
+        "loading",
+        &[("done", done.to_string()), ("total", total.to_string())],
+    );
+    for (_label, mut text) in &mut labels.iter() {
+        if text.value != value {
+            text.value = value.clone();
+        }
+    }
+    let progress = if total == 0 {
+        1.0
+    } else {
+        done as f32 / total as f32
+    };
+    for (_bar, mut style) in &mut bars.iter() {
+        style.size.width = Val::Percent(progress * 100.0);
+    }
+    if done < total {
+        return;
+    }
+
+    let mut failed = Vec::new();
+    for ((path, _handle), load_state) in loading.tracked.iter().zip(&states) {
+        if let Some(LoadState::Failed(_)) = load_state {
+            warn!("could not load {}", path);
+            let problem = AssetProblem::Broken {
+                path: path.clone(),
+                error: locale.text("asset-load-failed"),
+            };
+            if loading.required.contains(path) && !loading.problems.contains(&problem) {
+                failed.push(problem);
+            }
+        }
+    }
+    loading.problems.extend(failed);
+    for (ent, _screen) in &mut screens.iter() {
+        commands.despawn_recursive(ent);
+    }
+    if loading.problems.is_empty() {
+        info!(assets = total, "loading done");
+        *state = GameState::ProfileSelect;
+        return;
+    }
+
+    loading.failed = true;
+    let mut text = vec![locale.text("asset-error-title")];
+    text.extend(
+        loading
+            .problems
+            .iter()
+            .map(|problem| problem.describe(&locale)),
+    );
+    text.push(locale.text("asset-error-hint"));
+    for (row, line) in text.into_iter().enumerate() {
+        commands
+            .spawn(TextComponents {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        left: Val::Px(40.0),
+                        top: Val::Px(40.0 + row as f32 * (ERROR_FONT_SIZE + 10.0)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                text: fonts.text(line, ERROR_FONT_SIZE),
+                ..Default::default()
+            })
+            .with(AssetErrorLine);
+    }
+}
+
+/// Escape closes the game from the error screen.
+fn quit_on_asset_error(
+    keyboard_input: Res<Input<KeyCode>>,
+    loading: Res<LoadingAssets>,
+    mut exit: ResMut<Events<AppExit>>,
+) {
+    if loading.failed && keyboard_input.just_pressed(KeyCode::Escape) {
+        exit.send(AppExit);
+    }
+}
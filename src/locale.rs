@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::accessibility::Announcement;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum Language {
+    English,
+    Spanish,
+}
+
+impl Language {
+    const ALL: [Self; 2] = [Self::English, Self::Spanish];
+
+    fn source(self) -> &'static str {
+        match self {
+            Self::English => include_str!("../assets/locales/en-US.ftl"),
+            Self::Spanish => include_str!("../assets/locales/es-ES.ftl"),
+        }
+    }
+
+    /// BCP 47 tag, matching the name of the language's `.ftl` file.
+    fn code(self) -> &'static str {
+        match self {
+            Self::English => "en-US",
+            Self::Spanish => "es-ES",
+        }
+    }
+
+    /// The language to look a message up in when this one doesn't have it.
+    fn fallback(self) -> Option<Self> {
+        match self {
+            Self::English => None,
+            Self::Spanish => Some(Self::English),
+        }
+    }
+
+    fn next(self) -> Self {
+        match self {
+            Self::English => Self::Spanish,
+            Self::Spanish => Self::English,
+        }
+    }
+}
+
+/// All user-facing strings, looked up by key in the current language.
+pub struct Locale {
+    language: Language,
+    messages: HashMap<Language, HashMap<String, String>>,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Self {
+            language: Language::English,
+            messages: Language::ALL
+                .iter()
+                .map(|&language| (language, parse_messages(language.source())))
+                .collect(),
+        }
+    }
+}
+
+impl Locale {
+    /// Switches to the next language.
+    pub fn cycle_language(&mut self) {
+        self.language = self.language.next();
+    }
+
+    /// Picks the current language's entry from translations that live outside the `.ftl`
+    /// files, keyed by language tag, falling back like `text` does.
+    pub fn translate<'a>(&self, translations: &'a HashMap<String, String>) -> Option<&'a str> {
+        let mut language = Some(self.language);
+        while let Some(current) = language {
+            if let Some(text) = translations.get(current.code()) {
+                return Some(text);
+            }
+            language = current.fallback();
+        }
+        None
+    }
+
+    pub fn text(&self, key: &str) -> String {
+        self.format(key, &[])
+    }
+
+    /// Looks up `key`, walking the fallback chain, and fills in its `{ $name }` placeables.
+    /// Falls back to the key itself so a missing string is visible rather than blank.
+    pub fn format(&self, key: &str, args: &[(&str, String)]) -> String {
+        let mut language = Some(self.language);
+        while let Some(current) = language {
+            if let Some(message) = self.messages[&current].get(key) {
+                let mut message = message.clone();
+                for (name, value) in args {
+                    message = message.replace(&format!("{{ ${} }}", name), value);
+                }
+                return message;
+            }
+            language = current.fallback();
+        }
+        key.to_string()
+    }
+}
+
+/// Parses the subset of Fluent the locale files use: single-line `key = value` messages,
+/// `{ $name }` placeables and `#` comments.
+fn parse_messages(source: &str) -> HashMap<String, String> {
+    source
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next()?.trim();
+            let value = parts.next()?.trim();
+            Some((key.to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+pub struct LocalePlugin;
+
+impl Plugin for LocalePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Locale>()
+            .add_system(language_selection.system());
+    }
+}
+
+fn language_selection(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut locale: ResMut<Locale>,
+    mut announcements: ResMut<Events<Announcement>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::F4) {
+        locale.cycle_language();
+        let text = locale.format(
+            "language-changed",
+            &[("language", locale.text("language-name"))],
+        );
+        announcements.send(Announcement::essential(text));
+    }
+}
@@ -0,0 +1,64 @@
+use std::{
+    env,
+    fs::File,
+    io::{self, Write},
+};
+
+use tracing::Level;
+
+const DEFAULT_LEVEL: Level = Level::INFO;
+
+/// Logging options taken from the command line: `--log-level <level>` and
+/// `--log-file <path>`.
+struct LogOptions {
+    level: Level,
+    file: Option<String>,
+}
+
+impl LogOptions {
+    fn from_args() -> Self {
+        let mut options = Self {
+            level: DEFAULT_LEVEL,
+            file: None,
+        };
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--log-level" => match args.next().map(|level| level.parse()) {
+                    Some(Ok(level)) => options.level = level,
+                    _ => eprintln!("--log-level expects one of error, warn, info, debug, trace"),
+                },
+                "--log-file" => options.file = args.next(),
+                _ => {}
+            }
+        }
+        options
+    }
+}
+
+/// Installs the global `tracing` subscriber. Must run before the app is built. Without a log
+/// file it writes to stderr, leaving stdout to the screen reader announcements.
+pub fn init() {
+    let options = LogOptions::from_args();
+    let builder = tracing_subscriber::fmt().with_max_level(options.level);
+    match options
+        .file
+        .map(|path| File::create(&path).map_err(|err| (path, err)))
+    {
+        Some(Ok(file)) => builder
+            .with_ansi(false)
+            // If the handle can't be cloned, that line goes to stderr instead.
+            .with_writer(move || -> Box<dyn Write> {
+                match file.try_clone() {
+                    Ok(file) => Box::new(file),
+                    Err(_) => Box::new(io::stderr()),
+                }
+            })
+            .init(),
+        Some(Err((path, err))) => {
+            builder.with_writer(io::stderr).init();
+            tracing::error!("could not open log file {}: {}", path, err);
+        }
+        None => builder.with_writer(io::stderr).init(),
+    }
+}
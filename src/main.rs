@@ -1,17 +1,185 @@
-use std::time::Duration;
+use std::{collections::VecDeque, f32::consts::FRAC_PI_4, time::Duration};
 
-use bevy::{prelude::*, render::pass::ClearColor};
-use rand::prelude::random;
+use bevy::{prelude::*, render::pass::ClearColor, sprite::SpriteResizeMode};
+use rand::{prelude::random, rngs::StdRng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, info_span, warn, Span};
+
+mod accessibility;
+mod adaptive;
+mod body;
+mod camera;
+mod campaign;
+mod celebration;
+mod config;
+mod controller;
+mod crash;
+mod danger;
+mod dialog;
+mod director;
+mod editor;
+mod effects;
+mod event_log;
+mod game_error;
+mod game_event;
+mod head_on;
+mod heatmap;
+mod hud_layout;
+mod idle;
+mod input;
+mod latency;
+mod level;
+mod level_browser;
+mod lighting;
+mod loading;
+mod locale;
+mod logging;
+mod mods;
+mod net;
+mod objectives;
+mod obstacle;
+mod options;
+mod pack;
+mod popup;
+mod power_up;
+mod profile;
+mod puzzle;
+mod relay;
+mod replay;
+mod rules;
+mod save_format;
+mod scoring;
+mod seasonal;
+mod session;
+mod slow_motion;
+mod slow_start;
+mod snapshot;
+mod split;
+mod tasks;
+mod telemetry;
+mod tiles;
+mod time_scale;
+mod toast;
+mod tournament;
+mod ui;
+mod zen;
+mod zone;
+
+use accessibility::{AccessibilityPlugin, AccessibilitySettings, Palette};
+use adaptive::AdaptivePlugin;
+use body::{BodyChange, BodyPlugin, SnakeBody};
+use camera::{ArenaCamera, CameraPlugin};
+use campaign::CampaignPlugin;
+use celebration::CelebrationPlugin;
+use config::ConfigPlugin;
+use controller::{Controlled, ControllerPlugin, InputQueue, PlayerController};
+use crash::CrashPlugin;
+use danger::DangerPlugin;
+use dialog::DialogPlugin;
+use director::DirectorPlugin;
+use editor::EditorPlugin;
+use effects::{Effects, EffectsPlugin};
+use event_log::EventLogPlugin;
+use game_error::{FatalError, GameErrorPlugin};
+use game_event::GameEvent;
+use head_on::HeadOnPlugin;
+use heatmap::HeatmapPlugin;
+use hud_layout::HudLayoutPlugin;
+use idle::IdlePlugin;
+use input::{Action, InputPlugin};
+use latency::LatencyPlugin;
+use level::{ActiveLevel, Checkpoint, LevelPlugin};
+use level_browser::LevelBrowserPlugin;
+use lighting::LightingPlugin;
+use loading::{LoadingAssets, LoadingPlugin};
+use locale::{Locale, LocalePlugin};
+use mods::ModsPlugin;
+use objectives::ObjectivesPlugin;
+use obstacle::{ObstaclePlugin, SwitchState};
+use options::OptionsPlugin;
+use pack::PackPlugin;
+use popup::PopupPlugin;
+use power_up::{PowerUp, PowerUpPlugin};
+use profile::{ActiveProfile, ProfilePlugin};
+use puzzle::PuzzlePlugin;
+use relay::RelayPlugin;
+use replay::ReplayPlugin;
+use rules::{GameRules, RuleOverrides};
+use scoring::{Pace, ScoreEvent, ScoreReason, ScoringPlugin};
+use seasonal::SeasonalPlugin;
+use session::SessionPlugin;
+use slow_motion::SlowMotionPlugin;
+use slow_start::SlowStartPlugin;
+use split::{Ally, SplitPlugin};
+use tasks::TasksPlugin;
+use telemetry::TelemetryPlugin;
+use tiles::{TileKind, Tiles, TilesPlugin};
+use time_scale::{TimeScale, TimeScalePlugin};
+use toast::ToastPlugin;
+use tournament::TournamentPlugin;
+use ui::UiPlugin;
+use zen::ZenPlugin;
+use zone::ZonePlugin;
 
 const ARENA_WIDTH: u32 = 40;
 const ARENA_HEIGHT: u32 = 40;
 
-#[derive(Default, Copy, Clone, Debug, Eq, PartialEq, Hash)]
+const SNAKE_MOVE_INTERVAL: Duration = Duration::from_millis(150);
+
+#[derive(Default, Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
 struct Position {
     x: i32,
     y: i32,
 }
 
+impl Position {
+    /// The neighboring cell in `direction`.
+    fn moved(self, direction: Direction) -> Self {
+        match direction {
+            Direction::Left => Self {
+                x: self.x - 1,
+                ..self
+            },
+            Direction::Right => Self {
+                x: self.x + 1,
+                ..self
+            },
+            Direction::Up => Self {
+                y: self.y + 1,
+                ..self
+            },
+            Direction::Down => Self {
+                y: self.y - 1,
+                ..self
+            },
+        }
+    }
+
+    fn outside_arena(&self) -> bool {
+        self.x < 0 || self.y < 0 || self.x as u32 > ARENA_WIDTH || self.y as u32 > ARENA_HEIGHT
+    }
+
+    /// Brings a position that just left the arena back in on the opposite side.
+    fn wrapped(self) -> Self {
+        let wrap = |value: i32, max: u32| {
+            if value < 0 {
+                max as i32
+            } else if value as u32 > max {
+                0
+            } else {
+                value
+            }
+        };
+        Self {
+            x: wrap(self.x, ARENA_WIDTH),
+            y: wrap(self.y, ARENA_HEIGHT),
+        }
+    }
+}
+
+/// Where the head of a new snake starts.
+const START_POSITION: Position = Position { x: 10, y: 10 };
+
 struct Size {
     width: f32,
     height: f32,
@@ -26,7 +194,7 @@ impl Size {
     }
 }
 
-#[derive(PartialEq, Copy, Clone, Debug)]
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug, Serialize, Deserialize)]
 enum Direction {
     Left,
     Up,
@@ -43,228 +211,1065 @@ impl Direction {
             Self::Down => Self::Up,
         }
     }
+
+    fn clockwise(self: &Self) -> Self {
+        match self {
+            Self::Left => Self::Up,
+            Self::Up => Self::Right,
+            Self::Right => Self::Down,
+            Self::Down => Self::Left,
+        }
+    }
 }
 
 struct SnakeHead {
     direction: Direction,
     next_segment: Entity,
+    /// How many moves a turn waits before it's applied.
+    turn_delay: u64,
+    /// Turns waiting for their delay, with the tick each becomes due.
+    pending_turns: VecDeque<(u64, Direction)>,
+    /// Segments still to grow. One emerges from the tail on each move.
+    pending_growth: u32,
+    /// Set from the `wall_bounce`, `tail_cut` and `wrap_walls` rules for the player's snakes.
+    bounces_off_walls: bool,
+    bites_own_tail: bool,
+    wraps_at_walls: bool,
+    /// Ticks of the last few wall bounces, to stop a snake scraping along a wall forever.
+    recent_bounces: VecDeque<u64>,
+}
+
+impl SnakeHead {
+    /// The direction the head will be going in once every queued turn is applied.
+    fn queued_direction(&self) -> Direction {
+        self.pending_turns
+            .back()
+            .map_or(self.direction, |&(_due, direction)| direction)
+    }
+
+    /// Turns the head to `direction` straight away, unless that's back into its own body.
+    /// `length` counts the head and every segment. Returns whether it turned.
+    fn try_set_direction(&mut self, direction: Direction, length: u32) -> bool {
+        if !turn_allowed(self.direction, direction, length) {
+            return false;
+        }
+        self.direction = direction;
+        true
+    }
+
+    /// Queues a turn to `direction` for move `due`. It's checked against the direction the
+    /// queued turns leave the head going in, so two quick presses can take a corner but not
+    /// turn it around, and a turn that way would change nothing isn't queued at all. Returns
+    /// whether it was queued.
+    fn queue_turn(&mut self, due: u64, direction: Direction, length: u32) -> bool {
+        let queued = self.queued_direction();
+        if direction == queued || !turn_allowed(queued, direction, length) {
+            return false;
+        }
+        self.pending_turns.push_back((due, direction));
+        true
+    }
+}
+
+/// Whether a head going `from` can turn to `to`: anything but straight back into the segment
+/// behind it. A snake that's only a head has nothing there, so it may turn right around.
+fn turn_allowed(from: Direction, to: Direction, length: u32) -> bool {
+    length <= 1 || to != from.opposite()
+}
+
+/// What can differ from one snake to the next, so two snakes in one game can move at
+/// different speeds and play by different rules.
+struct SnakeConfig {
+    /// Runs out whenever the snake moves. It keeps pace with `SnakeMoveTimer`, which counts the
+    /// run's moves, unless `speed` says otherwise.
+    move_timer: Timer,
+    /// Set once `move_timer` has been lined up with `SnakeMoveTimer`, so a snake spawned
+    /// during a run moves in step with the others.
+    synced: bool,
+    /// How many times the run's pace the snake moves at.
+    speed: f32,
+    rules: RuleOverrides,
+    /// The player steering the snake, or `None` for the computer.
+    player: Option<u8>,
+}
+
+impl SnakeConfig {
+    fn player(player: u8) -> Self {
+        Self {
+            move_timer: Timer::new(SNAKE_MOVE_INTERVAL, true),
+            synced: false,
+            speed: 1.0,
+            rules: RuleOverrides::default(),
+            player: Some(player),
+        }
+    }
+
+    fn computer() -> Self {
+        Self {
+            player: None,
+            ..Self::player(0)
+        }
+    }
 }
 
 struct SnakeSegment {
     next_segment: Option<Entity>,
 }
 
-struct Food;
+/// Fresh food is worth this many points.
+const FOOD_POINTS: u32 = 10;
+
+/// Food loses a point every this many seconds it's left uneaten, down to `MIN_FOOD_POINTS`.
+const FOOD_DECAY_INTERVAL: f32 = 2.0;
+const MIN_FOOD_POINTS: u32 = 5;
+
+const FOOD_SIZE: f32 = 0.6;
+const ARMORED_FOOD_SIZE: f32 = 0.8;
+
+struct Food {
+    /// Seconds of play since the food was spawned.
+    age: f32,
+    /// Segments the snake grows by eating it. Kept up to date by `food_growth`.
+    growth: u32,
+}
+
+impl Food {
+    fn value(&self) -> u32 {
+        FOOD_POINTS
+            .saturating_sub((self.age / FOOD_DECAY_INTERVAL) as u32)
+            .max(MIN_FOOD_POINTS)
+    }
+}
+
+/// Segments grown by food with the `fast_growth` rule.
+const FAST_GROWTH: u32 = 3;
+
+/// Rare food that grows the snake by `SUPER_GROWTH` segments at once, whatever the rules.
+struct SuperGrowth;
+
+const SUPER_GROWTH: u32 = 5;
+
+/// Chance that newly spawned food is super-growth food.
+const SUPER_GROWTH_FOOD_CHANCE: f32 = 0.03;
+
+/// Super-growth food is drawn bigger still than armored food.
+const SUPER_GROWTH_FOOD_SIZE: f32 = 0.95;
+
+/// On food, makes the segment it grows armored. An armored segment absorbs one collision with
+/// the snake's own head, losing its armor instead of ending the game.
+struct Armor;
+
+/// Sets the move timer from the base interval and the active speed effects, adding the effects
+/// of any tiles under the player's heads first. Effects multiply together, so a speed pad and
+/// mud cancel out.
+fn apply_speed_modifiers(
+    move_interval: Res<MoveInterval>,
+    tiles: Res<Tiles>,
+    mut effects: ResMut<Effects>,
+    mut snake_timer: ResMut<SnakeMoveTimer>,
+    mut heads: Query<Without<Ally, (&SnakeHead, &Position)>>,
+) {
+    for (_head, position) in &mut heads.iter() {
+        if let Some(effect) = tiles.get(position).and_then(|kind| kind.effect()) {
+            effects.add(effect);
+        }
+    }
+    snake_timer.0.duration = (move_interval.0 * effects.interval_factor())
+        .max(MIN_MOVE_INTERVAL)
+        .min(MAX_MOVE_INTERVAL);
+}
+
+/// Points taken away for each wall bounce with the `wall_bounce` rule.
+const BOUNCE_PENALTY: u32 = 3;
+
+/// A snake that bounces this many times within `BOUNCE_WINDOW` moves crashes on the next wall,
+/// so holding a key into a wall can't keep it bouncing forever.
+const MAX_BOUNCES: usize = 5;
+const BOUNCE_WINDOW: u64 = 20;
+
+/// With the `weight` rule, snake length that adds one move of turn delay.
+const WEIGHT_PER_TURN_DELAY: u32 = 30;
+
+/// Chance that newly spawned food is armored.
+const ARMOR_FOOD_CHANCE: f32 = 0.1;
 
 struct HeadMaterial(Handle<ColorMaterial>);
 struct SegmentMaterial(Handle<ColorMaterial>);
 struct FoodMaterial(Handle<ColorMaterial>);
+struct PowerUpMaterial(Handle<ColorMaterial>);
 
 struct SnakeMoveTimer(Timer);
-struct FoodSpawnTimer(Timer);
 
-struct GameOverEvent;
+/// Time between snake moves in seconds, before speed modifiers like tiles are applied.
+struct MoveInterval(f32);
+
+impl Default for MoveInterval {
+    fn default() -> Self {
+        Self(SNAKE_MOVE_INTERVAL.as_secs_f32())
+    }
+}
+
+/// Speed modifiers can't push the move interval outside this range, in seconds.
+const MIN_MOVE_INTERVAL: f32 = 0.03;
+const MAX_MOVE_INTERVAL: f32 = 2.0;
+
+#[derive(Default)]
+struct Score(u32);
+
+/// Which part of the game is running. Gameplay only advances while `Playing`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum GameState {
+    /// Waiting for the files the game needs, see `loading`.
+    Loading,
+    ProfileSelect,
+    /// Setting up a tournament or looking at its bracket between runs.
+    Tournament,
+    /// Browsing or watching replays.
+    Replays,
+    /// Painting a level, see `editor`.
+    Editor,
+    /// Picking a level to play, see `level_browser`.
+    Levels,
+    /// Looking at the campaign map, see `campaign`.
+    Campaign,
+    Playing,
+    Paused,
+}
+
+impl Default for GameState {
+    fn default() -> Self {
+        Self::Loading
+    }
+}
+
+/// The current run. Every game over starts a new one with a fresh seed, so a run can be
+/// identified in the logs and its food placement reproduced.
+struct Run {
+    seed: u64,
+    /// Number of snake moves so far.
+    tick: u64,
+    /// Seconds of play so far, not counting pauses.
+    elapsed: f32,
+    rng: StdRng,
+}
+
+impl Default for Run {
+    fn default() -> Self {
+        Self::with_seed(random())
+    }
+}
+
+impl Run {
+    fn with_seed(seed: u64) -> Self {
+        Self {
+            seed,
+            tick: 0,
+            elapsed: 0.0,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    fn span(&self) -> Span {
+        info_span!("run", seed = self.seed, tick = self.tick)
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+enum GameOverCause {
+    Wall,
+    Tail,
+    HeadOn,
+    /// Ran onto spikes with no tail left to lose to them.
+    Spikes,
+    /// A puzzle's moves ran out with food left, see `puzzle`.
+    OutOfMoves,
+    /// Nobody touched the controls for a long while, see `idle`.
+    Idle,
+    /// Not a crash: the snake reached one of the level's exits.
+    LevelComplete,
+}
+
+/// Marks a snake head that crashed this tick. Dead snakes stop moving and get cleaned up by
+/// `resolve_deaths`.
+struct Dead {
+    cause: GameOverCause,
+}
+
+/// The primary window, or `None` while there isn't one, like as the game shuts down. Systems
+/// that size things to it skip the frame then.
+fn primary_window(windows: &Windows) -> Option<&Window> {
+    let window = windows.get_primary();
+    if window.is_none() {
+        debug!("no primary window, skipping the frame");
+    }
+    window
+}
+
+/// Sprites are sized from their `Size` component rather than from their texture.
+fn sized_sprite(material: Handle<ColorMaterial>) -> SpriteComponents {
+    SpriteComponents {
+        material,
+        sprite: Sprite {
+            resize_mode: SpriteResizeMode::Manual,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+const SEGMENT_SIZE: f32 = 0.65;
+/// Armored parts are drawn bigger, so they stand out without relying on color.
+const ARMORED_SEGMENT_SIZE: f32 = 0.85;
 
 fn spawn_segment(
     commands: &mut Commands,
     material: Handle<ColorMaterial>,
     position: Position,
+    next_segment: Option<Entity>,
 ) -> Entity {
     commands
-        .spawn(SpriteComponents {
-            material,
-            ..Default::default()
-        })
-        .with(SnakeSegment { next_segment: None })
+        .spawn(sized_sprite(material))
+        .with(SnakeSegment { next_segment })
         .with(position)
-        .with(Size::square(0.65));
+        .with(Size::square(SEGMENT_SIZE));
+    commands.current_entity().unwrap()
+}
+
+/// Spawns a snake with `segments` ordered from just behind the head to the tail, returning the
+/// head. A snake always has at least one segment. `pending_growth` more segments will emerge
+/// from its tail over its first moves.
+fn spawn_snake(
+    commands: &mut Commands,
+    head_material: Handle<ColorMaterial>,
+    segment_material: Handle<ColorMaterial>,
+    head: Position,
+    direction: Direction,
+    segments: &[Position],
+    pending_growth: u32,
+) -> Entity {
+    let mut next_segment = None;
+    for &position in segments.iter().rev() {
+        next_segment = Some(spawn_segment(
+            commands,
+            segment_material,
+            position,
+            next_segment,
+        ));
+    }
+    commands
+        .spawn(sized_sprite(head_material))
+        .with(SnakeHead {
+            direction,
+            next_segment: next_segment.expect("a snake needs at least one segment"),
+            turn_delay: 0,
+            pending_turns: VecDeque::new(),
+            pending_growth,
+            bounces_off_walls: false,
+            bites_own_tail: false,
+            wraps_at_walls: false,
+            recent_bounces: VecDeque::new(),
+        })
+        .with(head)
+        .with(Size::square(0.8))
+        .with(Controlled(Box::new(PlayerController)))
+        .with(InputQueue::default())
+        .with(SnakeConfig::player(0));
     commands.current_entity().unwrap()
 }
 
 fn spawn_initial_snake(
-    mut commands: &mut Commands,
+    commands: &mut Commands,
     head_material: Res<HeadMaterial>,
     segment_material: Res<SegmentMaterial>,
 ) {
-    let first_segment = spawn_segment(&mut commands, segment_material.0, Position { x: 10, y: 9 });
+    spawn_snake(
+        commands,
+        head_material.0,
+        segment_material.0,
+        START_POSITION,
+        Direction::Up,
+        &[Position {
+            y: START_POSITION.y - 1,
+            ..START_POSITION
+        }],
+        0,
+    );
+}
+
+fn spawn_food(
+    commands: &mut Commands,
+    material: Handle<ColorMaterial>,
+    position: Position,
+) -> Entity {
+    // Food is drawn as a diamond so it never relies on color alone to stand out from the square
+    // snake segments.
     commands
         .spawn(SpriteComponents {
-            material: head_material.0,
-            ..Default::default()
+            transform: Transform::from_rotation(Quat::from_rotation_z(FRAC_PI_4)),
+            ..sized_sprite(material)
         })
-        .with(SnakeHead {
-            direction: Direction::Up,
-            next_segment: first_segment,
+        .with(Food {
+            age: 0.0,
+            growth: 1,
         })
-        .with(Position { x: 10, y: 10 })
-        .with(Size::square(0.8));
+        .with(position)
+        .with(Size::square(FOOD_SIZE));
+    commands.current_entity().unwrap()
+}
+
+/// Counts the parts of a snake, head included.
+fn snake_length(first_segment: Entity, segments: &Query<&SnakeSegment>) -> u32 {
+    let mut length = 1;
+    let mut next = Some(first_segment);
+    while let Some(segment) = next.and_then(|ent| segments.get::<SnakeSegment>(ent).ok()) {
+        length += 1;
+        next = segment.next_segment;
+    }
+    length
+}
+
+/// Despawns every snake, food and power-up, leaving an empty arena. Snakes are despawned
+/// straight away rather than through `BodyChange`, since none of them is left to link to.
+fn despawn_arena(
+    commands: &mut Commands,
+    segments: &mut Query<(Entity, &SnakeSegment)>,
+    food: &mut Query<(Entity, &Food)>,
+    power_ups: &mut Query<(Entity, &PowerUp)>,
+    heads: &mut Query<(Entity, &SnakeHead)>,
+) {
+    for (ent, _segment) in &mut segments.iter() {
+        commands.despawn(ent);
+    }
+    for (ent, _food) in &mut food.iter() {
+        commands.despawn(ent);
+    }
+    for (ent, _power_up) in &mut power_ups.iter() {
+        commands.despawn(ent);
+    }
+    for (ent, _head) in &mut heads.iter() {
+        commands.despawn(ent);
+    }
 }
 
-fn setup(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
-    commands.spawn(Camera2dComponents::default());
-    commands.insert_resource(HeadMaterial(
-        materials.add(Color::rgb(0.7, 0.7, 0.7).into()),
-    ));
-    commands.insert_resource(SegmentMaterial(
-        materials.add(Color::rgb(0.3, 0.3, 0.3).into()),
-    ));
-    commands.insert_resource(FoodMaterial(
-        materials.add(Color::rgb(1.0, 0.0, 1.0).into()),
-    ));
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<AccessibilitySettings>,
+    mut loading: ResMut<LoadingAssets>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands
+        .spawn(Camera2dComponents::default())
+        .with(ArenaCamera)
+        .spawn(UiCameraComponents::default());
+
+    // Textures are tinted by the palette, so they should stay mostly white.
+    if let Err(err) = asset_server.watch_for_changes() {
+        warn!("assets won't reload when changed: {:?}", err);
+    }
+    let colors = settings.palette.colors();
+    let mut load_material = |path: &str, color: Color| match asset_server.load(path) {
+        Ok(texture) => {
+            loading.track(path, texture);
+            materials.add(ColorMaterial::modulated_texture(texture, color))
+        }
+        // The loading screen reports it if it's required; a plain square stands in otherwise.
+        Err(err) => {
+            warn!("could not load {}: {:?}", path, err);
+            materials.add(color.into())
+        }
+    };
+    commands.insert_resource(HeadMaterial(load_material(
+        "assets/textures/head.png",
+        colors.head,
+    )));
+    commands.insert_resource(SegmentMaterial(load_material(
+        "assets/textures/segment.png",
+        colors.segment,
+    )));
+    commands.insert_resource(FoodMaterial(load_material(
+        "assets/textures/food.png",
+        colors.food,
+    )));
+    commands.insert_resource(PowerUpMaterial(load_material(
+        "assets/textures/power_up.png",
+        colors.power_up,
+    )));
+}
+
+/// Marks materials as modified when their texture is reloaded from disk, so sprites pick up
+/// the new texture.
+fn refresh_reloaded_materials(
+    mut reader: Local<EventReader<AssetEvent<Texture>>>,
+    texture_events: Res<Events<AssetEvent<Texture>>>,
+    head_material: Res<HeadMaterial>,
+    segment_material: Res<SegmentMaterial>,
+    food_material: Res<FoodMaterial>,
+    power_up_material: Res<PowerUpMaterial>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for event in reader.iter(&texture_events) {
+        if let AssetEvent::Modified { handle } = event {
+            for material in &[
+                head_material.0,
+                segment_material.0,
+                food_material.0,
+                power_up_material.0,
+            ] {
+                let uses_texture = materials
+                    .get(material)
+                    .map_or(false, |material| material.texture == Some(*handle));
+                if uses_texture {
+                    // Mutable access alone is what flags the material as modified.
+                    let _ = materials.get_mut(material);
+                }
+            }
+        }
+    }
 }
 
 fn game_setup(
     mut commands: Commands,
+    run: Res<Run>,
     head_material: Res<HeadMaterial>,
     segment_material: Res<SegmentMaterial>,
 ) {
+    info!(seed = run.seed, "run started");
     spawn_initial_snake(&mut commands, head_material, segment_material);
 }
 
-fn snake_movement(
-    mut commands: Commands,
+fn pause_toggle(
+    mut reader: Local<EventReader<Action>>,
+    actions: Res<Events<Action>>,
+    mut state: ResMut<GameState>,
+) {
+    for action in reader.iter(&actions) {
+        if *action == Action::TogglePause {
+            *state = match *state {
+                GameState::Playing => GameState::Paused,
+                GameState::Paused => GameState::Playing,
+                other => other,
+            };
+        }
+    }
+}
+
+fn advance_clock(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    state: Res<GameState>,
+    mut run: ResMut<Run>,
+) {
+    if *state == GameState::Playing {
+        run.elapsed += time_scale.delta(&time);
+    }
+}
+
+/// Ticks the run's move timer and every snake's own one alongside it.
+fn tick_move_timer(
     time: Res<Time>,
-    keyboard_input: Res<Input<KeyCode>>,
+    time_scale: Res<TimeScale>,
+    state: Res<GameState>,
     mut snake_timer: ResMut<SnakeMoveTimer>,
-    segment_material: Res<SegmentMaterial>,
-    mut game_over_events: ResMut<Events<GameOverEvent>>,
-    mut head_positions: Query<(&mut SnakeHead, &mut Position)>,
-    segments: Query<&mut SnakeSegment>,
-    positions: Query<&mut Position>,
-    mut food_positions: Query<(Entity, &Food, &Position)>,
+    mut configs: Query<&mut SnakeConfig>,
 ) {
-    snake_timer.0.tick(time.delta_seconds);
-    for (mut head, mut head_pos) in &mut head_positions.iter() {
-        let mut dir = head.direction;
-        if keyboard_input.pressed(KeyCode::Left) {
-            dir = Direction::Left;
-        }
-        if keyboard_input.pressed(KeyCode::Right) {
-            dir = Direction::Right;
+    if *state != GameState::Playing {
+        return;
+    }
+    let delta = time_scale.delta(&time);
+    for mut config in &mut configs.iter() {
+        if !config.synced {
+            config.move_timer.elapsed = snake_timer.0.elapsed;
+            config.synced = true;
         }
-        if keyboard_input.pressed(KeyCode::Down) {
-            dir = Direction::Down;
+        config.move_timer.duration = snake_timer.0.duration;
+        let speed = config.speed;
+        config.move_timer.tick(delta * speed);
+    }
+    snake_timer.0.tick(delta);
+}
+
+fn snake_movement(
+    mut commands: Commands,
+    state: Res<GameState>,
+    mut run: ResMut<Run>,
+    snake_timer: Res<SnakeMoveTimer>,
+    mut body_changes: ResMut<Events<BodyChange>>,
+    mut score_events: ResMut<Events<ScoreEvent>>,
+    mut game_events: ResMut<Events<GameEvent>>,
+    mut fatal_errors: ResMut<Events<FatalError>>,
+    mut head_positions: Query<
+        Without<
+            Dead,
+            (
+                Entity,
+                &mut SnakeHead,
+                &mut Position,
+                &mut InputQueue,
+                &SnakeConfig,
+                Option<&Ally>,
+            ),
+        >,
+    >,
+    segments: Query<&SnakeSegment>,
+    armor: Query<&Armor>,
+    positions: Query<&mut Position>,
+    mut food_positions: Query<(Entity, &Food, &Position, Option<&Armor>)>,
+    tiles: Res<Tiles>,
+    level: Res<ActiveLevel>,
+    switch_state: Res<SwitchState>,
+) {
+    if *state != GameState::Playing {
+        return;
+    }
+
+    if snake_timer.0.finished {
+        run.tick += 1;
+    }
+    // What a head moving this tick would crash into: the arena's edge, the level's walls, and
+    // its obstacles and closed doors.
+    let tick = run.tick;
+    let blocked = |cell: Position| {
+        cell.outside_arena()
+            || level.0.as_ref().map_or(false, |level| {
+                level.is_wall(cell) || switch_state.is_blocked(level, cell, tick)
+            })
+    };
+    let span = run.span();
+    let _enter = span.enter();
+    for (head_entity, mut head, mut head_pos, mut queue, config, ally) in &mut head_positions.iter()
+    {
+        let length = snake_length(head.next_segment, &segments);
+        let due = run.tick + head.turn_delay;
+        for dir in queue.take() {
+            head.queue_turn(due, dir, length);
         }
-        if keyboard_input.pressed(KeyCode::Up) {
-            dir = Direction::Up;
+        // The head slides straight across ice, dropping any turns made on the way.
+        if tiles.get(&head_pos) == Some(TileKind::Ice) {
+            head.pending_turns.clear();
         }
-
-        if dir != head.direction.opposite() {
-            head.direction = dir;
+        while let Some(&(due, dir)) = head.pending_turns.front() {
+            if due > run.tick {
+                break;
+            }
+            head.pending_turns.pop_front();
+            // Checked again, since a bounce may have turned the head since it was queued.
+            head.try_set_direction(dir, length);
         }
 
-        if snake_timer.0.finished {
-            // Move each tail segment to its parent's position.
-            // TODO: Try out the algorithm where we instead just move the last segment to the
-            // head's current position and update the pointer to the last segment.
-            let mut last_position = *head_pos;
-            let mut segment_entity = head.next_segment;
-            loop {
-                let segment = segments.get::<SnakeSegment>(segment_entity).unwrap();
-                let mut segment_position = positions.get_mut::<Position>(segment_entity).unwrap();
-                let current_position = *segment_position;
-                *segment_position = last_position;
-                last_position = current_position;
-
-                // Check if we hit our own tail.
-                if *head_pos == last_position {
-                    game_over_events.send(GameOverEvent);
+        if config.move_timer.finished {
+            // Follow the links from the head to the tail.
+            let mut entities = Vec::new();
+            let mut cells = Vec::new();
+            let mut next = Some(head.next_segment);
+            // A segment despawned without being unlinked first, which breaks the snake.
+            let mut missing = None;
+            while let Some(ent) = next {
+                match (
+                    segments.get::<SnakeSegment>(ent),
+                    positions.get::<Position>(ent),
+                ) {
+                    (Ok(segment), Ok(position)) => {
+                        entities.push(ent);
+                        cells.push(*position);
+                        next = segment.next_segment;
+                    }
+                    _ => {
+                        missing = Some(ent);
+                        break;
+                    }
                 }
+            }
+            if let Some(missing) = missing {
+                fatal_errors.send(FatalError::new(
+                    "error-snake-broken",
+                    format!("segment {:?} of snake {:?} is gone", missing, head_entity),
+                ));
+                continue;
+            }
+            let mut body = SnakeBody::new(*head_pos, cells, head.pending_growth);
 
-                if let Some(next) = segment.next_segment {
-                    segment_entity = next;
+            // Check if we hit our own tail on the last move.
+            if let Some(index) = body.segment_at(*head_pos) {
+                let segment_entity = entities[index];
+                if armor.get::<Armor>(segment_entity).is_ok() {
+                    commands.remove_one::<Armor>(segment_entity);
+                    commands.insert_one(segment_entity, Size::square(SEGMENT_SIZE));
+                    info!(x = head_pos.x, y = head_pos.y, "armor broken");
+                } else if head.bites_own_tail && body.truncate_at(*head_pos).is_some() {
+                    // With the `tail_cut` rule, biting the tail cuts it off instead of ending
+                    // the run.
+                    body_changes.send(BodyChange::CutBehind {
+                        new_tail: entities[index - 1],
+                        first_cut: segment_entity,
+                    });
+                    entities.truncate(index);
+                    info!(x = head_pos.x, y = head_pos.y, "tail bitten off");
                 } else {
-                    break;
+                    commands.insert_one(
+                        head_entity,
+                        Dead {
+                            cause: GameOverCause::Tail,
+                        },
+                    );
                 }
             }
 
-            // Move the head segment.
-            match head.direction {
-                Direction::Left => {
-                    head_pos.x -= 1;
+            // With the `wall_bounce` rule the head turns instead of running into a wall, a level's
+            // wall or an obstacle: clockwise if that way is open, otherwise counter-clockwise.
+            if head.bounces_off_walls && blocked(head_pos.moved(head.direction)) {
+                head.recent_bounces
+                    .retain(|&bounce| bounce + BOUNCE_WINDOW > tick);
+                let turns = [
+                    head.direction.clockwise(),
+                    head.direction.clockwise().opposite(),
+                ];
+                let free = turns
+                    .iter()
+                    .copied()
+                    .find(|&dir| !blocked(head_pos.moved(dir)));
+                if let (Some(dir), true) = (free, head.recent_bounces.len() < MAX_BOUNCES) {
+                    head.direction = dir;
+                    head.recent_bounces.push_back(tick);
+                    score_events.send(
+                        ScoreEvent::new(BOUNCE_PENALTY, ScoreReason::WallBounce).at(*head_pos),
+                    );
+                    debug!(x = head_pos.x, y = head_pos.y, "bounced off a wall");
                 }
-                Direction::Right => {
-                    head_pos.x += 1;
+            }
+
+            // Work out where the head ends up first, so food eaten there starts growing the
+            // snake on this move. Conveyors push the whole snake one more cell. It moves
+            // rigidly, so it can't hit itself on the extra step, but any part of it can be
+            // pushed into a wall. With the `wrap_walls` rule the head comes back in on the far
+            // side.
+            let step = head_pos.moved(head.direction);
+            let push = match tiles.get(&step) {
+                Some(TileKind::Conveyor(push)) => Some(push),
+                _ => None,
+            };
+            let mut destination = push.map_or(step, |push| step.moved(push));
+            if head.wraps_at_walls {
+                destination = destination.wrapped();
+            }
+
+            let mut armored = false;
+            for (ent, food, food_pos, armor) in &mut food_positions.iter() {
+                if *food_pos == destination {
+                    body.grow(food.growth);
+                    armored |= armor.is_some();
+                    commands.despawn(ent);
+                    let points = food.value();
+                    score_events.send(
+                        ScoreEvent::new(points, ScoreReason::Food)
+                            .at(*food_pos)
+                            .paced(Pace {
+                                moves_per_second: config.speed / config.move_timer.duration,
+                                length: body.len(),
+                            }),
+                    );
+                    game_events.send(GameEvent::FoodEaten {
+                        position: *food_pos,
+                        points,
+                        by_ally: ally.is_some(),
+                    });
+                    info!(x = food_pos.x, y = food_pos.y, points, "food eaten");
+                }
+            }
+
+            let pushed_out = match push {
+                Some(push) => {
+                    body.advance(step);
+                    body.shift(push, head.wraps_at_walls)
                 }
-                Direction::Up => {
-                    head_pos.y += 1;
+                None => {
+                    body.advance(destination);
+                    false
                 }
-                Direction::Down => {
-                    head_pos.y -= 1;
+            };
+            *head_pos = body.head();
+            head.pending_growth = body.growth();
+            for (&ent, cell) in entities.iter().zip(body.segments()) {
+                if let Ok(mut position) = positions.get_mut::<Position>(ent) {
+                    *position = cell;
                 }
             }
 
             // Check if we hit a wall.
-            if head_pos.x < 0
-                || head_pos.y < 0
-                || head_pos.x as u32 > ARENA_WIDTH
-                || head_pos.y as u32 > ARENA_HEIGHT
-            {
-                game_over_events.send(GameOverEvent);
+            if head_pos.outside_arena() || pushed_out {
+                commands.insert_one(
+                    head_entity,
+                    Dead {
+                        cause: GameOverCause::Wall,
+                    },
+                );
             }
 
-            for (ent, _food, food_pos) in &mut food_positions.iter() {
-                if food_pos == &*head_pos {
-                    let new_segment =
-                        spawn_segment(&mut commands, segment_material.0, last_position);
-                    let mut segment = segments.get_mut::<SnakeSegment>(segment_entity).unwrap();
-                    segment.next_segment = Some(new_segment);
-                    commands.despawn(ent);
-                }
+            // A growing snake keeps its tail where it was, and the new segment goes there, so
+            // growth from one food keeps emerging from the tail over the next moves. Armor goes
+            // on the first of them.
+            if body.len() > entities.len() + 1 {
+                body_changes.send(BodyChange::Grow {
+                    tail: entities[entities.len() - 1],
+                    position: body.tail(),
+                    armored,
+                });
+                game_events.send(GameEvent::Grew {
+                    position: body.tail(),
+                });
             }
         }
     }
 }
 
-fn food_spawner(
+/// Segments a spike tile takes off the tail.
+const SPIKE_DAMAGE: usize = 3;
+
+/// A head that moves onto spikes loses `SPIKE_DAMAGE` segments off its tail rather than
+/// crashing, though it always keeps one. A snake already down to one segment crashes.
+fn spike_damage(
+    mut checked_tick: Local<u64>,
     mut commands: Commands,
-    food_material: Res<FoodMaterial>,
+    state: Res<GameState>,
+    run: Res<Run>,
+    tiles: Res<Tiles>,
+    mut body_changes: ResMut<Events<BodyChange>>,
+    mut heads: Query<Without<Dead, (Entity, &SnakeHead, &Position)>>,
+    segments: Query<&SnakeSegment>,
+) {
+    if *state != GameState::Playing || run.tick == *checked_tick {
+        return;
+    }
+    *checked_tick = run.tick;
+
+    for (ent, head, position) in &mut heads.iter() {
+        if tiles.get(position) != Some(TileKind::Spikes) {
+            continue;
+        }
+        let mut body = vec![head.next_segment];
+        while let Some(next) = segments
+            .get::<SnakeSegment>(body[body.len() - 1])
+            .ok()
+            .and_then(|segment| segment.next_segment)
+        {
+            body.push(next);
+        }
+        if body.len() == 1 {
+            commands.insert_one(
+                ent,
+                Dead {
+                    cause: GameOverCause::Spikes,
+                },
+            );
+            continue;
+        }
+        let kept = body.len().saturating_sub(SPIKE_DAMAGE).max(1);
+        body_changes.send(BodyChange::CutBehind {
+            new_tail: body[kept - 1],
+            first_cut: body[kept],
+        });
+        info!(
+            x = position.x,
+            y = position.y,
+            cut = body.len() - kept,
+            "spiked"
+        );
+    }
+}
+
+/// Applies the `wall_bounce`, `tail_cut` and `wrap_walls` rules to the player's snakes, unless
+/// a snake's own config overrides them. The computer's snakes steer clear of walls and
+/// themselves anyway. Puzzles are always played by the standard rules, which are the ones the
+/// solver checks them with.
+fn update_forgiving_rules(
+    rules: Res<GameRules>,
+    level: Res<ActiveLevel>,
+    mut heads: Query<(&mut SnakeHead, &SnakeConfig)>,
+) {
+    let puzzle = level.is_puzzle();
+    for (mut head, config) in &mut heads.iter() {
+        if config.player.is_none() {
+            continue;
+        }
+        let overrides = config.rules;
+        head.bounces_off_walls = !puzzle && overrides.wall_bounce.unwrap_or(rules.wall_bounce);
+        head.bites_own_tail = !puzzle && overrides.tail_cut.unwrap_or(rules.tail_cut);
+        head.wraps_at_walls = !puzzle && overrides.wrap_walls.unwrap_or(rules.wrap_walls);
+    }
+}
+
+/// With the `weight` rule, a snake longer than 30 parts turns one move late, and one more for
+/// every 30 parts after that: 31 to 60 parts is one move, 61 to 90 two. Puzzles turn straight
+/// away, like the solver.
+fn update_turn_delay(
+    rules: Res<GameRules>,
+    level: Res<ActiveLevel>,
+    mut heads: Query<(&mut SnakeHead, &SnakeConfig)>,
+    segments: Query<&SnakeSegment>,
+) {
+    let puzzle = level.is_puzzle();
+    for (mut head, config) in &mut heads.iter() {
+        head.turn_delay = if !puzzle && config.rules.weight.unwrap_or(rules.weight) {
+            let length = snake_length(head.next_segment, &segments);
+            (length.saturating_sub(1) / WEIGHT_PER_TURN_DELAY) as u64
+        } else {
+            0
+        };
+    }
+}
+
+/// Removes crashed snakes. The game is only over once none of the player's snakes is left;
+/// while another one survives, e.g. during the hydra power-up, the crashed one is simply
+/// despawned. Allies never end the game.
+fn resolve_deaths(
+    mut game_events: ResMut<Events<GameEvent>>,
+    mut body_changes: ResMut<Events<BodyChange>>,
+    mut heads: Query<(Entity, &SnakeHead, Option<&Dead>, Option<&Ally>)>,
+) {
+    let mut dead = Vec::new();
+    let mut player_death = None;
+    let mut survivors = 0;
+    for (ent, _head, death, ally) in &mut heads.iter() {
+        match (death, ally) {
+            (Some(death), ally) => {
+                dead.push(ent);
+                if ally.is_none() {
+                    player_death = Some(death.cause);
+                }
+            }
+            (None, None) => survivors += 1,
+            (None, Some(_ally)) => {}
+        }
+    }
+
+    if survivors == 0 {
+        if let Some(cause) = player_death {
+            // The whole arena is about to be reset.
+            game_events.send(GameEvent::GameOver { cause });
+            return;
+        }
+    }
+    for head in dead {
+        body_changes.send(BodyChange::Remove { head });
+    }
+}
+
+/// Sets how much each food grows the snake, following the `fast_growth` rule as it changes.
+/// A puzzle's food always grows it by one, as the solver expects.
+fn food_growth(
+    rules: Res<GameRules>,
+    level: Res<ActiveLevel>,
+    mut food: Query<(&mut Food, Option<&SuperGrowth>)>,
+) {
+    let puzzle = level.is_puzzle();
+    for (mut food, super_growth) in &mut food.iter() {
+        food.growth = if super_growth.is_some() {
+            SUPER_GROWTH
+        } else if rules.fast_growth && !puzzle {
+            FAST_GROWTH
+        } else {
+            1
+        };
+    }
+}
+
+/// Ages uneaten food, shrinking it as its value drops so fast routing is visibly rewarded.
+fn age_food(
     time: Res<Time>,
-    mut timer: ResMut<FoodSpawnTimer>,
+    time_scale: Res<TimeScale>,
+    state: Res<GameState>,
+    mut food: Query<(&mut Food, &mut Size, Option<&Armor>, Option<&SuperGrowth>)>,
 ) {
-    timer.0.tick(time.delta_seconds);
-    if timer.0.finished {
-        commands
-            .spawn(SpriteComponents {
-                material: food_material.0,
-                ..Default::default()
-            })
-            .with(Food)
-            .with(Position {
-                x: (random::<f32>() * ARENA_WIDTH as f32) as i32,
-                y: (random::<f32>() * ARENA_HEIGHT as f32) as i32,
-            })
-            .with(Size::square(0.8));
+    if *state != GameState::Playing {
+        return;
+    }
+    for (mut food, mut size, armor, super_growth) in &mut food.iter() {
+        food.age += time_scale.delta(&time);
+        let full_size = if super_growth.is_some() {
+            SUPER_GROWTH_FOOD_SIZE
+        } else if armor.is_some() {
+            ARMORED_FOOD_SIZE
+        } else {
+            FOOD_SIZE
+        };
+        let scale = 0.5 + 0.5 * food.value() as f32 / FOOD_POINTS as f32;
+        *size = Size::square(full_size * scale);
     }
 }
 
 fn game_over_system(
     mut commands: Commands,
-    mut reader: Local<EventReader<GameOverEvent>>,
-    game_over_events: Res<Events<GameOverEvent>>,
+    mut reader: Local<EventReader<GameEvent>>,
+    game_events: Res<Events<GameEvent>>,
     segment_material: Res<SegmentMaterial>,
     head_material: Res<HeadMaterial>,
+    food_material: Res<FoodMaterial>,
+    mut score: ResMut<Score>,
+    mut run: ResMut<Run>,
+    level: Res<ActiveLevel>,
+    checkpoint: Res<Checkpoint>,
+    mut profile: ResMut<ActiveProfile>,
     mut segments: Query<(Entity, &SnakeSegment)>,
     mut food: Query<(Entity, &Food)>,
+    mut power_ups: Query<(Entity, &PowerUp)>,
     mut heads: Query<(Entity, &SnakeHead)>,
 ) {
-    if reader.iter(&game_over_events).next().is_some() {
-        for (ent, _segment) in &mut segments.iter() {
-            commands.despawn(ent);
-        }
-        for (ent, _food) in &mut food.iter() {
-            commands.despawn(ent);
+    if let Some(cause) = reader.iter(&game_events).find_map(GameEvent::game_over) {
+        run.span()
+            .in_scope(|| info!(cause = ?cause, score = score.0, "game over"));
+        despawn_arena(
+            &mut commands,
+            &mut segments,
+            &mut food,
+            &mut power_ups,
+            &mut heads,
+        );
+
+        // A crash after a checkpoint carries on from it rather than ending the run.
+        let resume = match cause {
+            GameOverCause::LevelComplete | GameOverCause::Idle | GameOverCause::OutOfMoves => None,
+            _ => checkpoint
+                .0
+                .as_ref()
+                .filter(|snapshot| snapshot.seed == run.seed),
+        };
+        if let Some(snapshot) = resume {
+            let elapsed = run.elapsed;
+            snapshot.restore(
+                &mut commands,
+                &head_material,
+                &segment_material,
+                &food_material,
+                &mut run,
+                &mut score,
+            );
+            // The time lost still counts against the level's star times.
+            run.elapsed = elapsed;
+            info!(
+                seed = run.seed,
+                tick = run.tick,
+                "run resumed from checkpoint"
+            );
+            return;
         }
-        for (ent, _head) in &mut heads.iter() {
-            commands.despawn(ent);
+
+        if let Some(profile) = &mut profile.0 {
+            let level = level.0.as_ref().map(|level| level.name.as_str());
+            profile.record_run(score.0, run.elapsed, level);
         }
+        *run = Run::default();
+        info!(seed = run.seed, "run started");
+        score.0 = 0;
         spawn_initial_snake(&mut commands, head_material, segment_material);
     }
 }
 
 fn size_scaling(windows: Res<Windows>, mut q: Query<(&Size, &mut Sprite)>) {
+    let window = match primary_window(&windows) {
+        Some(window) => window,
+        None => return,
+    };
     for (size, mut sprite) in &mut q.iter() {
-        let window = windows.get_primary().unwrap();
         sprite.size = Vec2::new(
             size.width * window.width as f32 / ARENA_WIDTH as f32,
             size.height * window.height as f32 / ARENA_HEIGHT as f32,
@@ -276,37 +1281,109 @@ fn position_translation(windows: Res<Windows>, mut q: Query<(&Position, &mut Tra
     fn convert(p: f32, bound_window: f32, bound_game: f32) -> f32 {
         p / bound_game * bound_window - (bound_window / 2.0)
     }
-    let window = windows.get_primary().unwrap();
+    let window = match primary_window(&windows) {
+        Some(window) => window,
+        None => return,
+    };
     for (pos, mut transform) in &mut q.iter() {
+        // Keep the depth so layers like floor tiles stay behind the snake.
+        let depth = transform.translation().z();
         transform.set_translation(Vec3::new(
             convert(pos.x as f32, window.width as f32, ARENA_WIDTH as f32),
             convert(pos.y as f32, window.height as f32, ARENA_HEIGHT as f32),
-            0.0,
+            depth,
         ));
     }
 }
 
 fn main() {
+    logging::init();
+    crash::install_panic_hook();
+
     App::build()
         .add_resource(WindowDescriptor {
-            title: "Snake!".to_string(),
+            title: Locale::default().text("window-title"),
             width: 1000,
             height: 1000,
             ..Default::default()
         })
-        .add_resource(ClearColor(Color::rgb(0.04, 0.04, 0.04)))
-        .add_resource(SnakeMoveTimer(Timer::new(Duration::from_millis(150), true)))
-        .add_resource(FoodSpawnTimer(Timer::new(
-            Duration::from_millis(1000),
-            true,
-        )))
-        .add_event::<GameOverEvent>()
+        .add_resource(ClearColor(Palette::default().colors().background))
+        .add_resource(SnakeMoveTimer(Timer::new(SNAKE_MOVE_INTERVAL, true)))
+        .init_resource::<Score>()
+        .init_resource::<GameState>()
+        .init_resource::<Run>()
+        .init_resource::<GameRules>()
+        .init_resource::<MoveInterval>()
+        .add_event::<GameEvent>()
+        .add_plugin(LocalePlugin)
+        .add_plugin(InputPlugin)
+        .add_plugin(ControllerPlugin)
+        .add_plugin(AccessibilityPlugin)
+        .add_plugin(UiPlugin)
+        .add_plugin(ToastPlugin)
+        .add_plugin(DialogPlugin)
+        .add_plugin(BodyPlugin)
+        .add_plugin(GameErrorPlugin)
+        .add_plugin(ConfigPlugin)
+        .add_plugin(ModsPlugin)
+        .add_plugin(CrashPlugin)
+        .add_plugin(TasksPlugin)
+        .add_plugin(LoadingPlugin)
+        .add_plugin(TimeScalePlugin)
+        .add_plugin(ProfilePlugin)
+        .add_plugin(EffectsPlugin)
+        .add_plugin(SlowStartPlugin)
+        .add_plugin(AdaptivePlugin)
+        .add_plugin(DirectorPlugin)
+        .add_plugin(PowerUpPlugin)
+        .add_plugin(SplitPlugin)
+        .add_plugin(TilesPlugin)
+        .add_plugin(LevelPlugin)
+        .add_plugin(ObstaclePlugin)
+        .add_plugin(PuzzlePlugin)
+        .add_plugin(EditorPlugin)
+        .add_plugin(LevelBrowserPlugin)
+        .add_plugin(PackPlugin)
+        .add_plugin(CampaignPlugin)
+        .add_plugin(LightingPlugin)
+        .add_plugin(SeasonalPlugin)
+        .add_plugin(ScoringPlugin)
+        .add_plugin(PopupPlugin)
+        .add_plugin(ObjectivesPlugin)
+        .add_plugin(HeadOnPlugin)
+        .add_plugin(ZonePlugin)
+        .add_plugin(RelayPlugin)
+        .add_plugin(TournamentPlugin)
+        .add_plugin(ReplayPlugin)
+        .add_plugin(CameraPlugin)
+        .add_plugin(HudLayoutPlugin)
+        .add_plugin(OptionsPlugin)
+        .add_plugin(CelebrationPlugin)
+        .add_plugin(IdlePlugin)
+        .add_plugin(ZenPlugin)
+        .add_plugin(EventLogPlugin)
+        .add_plugin(DangerPlugin)
+        .add_plugin(SlowMotionPlugin)
+        .add_plugin(HeatmapPlugin)
+        .add_plugin(LatencyPlugin)
+        .add_plugin(TelemetryPlugin)
+        .add_plugin(SessionPlugin)
         .add_startup_system(setup.system())
         .add_startup_stage("game_setup")
         .add_startup_system_to_stage("game_setup", game_setup.system())
+        .add_system(pause_toggle.system())
+        .add_system(advance_clock.system())
+        .add_system(update_turn_delay.system())
+        .add_system(update_forgiving_rules.system())
+        .add_system(apply_speed_modifiers.system())
+        .add_system(tick_move_timer.system())
         .add_system(snake_movement.system())
-        .add_system(food_spawner.system())
+        .add_system(spike_damage.system())
+        .add_system(age_food.system())
+        .add_system(food_growth.system())
+        .add_system_to_stage(stage::POST_UPDATE, resolve_deaths.system())
         .add_system(game_over_system.system())
+        .add_system(refresh_reloaded_materials.system())
         .add_system(position_translation.system())
         .add_system(size_scaling.system())
         .add_default_plugins()
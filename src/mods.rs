@@ -0,0 +1,209 @@
+use std::{fs, path::PathBuf};
+
+use bevy::prelude::*;
+use serde::de::DeserializeOwned;
+use tracing::{info, warn};
+
+use crate::{locale::Locale, toast::Toast};
+
+/// Every directory in here is a mod.
+const MODS_DIR: &str = "mods";
+
+const SOUNDS_DIR: &str = "sounds";
+
+/// A content pack from the mods folder. It's only data, laid out like the game's own files,
+/// and any of them can be left out:
+///
+/// - `levels/*.ron` and `packs/*.snakepack` are added to the level browser, packs bringing
+///   their themes along;
+/// - `power_ups.ron` and `events.ron` add power-ups and seasonal events, with their food, or
+///   replace the ones with the same id;
+/// - `objectives.ron` adds objectives to the pool.
+///
+/// Paths inside those files, like textures, are from the game's folder, so a mod's own start
+/// with `mods/<name>/`. The game plays no sound yet, so a mod's `sounds` folder is left alone.
+pub struct Mod {
+    pub name: String,
+    dir: PathBuf,
+}
+
+impl Mod {
+    pub fn path(&self, relative: &str) -> PathBuf {
+        self.dir.join(relative)
+    }
+}
+
+/// An entry a mod replaced, because something loaded before it had the same id.
+#[derive(Clone, Debug)]
+pub struct ModConflict {
+    pub file: String,
+    pub id: String,
+    /// The mod whose entry was replaced, or `None` for the game's own.
+    pub replaced: Option<String>,
+    pub by: String,
+}
+
+/// The mods found at startup, loaded in name order, and the conflicts between them.
+#[derive(Default)]
+pub struct Mods {
+    pub mods: Vec<Mod>,
+    pub conflicts: Vec<ModConflict>,
+}
+
+impl Mods {
+    fn scan() -> Self {
+        let mut mods: Vec<Mod> = fs::read_dir(MODS_DIR)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| entry.path())
+                    .filter(|path| path.is_dir())
+                    .filter_map(|dir| {
+                        let name = dir.file_name()?.to_str()?.to_string();
+                        Some(Mod { name, dir })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        mods.sort_by(|a, b| a.name.cmp(&b.name));
+        Self {
+            mods,
+            conflicts: Vec::new(),
+        }
+    }
+
+    /// Every mod's list of `T` from `file`, for the mods that have one. A file that can't be
+    /// read is skipped with a warning, like the game's own.
+    pub fn read_lists<T: DeserializeOwned>(&self, file: &str) -> Vec<(&str, Vec<T>)> {
+        self.mods
+            .iter()
+            .filter_map(|content| {
+                let path = content.path(file);
+                let text = fs::read_to_string(&path).ok()?;
+                match ron::de::from_str(&text) {
+                    Ok(list) => Some((content.name.as_str(), list)),
+                    Err(err) => {
+                        warn!("ignoring {}: {}", path.display(), err);
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Adds every mod's entries from `file` to `entries`, which hold the game's own. An entry
+    /// with the id of one loaded before it takes its place, and that's recorded as a conflict.
+    pub fn merge<T: DeserializeOwned>(
+        &mut self,
+        file: &str,
+        entries: &mut Vec<T>,
+        id: impl Fn(&T) -> &str,
+    ) {
+        let mut origins: Vec<Option<String>> = vec![None; entries.len()];
+        let mut conflicts = Vec::new();
+        for (name, list) in self.read_lists::<T>(file) {
+            for entry in list {
+                match entries
+                    .iter()
+                    .position(|existing| id(existing) == id(&entry))
+                {
+                    Some(index) => {
+                        conflicts.push(ModConflict {
+                            file: file.to_string(),
+                            id: id(&entry).to_string(),
+                            replaced: origins[index].replace(name.to_string()),
+                            by: name.to_string(),
+                        });
+                        entries[index] = entry;
+                    }
+                    None => {
+                        entries.push(entry);
+                        origins.push(Some(name.to_string()));
+                    }
+                }
+            }
+        }
+        for conflict in &conflicts {
+            warn!(
+                file = conflict.file.as_str(),
+                id = conflict.id.as_str(),
+                replaced = conflict.replaced.as_deref().unwrap_or("the game"),
+                by = conflict.by.as_str(),
+                "mod conflict"
+            );
+        }
+        self.conflicts.extend(conflicts);
+    }
+
+    /// Every mod's copy of a directory, for the mods that have one.
+    pub fn dirs(&self, relative: &str) -> Vec<PathBuf> {
+        self.mods
+            .iter()
+            .map(|content| content.path(relative))
+            .filter(|path| path.is_dir())
+            .collect()
+    }
+}
+
+impl ModConflict {
+    fn describe(&self, locale: &Locale) -> String {
+        locale.format(
+            "mod-conflict",
+            &[
+                ("id", self.id.clone()),
+                ("file", self.file.clone()),
+                (
+                    "replaced",
+                    self.replaced
+                        .clone()
+                        .unwrap_or_else(|| locale.text("mod-conflict-game")),
+                ),
+                ("by", self.by.clone()),
+            ],
+        )
+    }
+}
+
+pub struct ModsPlugin;
+
+impl Plugin for ModsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        // Read before anything starts up, so every registry can merge them in.
+        let mods = Mods::scan();
+        if !mods.mods.is_empty() {
+            let names: Vec<&str> = mods
+                .mods
+                .iter()
+                .map(|content| content.name.as_str())
+                .collect();
+            info!(mods = ?names, "found mods");
+        }
+        for dir in mods.dirs(SOUNDS_DIR) {
+            warn!("ignoring {}: the game has no sound", dir.display());
+        }
+        app.add_resource(mods).add_system(report_mods.system());
+    }
+}
+
+/// Says which mods are on once the game is up, and what they replaced of each other's, or of
+/// the game's.
+fn report_mods(
+    mut reported: Local<bool>,
+    mods: Res<Mods>,
+    locale: Res<Locale>,
+    mut toasts: ResMut<Events<Toast>>,
+) {
+    if *reported {
+        return;
+    }
+    *reported = true;
+    if mods.mods.is_empty() {
+        return;
+    }
+    toasts.send(Toast::new(
+        locale.format("mods-loaded", &[("count", mods.mods.len().to_string())]),
+    ));
+    for conflict in &mods.conflicts {
+        toasts.send(Toast::new(conflict.describe(&locale)));
+    }
+}
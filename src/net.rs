@@ -0,0 +1,29 @@
+/// The text at `url`. Blocks until it's done, so it's for the task pool.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn fetch(url: &str) -> Result<String, String> {
+    ureq::get(url)
+        .call()
+        .map_err(|err| err.to_string())?
+        .into_string()
+        .map_err(|err| err.to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn fetch(_url: &str) -> Result<String, String> {
+    Err("downloading isn't supported in the browser".to_string())
+}
+
+/// Sends `body` to `url`. Blocks until it's done, so it's for the task pool.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn post(url: &str, content_type: &str, body: &str) -> Result<(), String> {
+    ureq::post(url)
+        .set("Content-Type", content_type)
+        .send_string(body)
+        .map(|_response| ())
+        .map_err(|err| err.to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn post(_url: &str, _content_type: &str, _body: &str) -> Result<(), String> {
+    Err("uploading isn't supported in the browser".to_string())
+}
@@ -0,0 +1,212 @@
+use std::{collections::HashMap, fs};
+
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::{
+    accessibility::Announcement,
+    locale::Locale,
+    mods::Mods,
+    scoring::{ScoreEvent, ScoreReason},
+    time_scale::TimeScale,
+    toast::Toast,
+    GameState, Run, FOOD_POINTS,
+};
+
+const POOL_PATH: &str = "assets/objectives.ron";
+
+/// Objectives a mod adds to the pool.
+const MOD_POOL: &str = "objectives.ron";
+
+/// What an objective counts.
+#[derive(Copy, Clone, Debug, Deserialize)]
+enum Goal {
+    /// Food eaten.
+    Eat,
+    /// Food eaten at full value, in a row. Eating decayed food starts the count again.
+    FreshStreak,
+    /// Moves spent earning the danger bonus.
+    DangerMoves,
+    /// Seasonal event food collected.
+    EventFood,
+    /// Points scored, before multipliers.
+    Points,
+}
+
+/// An entry in the objectives pool.
+#[derive(Clone, Deserialize)]
+struct Objective {
+    goal: Goal,
+    target: u32,
+    /// Bonus points for completing it.
+    reward: u32,
+    /// Seconds the player has before it's swapped for another.
+    time_limit: f32,
+    /// Descriptions keyed by language tag.
+    descriptions: HashMap<String, String>,
+}
+
+impl Objective {
+    /// How much `event` moves this objective on, or `None` if it breaks a streak.
+    fn progress_from(&self, event: &ScoreEvent) -> Option<u32> {
+        let counts = match (self.goal, event.reason) {
+            (Goal::Eat, ScoreReason::Food)
+            | (Goal::DangerMoves, ScoreReason::Danger)
+            | (Goal::EventFood, ScoreReason::EventFood) => 1,
+            (Goal::FreshStreak, ScoreReason::Food) if event.points >= FOOD_POINTS => 1,
+            (Goal::FreshStreak, ScoreReason::Food) => return None,
+            (Goal::Points, ScoreReason::Objective) | (Goal::Points, ScoreReason::WallBounce) => 0,
+            (Goal::Points, _) => event.points,
+            _ => 0,
+        };
+        Some(counts)
+    }
+}
+
+/// The objective the player is working on and how far along they are.
+struct ActiveObjective {
+    index: usize,
+    progress: u32,
+    remaining: f32,
+}
+
+/// Rotating mid-run objectives drawn from the pool in `assets/objectives.ron`.
+#[derive(Default)]
+pub struct Objectives {
+    pool: Vec<Objective>,
+    active: Option<ActiveObjective>,
+    seed: u64,
+}
+
+impl Objectives {
+    /// The HUD line for the current objective, if there is one.
+    pub fn hud_text(&self, locale: &Locale) -> Option<String> {
+        let active = self.active.as_ref()?;
+        let objective = &self.pool[active.index];
+        let description = locale
+            .translate(&objective.descriptions)
+            .unwrap_or_default()
+            .to_string();
+        Some(locale.format(
+            "objective",
+            &[
+                ("objective", description),
+                ("progress", active.progress.to_string()),
+                ("target", objective.target.to_string()),
+            ],
+        ))
+    }
+
+    /// Swaps in a random objective from the pool, avoiding the one just finished if possible.
+    fn draw(&mut self, run: &mut Run) {
+        if self.pool.is_empty() {
+            return;
+        }
+        let previous = self.active.as_ref().map(|active| active.index);
+        let mut index = run.rng.gen_range(0, self.pool.len());
+        if self.pool.len() > 1 && Some(index) == previous {
+            index = (index + 1) % self.pool.len();
+        }
+        self.active = Some(ActiveObjective {
+            index,
+            progress: 0,
+            remaining: self.pool[index].time_limit,
+        });
+    }
+}
+
+pub struct ObjectivesPlugin;
+
+impl Plugin for ObjectivesPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Objectives>()
+            .add_startup_system(load_pool.system())
+            .add_system(rotate_objectives.system())
+            .add_system(track_objectives.system());
+    }
+}
+
+fn load_pool(mut objectives: ResMut<Objectives>, mods: Res<Mods>) {
+    let mut pool = match fs::read_to_string(POOL_PATH) {
+        Ok(text) => ron::de::from_str::<Vec<Objective>>(&text).unwrap_or_else(|err| {
+            warn!("ignoring {}: {}", POOL_PATH, err);
+            Vec::new()
+        }),
+        Err(_) => Vec::new(),
+    };
+    // Objectives have no ids, so a mod's only ever add to the pool.
+    for (_name, list) in mods.read_lists::<Objective>(MOD_POOL) {
+        pool.extend(list);
+    }
+    if !pool.is_empty() {
+        info!(objectives = pool.len(), "loaded objectives");
+        objectives.pool = pool;
+    }
+}
+
+/// Starts every run with a fresh objective and swaps it out when its time runs out.
+fn rotate_objectives(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    state: Res<GameState>,
+    mut run: ResMut<Run>,
+    mut objectives: ResMut<Objectives>,
+) {
+    if objectives.seed != run.seed {
+        objectives.seed = run.seed;
+        objectives.active = None;
+        objectives.draw(&mut run);
+    }
+    if *state != GameState::Playing {
+        return;
+    }
+    let expired = match &mut objectives.active {
+        Some(active) => {
+            active.remaining -= time_scale.delta(&time);
+            active.remaining <= 0.0
+        }
+        None => false,
+    };
+    if expired {
+        objectives.draw(&mut run);
+    }
+}
+
+/// Counts score events towards the current objective and pays out the reward once it's met.
+fn track_objectives(
+    mut reader: Local<EventReader<ScoreEvent>>,
+    locale: Res<Locale>,
+    mut run: ResMut<Run>,
+    mut objectives: ResMut<Objectives>,
+    mut score_events: ResMut<Events<ScoreEvent>>,
+    mut announcements: ResMut<Events<Announcement>>,
+    mut toasts: ResMut<Events<Toast>>,
+) {
+    let mut completed = None;
+    for event in reader.iter(&score_events) {
+        let Objectives { pool, active, .. } = &mut *objectives;
+        let active = match active {
+            Some(active) => active,
+            None => break,
+        };
+        let objective = &pool[active.index];
+        match objective.progress_from(event) {
+            Some(progress) => active.progress += progress,
+            None => active.progress = 0,
+        }
+        if active.progress >= objective.target {
+            completed = Some(objective.reward);
+            break;
+        }
+    }
+
+    if let Some(reward) = completed {
+        score_events.send(ScoreEvent::new(reward, ScoreReason::Objective));
+        let text = locale.format("objective-complete", &[("points", reward.to_string())]);
+        announcements.send(Announcement::essential(text.clone()));
+        toasts.send(Toast::new(text));
+        objectives.draw(&mut run);
+    }
+}
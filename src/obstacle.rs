@@ -0,0 +1,383 @@
+use std::collections::HashSet;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    level::{ActiveLevel, Level},
+    primary_window, sized_sprite, Dead, GameOverCause, GameState, Position, Run, Size, SnakeHead,
+    SnakeMoveTimer, SnakeSegment, ARENA_HEIGHT, ARENA_WIDTH,
+};
+
+/// In front of the level's walls, behind the snake.
+const OBSTACLE_DEPTH: f32 = -0.01;
+
+/// Something in a level that crashes the snake like a wall, but doesn't stay put. Where it is
+/// only depends on the run's move count, so it follows along when a run is rewound to a
+/// checkpoint or a replay frame.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Obstacle {
+    /// Walks its path and back again, one cell every `every` snake moves. The path lists
+    /// neighbouring cells, since it slides straight from each one to the next.
+    Patrol { path: Vec<Position>, every: u32 },
+    /// A cell that's closed for `closed` moves, then open for `open`, starting `offset` moves
+    /// into that cycle.
+    Gate {
+        position: Position,
+        closed: u32,
+        open: u32,
+        #[serde(default)]
+        offset: u32,
+    },
+}
+
+impl Obstacle {
+    /// Where the obstacle blocks after `tick` snake moves, or `None` while it's an open gate.
+    pub fn position_at(&self, tick: u64) -> Option<Position> {
+        match self {
+            Self::Patrol { path, every } => {
+                if path.len() < 2 {
+                    return path.first().copied();
+                }
+                // Back and forth without lingering at the ends.
+                let period = 2 * (path.len() as u64 - 1);
+                let step = (tick / u64::from((*every).max(1))) % period;
+                let index = if step < path.len() as u64 {
+                    step
+                } else {
+                    period - step
+                };
+                Some(path[index as usize])
+            }
+            Self::Gate {
+                position,
+                closed,
+                open,
+                offset,
+            } => {
+                let cycle = u64::from(closed + open).max(1);
+                let step = (tick + u64::from(*offset)) % cycle;
+                if step < u64::from(*closed) {
+                    Some(*position)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// Every cell the obstacle can ever block.
+    pub fn cells(&self) -> Vec<Position> {
+        match self {
+            Self::Patrol { path, .. } => path.clone(),
+            Self::Gate { position, .. } => vec![*position],
+        }
+    }
+}
+
+/// A pressure plate and the doors it holds open. The doors are closed, and block like walls,
+/// except while some part of a snake rests on the plate. Doors shared by several plates open
+/// for any of them.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Switch {
+    pub plate: Position,
+    pub doors: Vec<Position>,
+}
+
+/// Plates pressed and doors open as of the snakes' last move.
+#[derive(Default)]
+pub struct SwitchState {
+    pressed: HashSet<Position>,
+    open_doors: HashSet<Position>,
+}
+
+impl SwitchState {
+    /// Whether an obstacle or a closed door in `level` crashes a head in `position` after
+    /// `tick` snake moves. Doors are as of the last move, since plates only change as the
+    /// snakes move.
+    pub fn is_blocked(&self, level: &Level, position: Position, tick: u64) -> bool {
+        level
+            .obstacles
+            .iter()
+            .any(|obstacle| obstacle.position_at(tick) == Some(position))
+            || level.switches.iter().any(|switch| {
+                switch.doors.contains(&position) && !self.open_doors.contains(&position)
+            })
+    }
+}
+
+/// The sprite for the level's obstacle at `index`.
+struct ObstacleSprite {
+    index: usize,
+}
+
+/// The sprite for a plate, or a door if `door` is set.
+struct SwitchSprite {
+    position: Position,
+    door: bool,
+}
+
+struct ObstacleMaterials {
+    patrol: Handle<ColorMaterial>,
+    gate: Handle<ColorMaterial>,
+    open_gate: Handle<ColorMaterial>,
+    plate: Handle<ColorMaterial>,
+    pressed_plate: Handle<ColorMaterial>,
+    door: Handle<ColorMaterial>,
+    open_door: Handle<ColorMaterial>,
+}
+
+pub struct ObstaclePlugin;
+
+impl Plugin for ObstaclePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<SwitchState>()
+            .add_startup_system(setup_obstacle_materials.system())
+            .add_system(spawn_obstacles.system())
+            .add_system(press_switches.system())
+            .add_system(obstacle_collisions.system())
+            .add_system(move_obstacles.system())
+            .add_system(draw_switches.system());
+    }
+}
+
+fn setup_obstacle_materials(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+    commands.insert_resource(ObstacleMaterials {
+        patrol: materials.add(Color::rgb(0.9, 0.35, 0.2).into()),
+        gate: materials.add(Color::rgb(0.7, 0.55, 0.3).into()),
+        open_gate: materials.add(Color::rgba(0.7, 0.55, 0.3, 0.3).into()),
+        plate: materials.add(Color::rgb(0.35, 0.5, 0.7).into()),
+        pressed_plate: materials.add(Color::rgb(0.55, 0.8, 1.0).into()),
+        door: materials.add(Color::rgb(0.3, 0.45, 0.65).into()),
+        open_door: materials.add(Color::rgba(0.3, 0.45, 0.65, 0.3).into()),
+    });
+}
+
+/// Gives every obstacle, plate and door of the level a sprite whenever the level changes.
+fn spawn_obstacles(
+    mut spawned: Local<(Vec<Obstacle>, Vec<Switch>)>,
+    mut commands: Commands,
+    level: Res<ActiveLevel>,
+    materials: Res<ObstacleMaterials>,
+    mut sprites: Query<(Entity, &ObstacleSprite)>,
+    mut switch_sprites: Query<(Entity, &SwitchSprite)>,
+) {
+    let (obstacles, switches) = match &level.0 {
+        Some(level) => (&level.obstacles[..], &level.switches[..]),
+        None => (&[][..], &[][..]),
+    };
+    if spawned.0 == obstacles && spawned.1 == switches {
+        return;
+    }
+    *spawned = (obstacles.to_vec(), switches.to_vec());
+
+    for (ent, _sprite) in &mut sprites.iter() {
+        commands.despawn(ent);
+    }
+    for (ent, _sprite) in &mut switch_sprites.iter() {
+        commands.despawn(ent);
+    }
+    // Plates are drawn smaller than doors, so the two don't rely on color alone.
+    let parts = switches.iter().flat_map(|switch| {
+        let doors = switch.doors.iter().map(|&door| (door, true));
+        Some((switch.plate, false)).into_iter().chain(doors)
+    });
+    for (position, door) in parts {
+        let (material, size) = if door {
+            (materials.door, 1.0)
+        } else {
+            (materials.plate, 0.6)
+        };
+        let mut transform = Transform::default();
+        transform.set_translation(Vec3::new(0.0, 0.0, OBSTACLE_DEPTH));
+        commands
+            .spawn(SpriteComponents {
+                transform,
+                draw: Draw {
+                    is_transparent: true,
+                    ..Default::default()
+                },
+                ..sized_sprite(material)
+            })
+            .with(SwitchSprite { position, door })
+            .with(position)
+            .with(Size::square(size));
+    }
+    for (index, obstacle) in obstacles.iter().enumerate() {
+        let material = match obstacle {
+            Obstacle::Patrol { .. } => materials.patrol,
+            Obstacle::Gate { .. } => materials.gate,
+        };
+        let mut transform = Transform::default();
+        transform.set_translation(Vec3::new(0.0, 0.0, OBSTACLE_DEPTH));
+        commands
+            .spawn(SpriteComponents {
+                transform,
+                draw: Draw {
+                    is_transparent: true,
+                    ..Default::default()
+                },
+                ..sized_sprite(material)
+            })
+            .with(ObstacleSprite { index });
+    }
+}
+
+/// Works out which plates have a snake on them, heads or segments, and so which doors are
+/// open.
+fn press_switches(
+    level: Res<ActiveLevel>,
+    mut switch_state: ResMut<SwitchState>,
+    mut heads: Query<(&SnakeHead, &Position)>,
+    mut segments: Query<(&SnakeSegment, &Position)>,
+) {
+    let switches = match &level.0 {
+        Some(level) if !level.switches.is_empty() => &level.switches,
+        _ => {
+            if !switch_state.pressed.is_empty() || !switch_state.open_doors.is_empty() {
+                *switch_state = SwitchState::default();
+            }
+            return;
+        }
+    };
+    let mut occupied = HashSet::new();
+    for (_head, &position) in &mut heads.iter() {
+        occupied.insert(position);
+    }
+    for (_segment, &position) in &mut segments.iter() {
+        occupied.insert(position);
+    }
+    let pressed: HashSet<Position> = switches
+        .iter()
+        .map(|switch| switch.plate)
+        .filter(|plate| occupied.contains(plate))
+        .collect();
+    if pressed == switch_state.pressed {
+        return;
+    }
+    switch_state.open_doors = switches
+        .iter()
+        .filter(|switch| pressed.contains(&switch.plate))
+        .flat_map(|switch| switch.doors.iter().copied())
+        .collect();
+    switch_state.pressed = pressed;
+}
+
+/// Crashes any head that ends a move where an obstacle or a closed door is. Only heads
+/// collide, so a patrol can pass over the body, and a door can close on it.
+fn obstacle_collisions(
+    mut checked_tick: Local<u64>,
+    mut commands: Commands,
+    state: Res<GameState>,
+    run: Res<Run>,
+    level: Res<ActiveLevel>,
+    switch_state: Res<SwitchState>,
+    mut heads: Query<Without<Dead, (Entity, &SnakeHead, &Position)>>,
+) {
+    let level = match &level.0 {
+        Some(level) if !level.obstacles.is_empty() || !level.switches.is_empty() => level,
+        _ => return,
+    };
+    if *state != GameState::Playing || run.tick == *checked_tick {
+        return;
+    }
+    *checked_tick = run.tick;
+
+    for (ent, _head, position) in &mut heads.iter() {
+        if switch_state.is_blocked(level, *position, run.tick) {
+            commands.insert_one(
+                ent,
+                Dead {
+                    cause: GameOverCause::Wall,
+                },
+            );
+        }
+    }
+}
+
+/// Slides patrols from the cell they're on towards the next one as the move timer runs, so
+/// they glide rather than jump, and fades open gates.
+fn move_obstacles(
+    windows: Res<Windows>,
+    run: Res<Run>,
+    level: Res<ActiveLevel>,
+    snake_timer: Res<SnakeMoveTimer>,
+    materials: Res<ObstacleMaterials>,
+    mut sprites: Query<(
+        &ObstacleSprite,
+        &mut Transform,
+        &mut Sprite,
+        &mut Handle<ColorMaterial>,
+    )>,
+) {
+    let level = match &level.0 {
+        Some(level) => level,
+        None => return,
+    };
+    let window = match primary_window(&windows) {
+        Some(window) => window,
+        None => return,
+    };
+    let cell_width = window.width as f32 / ARENA_WIDTH as f32;
+    let cell_height = window.height as f32 / ARENA_HEIGHT as f32;
+    let progress = (snake_timer.0.elapsed / snake_timer.0.duration).min(1.0);
+
+    for (sprite, mut transform, mut size, mut material) in &mut sprites.iter() {
+        let obstacle = match level.obstacles.get(sprite.index) {
+            Some(obstacle) => obstacle,
+            None => continue,
+        };
+        let (from, to) = match obstacle {
+            Obstacle::Patrol { .. } => (
+                obstacle.position_at(run.tick),
+                obstacle.position_at(run.tick + 1),
+            ),
+            Obstacle::Gate { position, .. } => {
+                let shown = if obstacle.position_at(run.tick).is_some() {
+                    materials.gate
+                } else {
+                    materials.open_gate
+                };
+                if *material != shown {
+                    *material = shown;
+                }
+                (Some(*position), Some(*position))
+            }
+        };
+        let (from, to) = match (from, to) {
+            (Some(from), Some(to)) => (from, to),
+            _ => continue,
+        };
+        let x = from.x as f32 + (to.x - from.x) as f32 * progress;
+        let y = from.y as f32 + (to.y - from.y) as f32 * progress;
+        transform.set_translation(Vec3::new(
+            x * cell_width - window.width as f32 / 2.0,
+            y * cell_height - window.height as f32 / 2.0,
+            OBSTACLE_DEPTH,
+        ));
+        size.size = Vec2::new(cell_width, cell_height);
+    }
+}
+
+/// Lights up pressed plates and fades open doors.
+fn draw_switches(
+    switch_state: Res<SwitchState>,
+    materials: Res<ObstacleMaterials>,
+    mut sprites: Query<(&SwitchSprite, &mut Handle<ColorMaterial>)>,
+) {
+    for (sprite, mut material) in &mut sprites.iter() {
+        let shown = match (
+            sprite.door,
+            switch_state.pressed.contains(&sprite.position),
+            switch_state.open_doors.contains(&sprite.position),
+        ) {
+            (true, _, true) => materials.open_door,
+            (true, _, false) => materials.door,
+            (false, true, _) => materials.pressed_plate,
+            (false, false, _) => materials.plate,
+        };
+        if *material != shown {
+            *material = shown;
+        }
+    }
+}
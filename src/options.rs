@@ -0,0 +1,223 @@
+use bevy::prelude::*;
+
+use crate::{
+    accessibility::{AccessibilitySettings, Announcement},
+    event_log::EventLog,
+    input::InputSettings,
+    locale::Locale,
+    telemetry::TelemetrySettings,
+    ui::UiFonts,
+    GameState,
+};
+
+const OPTIONS_FONT_SIZE: f32 = 26.0;
+
+/// The settings listed in the options menu, in order.
+#[derive(Copy, Clone)]
+enum Setting {
+    Palette,
+    ReducedMotion,
+    Verbosity,
+    Language,
+    Controls,
+    Dpad,
+    MouseSteering,
+    EventLog,
+    DangerPreview,
+    Telemetry,
+}
+
+const SETTINGS: [Setting; 10] = [
+    Setting::Palette,
+    Setting::ReducedMotion,
+    Setting::Verbosity,
+    Setting::Language,
+    Setting::Controls,
+    Setting::Dpad,
+    Setting::MouseSteering,
+    Setting::EventLog,
+    Setting::DangerPreview,
+    Setting::Telemetry,
+];
+
+/// The options menu opened from the pause screen. Settings change the same resources as their
+/// function keys, so they apply right away, and last until the config file next changes.
+#[derive(Default)]
+struct OptionsMenu {
+    open: bool,
+    selected: usize,
+}
+
+struct OptionsLine;
+
+pub struct OptionsPlugin;
+
+impl Plugin for OptionsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<OptionsMenu>()
+            .add_system(options_input.system())
+            .add_system(draw_options.system());
+    }
+}
+
+/// Describes a setting's current value. Doubles as the announcement when it changes.
+fn setting_text(
+    setting: Setting,
+    locale: &Locale,
+    accessibility: &AccessibilitySettings,
+    input: &InputSettings,
+    log: &EventLog,
+    telemetry: &TelemetrySettings,
+) -> String {
+    let on_off =
+        |on: bool, on_key: &str, off_key: &str| locale.text(if on { on_key } else { off_key });
+    match setting {
+        Setting::Palette => locale.format(
+            "palette-changed",
+            &[("palette", locale.text(accessibility.palette.name_key()))],
+        ),
+        Setting::ReducedMotion => on_off(
+            accessibility.reduced_motion,
+            "reduced-motion-on",
+            "reduced-motion-off",
+        ),
+        Setting::Verbosity => locale.format(
+            "verbosity-changed",
+            &[("verbosity", locale.text(accessibility.verbosity.name_key()))],
+        ),
+        Setting::Language => locale.format(
+            "language-changed",
+            &[("language", locale.text("language-name"))],
+        ),
+        Setting::Controls => locale.format(
+            "controls-changed",
+            &[("controls", locale.text(input.preset.name_key()))],
+        ),
+        Setting::Dpad => on_off(input.show_dpad, "dpad-on", "dpad-off"),
+        Setting::MouseSteering => on_off(
+            input.mouse_steering,
+            "mouse-steering-on",
+            "mouse-steering-off",
+        ),
+        Setting::EventLog => on_off(log.open, "event-log-on", "event-log-off"),
+        Setting::DangerPreview => on_off(
+            accessibility.danger_preview,
+            "danger-preview-on",
+            "danger-preview-off",
+        ),
+        Setting::Telemetry => on_off(telemetry.enabled, "telemetry-on", "telemetry-off"),
+    }
+}
+
+/// O while paused opens the menu. Up and Down pick a setting, Enter or Right changes it, and O
+/// closes the menu again. Resuming the game closes it too.
+fn options_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    state: Res<GameState>,
+    mut menu: ResMut<OptionsMenu>,
+    mut locale: ResMut<Locale>,
+    mut accessibility: ResMut<AccessibilitySettings>,
+    mut input: ResMut<InputSettings>,
+    mut log: ResMut<EventLog>,
+    mut telemetry: ResMut<TelemetrySettings>,
+    mut announcements: ResMut<Events<Announcement>>,
+) {
+    if *state != GameState::Paused {
+        menu.open = false;
+        return;
+    }
+    if keyboard_input.just_pressed(KeyCode::O) {
+        menu.open = !menu.open;
+        menu.selected = 0;
+        return;
+    }
+    if !menu.open {
+        return;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Up) {
+        menu.selected = (menu.selected + SETTINGS.len() - 1) % SETTINGS.len();
+    }
+    if keyboard_input.just_pressed(KeyCode::Down) {
+        menu.selected = (menu.selected + 1) % SETTINGS.len();
+    }
+    if !keyboard_input.just_pressed(KeyCode::Return) && !keyboard_input.just_pressed(KeyCode::Right)
+    {
+        return;
+    }
+    let setting = SETTINGS[menu.selected];
+    match setting {
+        Setting::Palette => accessibility.palette = accessibility.palette.next(),
+        Setting::ReducedMotion => accessibility.reduced_motion = !accessibility.reduced_motion,
+        Setting::Verbosity => accessibility.verbosity = accessibility.verbosity.next(),
+        Setting::Language => locale.cycle_language(),
+        Setting::Controls => input.preset = input.preset.next(),
+        Setting::Dpad => input.show_dpad = !input.show_dpad,
+        Setting::MouseSteering => input.mouse_steering = !input.mouse_steering,
+        Setting::EventLog => log.open = !log.open,
+        Setting::DangerPreview => accessibility.danger_preview = !accessibility.danger_preview,
+        Setting::Telemetry => telemetry.enabled = !telemetry.enabled,
+    }
+    announcements.send(Announcement::essential(setting_text(
+        setting,
+        &locale,
+        &accessibility,
+        &input,
+        &log,
+        &telemetry,
+    )));
+}
+
+/// Redraws the menu whenever its text changes, so settings changed by function keys or the
+/// config file show up too.
+fn draw_options(
+    mut drawn: Local<Vec<String>>,
+    mut commands: Commands,
+    fonts: Res<UiFonts>,
+    locale: Res<Locale>,
+    accessibility: Res<AccessibilitySettings>,
+    input: Res<InputSettings>,
+    log: Res<EventLog>,
+    telemetry: Res<TelemetrySettings>,
+    menu: Res<OptionsMenu>,
+    mut lines: Query<(Entity, &OptionsLine)>,
+) {
+    let mut text = Vec::new();
+    if menu.open {
+        text.push(locale.text("options-title"));
+        for (i, &setting) in SETTINGS.iter().enumerate() {
+            let marker = if i == menu.selected { ">" } else { " " };
+            text.push(format!(
+                "{} {}",
+                marker,
+                setting_text(setting, &locale, &accessibility, &input, &log, &telemetry)
+            ));
+        }
+        text.push(locale.text("options-hint"));
+    }
+    if *drawn == text {
+        return;
+    }
+    *drawn = text.clone();
+
+    for (ent, _line) in &mut lines.iter() {
+        commands.despawn(ent);
+    }
+    for (row, line) in text.into_iter().enumerate() {
+        commands
+            .spawn(TextComponents {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        left: Val::Px(40.0),
+                        top: Val::Px(560.0 + row as f32 * (OPTIONS_FONT_SIZE + 8.0)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                text: fonts.text(line, OPTIONS_FONT_SIZE),
+                ..Default::default()
+            })
+            .with(OptionsLine);
+    }
+}
@@ -0,0 +1,302 @@
+use std::path::{Path, PathBuf};
+
+use bevy::{asset::AssetLoader, prelude::*, render::pass::ClearColor};
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::{
+    accessibility::AccessibilitySettings,
+    game_event::GameEvent,
+    level::{ActiveLevel, Level, LevelError, LevelMaterials, WALL_COLOR},
+    locale::Locale,
+    mods::Mods,
+    rules::GameRules,
+    toast::Toast,
+    GameOverCause, GameState,
+};
+
+/// Dropping a `.snakepack` in here adds it to the level browser.
+const PACKS_DIR: &str = "packs";
+
+/// Colors a pack paints its levels in.
+#[derive(Clone, Deserialize)]
+pub struct Theme {
+    pub background: (f32, f32, f32),
+    pub walls: (f32, f32, f32),
+}
+
+/// Several levels bundled into one `.snakepack` file, played in order as a campaign, with the
+/// rules and colors to play them with. A pack is plain RON, so it can be written by hand from
+/// levels saved by the editor.
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct Pack {
+    pub name: String,
+    pub author: String,
+    /// Keeps the player's colors when missing.
+    pub theme: Option<Theme>,
+    /// Replaces the player's rules for the whole campaign when present.
+    pub rules: Option<GameRules>,
+    pub levels: Vec<Level>,
+    /// Found once by the loader, off the main thread, since checking a puzzle means solving it.
+    #[serde(skip)]
+    problems: Vec<PackError>,
+}
+
+impl Default for Pack {
+    fn default() -> Self {
+        Self {
+            name: "pack".to_string(),
+            author: String::new(),
+            theme: None,
+            rules: None,
+            levels: Vec::new(),
+            problems: Vec::new(),
+        }
+    }
+}
+
+/// Why a pack can't be played.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PackError {
+    Empty,
+    /// A problem with one of its levels, counting from 0.
+    Level {
+        index: usize,
+        error: LevelError,
+    },
+}
+
+impl PackError {
+    pub fn describe(&self, pack: &Pack, locale: &Locale) -> String {
+        match self {
+            Self::Empty => locale.text("pack-error-empty"),
+            Self::Level { index, error } => locale.format(
+                "pack-error-level",
+                &[
+                    ("number", (index + 1).to_string()),
+                    ("name", pack.levels[*index].name.clone()),
+                    ("error", error.describe(locale)),
+                ],
+            ),
+        }
+    }
+}
+
+impl Pack {
+    /// Every reason the pack can't be played, or none, as found when it was loaded.
+    pub fn validate(&self) -> Result<(), &[PackError]> {
+        if self.problems.is_empty() {
+            Ok(())
+        } else {
+            Err(&self.problems)
+        }
+    }
+
+    fn check(&self) -> Vec<PackError> {
+        if self.levels.is_empty() {
+            return vec![PackError::Empty];
+        }
+        self.levels
+            .iter()
+            .enumerate()
+            .filter_map(|(index, level)| level.validate().err().map(|errors| (index, errors)))
+            .flat_map(|(index, errors)| {
+                errors
+                    .into_iter()
+                    .map(move |error| PackError::Level { index, error })
+            })
+            .collect()
+    }
+}
+
+#[derive(Default)]
+struct PackLoader;
+
+impl AssetLoader<Pack> for PackLoader {
+    fn from_bytes(&self, _asset_path: &Path, bytes: Vec<u8>) -> Result<Pack, anyhow::Error> {
+        let mut pack: Pack = ron::de::from_bytes(&bytes)?;
+        pack.problems = pack.check();
+        Ok(pack)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["snakepack"]
+    }
+}
+
+/// Starts loading every pack in the packs folder and in each mod's, or hands back the ones
+/// already loading. A pack that can't be parsed shows up as failed in the asset server rather
+/// than stopping the game.
+pub fn load_packs(asset_server: &AssetServer, mods: &Mods) -> Vec<Handle<Pack>> {
+    let mut packs = Vec::new();
+    let mod_packs = mods.dirs(PACKS_DIR);
+    for dir in Some(PathBuf::from(PACKS_DIR)).iter().chain(&mod_packs) {
+        if !dir.is_dir() {
+            continue;
+        }
+        match asset_server.load_asset_folder(dir) {
+            Ok(ids) => packs.extend(ids.into_iter().map(Handle::from)),
+            Err(err) => warn!("could not load packs from {}: {:?}", dir.display(), err),
+        }
+    }
+    packs
+}
+
+/// A pack being played through.
+pub struct Campaign {
+    pub pack: Handle<Pack>,
+    /// Index of the level being played.
+    pub level: usize,
+    /// Started from the campaign map, which it goes back to once the pack is done.
+    pub from_map: bool,
+}
+
+#[derive(Default)]
+pub struct ActiveCampaign(pub Option<Campaign>);
+
+pub struct PackPlugin;
+
+impl Plugin for PackPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_asset::<Pack>()
+            .add_asset_loader::<Pack, PackLoader>()
+            .init_resource::<ActiveCampaign>()
+            .add_system(advance_campaign.system())
+            .add_system(apply_pack_rules.system())
+            .add_system(apply_pack_theme.system());
+    }
+}
+
+/// Moves on to the pack's next level once one is completed, and ends the campaign after the
+/// last one, going back to the map if it came from there, or when another level, or none, is
+/// picked.
+fn advance_campaign(
+    mut advancing: Local<bool>,
+    mut reader: Local<EventReader<GameEvent>>,
+    game_events: Res<Events<GameEvent>>,
+    locale: Res<Locale>,
+    packs: Res<Assets<Pack>>,
+    mut state: ResMut<GameState>,
+    mut campaign: ResMut<ActiveCampaign>,
+    mut level: ResMut<ActiveLevel>,
+    mut toasts: ResMut<Events<Toast>>,
+) {
+    let completed = reader
+        .iter(&game_events)
+        .filter_map(GameEvent::game_over)
+        .any(|cause| matches!(cause, GameOverCause::LevelComplete));
+    let current = match &mut campaign.0 {
+        Some(current) => current,
+        None => return,
+    };
+    let pack = match packs.get(&current.pack) {
+        Some(pack) if level.0.is_some() && *state != GameState::Editor => pack,
+        _ => {
+            campaign.0 = None;
+            return;
+        }
+    };
+
+    // The level only changes the frame after it's completed, so the finished run is still
+    // recorded under the level it was played on.
+    if *advancing {
+        *advancing = false;
+        current.level += 1;
+        match pack.levels.get(current.level) {
+            Some(next) => {
+                info!(pack = %pack.name, level = current.level, "campaign level started");
+                level.0 = Some(next.clone());
+                toasts.send(Toast::new(locale.format(
+                    "campaign-level",
+                    &[
+                        ("number", (current.level + 1).to_string()),
+                        ("count", pack.levels.len().to_string()),
+                        ("name", next.name.clone()),
+                    ],
+                )));
+            }
+            None => {
+                info!(pack = %pack.name, "campaign complete");
+                toasts.send(Toast::new(
+                    locale.format("campaign-complete", &[("name", pack.name.clone())]),
+                ));
+                if current.from_map {
+                    *state = GameState::Campaign;
+                }
+                campaign.0 = None;
+                level.0 = None;
+            }
+        }
+    }
+    *advancing = completed;
+}
+
+/// Plays a campaign under its pack's rules, if it has any, and puts the player's rules back
+/// afterwards. Rules reloaded from the config mid-campaign are kept for afterwards too.
+fn apply_pack_rules(
+    mut players_rules: Local<Option<GameRules>>,
+    campaign: Res<ActiveCampaign>,
+    packs: Res<Assets<Pack>>,
+    mut rules: ResMut<GameRules>,
+) {
+    let pack_rules = campaign
+        .0
+        .as_ref()
+        .and_then(|campaign| packs.get(&campaign.pack))
+        .and_then(|pack| pack.rules.as_ref());
+    match pack_rules {
+        Some(pack_rules) if *rules != *pack_rules => {
+            *players_rules = Some(rules.clone());
+            *rules = pack_rules.clone();
+        }
+        Some(_) => {}
+        None => {
+            if let Some(players_rules) = players_rules.take() {
+                *rules = players_rules;
+            }
+        }
+    }
+}
+
+/// Paints the background and walls in the pack's theme during its campaign.
+fn apply_pack_theme(
+    mut themed: Local<bool>,
+    campaign: Res<ActiveCampaign>,
+    packs: Res<Assets<Pack>>,
+    settings: Res<AccessibilitySettings>,
+    level_materials: Res<LevelMaterials>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut clear_color: ResMut<ClearColor>,
+) {
+    let theme = campaign
+        .0
+        .as_ref()
+        .and_then(|campaign| packs.get(&campaign.pack))
+        .and_then(|pack| pack.theme.as_ref());
+    let (background, walls) = match theme {
+        Some(theme) => {
+            *themed = true;
+            let (r, g, b) = theme.background;
+            let background = Color::rgb(r, g, b);
+            let (r, g, b) = theme.walls;
+            (background, Color::rgb(r, g, b))
+        }
+        None if *themed => {
+            *themed = false;
+            (settings.palette.colors().background, WALL_COLOR)
+        }
+        None => return,
+    };
+    if clear_color.0 != background {
+        clear_color.0 = background;
+    }
+    let unchanged = materials
+        .get(&level_materials.wall)
+        .map_or(true, |material| material.color == walls);
+    if !unchanged {
+        if let Some(material) = materials.get_mut(&level_materials.wall) {
+            material.color = walls;
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use bevy::prelude::*;
+
+use crate::{
+    accessibility::AccessibilitySettings, locale::Locale, primary_window, scoring::ScoreReason,
+    time_scale::TimeScale, ui::UiFonts, Position, ARENA_HEIGHT, ARENA_WIDTH,
+};
+
+/// How long a popup stays on screen, in seconds.
+const POPUP_DURATION: f32 = 1.0;
+
+/// How far a popup floats up over its lifetime, in pixels.
+const POPUP_RISE: f32 = 40.0;
+
+const POPUP_FONT_SIZE: f32 = 22.0;
+
+/// How long points are held back, in seconds, in case more are scored right after.
+const SCORE_WINDOW: f32 = 0.25;
+
+/// Short text shown over a cell of the arena, like points scored or "Close call!". Any system
+/// can send one.
+pub struct Popup {
+    pub text: String,
+    pub position: Position,
+    /// Points the popup is for, if it's for points scored.
+    pub points: Option<u32>,
+    /// The combo multiplier the points were scored with.
+    pub multiplier: u32,
+    /// What the points were for. Only points for the same thing are added up together.
+    pub reason: Option<ScoreReason>,
+}
+
+impl Popup {
+    pub fn new(text: impl Into<String>, position: Position) -> Self {
+        Self {
+            text: text.into(),
+            position,
+            points: None,
+            multiplier: 1,
+            reason: None,
+        }
+    }
+
+    /// Points scored for `reason` at `position` with `multiplier`, shown as `text`. Points
+    /// scored together for the same reason, like several food pulled in at once, are added up
+    /// into one popup instead.
+    pub fn score(
+        text: impl Into<String>,
+        points: u32,
+        multiplier: u32,
+        reason: ScoreReason,
+        position: Position,
+    ) -> Self {
+        Self {
+            points: Some(points),
+            multiplier,
+            reason: Some(reason),
+            ..Self::new(text, position)
+        }
+    }
+}
+
+/// Score popups still in their window, and how long it's been open.
+#[derive(Default)]
+struct ScoreWindow {
+    popups: Vec<Popup>,
+    elapsed: f32,
+}
+
+struct PopupTimer {
+    timer: Timer,
+    position: Position,
+}
+
+pub struct PopupPlugin;
+
+impl Plugin for PopupPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<Popup>()
+            .add_system(spawn_popups.system())
+            .add_system(animate_popups.system());
+    }
+}
+
+/// Shows popups as they come, except score popups, which wait out a short window. One on its
+/// own is shown as it was sent, and several for the same reason become one "+120 (x4)" popup
+/// where the last was scored, rather than a pile of them on top of each other. A combo shows
+/// as well, with the highest multiplier among them.
+fn spawn_popups(
+    mut score_window: Local<ScoreWindow>,
+    mut commands: Commands,
+    mut reader: Local<EventReader<Popup>>,
+    popups: Res<Events<Popup>>,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    fonts: Res<UiFonts>,
+    locale: Res<Locale>,
+) {
+    for popup in reader.iter(&popups) {
+        match popup.points {
+            Some(_points) => score_window.popups.push(Popup {
+                text: popup.text.clone(),
+                ..*popup
+            }),
+            None => spawn_popup(&mut commands, &fonts, popup.text.clone(), popup.position),
+        }
+    }
+    if score_window.popups.is_empty() {
+        return;
+    }
+    score_window.elapsed += time_scale.delta(&time);
+    if score_window.elapsed < SCORE_WINDOW {
+        return;
+    }
+    score_window.elapsed = 0.0;
+    let mut groups: Vec<Vec<Popup>> = Vec::new();
+    for popup in std::mem::take(&mut score_window.popups) {
+        match groups
+            .iter_mut()
+            .find(|group| group[0].reason == popup.reason)
+        {
+            Some(group) => group.push(popup),
+            None => groups.push(vec![popup]),
+        }
+    }
+    for group in groups {
+        let (text, position) = merged_popup(&group, &locale);
+        spawn_popup(&mut commands, &fonts, text, position);
+    }
+}
+
+/// The text for score popups added up into one, and where it goes. `scored` is never empty.
+fn merged_popup(scored: &[Popup], locale: &Locale) -> (String, Position) {
+    let last = &scored[scored.len() - 1];
+    if scored.len() == 1 {
+        return (last.text.clone(), last.position);
+    }
+    let total: u32 = scored.iter().filter_map(|popup| popup.points).sum();
+    let multiplier = scored
+        .iter()
+        .map(|popup| popup.multiplier)
+        .max()
+        .unwrap_or(1);
+    let mut args = vec![
+        ("points", total.to_string()),
+        ("count", scored.len().to_string()),
+    ];
+    let key = if multiplier > 1 {
+        args.push(("multiplier", multiplier.to_string()));
+        "popup-merged-combo"
+    } else {
+        "popup-merged"
+    };
+    (locale.format(key, &args), last.position)
+}
+
+fn spawn_popup(commands: &mut Commands, fonts: &UiFonts, text: String, position: Position) {
+    commands
+        .spawn(TextComponents {
+            style: Style {
+                position_type: PositionType::Absolute,
+                ..Default::default()
+            },
+            text: fonts.text(text, POPUP_FONT_SIZE),
+            ..Default::default()
+        })
+        .with(PopupTimer {
+            timer: Timer::from_seconds(POPUP_DURATION, false),
+            position,
+        });
+}
+
+/// Keeps popups over their cell, floating them upwards unless reduced motion is on, and
+/// removes them once they expire.
+fn animate_popups(
+    mut commands: Commands,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    windows: Res<Windows>,
+    settings: Res<AccessibilitySettings>,
+    mut popups: Query<(Entity, &mut PopupTimer, &mut Style)>,
+) {
+    let window = match primary_window(&windows) {
+        Some(window) => window,
+        None => return,
+    };
+    for (ent, mut popup, mut style) in &mut popups.iter() {
+        popup.timer.tick(time_scale.delta(&time));
+        if popup.timer.finished {
+            commands.despawn(ent);
+            continue;
+        }
+        let rise = if settings.reduced_motion {
+            0.0
+        } else {
+            popup.timer.elapsed / POPUP_DURATION * POPUP_RISE
+        };
+        style.position = Rect {
+            left: Val::Px(popup.position.x as f32 / ARENA_WIDTH as f32 * window.width as f32),
+            bottom: Val::Px(
+                popup.position.y as f32 / ARENA_HEIGHT as f32 * window.height as f32 + rise,
+            ),
+            ..Default::default()
+        };
+    }
+}
@@ -0,0 +1,402 @@
+use std::{f32::consts::FRAC_PI_4, fs};
+
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::{
+    accessibility::Announcement,
+    body::BodyChange,
+    director::{level_blocked_cells, random_free_cell},
+    effects::{ActiveEffect, EffectKind, Effects},
+    game_event::GameEvent,
+    level::ActiveLevel,
+    lighting::LANTERN_DURATION,
+    loading::LoadingAssets,
+    locale::Locale,
+    mods::Mods,
+    rules::GameRules,
+    sized_sprite, spawn_snake,
+    split::{split_snake, Ally},
+    time_scale::TimeScale,
+    toast::Toast,
+    Direction, Food, GameState, HeadMaterial, Position, PowerUpMaterial, Run, SegmentMaterial,
+    Size, SnakeHead, SnakeSegment, ARENA_WIDTH,
+};
+
+/// How often, in seconds, the game considers spawning a power-up.
+const SPAWN_INTERVAL: f32 = 10.0;
+
+/// Chance that a power-up actually appears when the spawn timer fires.
+const SPAWN_CHANCE: f32 = 0.25;
+
+/// Every power-up that can appear and its settings.
+const REGISTRY_PATH: &str = "assets/power_ups.ron";
+
+/// A mod's power-ups, added to the registry or replacing the ones with the same id.
+const MOD_REGISTRY: &str = "power_ups.ron";
+
+/// What a power-up does when it's picked up. Each one is handled in `power_up_pickup`, and the
+/// registry can list it any number of times with different settings.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub enum PowerUpKind {
+    /// Spawns a second snake mirrored across the arena. Both follow the same inputs, and the
+    /// effect lasts until one of them crashes.
+    Hydra,
+    /// Cuts the snake in half, the rear half becoming an ally. Only appears with the
+    /// `split_food` rule.
+    Split,
+    /// Lets the player see all food at night for a while. Only appears with the `day_night`
+    /// rule.
+    Lantern,
+}
+
+impl PowerUpKind {
+    pub fn name_key(self) -> &'static str {
+        match self {
+            Self::Hydra => "power-up-name-hydra",
+            Self::Split => "power-up-name-split",
+            Self::Lantern => "power-up-name-lantern",
+        }
+    }
+
+    /// Whether the rules let power-ups with this effect appear.
+    fn allowed(self, rules: &GameRules) -> bool {
+        match self {
+            Self::Hydra => true,
+            Self::Split => rules.split_food,
+            Self::Lantern => rules.day_night,
+        }
+    }
+}
+
+fn default_weight() -> f32 {
+    1.0
+}
+
+fn default_size() -> f32 {
+    0.7
+}
+
+/// A power-up from the registry: an effect and the settings it comes with. A level pack or a
+/// mod can add, say, a rarer lantern that lasts longer, without touching the code.
+#[derive(Clone, Debug, Deserialize)]
+struct PowerUpDef {
+    id: String,
+    kind: PowerUpKind,
+    /// How likely it is to be the one that appears, next to the others' weights.
+    #[serde(default = "default_weight")]
+    weight: f32,
+    /// Seconds the effect lasts, for effects that run out. Without it they last their usual
+    /// time.
+    #[serde(default)]
+    duration: Option<f32>,
+    /// Texture it's drawn with, tinted by `color`. With neither it's drawn in the palette's
+    /// power-up color.
+    #[serde(default)]
+    icon: Option<String>,
+    #[serde(default)]
+    color: Option<(f32, f32, f32)>,
+    /// Size in cells.
+    #[serde(default = "default_size")]
+    size: f32,
+    /// Turned on its corner, so it differs from others by shape as well as color.
+    #[serde(default)]
+    diamond: bool,
+}
+
+/// The power-ups listed in `assets/power_ups.ron`, each with its material, or `None` for the
+/// palette's power-up color.
+#[derive(Default)]
+pub struct PowerUpRegistry {
+    defs: Vec<PowerUpDef>,
+    materials: Vec<Option<Handle<ColorMaterial>>>,
+}
+
+/// A power-up in the arena, by its place in the registry.
+pub struct PowerUp(usize);
+
+struct PowerUpSpawnTimer(Timer);
+
+pub struct PowerUpPlugin;
+
+impl Plugin for PowerUpPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_resource(PowerUpSpawnTimer(Timer::from_seconds(SPAWN_INTERVAL, true)))
+            .init_resource::<PowerUpRegistry>()
+            .add_startup_system(load_registry.system())
+            .add_system(power_up_spawner.system())
+            .add_system(power_up_pickup.system())
+            .add_system(hydra_watch.system());
+    }
+}
+
+/// Reads the registry and makes a material for every power-up with its own look. Without a
+/// readable registry no power-ups appear.
+fn load_registry(
+    asset_server: Res<AssetServer>,
+    mut registry: ResMut<PowerUpRegistry>,
+    mut loading: ResMut<LoadingAssets>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut mods: ResMut<Mods>,
+) {
+    let mut defs: Vec<PowerUpDef> = match fs::read_to_string(REGISTRY_PATH) {
+        Ok(text) => ron::de::from_str(&text).unwrap_or_else(|err| {
+            warn!("ignoring {}: {}", REGISTRY_PATH, err);
+            Vec::new()
+        }),
+        Err(err) => {
+            warn!("could not read {}: {}", REGISTRY_PATH, err);
+            Vec::new()
+        }
+    };
+    mods.merge(MOD_REGISTRY, &mut defs, |def| def.id.as_str());
+    let mut def_materials = Vec::new();
+    for def in &defs {
+        let color = def.color.map(|(r, g, b)| Color::rgb(r, g, b));
+        let texture = def
+            .icon
+            .as_ref()
+            .and_then(|path| match asset_server.load(path.as_str()) {
+                Ok(texture) => {
+                    loading.track(path.as_str(), texture);
+                    Some(texture)
+                }
+                Err(err) => {
+                    warn!("could not load {}: {:?}", path, err);
+                    None
+                }
+            });
+        def_materials.push(match (texture, color) {
+            (Some(texture), color) => Some(materials.add(ColorMaterial::modulated_texture(
+                texture,
+                color.unwrap_or(Color::WHITE),
+            ))),
+            (None, Some(color)) => Some(materials.add(color.into())),
+            (None, None) => None,
+        });
+    }
+    info!(power_ups = defs.len(), "loaded power-ups");
+    registry.defs = defs;
+    registry.materials = def_materials;
+}
+
+fn power_up_spawner(
+    mut commands: Commands,
+    material: Res<PowerUpMaterial>,
+    registry: Res<PowerUpRegistry>,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    state: Res<GameState>,
+    rules: Res<GameRules>,
+    level: Res<ActiveLevel>,
+    mut run: ResMut<Run>,
+    mut timer: ResMut<PowerUpSpawnTimer>,
+    mut game_events: ResMut<Events<GameEvent>>,
+    mut power_ups: Query<&PowerUp>,
+    mut heads: Query<(&SnakeHead, &Position, Option<&Ally>)>,
+    mut segments: Query<(&SnakeSegment, &Position)>,
+    mut food: Query<(&Food, &Position)>,
+) {
+    if *state != GameState::Playing || level.is_puzzle() {
+        return;
+    }
+    timer.0.tick(time_scale.delta(&time));
+    if !timer.0.finished {
+        return;
+    }
+    // Power-ups don't stack: wait until the current one is used up.
+    let players = heads
+        .iter()
+        .iter()
+        .filter(|(_head, _pos, ally)| ally.is_none())
+        .count();
+    if power_ups.iter().iter().next().is_some() || players > 1 {
+        return;
+    }
+    if run.rng.gen::<f32>() >= SPAWN_CHANCE {
+        return;
+    }
+    // Never where it can't be picked up, or on top of the snake or food. Without a free cell
+    // this time, the next spawn can try again.
+    let mut blocked = level_blocked_cells(&level);
+    blocked.extend(heads.iter().iter().map(|(_head, pos, _ally)| *pos));
+    blocked.extend(segments.iter().iter().map(|(_seg, pos)| *pos));
+    blocked.extend(food.iter().iter().map(|(_food, pos)| *pos));
+    let position = match random_free_cell(&mut run, &blocked) {
+        Some(position) => position,
+        None => return,
+    };
+    let allowed: Vec<usize> = (0..registry.defs.len())
+        .filter(|&index| {
+            let def = &registry.defs[index];
+            def.weight > 0.0 && def.kind.allowed(&rules)
+        })
+        .collect();
+    let total: f32 = allowed
+        .iter()
+        .map(|&index| registry.defs[index].weight)
+        .sum();
+    let mut roll = run.rng.gen::<f32>() * total;
+    let index = match allowed.iter().copied().find(|&index| {
+        roll -= registry.defs[index].weight;
+        roll < 0.0
+    }) {
+        Some(index) => index,
+        // Rounding can leave a sliver past the last one.
+        None => match allowed.last() {
+            Some(&index) => index,
+            None => return,
+        },
+    };
+    let def = &registry.defs[index];
+    let kind = def.kind;
+    run.span().in_scope(|| {
+        info!(
+            id = def.id.as_str(),
+            x = position.x,
+            y = position.y,
+            "power-up spawned"
+        )
+    });
+    game_events.send(GameEvent::PowerUpSpawned { kind, position });
+    let rotation = if def.diamond { FRAC_PI_4 } else { 0.0 };
+    commands
+        .spawn(SpriteComponents {
+            transform: Transform::from_rotation(Quat::from_rotation_z(rotation)),
+            ..sized_sprite(registry.materials[index].unwrap_or(material.0))
+        })
+        .with(PowerUp(index))
+        .with(position)
+        .with(Size::square(def.size));
+}
+
+fn power_up_pickup(
+    mut commands: Commands,
+    locale: Res<Locale>,
+    run: Res<Run>,
+    head_material: Res<HeadMaterial>,
+    segment_material: Res<SegmentMaterial>,
+    mut effects: ResMut<Effects>,
+    mut announcements: ResMut<Events<Announcement>>,
+    mut toasts: ResMut<Events<Toast>>,
+    mut game_events: ResMut<Events<GameEvent>>,
+    rules: Res<GameRules>,
+    registry: Res<PowerUpRegistry>,
+    mut body_changes: ResMut<Events<BodyChange>>,
+    mut heads: Query<Without<Ally, (&SnakeHead, &Position)>>,
+    mut power_ups: Query<(Entity, &PowerUp, &Position)>,
+    segments: Query<(&SnakeSegment, &Position)>,
+) {
+    for (head, head_pos) in &mut heads.iter() {
+        for (ent, power_up, pos) in &mut power_ups.iter() {
+            if pos != head_pos {
+                continue;
+            }
+            commands.despawn(ent);
+            let def = match registry.defs.get(power_up.0) {
+                Some(def) => def,
+                None => continue,
+            };
+            run.span()
+                .in_scope(|| info!(id = def.id.as_str(), "power-up collected"));
+            game_events.send(GameEvent::PowerUpCollected { kind: def.kind });
+            match def.kind {
+                PowerUpKind::Hydra => {
+                    spawn_hydra(
+                        &mut commands,
+                        &head_material,
+                        &segment_material,
+                        *head_pos,
+                        head.direction,
+                    );
+                    let text = locale.text("power-up-hydra");
+                    announcements.send(Announcement::essential(text.clone()));
+                    toasts.send(Toast::new(text));
+                }
+                PowerUpKind::Split => {
+                    if split_snake(
+                        &mut commands,
+                        &mut body_changes,
+                        &segment_material,
+                        rules.ally_personality,
+                        head.next_segment,
+                        &segments,
+                    ) {
+                        let text = locale.text("power-up-split");
+                        announcements.send(Announcement::essential(text.clone()));
+                        toasts.send(Toast::new(text));
+                    }
+                }
+                PowerUpKind::Lantern => {
+                    effects.add(ActiveEffect::new(
+                        EffectKind::Lantern,
+                        def.duration.unwrap_or(LANTERN_DURATION),
+                        1.0,
+                    ));
+                    let text = locale.text("power-up-lantern");
+                    announcements.send(Announcement::essential(text.clone()));
+                    toasts.send(Toast::new(text));
+                }
+            }
+        }
+    }
+}
+
+/// Spawns the hydra's second snake on the far side of the arena, mirrored horizontally.
+fn spawn_hydra(
+    commands: &mut Commands,
+    head_material: &HeadMaterial,
+    segment_material: &SegmentMaterial,
+    head: Position,
+    direction: Direction,
+) {
+    let mirrored = Position {
+        x: ARENA_WIDTH as i32 - 1 - head.x,
+        y: head.y,
+    };
+    let tail = mirrored.moved(direction.opposite());
+    spawn_snake(
+        commands,
+        head_material.0,
+        segment_material.0,
+        mirrored,
+        direction,
+        &[tail],
+        0,
+    );
+}
+
+/// Lets the player know when the hydra effect ends because one of the snakes crashed.
+fn hydra_watch(
+    mut last_count: Local<Option<usize>>,
+    mut reader: Local<EventReader<GameEvent>>,
+    locale: Res<Locale>,
+    mut announcements: ResMut<Events<Announcement>>,
+    mut toasts: ResMut<Events<Toast>>,
+    mut game_events: ResMut<Events<GameEvent>>,
+    mut heads: Query<Without<Ally, &SnakeHead>>,
+) {
+    // A game over resets the arena to a single snake, which isn't the hydra ending.
+    if reader
+        .iter(&game_events)
+        .find_map(GameEvent::game_over)
+        .is_some()
+    {
+        *last_count = None;
+        return;
+    }
+    let count = heads.iter().iter().count();
+    if let Some(last) = *last_count {
+        if last > 1 && count == 1 {
+            let text = locale.text("hydra-ended");
+            announcements.send(Announcement::essential(text.clone()));
+            toasts.send(Toast::new(text));
+            game_events.send(GameEvent::PowerUpExpired {
+                kind: PowerUpKind::Hydra,
+            });
+        }
+    }
+    *last_count = Some(count);
+}
@@ -0,0 +1,359 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bevy::{prelude::*, window::ReceivedCharacter};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    dialog::OpenDialog,
+    locale::Locale,
+    replay::now,
+    save_format::{self, Versioned},
+    ui::UiFonts,
+    GameState,
+};
+
+const PROFILES_DIR: &str = "profiles";
+
+const MAX_NAME_LEN: usize = 16;
+
+const PICKER_FONT_SIZE: f32 = 32.0;
+
+/// How many of the best scores on each level are kept.
+const MAX_LEVEL_SCORES: usize = 5;
+
+/// How many of the latest runs' lengths are kept.
+const RECENT_RUNS: usize = 10;
+
+/// One run on a level's leaderboard.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct LevelScore {
+    pub score: u32,
+    /// Unix time the run ended.
+    pub recorded_at: u64,
+}
+
+/// Progress tracked separately for every profile.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfileStats {
+    pub high_score: u32,
+    pub runs: u32,
+    /// The best runs on each level, best first, keyed by level name.
+    pub level_scores: BTreeMap<String, Vec<LevelScore>>,
+    /// The most stars earned on each level completed, keyed by level name.
+    pub level_stars: BTreeMap<String, u8>,
+    /// Seconds each of the latest runs lasted, oldest first.
+    pub recent_runs: Vec<f32>,
+}
+
+impl ProfileStats {
+    pub fn level_best(&self, level: &str) -> Option<u32> {
+        self.level_scores
+            .get(level)
+            .and_then(|scores| scores.first())
+            .map(|best| best.score)
+    }
+
+    pub fn completed(&self, level: &str) -> bool {
+        self.level_stars.contains_key(level)
+    }
+}
+
+impl Versioned for ProfileStats {
+    const KIND: &'static str = "profile";
+    const VERSION: u32 = 1;
+
+    fn migrate(version: u32, body: &str) -> Result<Self, String> {
+        match version {
+            // Profiles from before the header have the same layout.
+            0 => ron::de::from_str(body).map_err(|err| err.to_string()),
+            _ => Err(format!("can't read profile version {}", version)),
+        }
+    }
+}
+
+/// A named local player. Each one has its own folder under `profiles/` holding its stats and,
+/// optionally, its own `config.ron`.
+pub struct Profile {
+    pub name: String,
+    pub stats: ProfileStats,
+}
+
+impl Profile {
+    fn dir(&self) -> PathBuf {
+        Path::new(PROFILES_DIR).join(&self.name)
+    }
+
+    fn stats_path(&self) -> PathBuf {
+        self.dir().join("profile.ron")
+    }
+
+    pub fn config_path(&self) -> PathBuf {
+        self.dir().join("config.ron")
+    }
+
+    pub fn hud_layout_path(&self) -> PathBuf {
+        self.dir().join("hud.ron")
+    }
+
+    fn load(name: String) -> Self {
+        let mut profile = Self {
+            name,
+            stats: ProfileStats::default(),
+        };
+        let path = profile.stats_path();
+        if let Ok(text) = fs::read_to_string(&path) {
+            match save_format::decode(&text) {
+                Ok(stats) => profile.stats = stats,
+                Err(err) => warn!("resetting unreadable profile {}: {}", path.display(), err),
+            }
+        }
+        profile
+    }
+
+    fn save(&self) {
+        let result = save_format::encode(&self.stats).and_then(|text| {
+            fs::create_dir_all(self.dir())
+                .and_then(|_| fs::write(self.stats_path(), text))
+                .map_err(|err| err.to_string())
+        });
+        if let Err(err) = result {
+            warn!("could not save profile {}: {}", self.name, err);
+        }
+    }
+
+    /// Records a completed level, keeping the most stars it has earned, and saves the profile.
+    pub fn record_completion(&mut self, level: &str, stars: u8) {
+        let best = self.stats.level_stars.entry(level.to_string()).or_insert(0);
+        *best = (*best).max(stars);
+        self.save();
+    }
+
+    /// Records a finished run, how long it lasted and the level it was played on if any, and
+    /// saves the profile.
+    pub fn record_run(&mut self, score: u32, seconds: f32, level: Option<&str>) {
+        self.stats.runs += 1;
+        self.stats.high_score = self.stats.high_score.max(score);
+        self.stats.recent_runs.push(seconds);
+        let excess = self.stats.recent_runs.len().saturating_sub(RECENT_RUNS);
+        self.stats.recent_runs.drain(..excess);
+        if let Some(level) = level {
+            let scores = self
+                .stats
+                .level_scores
+                .entry(level.to_string())
+                .or_default();
+            let rank = scores
+                .iter()
+                .position(|other| other.score < score)
+                .unwrap_or_else(|| scores.len());
+            scores.insert(
+                rank,
+                LevelScore {
+                    score,
+                    recorded_at: now(),
+                },
+            );
+            scores.truncate(MAX_LEVEL_SCORES);
+        }
+        self.save();
+    }
+}
+
+/// The profile progress is saved under. `None` until one is picked at startup.
+#[derive(Default)]
+pub struct ActiveProfile(pub Option<Profile>);
+
+fn valid_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == ' ' || c == '-' || c == '_'
+}
+
+#[derive(Default)]
+struct Picker {
+    profiles: Vec<Profile>,
+    /// Index into `profiles`, or `profiles.len()` for the new-profile row.
+    selected: usize,
+    new_name: String,
+    /// Set when the picker changed and needs to be redrawn.
+    dirty: bool,
+}
+
+impl Picker {
+    fn load() -> Self {
+        let mut names: Vec<String> = fs::read_dir(PROFILES_DIR)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter(|entry| entry.path().is_dir())
+                    .filter_map(|entry| entry.file_name().into_string().ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+        names.sort();
+        Self {
+            profiles: names.into_iter().map(Profile::load).collect(),
+            selected: 0,
+            new_name: String::new(),
+            dirty: true,
+        }
+    }
+}
+
+struct PickerLine;
+
+pub struct ProfilePlugin;
+
+impl Plugin for ProfilePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<ActiveProfile>()
+            .add_resource(Picker::load())
+            .add_system(return_profile.system())
+            .add_system(profile_picker.system())
+            .add_system(draw_picker.system());
+    }
+}
+
+/// Puts the active profile back on the picker when the game returns to it, so it can be picked
+/// again.
+fn return_profile(
+    state: Res<GameState>,
+    mut picker: ResMut<Picker>,
+    mut active: ResMut<ActiveProfile>,
+) {
+    if *state != GameState::ProfileSelect {
+        return;
+    }
+    if let Some(profile) = active.0.take() {
+        info!(profile = %profile.name, "profile put back");
+        let index = picker
+            .profiles
+            .iter()
+            .position(|other| other.name > profile.name)
+            .unwrap_or_else(|| picker.profiles.len());
+        picker.profiles.insert(index, profile);
+        picker.selected = index;
+        picker.dirty = true;
+    }
+}
+
+fn profile_picker(
+    mut char_reader: Local<EventReader<ReceivedCharacter>>,
+    characters: Res<Events<ReceivedCharacter>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    open_dialog: Res<OpenDialog>,
+    mut picker: ResMut<Picker>,
+    mut active: ResMut<ActiveProfile>,
+    mut state: ResMut<GameState>,
+) {
+    if *state != GameState::ProfileSelect {
+        return;
+    }
+
+    let new_row = picker.profiles.len();
+    for event in char_reader.iter(&characters) {
+        if valid_name_char(event.char) && picker.new_name.chars().count() < MAX_NAME_LEN {
+            picker.new_name.push(event.char);
+            picker.selected = new_row;
+            picker.dirty = true;
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::Back) {
+        picker.new_name.pop();
+        picker.dirty = true;
+    }
+    if keyboard_input.just_pressed(KeyCode::Up) && picker.selected > 0 {
+        picker.selected -= 1;
+        picker.dirty = true;
+    }
+    if keyboard_input.just_pressed(KeyCode::Down) && picker.selected < new_row {
+        picker.selected += 1;
+        picker.dirty = true;
+    }
+    if !keyboard_input.just_pressed(KeyCode::Return) || open_dialog.is_open() {
+        return;
+    }
+
+    let profile = if picker.selected < new_row {
+        let selected = picker.selected;
+        picker.profiles.remove(selected)
+    } else {
+        let name = picker.new_name.trim().to_string();
+        if name.is_empty() || picker.profiles.iter().any(|profile| profile.name == name) {
+            return;
+        }
+        let profile = Profile::load(name);
+        profile.save();
+        profile
+    };
+    info!(profile = %profile.name, "profile selected");
+    active.0 = Some(profile);
+    *state = GameState::Playing;
+    picker.dirty = true;
+}
+
+fn draw_picker(
+    mut commands: Commands,
+    mut drawn_for: Local<Option<GameState>>,
+    state: Res<GameState>,
+    fonts: Res<UiFonts>,
+    locale: Res<Locale>,
+    mut picker: ResMut<Picker>,
+    mut lines: Query<(Entity, &PickerLine)>,
+) {
+    // Other screens can hand control back to the picker, so redraw on state changes too.
+    if !picker.dirty && *drawn_for == Some(*state) {
+        return;
+    }
+    picker.dirty = false;
+    *drawn_for = Some(*state);
+
+    for (ent, _line) in &mut lines.iter() {
+        commands.despawn(ent);
+    }
+    if *state != GameState::ProfileSelect {
+        return;
+    }
+
+    let mut text = vec![locale.text("profile-title")];
+    for profile in &picker.profiles {
+        text.push(locale.format(
+            "profile-entry",
+            &[
+                ("name", profile.name.clone()),
+                ("best", profile.stats.high_score.to_string()),
+            ],
+        ));
+    }
+    text.push(locale.format("profile-new", &[("name", picker.new_name.clone())]));
+    text.push(locale.text("profile-hint"));
+
+    let hint_row = text.len() - 1;
+    for (row, line) in text.into_iter().enumerate() {
+        // Row 0 is the title, so profile rows start at 1.
+        let marker = if row == picker.selected + 1 && row != hint_row {
+            "> "
+        } else {
+            "  "
+        };
+        commands
+            .spawn(TextComponents {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        left: Val::Px(40.0),
+                        bottom: Val::Px(800.0 - row as f32 * (PICKER_FONT_SIZE + 12.0)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                text: fonts.text(format!("{}{}", marker, line), PICKER_FONT_SIZE),
+                ..Default::default()
+            })
+            .with(PickerLine);
+    }
+}
@@ -0,0 +1,704 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{
+    body::SnakeBody,
+    despawn_arena,
+    director::reachable_cells,
+    game_event::GameEvent,
+    hud_layout::{HudLayout, HudWidget},
+    input::Action,
+    level::{ActiveLevel, Level},
+    locale::Locale,
+    power_up::PowerUp,
+    sized_sprite,
+    snapshot::Snapshot,
+    spawn_food,
+    split::Ally,
+    tasks::{Task, TaskPool},
+    toast::Toast,
+    turn_allowed, Direction, Food, FoodMaterial, GameOverCause, GameState, HeadMaterial, Position,
+    Run, Score, SegmentMaterial, Size, SnakeConfig, SnakeHead, SnakeMoveTimer, SnakeSegment,
+};
+
+/// Moves a puzzle gets when the editor first turns a level into one.
+pub const DEFAULT_PUZZLE_MOVES: u32 = 30;
+
+/// The solver gives up after looking at this many positions, so a puzzle too open to search
+/// doesn't hold up whoever asked.
+const MAX_SEARCH_STATES: usize = 100_000;
+
+/// Hints a player gets on each puzzle level.
+pub const MAX_HINTS: u32 = 3;
+
+/// In front of the snake, so a hint into the tail's cell still shows.
+const HINT_DEPTH: f32 = 0.05;
+
+/// A level played as a puzzle: all of its food is placed by hand and has to be eaten within
+/// `moves` moves. The snake only moves when a direction is pressed, U takes moves back and H
+/// shows the next move, a few times a level.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Puzzle {
+    pub moves: u32,
+    pub food: Vec<Position>,
+}
+
+impl Default for Puzzle {
+    fn default() -> Self {
+        Self {
+            moves: DEFAULT_PUZZLE_MOVES,
+            food: Vec::new(),
+        }
+    }
+}
+
+/// A puzzle position the solver can step through without touching the world. Only walls,
+/// portals and the arena's edge are taken into account, which is why `Level::validate` turns
+/// down puzzles with anything else in them. It plays by the standard rules, which the game
+/// keeps to on puzzle levels whatever the player's rules say.
+#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+pub struct PuzzleState {
+    pub body: SnakeBody,
+    pub direction: Direction,
+    /// Food still to eat.
+    pub food: Vec<Position>,
+}
+
+impl PuzzleState {
+    /// The snake at the level's spawn point, facing up with one segment below, next to all of
+    /// the puzzle's food.
+    pub fn start(level: &Level, puzzle: &Puzzle) -> Self {
+        Self {
+            body: SnakeBody::new(level.spawn, Some(level.spawn.moved(Direction::Down)), 0),
+            direction: Direction::Up,
+            food: puzzle.food.clone(),
+        }
+    }
+
+    /// One move after pressing `turn`, or `None` if the snake crashes. Turning back on itself
+    /// keeps it going straight, like in the game.
+    pub fn step(&self, level: &Level, turn: Direction) -> Option<Self> {
+        let direction = if !turn_allowed(self.direction, turn, self.body.len() as u32) {
+            self.direction
+        } else {
+            turn
+        };
+        let head = self.body.head().moved(direction);
+        if head.outside_arena() || level.is_wall(head) {
+            return None;
+        }
+
+        // Like in the game, food is eaten where the head moves to, and only then does a portal
+        // carry it through.
+        let mut next = self.clone();
+        next.direction = direction;
+        if let Some(eaten) = next.food.iter().position(|&food| food == head) {
+            next.food.remove(eaten);
+            next.body.grow(1);
+        }
+        let head = level.portal_exit(head).unwrap_or(head);
+        next.body.advance(head);
+        if next.body.segment_at(head).is_some() {
+            return None;
+        }
+        Some(next)
+    }
+
+    /// The position a snapshot of an attempt was taken at.
+    pub fn from_snapshot(snapshot: &Snapshot) -> Self {
+        Self {
+            body: SnakeBody::new(
+                snapshot.head,
+                snapshot.segments.iter().copied(),
+                snapshot.pending_growth,
+            ),
+            direction: snapshot.direction,
+            food: snapshot.food.clone(),
+        }
+    }
+
+    pub fn solved(&self) -> bool {
+        self.food.is_empty()
+    }
+}
+
+/// What the solver found.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Solution {
+    /// The fewest presses that eat all the food.
+    Moves(Vec<Direction>),
+    /// There's no way to eat it all within the moves.
+    Impossible,
+    /// The search ran too long to tell.
+    GaveUp,
+}
+
+/// Searches for the shortest way to eat all the food in at most `moves` moves, A* style: the
+/// positions that look closest to done are tried first, going by how far the head is from the
+/// furthest food, which portals make useless, and how much food is left.
+pub fn solve(level: &Level, start: &PuzzleState, moves: u32) -> Solution {
+    search(level, start, moves, MAX_SEARCH_STATES)
+}
+
+/// `solve`, giving up once more than `max_states` positions have been reached.
+fn search(level: &Level, start: &PuzzleState, moves: u32, max_states: usize) -> Solution {
+    let portals = !level.portals.is_empty();
+    // Never more than the moves still needed, so the first solution found is a shortest one.
+    let estimate = |state: &PuzzleState| -> u32 {
+        let left = state.food.len() as u32;
+        if portals {
+            return left;
+        }
+        let head = state.body.head();
+        state
+            .food
+            .iter()
+            .map(|food| ((food.x - head.x).abs() + (food.y - head.y).abs()) as u32)
+            .max()
+            .unwrap_or(0)
+            .max(left)
+    };
+    // Everything the search has reached, with the position and press it was reached from.
+    let mut reached: Vec<(PuzzleState, Option<(usize, Direction)>, u32)> =
+        vec![(start.clone(), None, 0)];
+    // The fewest moves each position has been reached in.
+    let mut fewest: HashMap<PuzzleState, u32> = HashMap::new();
+    fewest.insert(start.clone(), 0);
+    let mut open = BinaryHeap::new();
+    open.push(Reverse((estimate(start), 0)));
+    while let Some(Reverse((_estimate, index))) = open.pop() {
+        let (state, _from, depth) = reached[index].clone();
+        if fewest.get(&state).map_or(false, |&best| best < depth) {
+            continue;
+        }
+        if state.solved() {
+            let mut presses = Vec::new();
+            let mut at = index;
+            while let Some((from, press)) = reached[at].1 {
+                presses.push(press);
+                at = from;
+            }
+            presses.reverse();
+            return Solution::Moves(presses);
+        }
+        for &turn in &[
+            Direction::Up,
+            Direction::Left,
+            Direction::Down,
+            Direction::Right,
+        ] {
+            if !turn_allowed(state.direction, turn, state.body.len() as u32) {
+                continue;
+            }
+            let stepped = match state.step(level, turn) {
+                Some(stepped) => stepped,
+                None => continue,
+            };
+            let total = depth + 1 + estimate(&stepped);
+            if total > moves
+                || fewest
+                    .get(&stepped)
+                    .map_or(false, |&best| best <= depth + 1)
+            {
+                continue;
+            }
+            fewest.insert(stepped.clone(), depth + 1);
+            reached.push((stepped, Some((index, turn)), depth + 1));
+            open.push(Reverse((total, reached.len() - 1)));
+        }
+        if reached.len() > max_states {
+            return Solution::GaveUp;
+        }
+    }
+    Solution::Impossible
+}
+
+/// Whether a level's puzzle can be finished at all. Food the head can't reach around the walls
+/// is caught by the pathfinding first, which is much quicker than searching.
+pub fn check_solvable(level: &Level, puzzle: &Puzzle) -> Solution {
+    let walls: HashSet<Position> = level.walls.iter().copied().collect();
+    let reachable = reachable_cells(level.spawn, &walls, false);
+    if puzzle.food.iter().any(|food| !reachable.contains(food)) {
+        return Solution::Impossible;
+    }
+    solve(level, &PuzzleState::start(level, puzzle), puzzle.moves)
+}
+
+/// Every move of the current puzzle attempt, oldest first, for taking moves back.
+#[derive(Default)]
+struct MoveHistory(Vec<Snapshot>);
+
+/// A search for the next move, running in the background.
+struct PendingHint {
+    /// The seed and move the search started from.
+    asked_at: (u64, u64),
+    head: Position,
+    solution: Task<Solution>,
+}
+
+/// Hints used on the level being played, and the one being worked out or shown.
+#[derive(Default)]
+struct Hints {
+    /// The level they're counted for; they're counted afresh on another one.
+    level: Option<String>,
+    used: u32,
+    pending: Option<PendingHint>,
+    /// The cell to move into next, with the seed and move it's good for.
+    shown: Option<((u64, u64), Position)>,
+}
+
+struct HintMarker;
+
+struct HintMaterial(Handle<ColorMaterial>);
+
+/// The HUD line counting down the moves left.
+pub struct MovesLabel;
+
+pub struct PuzzlePlugin;
+
+impl Plugin for PuzzlePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<MoveHistory>()
+            .init_resource::<Hints>()
+            .add_startup_system(setup_hint_material.system())
+            .add_system(place_puzzle_food.system())
+            .add_system(step_on_press.system())
+            .add_system(finish_puzzle.system())
+            .add_system(record_moves.system())
+            .add_system(undo_move.system())
+            .add_system(request_hint.system())
+            .add_system(receive_hint.system())
+            .add_system(draw_hint.system())
+            .add_system(moves_label.system());
+    }
+}
+
+fn setup_hint_material(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
+    commands.insert_resource(HintMaterial(
+        materials.add(Color::rgba(1.0, 1.0, 1.0, 0.6).into()),
+    ));
+}
+
+fn active_puzzle(level: &ActiveLevel) -> Option<(&Level, &Puzzle)> {
+    let level = level.0.as_ref()?;
+    Some((level, level.puzzle.as_ref()?))
+}
+
+/// Lays out the puzzle's food at the start of every attempt.
+fn place_puzzle_food(
+    mut placed_for: Local<Option<u64>>,
+    mut commands: Commands,
+    level: Res<ActiveLevel>,
+    run: Res<Run>,
+    food_material: Res<FoodMaterial>,
+    state: Res<GameState>,
+) {
+    let puzzle = match active_puzzle(&level) {
+        Some((_level, puzzle)) if *state == GameState::Playing => puzzle,
+        _ => return,
+    };
+    if *placed_for == Some(run.seed) || run.tick > 0 {
+        return;
+    }
+    *placed_for = Some(run.seed);
+    for &position in &puzzle.food {
+        spawn_food(&mut commands, food_material.0, position);
+    }
+}
+
+/// Makes the snake wait for presses: each direction pressed runs out the move timer, so it
+/// takes exactly one move.
+fn step_on_press(
+    mut reader: Local<EventReader<Action>>,
+    actions: Res<Events<Action>>,
+    level: Res<ActiveLevel>,
+    mut snake_timer: ResMut<SnakeMoveTimer>,
+    mut configs: Query<&mut SnakeConfig>,
+) {
+    let pressed = reader
+        .iter(&actions)
+        .any(|action| matches!(action, Action::Turn(_)));
+    if active_puzzle(&level).is_none() {
+        return;
+    }
+    snake_timer.0.elapsed = if pressed { snake_timer.0.duration } else { 0.0 };
+    for mut config in &mut configs.iter() {
+        config.move_timer.elapsed = snake_timer.0.elapsed;
+    }
+}
+
+/// Completes the level once all the food is eaten, and ends the attempt when it runs out of
+/// moves first.
+fn finish_puzzle(
+    mut checked: Local<(u64, u64)>,
+    state: Res<GameState>,
+    run: Res<Run>,
+    level: Res<ActiveLevel>,
+    mut game_events: ResMut<Events<GameEvent>>,
+    mut food: Query<&Food>,
+) {
+    let (level, puzzle) = match active_puzzle(&level) {
+        Some(active) if *state == GameState::Playing => active,
+        _ => return,
+    };
+    if *checked == (run.seed, run.tick) || run.tick == 0 {
+        return;
+    }
+    *checked = (run.seed, run.tick);
+
+    let cause = if food.iter().iter().next().is_none() {
+        info!(level = %level.name, moves = run.tick, "puzzle solved");
+        GameOverCause::LevelComplete
+    } else if run.tick >= u64::from(puzzle.moves) {
+        GameOverCause::OutOfMoves
+    } else {
+        return;
+    };
+    game_events.send(GameEvent::GameOver { cause });
+}
+
+/// Keeps a snapshot of every move of the attempt, starting from the food being laid out.
+fn record_moves(
+    level: Res<ActiveLevel>,
+    run: Res<Run>,
+    score: Res<Score>,
+    mut history: ResMut<MoveHistory>,
+    mut heads: Query<Without<Ally, (&SnakeHead, &Position)>>,
+    segments: Query<(&SnakeSegment, &Position)>,
+    mut food: Query<(&Food, &Position)>,
+) {
+    if active_puzzle(&level).is_none() {
+        history.0.clear();
+        return;
+    }
+    if let Some(last) = history.0.last() {
+        if last.seed == run.seed && last.tick == run.tick {
+            return;
+        }
+    }
+    if history
+        .0
+        .first()
+        .map_or(false, |first| first.seed != run.seed)
+    {
+        history.0.clear();
+    }
+    // The food is only there from the frame after it's laid out.
+    if run.tick == 0 && food.iter().iter().next().is_none() {
+        return;
+    }
+    if let Some(snapshot) = Snapshot::capture(&run, &score, &mut heads, &segments, &mut food) {
+        history.0.push(snapshot);
+    }
+}
+
+/// U takes back the last move of a puzzle.
+fn undo_move(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    state: Res<GameState>,
+    level: Res<ActiveLevel>,
+    head_material: Res<HeadMaterial>,
+    segment_material: Res<SegmentMaterial>,
+    food_material: Res<FoodMaterial>,
+    mut history: ResMut<MoveHistory>,
+    mut run: ResMut<Run>,
+    mut score: ResMut<Score>,
+    mut segments: Query<(Entity, &SnakeSegment)>,
+    mut food: Query<(Entity, &Food)>,
+    mut power_ups: Query<(Entity, &PowerUp)>,
+    mut heads: Query<(Entity, &SnakeHead)>,
+) {
+    let undo = keyboard_input.just_pressed(KeyCode::U)
+        && *state == GameState::Playing
+        && active_puzzle(&level).is_some()
+        && history.0.len() > 1;
+    if !undo {
+        return;
+    }
+    history.0.pop();
+    let previous = history.0.last().unwrap();
+    despawn_arena(
+        &mut commands,
+        &mut segments,
+        &mut food,
+        &mut power_ups,
+        &mut heads,
+    );
+    let elapsed = run.elapsed;
+    previous.restore(
+        &mut commands,
+        &head_material,
+        &segment_material,
+        &food_material,
+        &mut run,
+        &mut score,
+    );
+    run.elapsed = elapsed;
+    info!(tick = run.tick, "puzzle move undone");
+}
+
+/// H asks the solver for the next move from the last one made. It searches on the task pool,
+/// so a slow search doesn't hold up the game.
+fn request_hint(
+    keyboard_input: Res<Input<KeyCode>>,
+    state: Res<GameState>,
+    level: Res<ActiveLevel>,
+    run: Res<Run>,
+    locale: Res<Locale>,
+    history: Res<MoveHistory>,
+    tasks: Res<TaskPool>,
+    mut hints: ResMut<Hints>,
+    mut toasts: ResMut<Events<Toast>>,
+) {
+    let (level, puzzle) = match active_puzzle(&level) {
+        Some(active) => active,
+        None => return,
+    };
+    if hints.level.as_ref() != Some(&level.name) {
+        *hints = Hints {
+            level: Some(level.name.clone()),
+            ..Default::default()
+        };
+    }
+    let asked = keyboard_input.just_pressed(KeyCode::H)
+        && *state == GameState::Playing
+        && hints.pending.is_none();
+    if !asked {
+        return;
+    }
+    if hints.used >= MAX_HINTS {
+        toasts.send(Toast::new(locale.text("puzzle-no-hints")));
+        return;
+    }
+    let snapshot = match history.0.last() {
+        Some(snapshot) if snapshot.seed == run.seed && snapshot.tick == run.tick => snapshot,
+        _ => return,
+    };
+    let from = PuzzleState::from_snapshot(snapshot);
+    let moves = u64::from(puzzle.moves).saturating_sub(run.tick) as u32;
+    let level = level.clone();
+    hints.pending = Some(PendingHint {
+        asked_at: (run.seed, run.tick),
+        head: snapshot.head,
+        solution: tasks.spawn("puzzle hint", move || solve(&level, &from, moves)),
+    });
+}
+
+/// Picks up what the solver found and shows the move it starts with, unless the snake has
+/// moved on since. Only hints that show a move are used up.
+fn receive_hint(
+    run: Res<Run>,
+    locale: Res<Locale>,
+    mut hints: ResMut<Hints>,
+    mut toasts: ResMut<Events<Toast>>,
+) {
+    let solution = match hints
+        .pending
+        .as_ref()
+        .and_then(|pending| pending.solution.poll())
+    {
+        Some(solution) => solution.unwrap_or(Solution::GaveUp),
+        None => return,
+    };
+    let pending = hints.pending.take().unwrap();
+    if pending.asked_at != (run.seed, run.tick) {
+        return;
+    }
+    match solution {
+        Solution::Moves(presses) => {
+            if let Some(&press) = presses.first() {
+                hints.used += 1;
+                hints.shown = Some((pending.asked_at, pending.head.moved(press)));
+                info!(used = hints.used, "puzzle hint shown");
+                toasts.send(Toast::new(locale.format(
+                    "puzzle-hint",
+                    &[("hints", (MAX_HINTS - hints.used).to_string())],
+                )));
+            }
+        }
+        Solution::Impossible => toasts.send(Toast::new(locale.text("puzzle-hint-stuck"))),
+        Solution::GaveUp => toasts.send(Toast::new(locale.text("puzzle-hint-gave-up"))),
+    }
+}
+
+/// Marks the hinted cell until the snake makes its next move.
+fn draw_hint(
+    mut commands: Commands,
+    state: Res<GameState>,
+    run: Res<Run>,
+    material: Res<HintMaterial>,
+    mut hints: ResMut<Hints>,
+    mut markers: Query<(Entity, &HintMarker, &mut Position)>,
+) {
+    let playing = matches!(*state, GameState::Playing | GameState::Paused);
+    let cell = match hints.shown {
+        Some((at, cell)) if at == (run.seed, run.tick) && playing => Some(cell),
+        _ => None,
+    };
+    if cell.is_none() {
+        hints.shown = None;
+    }
+    let mut drawn = false;
+    for (ent, _marker, mut position) in &mut markers.iter() {
+        match cell {
+            Some(cell) if !drawn => {
+                if *position != cell {
+                    *position = cell;
+                }
+                drawn = true;
+            }
+            _ => commands.despawn(ent),
+        }
+    }
+    if let (Some(cell), false) = (cell, drawn) {
+        let mut transform = Transform::default();
+        transform.set_translation(Vec3::new(0.0, 0.0, HINT_DEPTH));
+        commands
+            .spawn(SpriteComponents {
+                transform,
+                draw: Draw {
+                    is_transparent: true,
+                    ..Default::default()
+                },
+                ..sized_sprite(material.0)
+            })
+            .with(HintMarker)
+            .with(cell)
+            .with(Size::square(0.5));
+    }
+}
+
+fn moves_label(
+    state: Res<GameState>,
+    level: Res<ActiveLevel>,
+    run: Res<Run>,
+    locale: Res<Locale>,
+    layout: Res<HudLayout>,
+    hints: Res<Hints>,
+    mut labels: Query<(&MovesLabel, &mut Text)>,
+) {
+    // While the layout is edited every widget shows its name instead.
+    if layout.editing {
+        return;
+    }
+    let playing = matches!(*state, GameState::Playing | GameState::Paused);
+    let value = match active_puzzle(&level) {
+        Some((_level, puzzle)) if playing && layout.shows(HudWidget::Moves) => {
+            let left = u64::from(puzzle.moves).saturating_sub(run.tick);
+            let hints = MAX_HINTS.saturating_sub(hints.used);
+            locale.format(
+                "puzzle-moves",
+                &[("moves", left.to_string()), ("hints", hints.to_string())],
+            )
+        }
+        _ => String::new(),
+    };
+    for (_label, mut text) in &mut labels.iter() {
+        if text.value != value {
+            text.value = value.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: i32, y: i32) -> Position {
+        Position { x, y }
+    }
+
+    /// An open level with the snake at (5, 5), facing up, and a puzzle with `food`.
+    fn puzzle_level(food: Vec<Position>, moves: u32) -> Level {
+        Level {
+            spawn: at(5, 5),
+            puzzle: Some(Puzzle { moves, food }),
+            ..Level::default()
+        }
+    }
+
+    fn start(level: &Level) -> PuzzleState {
+        PuzzleState::start(level, level.puzzle.as_ref().unwrap())
+    }
+
+    #[test]
+    fn turning_back_keeps_going_straight() {
+        let level = puzzle_level(vec![at(5, 9)], 10);
+        let next = start(&level).step(&level, Direction::Down).unwrap();
+        assert_eq!(next.direction, Direction::Up);
+        assert_eq!(next.body.head(), at(5, 6));
+    }
+
+    #[test]
+    fn walls_crash_the_snake() {
+        let mut level = puzzle_level(vec![at(5, 9)], 10);
+        level.walls.push(at(5, 6));
+        assert_eq!(start(&level).step(&level, Direction::Up), None);
+    }
+
+    #[test]
+    fn portals_carry_the_head_through() {
+        let mut level = puzzle_level(vec![at(5, 9)], 10);
+        level.portals = vec![at(5, 6), at(20, 20)];
+        let next = start(&level).step(&level, Direction::Up).unwrap();
+        assert_eq!(next.body.head(), at(20, 20));
+        assert_eq!(next.body.segment_at(at(5, 5)), Some(0));
+        assert_eq!(next.body.len(), 2);
+    }
+
+    #[test]
+    fn food_is_eaten_before_the_portal() {
+        let mut level = puzzle_level(vec![at(5, 6)], 10);
+        level.portals = vec![at(5, 6), at(20, 20)];
+        let next = start(&level).step(&level, Direction::Up).unwrap();
+        assert!(next.solved());
+        assert_eq!(next.body.head(), at(20, 20));
+        assert_eq!(next.body.len(), 3);
+
+        // Food where the portal comes out isn't passed over.
+        let mut level = puzzle_level(vec![at(20, 20)], 10);
+        level.portals = vec![at(5, 6), at(20, 20)];
+        let next = start(&level).step(&level, Direction::Up).unwrap();
+        assert_eq!(next.food, vec![at(20, 20)]);
+        assert_eq!(next.body.len(), 2);
+    }
+
+    #[test]
+    fn solutions_fit_in_the_moves() {
+        let level = puzzle_level(vec![at(5, 8)], 3);
+        let puzzle = level.puzzle.as_ref().unwrap();
+        assert_eq!(
+            check_solvable(&level, puzzle),
+            Solution::Moves(vec![Direction::Up; 3])
+        );
+
+        let level = puzzle_level(vec![at(5, 8)], 2);
+        let puzzle = level.puzzle.as_ref().unwrap();
+        assert_eq!(check_solvable(&level, puzzle), Solution::Impossible);
+    }
+
+    #[test]
+    fn walled_off_food_is_impossible() {
+        let mut level = puzzle_level(vec![at(12, 12)], 100);
+        level.walls = vec![at(11, 12), at(13, 12), at(12, 11), at(12, 13)];
+        let puzzle = level.puzzle.as_ref().unwrap();
+        assert_eq!(check_solvable(&level, puzzle), Solution::Impossible);
+    }
+
+    #[test]
+    fn search_gives_up_past_the_state_cap() {
+        let level = puzzle_level(vec![at(5, 15)], 30);
+        assert_eq!(search(&level, &start(&level), 30, 5), Solution::GaveUp);
+        assert_eq!(
+            solve(&level, &start(&level), 30),
+            Solution::Moves(vec![Direction::Up; 10])
+        );
+    }
+}
@@ -0,0 +1,237 @@
+use std::f32::consts::FRAC_PI_4;
+
+use bevy::prelude::*;
+use rand::Rng;
+use tracing::info;
+
+use crate::{
+    accessibility::{AccessibilitySettings, Announcement, Palette},
+    body::read_body,
+    locale::Locale,
+    rules::GameRules,
+    scoring::{ScoreEvent, ScoreReason},
+    sized_sprite,
+    split::Ally,
+    toast::Toast,
+    Dead, FoodMaterial, Position, Run, Size, SnakeHead, SnakeSegment, ARENA_HEIGHT, ARENA_WIDTH,
+};
+
+/// Side of the square home corner, in cells.
+const HOME_SIZE: i32 = 3;
+
+/// Points for bringing the relay food home.
+const RELAY_POINTS: u32 = 50;
+
+/// The home corner is drawn on the floor, over the tiles.
+const HOME_DEPTH: f32 = -0.04;
+
+/// How see-through the home corner is drawn.
+const HOME_ALPHA: f32 = 0.35;
+
+/// The single piece of food that has to be carried home. While carried it trails right
+/// behind the carrier's head.
+struct RelayFood {
+    carrier: Option<Entity>,
+}
+
+struct Home;
+
+struct HomeMaterial(Handle<ColorMaterial>);
+
+pub struct RelayPlugin;
+
+impl Plugin for RelayPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_startup_system(setup_home.system())
+            .add_system(reset_relay.system())
+            .add_system(carry_relay_food.system())
+            .add_system(apply_home_palette.system());
+    }
+}
+
+fn in_home(position: &Position) -> bool {
+    position.x < HOME_SIZE && position.y < HOME_SIZE
+}
+
+fn home_color(palette: Palette) -> Color {
+    let mut color = palette.colors().head;
+    color.a = HOME_ALPHA;
+    color
+}
+
+fn setup_home(
+    mut commands: Commands,
+    settings: Res<AccessibilitySettings>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let material = materials.add(home_color(settings.palette).into());
+    // The home's center cell, which is where its sprite is placed.
+    let center = Position {
+        x: HOME_SIZE / 2,
+        y: HOME_SIZE / 2,
+    };
+    commands
+        .spawn(SpriteComponents {
+            transform: Transform::from_translation(Vec3::new(0.0, 0.0, HOME_DEPTH)),
+            draw: Draw {
+                is_visible: false,
+                is_transparent: true,
+                ..Default::default()
+            },
+            ..sized_sprite(material)
+        })
+        .with(Home)
+        .with(center)
+        .with(Size::square(HOME_SIZE as f32));
+    commands.insert_resource(HomeMaterial(material));
+}
+
+/// Somewhere in the half of the arena away from home.
+fn random_far_position(run: &mut Run) -> Position {
+    loop {
+        let position = Position {
+            x: run.rng.gen_range(0, ARENA_WIDTH as i32),
+            y: run.rng.gen_range(0, ARENA_HEIGHT as i32),
+        };
+        if position.x + position.y >= (ARENA_WIDTH + ARENA_HEIGHT) as i32 / 2 {
+            return position;
+        }
+    }
+}
+
+fn spawn_relay_food(commands: &mut Commands, material: Handle<ColorMaterial>, position: Position) {
+    // Drawn as a diamond so it can't be mistaken for ordinary food.
+    commands
+        .spawn(SpriteComponents {
+            transform: Transform::from_rotation(Quat::from_rotation_z(FRAC_PI_4)),
+            ..sized_sprite(material)
+        })
+        .with(RelayFood { carrier: None })
+        .with(position)
+        .with(Size::square(0.7));
+}
+
+/// Sets up the relay food and home corner at the start of each run with the `relay` rule, and
+/// clears them away when the rule is turned off.
+fn reset_relay(
+    mut commands: Commands,
+    mut active: Local<Option<u64>>,
+    rules: Res<GameRules>,
+    food_material: Res<FoodMaterial>,
+    mut run: ResMut<Run>,
+    mut homes: Query<(&Home, &mut Draw)>,
+    mut relay_food: Query<(Entity, &RelayFood)>,
+) {
+    let wanted = if rules.relay { Some(run.seed) } else { None };
+    if *active == wanted {
+        return;
+    }
+    *active = wanted;
+
+    for (ent, _food) in &mut relay_food.iter() {
+        commands.despawn(ent);
+    }
+    for (_home, mut draw) in &mut homes.iter() {
+        draw.is_visible = rules.relay;
+    }
+    if rules.relay {
+        let position = random_far_position(&mut run);
+        spawn_relay_food(&mut commands, food_material.0, position);
+    }
+}
+
+/// Picks up, carries, drops and delivers the relay food. It's dropped where it is if its
+/// carrier crashes or another snake's head runs into the carrier.
+fn carry_relay_food(
+    mut commands: Commands,
+    locale: Res<Locale>,
+    food_material: Res<FoodMaterial>,
+    mut run: ResMut<Run>,
+    mut score_events: ResMut<Events<ScoreEvent>>,
+    mut announcements: ResMut<Events<Announcement>>,
+    mut toasts: ResMut<Events<Toast>>,
+    mut heads: Query<(Entity, &SnakeHead, &Position, Option<&Ally>, Option<&Dead>)>,
+    segments: Query<(&SnakeSegment, &Position)>,
+    mut relay_food: Query<(Entity, &mut RelayFood, &mut Position)>,
+) {
+    for (food_ent, mut food, mut food_pos) in &mut relay_food.iter() {
+        let carrier = food.carrier.and_then(|carrier| {
+            let head = heads.get::<SnakeHead>(carrier).ok()?;
+            let position = heads.get::<Position>(carrier).ok()?;
+            if heads.get::<Dead>(carrier).is_ok() {
+                return None;
+            }
+            Some((carrier, head.next_segment, *position))
+        });
+
+        let (carrier, first_segment, head_pos) = match carrier {
+            Some(carrier) => carrier,
+            None => {
+                if food.carrier.take().is_some() {
+                    announcements.send(Announcement::verbose(locale.text("relay-dropped")));
+                }
+                // Only the player's snakes can pick it up.
+                for (ent, _head, head_pos, ally, dead) in &mut heads.iter() {
+                    if ally.is_none() && dead.is_none() && *head_pos == *food_pos {
+                        food.carrier = Some(ent);
+                        announcements.send(Announcement::verbose(locale.text("relay-picked-up")));
+                        break;
+                    }
+                }
+                continue;
+            }
+        };
+
+        if in_home(&head_pos) {
+            commands.despawn(food_ent);
+            score_events.send(ScoreEvent::new(RELAY_POINTS, ScoreReason::Relay).at(head_pos));
+            run.span().in_scope(|| info!("relay food delivered"));
+            let text = locale.format("relay-delivered", &[("points", RELAY_POINTS.to_string())]);
+            announcements.send(Announcement::essential(text.clone()));
+            toasts.send(Toast::new(text));
+            let position = random_far_position(&mut run);
+            spawn_relay_food(&mut commands, food_material.0, position);
+            continue;
+        }
+
+        // Another head running into any part of the carrier knocks the food loose.
+        let carrier_body = read_body(head_pos, first_segment, &segments);
+        let knocked = heads.iter().iter().any(|(ent, _head, position, _, dead)| {
+            ent != carrier
+                && dead.is_none()
+                && carrier_body
+                    .as_ref()
+                    .map_or(*position == head_pos, |body| body.contains(*position))
+        });
+        if knocked {
+            food.carrier = None;
+            announcements.send(Announcement::verbose(locale.text("relay-dropped")));
+            continue;
+        }
+
+        if let Some(behind) = carrier_body
+            .as_ref()
+            .and_then(|body| body.segments().next())
+        {
+            if *food_pos != behind {
+                *food_pos = behind;
+            }
+        }
+    }
+}
+
+fn apply_home_palette(
+    mut applied: Local<Option<Palette>>,
+    settings: Res<AccessibilitySettings>,
+    home_material: Res<HomeMaterial>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if *applied == Some(settings.palette) {
+        return;
+    }
+    *applied = Some(settings.palette);
+
+    if let Some(material) = materials.get_mut(&home_material.0) {
+        material.color = home_color(settings.palette);
+    }
+}
@@ -0,0 +1,480 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    despawn_arena,
+    game_event::GameEvent,
+    locale::Locale,
+    power_up::PowerUp,
+    rules::GameRules,
+    save_format::{self, Versioned},
+    seasonal::civil_date,
+    snapshot::Snapshot,
+    spawn_initial_snake,
+    split::Ally,
+    tasks::TaskPool,
+    time_scale::{step_speed, TimeScale, TimeSource},
+    ui::UiFonts,
+    Food, FoodMaterial, GameState, HeadMaterial, Position, Run, Score, SegmentMaterial, SnakeHead,
+    SnakeSegment, SNAKE_MOVE_INTERVAL,
+};
+
+const REPLAYS_DIR: &str = "replays";
+
+/// Older replays are deleted once there are more than this many.
+const MAX_REPLAYS: usize = 20;
+
+/// Longer runs only keep their first this many moves.
+const MAX_FRAMES: usize = 20_000;
+
+/// How many moves Page Up and Page Down jump.
+const SCRUB_STEP: usize = 50;
+
+const BROWSER_FONT_SIZE: f32 = 26.0;
+
+/// A recorded run. The simulation can't be stepped deterministically yet, so rather than
+/// inputs it keeps a snapshot of every move, which also lets playback jump anywhere.
+#[derive(Serialize, Deserialize)]
+struct Replay {
+    /// Unix time the run ended.
+    recorded_at: u64,
+    /// Which rules were on.
+    mode: String,
+    score: u32,
+    frames: Vec<Snapshot>,
+}
+
+impl Versioned for Replay {
+    const KIND: &'static str = "replay";
+    const VERSION: u32 = 1;
+}
+
+impl Replay {
+    fn load(path: &Path) -> Option<Self> {
+        let text = fs::read_to_string(path).ok()?;
+        save_format::decode(&text)
+            .map_err(|err| warn!("unreadable replay {}: {}", path.display(), err))
+            .ok()
+    }
+}
+
+/// The run being recorded.
+#[derive(Default)]
+struct Recording {
+    seed: u64,
+    last_tick: u64,
+    frames: Vec<Snapshot>,
+}
+
+/// A replay being watched.
+struct Playback {
+    replay: Replay,
+    frame: usize,
+    /// Frame currently spawned in the arena.
+    shown: Option<usize>,
+    paused: bool,
+    speed: f32,
+    timer: Timer,
+}
+
+/// The replay browser, listing saved replays newest first.
+#[derive(Default)]
+struct Browser {
+    entries: Vec<(PathBuf, Replay)>,
+    selected: usize,
+    playback: Option<Playback>,
+    /// Set when the browser changed and needs to be redrawn.
+    dirty: bool,
+}
+
+struct BrowserLine;
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Recording>()
+            .init_resource::<Browser>()
+            .add_system(record_replay.system())
+            .add_system(save_replay.system())
+            .add_system(browser_input.system())
+            .add_system(play_replay.system())
+            .add_system(draw_browser.system());
+    }
+}
+
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// Replay files, newest first.
+fn replay_paths() -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(REPLAYS_DIR)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().map_or(false, |ext| ext == "ron"))
+                .collect()
+        })
+        .unwrap_or_default();
+    // Names carry the timestamp, so they sort by age.
+    paths.sort();
+    paths.reverse();
+    paths
+}
+
+/// Snapshots every move of the current run.
+fn record_replay(
+    state: Res<GameState>,
+    run: Res<Run>,
+    score: Res<Score>,
+    mut recording: ResMut<Recording>,
+    mut heads: Query<Without<Ally, (&SnakeHead, &Position)>>,
+    segments: Query<(&SnakeSegment, &Position)>,
+    mut food: Query<(&Food, &Position)>,
+) {
+    if recording.seed != run.seed {
+        *recording = Recording {
+            seed: run.seed,
+            ..Recording::default()
+        };
+    }
+    if *state != GameState::Playing
+        || run.tick == recording.last_tick
+        || recording.frames.len() >= MAX_FRAMES
+    {
+        return;
+    }
+    recording.last_tick = run.tick;
+    if let Some(snapshot) = Snapshot::capture(&run, &score, &mut heads, &segments, &mut food) {
+        recording.frames.push(snapshot);
+    }
+}
+
+/// Writes the finished run to the replays folder, dropping the oldest replays past the limit.
+/// A long run takes a while to encode, so it's saved on the task pool.
+fn save_replay(
+    mut reader: Local<EventReader<GameEvent>>,
+    game_events: Res<Events<GameEvent>>,
+    rules: Res<GameRules>,
+    score: Res<Score>,
+    tasks: Res<TaskPool>,
+    mut recording: ResMut<Recording>,
+) {
+    if reader
+        .iter(&game_events)
+        .find_map(GameEvent::game_over)
+        .is_none()
+        || recording.frames.is_empty()
+    {
+        return;
+    }
+    let recorded_at = now();
+    let replay = Replay {
+        recorded_at,
+        mode: rules.summary(),
+        score: score.0,
+        frames: std::mem::take(&mut recording.frames),
+    };
+    let path =
+        Path::new(REPLAYS_DIR).join(format!("replay-{}-{}.ron", recorded_at, recording.seed));
+    // Nothing waits for the save, it only logs how it went.
+    let _saving = tasks.spawn("replay save", move || {
+        let result = save_format::encode(&replay).and_then(|text| {
+            fs::create_dir_all(REPLAYS_DIR)
+                .and_then(|_| fs::write(&path, text))
+                .map_err(|err| err.to_string())
+        });
+        match result {
+            Ok(()) => info!(path = %path.display(), frames = replay.frames.len(), "replay saved"),
+            Err(err) => warn!("could not save replay {}: {}", path.display(), err),
+        }
+        for old in replay_paths().iter().skip(MAX_REPLAYS) {
+            if let Err(err) = fs::remove_file(old) {
+                warn!("could not remove old replay {}: {}", old.display(), err);
+            }
+        }
+    });
+}
+
+/// F8 on the profile picker opens the browser. Up, Down and Enter pick a replay; while one
+/// plays, Space pauses, 1, 2 and 4 set the speed and Minus and Equals step it between 0.25x and
+/// 8x, Left and Right step a move, Page Up and Page Down jump further, Home and End go to
+/// either end, and Escape goes back.
+fn browser_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut browser: ResMut<Browser>,
+    mut state: ResMut<GameState>,
+) {
+    if *state == GameState::ProfileSelect && keyboard_input.just_pressed(KeyCode::F8) {
+        browser.entries = replay_paths()
+            .into_iter()
+            .filter_map(|path| Replay::load(&path).map(|replay| (path, replay)))
+            .collect();
+        browser.selected = 0;
+        browser.dirty = true;
+        *state = GameState::Replays;
+        return;
+    }
+    if *state != GameState::Replays {
+        return;
+    }
+    if keyboard_input.get_just_pressed().next().is_none() {
+        return;
+    }
+    let pressed = |key| keyboard_input.just_pressed(key);
+    let Browser {
+        entries,
+        selected,
+        playback,
+        dirty,
+    } = &mut *browser;
+
+    if let Some(playback) = playback {
+        let last = playback.replay.frames.len() - 1;
+        if pressed(KeyCode::Space) {
+            playback.paused = !playback.paused;
+        }
+        for &(key, speed) in &[
+            (KeyCode::Key1, 1.0),
+            (KeyCode::Key2, 2.0),
+            (KeyCode::Key4, 4.0),
+        ] {
+            if pressed(key) {
+                playback.speed = speed;
+            }
+        }
+        if pressed(KeyCode::Minus) {
+            playback.speed = step_speed(playback.speed, false);
+        }
+        if pressed(KeyCode::Equals) {
+            playback.speed = step_speed(playback.speed, true);
+        }
+        if pressed(KeyCode::Right) {
+            playback.frame = (playback.frame + 1).min(last);
+        }
+        if pressed(KeyCode::Left) {
+            playback.frame = playback.frame.saturating_sub(1);
+        }
+        if pressed(KeyCode::PageDown) {
+            playback.frame = (playback.frame + SCRUB_STEP).min(last);
+        }
+        if pressed(KeyCode::PageUp) {
+            playback.frame = playback.frame.saturating_sub(SCRUB_STEP);
+        }
+        if pressed(KeyCode::Home) {
+            playback.frame = 0;
+        }
+        if pressed(KeyCode::End) {
+            playback.frame = last;
+        }
+        *dirty = true;
+        return;
+    }
+
+    if pressed(KeyCode::Escape) {
+        *state = GameState::ProfileSelect;
+    }
+    if pressed(KeyCode::Up) && *selected > 0 {
+        *selected -= 1;
+    }
+    if pressed(KeyCode::Down) && *selected + 1 < entries.len() {
+        *selected += 1;
+    }
+    if pressed(KeyCode::Return) && *selected < entries.len() {
+        let (path, _replay) = &entries[*selected];
+        if let Some(replay) = Replay::load(path) {
+            *playback = Some(Playback {
+                replay,
+                frame: 0,
+                shown: None,
+                paused: false,
+                speed: 1.0,
+                timer: Timer::new(SNAKE_MOVE_INTERVAL, true),
+            });
+        }
+    }
+    *dirty = true;
+}
+
+/// Advances the replay being watched and puts its current frame into the arena. Escape puts
+/// back a fresh snake and returns to the list.
+fn play_replay(
+    mut commands: Commands,
+    time: Res<Time>,
+    keyboard_input: Res<Input<KeyCode>>,
+    head_material: Res<HeadMaterial>,
+    segment_material: Res<SegmentMaterial>,
+    food_material: Res<FoodMaterial>,
+    mut browser: ResMut<Browser>,
+    mut time_scale: ResMut<TimeScale>,
+    mut run: ResMut<Run>,
+    mut score: ResMut<Score>,
+    mut segments: Query<(Entity, &SnakeSegment)>,
+    mut food: Query<(Entity, &Food)>,
+    mut power_ups: Query<(Entity, &PowerUp)>,
+    mut heads: Query<(Entity, &SnakeHead)>,
+) {
+    let Browser {
+        playback: current,
+        dirty,
+        ..
+    } = &mut *browser;
+    let playback = match current {
+        Some(playback) => playback,
+        None => {
+            time_scale.clear(TimeSource::Replay);
+            return;
+        }
+    };
+
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        *current = None;
+        time_scale.clear(TimeSource::Replay);
+        *dirty = true;
+        despawn_arena(
+            &mut commands,
+            &mut segments,
+            &mut food,
+            &mut power_ups,
+            &mut heads,
+        );
+        *run = Run::default();
+        score.0 = 0;
+        spawn_initial_snake(&mut commands, head_material, segment_material);
+        return;
+    }
+
+    time_scale.set(TimeSource::Replay, playback.speed);
+    if !playback.paused {
+        playback.timer.tick(time_scale.delta(&time));
+        if playback.timer.finished && playback.frame + 1 < playback.replay.frames.len() {
+            playback.frame += 1;
+            *dirty = true;
+        }
+    }
+    if playback.shown == Some(playback.frame) {
+        return;
+    }
+    playback.shown = Some(playback.frame);
+    despawn_arena(
+        &mut commands,
+        &mut segments,
+        &mut food,
+        &mut power_ups,
+        &mut heads,
+    );
+    playback.replay.frames[playback.frame].restore(
+        &mut commands,
+        &head_material,
+        &segment_material,
+        &food_material,
+        &mut run,
+        &mut score,
+    );
+}
+
+fn draw_browser(
+    mut commands: Commands,
+    state: Res<GameState>,
+    fonts: Res<UiFonts>,
+    locale: Res<Locale>,
+    mut browser: ResMut<Browser>,
+    mut lines: Query<(Entity, &BrowserLine)>,
+) {
+    if !browser.dirty {
+        return;
+    }
+    browser.dirty = false;
+
+    for (ent, _line) in &mut lines.iter() {
+        commands.despawn(ent);
+    }
+    if *state != GameState::Replays {
+        return;
+    }
+
+    let mut text = Vec::new();
+    match &browser.playback {
+        Some(playback) => {
+            let frames = playback.replay.frames.len();
+            // A 20-character scrubber bar.
+            let filled = (playback.frame + 1) * 20 / frames;
+            let key = if playback.paused {
+                "replay-paused"
+            } else {
+                "replay-playing"
+            };
+            text.push(locale.format(
+                key,
+                &[
+                    ("tick", (playback.frame + 1).to_string()),
+                    ("ticks", frames.to_string()),
+                    ("speed", playback.speed.to_string()),
+                    (
+                        "bar",
+                        format!("[{}{}]", "=".repeat(filled), "-".repeat(20 - filled)),
+                    ),
+                ],
+            ));
+            text.push(locale.text("replay-controls"));
+        }
+        None => {
+            text.push(locale.text("replays-title"));
+            if browser.entries.is_empty() {
+                text.push(locale.text("replays-empty"));
+            }
+            for (row, (_path, replay)) in browser.entries.iter().enumerate() {
+                let (year, month, day) = civil_date(replay.recorded_at);
+                let marker = if row == browser.selected { "> " } else { "  " };
+                text.push(format!(
+                    "{}{}",
+                    marker,
+                    locale.format(
+                        "replays-entry",
+                        &[
+                            ("date", format!("{}-{:02}-{:02}", year, month, day)),
+                            ("mode", replay.mode.clone()),
+                            ("score", replay.score.to_string()),
+                        ],
+                    )
+                ));
+            }
+            text.push(locale.text("replays-hint"));
+        }
+    }
+
+    // The list fills the screen, but playback status stays at the bottom out of the way.
+    let top = if browser.playback.is_some() {
+        80.0
+    } else {
+        900.0
+    };
+    for (row, line) in text.into_iter().enumerate() {
+        commands
+            .spawn(TextComponents {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        left: Val::Px(40.0),
+                        bottom: Val::Px(top - row as f32 * (BROWSER_FONT_SIZE + 8.0)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                text: fonts.text(line, BROWSER_FONT_SIZE),
+                ..Default::default()
+            })
+            .with(BrowserLine);
+    }
+}
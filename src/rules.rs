@@ -0,0 +1,93 @@
+use serde::Deserialize;
+
+use crate::{director::FoodPacing, head_on::HeadOnRule, split::Personality};
+
+/// Optional gameplay mutators. They're read from the `rules` section of `config.ron` and are
+/// all off by default, giving the classic game.
+#[derive(Clone, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct GameRules {
+    /// Lets split food appear. Eating it cuts the snake in half and the rear half becomes an
+    /// ally for a while.
+    pub split_food: bool,
+    /// Makes long snakes ponderous: past 30 parts, every 30 parts delay turns by one more move.
+    pub weight: bool,
+    /// Scatters patches of ice over the arena. The snake can't turn while its head is on ice.
+    pub ice_tiles: bool,
+    /// Scatters speed pads, which halve the move interval, and mud, which doubles it.
+    pub speed_tiles: bool,
+    /// Lays conveyor belts that push the snake one extra cell every move.
+    pub conveyor_tiles: bool,
+    /// Cycles between day and night. At night the arena dims and distant food is hidden
+    /// unless the player holds a lantern.
+    pub day_night: bool,
+    /// How allies pick their moves.
+    pub ally_personality: Personality,
+    /// What happens when two heads meet. Without a rule snakes pass through each other.
+    pub head_on: Option<HeadOnRule>,
+    /// King of the hill: a zone that moves every 20 seconds pays out points for every move
+    /// the head ends inside it.
+    pub king_of_the_hill: bool,
+    /// Capture the food: a special food has to be carried back to the home corner to score.
+    pub relay: bool,
+    /// Food grows the snake by three segments instead of one.
+    pub fast_growth: bool,
+    /// Walls turn the snake aside instead of ending the run, for a few points each time.
+    pub wall_bounce: bool,
+    /// Running into the tail bites it off where the head hit instead of ending the run.
+    pub tail_cut: bool,
+    /// Leaving the arena brings the snake back in on the opposite side.
+    pub wrap_walls: bool,
+    /// Food is worth more the faster and longer the snake is, see `scoring::speed_points`.
+    pub speed_scoring: bool,
+    /// Runs start slower and speed up over the first few food, see `slow_start`.
+    pub slow_start: bool,
+    /// Slows the game down a little after a few quick deaths in a row and speeds it up after a
+    /// few long runs, see `adaptive`.
+    pub adaptive_difficulty: bool,
+    /// How often food appears and how much of it there can be.
+    pub food_pacing: FoodPacing,
+}
+
+/// Rules for one snake that take the place of the game's, e.g. to handicap one player in a
+/// versus game. `None` leaves the setting from `GameRules`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct RuleOverrides {
+    pub weight: Option<bool>,
+    pub wall_bounce: Option<bool>,
+    pub tail_cut: Option<bool>,
+    pub wrap_walls: Option<bool>,
+}
+
+impl GameRules {
+    /// Short description of which mutators are on, e.g. for listing replays.
+    pub fn summary(&self) -> String {
+        let enabled: Vec<&str> = [
+            (self.split_food, "split food"),
+            (self.weight, "weight"),
+            (self.ice_tiles, "ice"),
+            (self.speed_tiles, "speed tiles"),
+            (self.conveyor_tiles, "conveyors"),
+            (self.day_night, "day/night"),
+            (self.head_on.is_some(), "head-on"),
+            (self.king_of_the_hill, "king of the hill"),
+            (self.relay, "relay"),
+            (self.fast_growth, "fast growth"),
+            (self.wall_bounce, "wall bounce"),
+            (self.tail_cut, "tail cut"),
+            (self.wrap_walls, "wrap-around"),
+            (self.speed_scoring, "speed scoring"),
+            (self.slow_start, "slow start"),
+            (self.adaptive_difficulty, "adaptive difficulty"),
+        ]
+        .iter()
+        .filter(|(on, _name)| *on)
+        .map(|&(_on, name)| name)
+        .collect();
+        if enabled.is_empty() {
+            "classic".to_string()
+        } else {
+            enabled.join(", ")
+        }
+    }
+}
@@ -0,0 +1,57 @@
+use ron::ser::PrettyConfig;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Starts every save and replay file. The rest of the header line names the kind of file and
+/// its schema version, and the body follows as RON. The header is a RON comment, so the files
+/// stay readable and editable as plain RON.
+const MAGIC: &str = "// snake-save";
+
+/// A type that can be written to and read back from a versioned file.
+pub trait Versioned: Serialize + DeserializeOwned {
+    /// Names the kind of file, so e.g. a replay isn't mistaken for a save.
+    const KIND: &'static str;
+    /// Bump this whenever the serialized layout changes, and teach `migrate` to read the old
+    /// one.
+    const VERSION: u32;
+
+    /// Reads a body written by an older version. Files from before the header existed are
+    /// version 0.
+    fn migrate(version: u32, _body: &str) -> Result<Self, String> {
+        Err(format!("can't read {} version {}", Self::KIND, version))
+    }
+}
+
+pub fn encode<T: Versioned>(value: &T) -> Result<String, String> {
+    let body = ron::ser::to_string_pretty(value, PrettyConfig::default())
+        .map_err(|err| err.to_string())?;
+    Ok(format!("{} {} {}\n{}", MAGIC, T::KIND, T::VERSION, body))
+}
+
+pub fn decode<T: Versioned>(text: &str) -> Result<T, String> {
+    if !text.starts_with(MAGIC) {
+        return T::migrate(0, text);
+    }
+    let mut lines = text.splitn(2, '\n');
+    let header = lines.next().unwrap_or_default();
+    let body = lines.next().unwrap_or_default();
+    let mut fields = header[MAGIC.len()..].split_whitespace();
+    let kind = fields.next().unwrap_or_default();
+    if kind != T::KIND {
+        return Err(format!("expected a {} file, found {}", T::KIND, kind));
+    }
+    let version: u32 = fields
+        .next()
+        .and_then(|version| version.parse().ok())
+        .ok_or_else(|| format!("bad header: {}", header))?;
+    if version == T::VERSION {
+        ron::de::from_str(body).map_err(|err| err.to_string())
+    } else if version > T::VERSION {
+        Err(format!(
+            "{} version {} is from a newer build",
+            T::KIND,
+            version
+        ))
+    } else {
+        T::migrate(version, body)
+    }
+}
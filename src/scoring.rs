@@ -0,0 +1,412 @@
+use bevy::prelude::*;
+use tracing::debug;
+
+use crate::{
+    accessibility::{AccessibilitySettings, Announcement},
+    config::ZenMode,
+    game_event::GameEvent,
+    level::ActiveLevel,
+    locale::Locale,
+    obstacle::SwitchState,
+    popup::Popup,
+    rules::GameRules,
+    split::Ally,
+    time_scale::TimeScale,
+    Dead, Direction, GameState, HeadMaterial, Position, Run, Score, SegmentMaterial, SnakeHead,
+    SnakeSegment,
+};
+
+/// Points for every move the head spends next to a wall, an obstacle or a closed door.
+const DANGER_POINTS: u32 = 1;
+
+const MAX_MULTIPLIER: u32 = 4;
+
+/// How long a combo lasts without another near miss, in seconds.
+const COMBO_DURATION: f32 = 5.0;
+
+/// How far each step of the multiplier brightens the snake towards white.
+const GLOW_PER_MULTIPLIER: f32 = 0.15;
+
+/// The pace food is worth its usual points at with the `speed_scoring` rule: the default
+/// 150 ms between moves.
+const REFERENCE_MOVES_PER_SECOND: f32 = 1.0 / 0.15;
+
+/// With the `speed_scoring` rule, every whole this many parts of the snake add the food's
+/// usual points once more.
+const LENGTH_BONUS_STEP: usize = 20;
+
+/// Why points were awarded.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum ScoreReason {
+    Food,
+    AllyExpired,
+    EventFood,
+    Danger,
+    Objective,
+    Zone,
+    Relay,
+    /// A penalty, so its points are taken away.
+    WallBounce,
+}
+
+/// Points to add to the score. Everything that awards points sends one of these instead of
+/// changing `Score` directly, so bonuses can be worked out in one place.
+pub struct ScoreEvent {
+    pub points: u32,
+    pub reason: ScoreReason,
+    /// Where to show the points, if anywhere.
+    pub position: Option<Position>,
+    /// How fast and how long the snake was, for food scored by the `speed_scoring` rule.
+    pub pace: Option<Pace>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Pace {
+    pub moves_per_second: f32,
+    /// The head and every segment.
+    pub length: usize,
+}
+
+impl ScoreEvent {
+    pub fn new(points: u32, reason: ScoreReason) -> Self {
+        Self {
+            points,
+            reason,
+            position: None,
+            pace: None,
+        }
+    }
+
+    pub fn at(self, position: Position) -> Self {
+        Self {
+            position: Some(position),
+            ..self
+        }
+    }
+
+    pub fn paced(self, pace: Pace) -> Self {
+        Self {
+            pace: Some(pace),
+            ..self
+        }
+    }
+}
+
+/// The classic arcade formula behind the `speed_scoring` rule: food is worth its `points` at
+/// the reference pace, in proportion to how fast the snake actually goes, and once more for
+/// every whole `LENGTH_BONUS_STEP` parts of it. Always worth at least a point.
+pub fn speed_points(points: u32, pace: Pace) -> u32 {
+    let speed = pace.moves_per_second.max(0.0) / REFERENCE_MOVES_PER_SECOND;
+    let length = 1 + pace.length / LENGTH_BONUS_STEP;
+    ((points as f32 * speed * length as f32).round() as u32).max(1)
+}
+
+/// Near misses build up a multiplier for food points. It drops back to one if the player goes
+/// too long without another, or when the run ends.
+pub struct Combo {
+    pub multiplier: u32,
+    timer: Timer,
+    seed: u64,
+}
+
+impl Default for Combo {
+    fn default() -> Self {
+        Self {
+            multiplier: 1,
+            timer: Timer::from_seconds(COMBO_DURATION, false),
+            seed: 0,
+        }
+    }
+}
+
+impl Combo {
+    fn extend(&mut self) {
+        self.multiplier = (self.multiplier + 1).min(MAX_MULTIPLIER);
+        self.timer.reset();
+    }
+}
+
+/// Whether the player earned the danger bonus on the last move, for the HUD.
+#[derive(Default)]
+pub struct DangerBonus {
+    pub active: bool,
+}
+
+pub struct ScoringPlugin;
+
+impl Plugin for ScoringPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<ScoreEvent>()
+            .init_resource::<DangerBonus>()
+            .init_resource::<Combo>()
+            .add_system(danger_bonus.system())
+            .add_system(near_misses.system())
+            .add_system(update_combo.system())
+            .add_system(combo_glow.system())
+            .add_system(apply_score_events.system());
+    }
+}
+
+fn apply_score_events(
+    mut reader: Local<EventReader<ScoreEvent>>,
+    score_events: Res<Events<ScoreEvent>>,
+    combo: Res<Combo>,
+    rules: Res<GameRules>,
+    zen_mode: Res<ZenMode>,
+    mut score: ResMut<Score>,
+    mut popups: ResMut<Events<Popup>>,
+    mut game_events: ResMut<Events<GameEvent>>,
+) {
+    for event in reader.iter(&score_events) {
+        // Zen mode keeps no score at all.
+        if zen_mode.0 {
+            continue;
+        }
+        let multiplier = match event.reason {
+            ScoreReason::Food | ScoreReason::EventFood => combo.multiplier,
+            ScoreReason::AllyExpired
+            | ScoreReason::Danger
+            | ScoreReason::Objective
+            | ScoreReason::Zone
+            | ScoreReason::Relay
+            | ScoreReason::WallBounce => 1,
+        };
+        let points = match event.pace {
+            Some(pace) if rules.speed_scoring => speed_points(event.points, pace),
+            _ => event.points,
+        } * multiplier;
+        let penalty = event.reason == ScoreReason::WallBounce;
+        if penalty {
+            score.0 = score.0.saturating_sub(points);
+        } else {
+            score.0 += points;
+        }
+        debug!(points, reason = ?event.reason, score = score.0, "points scored");
+        game_events.send(GameEvent::Scored {
+            points,
+            reason: event.reason,
+        });
+        if let Some(position) = event.position {
+            if penalty {
+                popups.send(Popup::new(format!("-{}", points), position));
+            } else if multiplier > 1 {
+                let text = format!("+{} (x{})", points, multiplier);
+                popups.send(Popup::score(
+                    text,
+                    points,
+                    multiplier,
+                    event.reason,
+                    position,
+                ));
+            } else {
+                let text = format!("+{}", points);
+                popups.send(Popup::score(text, points, 1, event.reason, position));
+            }
+        }
+    }
+}
+
+fn update_combo(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    state: Res<GameState>,
+    run: Res<Run>,
+    mut combo: ResMut<Combo>,
+) {
+    if combo.seed != run.seed {
+        *combo = Combo {
+            seed: run.seed,
+            ..Combo::default()
+        };
+    }
+    if *state != GameState::Playing || combo.multiplier == 1 {
+        return;
+    }
+    combo.timer.tick(time_scale.delta(&time));
+    if combo.timer.finished {
+        combo.multiplier = 1;
+    }
+}
+
+/// Brightens the snake with the combo multiplier, so a streak can be judged without looking at
+/// the HUD. Works from the palette's colors, so changing palette mid-combo keeps the glow.
+fn combo_glow(
+    combo: Res<Combo>,
+    settings: Res<AccessibilitySettings>,
+    head_material: Res<HeadMaterial>,
+    segment_material: Res<SegmentMaterial>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let amount = GLOW_PER_MULTIPLIER * (combo.multiplier - 1) as f32;
+    let colors = settings.palette.colors();
+    for (handle, color) in &[
+        (head_material.0, colors.head),
+        (segment_material.0, colors.segment),
+    ] {
+        let glowing = Color::rgba(
+            color.r + (1.0 - color.r) * amount,
+            color.g + (1.0 - color.g) * amount,
+            color.b + (1.0 - color.b) * amount,
+            color.a,
+        );
+        // Written only when it differs, so the material isn't re-sent every frame.
+        if materials
+            .get(handle)
+            .map_or(false, |material| material.color != glowing)
+        {
+            materials.get_mut(handle).unwrap().color = glowing;
+        }
+    }
+}
+
+/// Whether a head moving into `position` after `tick` moves would crash there, into the arena
+/// edge, a level wall, an obstacle or a closed door.
+fn is_hazard(
+    position: Position,
+    tick: u64,
+    level: &ActiveLevel,
+    switch_state: &SwitchState,
+) -> bool {
+    position.outside_arena()
+        || level.0.as_ref().map_or(false, |level| {
+            level.is_wall(position) || switch_state.is_blocked(level, position, tick)
+        })
+}
+
+/// Spots moves where carrying straight on would have been fatal but the player turned away
+/// at the last moment. Each near miss raises the combo multiplier.
+fn near_misses(
+    mut last_tick: Local<u64>,
+    state: Res<GameState>,
+    run: Res<Run>,
+    locale: Res<Locale>,
+    level: Res<ActiveLevel>,
+    switch_state: Res<SwitchState>,
+    mut combo: ResMut<Combo>,
+    mut popups: ResMut<Events<Popup>>,
+    mut announcements: ResMut<Events<Announcement>>,
+    mut heads: Query<Without<Ally, Without<Dead, &SnakeHead>>>,
+    segments: Query<(&SnakeSegment, &Position)>,
+) {
+    if *state != GameState::Playing || run.tick == *last_tick {
+        return;
+    }
+    *last_tick = run.tick;
+
+    for head in &mut heads.iter() {
+        // The first two segments are where the head was over the last two moves, which gives
+        // the direction it was going before this move.
+        let mut body = Vec::new();
+        let mut next = Some(head.next_segment);
+        while let Some(ent) = next {
+            match (
+                segments.get::<SnakeSegment>(ent),
+                segments.get::<Position>(ent),
+            ) {
+                (Ok(segment), Ok(position)) => {
+                    body.push(*position);
+                    next = segment.next_segment;
+                }
+                _ => break,
+            }
+        }
+        if body.len() < 2 {
+            continue;
+        }
+        let (turned_at, before) = (body[0], body[1]);
+        let previous = [
+            Direction::Left,
+            Direction::Up,
+            Direction::Right,
+            Direction::Down,
+        ]
+        .iter()
+        .copied()
+        .find(|&direction| before.moved(direction) == turned_at);
+        let previous = match previous {
+            Some(previous) if previous != head.direction => previous,
+            _ => continue,
+        };
+
+        let straight_on = turned_at.moved(previous);
+        if is_hazard(straight_on, run.tick, &level, &switch_state) || body.contains(&straight_on) {
+            combo.extend();
+            let text = locale.text("close-call");
+            popups.send(Popup::new(text.clone(), turned_at));
+            announcements.send(Announcement::verbose(text));
+        }
+    }
+}
+
+/// Rewards risky play: every move that ends with a head right next to a wall, an obstacle or
+/// a closed door earns a little.
+fn danger_bonus(
+    mut last_tick: Local<u64>,
+    state: Res<GameState>,
+    run: Res<Run>,
+    level: Res<ActiveLevel>,
+    switch_state: Res<SwitchState>,
+    mut bonus: ResMut<DangerBonus>,
+    mut score_events: ResMut<Events<ScoreEvent>>,
+    mut heads: Query<Without<Ally, Without<Dead, (&SnakeHead, &Position)>>>,
+) {
+    if *state != GameState::Playing || run.tick == *last_tick {
+        return;
+    }
+    *last_tick = run.tick;
+
+    bonus.active = false;
+    for (_head, position) in &mut heads.iter() {
+        let near_wall = [
+            Direction::Left,
+            Direction::Up,
+            Direction::Right,
+            Direction::Down,
+        ]
+        .iter()
+        .any(|&direction| is_hazard(position.moved(direction), run.tick, &level, &switch_state));
+        if near_wall {
+            bonus.active = true;
+            score_events.send(ScoreEvent::new(DANGER_POINTS, ScoreReason::Danger));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pace(moves_per_second: f32, length: usize) -> Pace {
+        Pace {
+            moves_per_second,
+            length,
+        }
+    }
+
+    #[test]
+    fn reference_pace_is_worth_the_usual_points() {
+        assert_eq!(speed_points(10, pace(REFERENCE_MOVES_PER_SECOND, 0)), 10);
+        assert_eq!(speed_points(10, pace(REFERENCE_MOVES_PER_SECOND, 19)), 10);
+    }
+
+    #[test]
+    fn every_whole_step_of_length_adds_the_points_again() {
+        assert_eq!(speed_points(10, pace(REFERENCE_MOVES_PER_SECOND, 20)), 20);
+        assert_eq!(speed_points(10, pace(REFERENCE_MOVES_PER_SECOND, 39)), 20);
+        assert_eq!(speed_points(10, pace(REFERENCE_MOVES_PER_SECOND, 40)), 30);
+    }
+
+    #[test]
+    fn standing_still_is_still_worth_a_point() {
+        assert_eq!(speed_points(10, pace(0.0, 40)), 1);
+        assert_eq!(speed_points(10, pace(-5.0, 40)), 1);
+    }
+
+    #[test]
+    fn points_are_rounded() {
+        let reference = REFERENCE_MOVES_PER_SECOND;
+        assert_eq!(speed_points(10, pace(reference * 1.26, 0)), 13);
+        assert_eq!(speed_points(10, pace(reference * 1.24, 0)), 12);
+        assert_eq!(speed_points(10, pace(reference * 2.0, 20)), 40);
+    }
+}
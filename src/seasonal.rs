@@ -0,0 +1,350 @@
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use bevy::prelude::*;
+use rand::Rng;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    accessibility::Announcement,
+    game_event::GameEvent,
+    locale::Locale,
+    mods::Mods,
+    profile::ActiveProfile,
+    scoring::{ScoreEvent, ScoreReason},
+    sized_sprite,
+    time_scale::TimeScale,
+    toast::Toast,
+    GameState, Position, Run, Size, SnakeHead, ARENA_HEIGHT, ARENA_WIDTH,
+};
+
+const MANIFEST_PATH: &str = "assets/events.ron";
+
+/// A mod's events, with their food.
+const MOD_MANIFEST: &str = "events.ron";
+
+const LEADERBOARDS_DIR: &str = "leaderboards";
+
+const LEADERBOARD_SIZE: usize = 5;
+
+/// How often, in seconds, event food is spawned if there is none in the arena.
+const SPAWN_INTERVAL: f32 = 8.0;
+
+/// A recurring event from the events manifest, active every year between two dates.
+#[derive(Clone, Deserialize)]
+struct SeasonalEvent {
+    id: String,
+    /// Display names keyed by language tag.
+    names: HashMap<String, String>,
+    /// Inclusive `(month, day)` dates. An end before the start wraps around the new year.
+    start: (u32, u32),
+    end: (u32, u32),
+    /// Texture of the event food, tinted by `color`.
+    texture: String,
+    color: (f32, f32, f32),
+    points: u32,
+}
+
+impl SeasonalEvent {
+    fn is_active_on(&self, date: (u32, u32)) -> bool {
+        if self.start <= self.end {
+            self.start <= date && date <= self.end
+        } else {
+            date >= self.start || date <= self.end
+        }
+    }
+
+    fn name(&self, locale: &Locale) -> String {
+        locale
+            .translate(&self.names)
+            .unwrap_or(&self.id)
+            .to_string()
+    }
+
+    fn leaderboard_path(&self) -> PathBuf {
+        PathBuf::from(LEADERBOARDS_DIR).join(format!("{}.ron", self.id))
+    }
+}
+
+/// Today's `(month, day)` in UTC.
+fn today() -> (u32, u32) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since_epoch| since_epoch.as_secs());
+    let (_year, month, day) = civil_date(now);
+    (month, day)
+}
+
+/// The UTC `(year, month, day)` of a Unix timestamp in seconds.
+pub fn civil_date(timestamp: u64) -> (i64, u32, u32) {
+    let days = (timestamp / 86_400) as i64;
+    // Converts days since 1970-01-01 to a civil date, following
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month as u32, day as u32)
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct LeaderboardEntry {
+    name: String,
+    collected: u32,
+}
+
+/// Best runs of an event, most collected first.
+#[derive(Default, Serialize, Deserialize)]
+struct Leaderboard {
+    entries: Vec<LeaderboardEntry>,
+}
+
+impl Leaderboard {
+    fn load(event: &SeasonalEvent) -> Self {
+        let path = event.leaderboard_path();
+        match fs::read_to_string(&path) {
+            Ok(text) => ron::de::from_str(&text).unwrap_or_else(|err| {
+                warn!(
+                    "resetting unreadable leaderboard {}: {}",
+                    path.display(),
+                    err
+                );
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    fn save(&self, event: &SeasonalEvent) {
+        let path = event.leaderboard_path();
+        let result = ron::ser::to_string_pretty(self, PrettyConfig::default())
+            .map_err(|err| err.to_string())
+            .and_then(|text| {
+                fs::create_dir_all(LEADERBOARDS_DIR)
+                    .and_then(|_| fs::write(&path, text))
+                    .map_err(|err| err.to_string())
+            });
+        if let Err(err) = result {
+            warn!("could not save leaderboard {}: {}", path.display(), err);
+        }
+    }
+
+    /// Adds a run if it makes the board.
+    fn record(&mut self, name: String, collected: u32) {
+        self.entries.push(LeaderboardEntry { name, collected });
+        self.entries.sort_by_key(|entry| Reverse(entry.collected));
+        self.entries.truncate(LEADERBOARD_SIZE);
+    }
+
+    fn best(&self) -> u32 {
+        self.entries.first().map_or(0, |entry| entry.collected)
+    }
+}
+
+/// The event running today and how the current run is doing in it.
+struct ActiveEvent {
+    event: SeasonalEvent,
+    material: Handle<ColorMaterial>,
+    leaderboard: Leaderboard,
+    spawn_timer: Timer,
+    collected: u32,
+    seed: u64,
+}
+
+#[derive(Default)]
+struct CurrentEvent(Option<ActiveEvent>);
+
+struct EventFood;
+
+pub struct SeasonalPlugin;
+
+impl Plugin for SeasonalPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<CurrentEvent>()
+            .add_startup_system(start_event.system())
+            .add_system(event_food_spawner.system())
+            .add_system(event_food_pickup.system())
+            .add_system(record_event_run.system());
+    }
+}
+
+/// The game's events, and any a mod adds or replaces by id.
+fn load_manifest(mods: &mut Mods) -> Vec<SeasonalEvent> {
+    let mut events: Vec<SeasonalEvent> = match fs::read_to_string(MANIFEST_PATH) {
+        Ok(text) => ron::de::from_str(&text).unwrap_or_else(|err| {
+            warn!("ignoring {}: {}", MANIFEST_PATH, err);
+            Vec::new()
+        }),
+        Err(_) => Vec::new(),
+    };
+    mods.merge(MOD_MANIFEST, &mut events, |event| event.id.as_str());
+    events
+}
+
+fn start_event(
+    mut current: ResMut<CurrentEvent>,
+    asset_server: Res<AssetServer>,
+    locale: Res<Locale>,
+    run: Res<Run>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut toasts: ResMut<Events<Toast>>,
+    mut mods: ResMut<Mods>,
+) {
+    let date = today();
+    let event = match load_manifest(&mut mods)
+        .into_iter()
+        .find(|event| event.is_active_on(date))
+    {
+        Some(event) => event,
+        None => return,
+    };
+    let texture = match asset_server.load(event.texture.as_str()) {
+        Ok(texture) => texture,
+        Err(err) => {
+            warn!("skipping event {}: {:?}", event.id, err);
+            return;
+        }
+    };
+    let (r, g, b) = event.color;
+    let material = materials.add(ColorMaterial::modulated_texture(
+        texture,
+        Color::rgb(r, g, b),
+    ));
+    info!(event = event.id.as_str(), "seasonal event active");
+    toasts.send(Toast::new(
+        locale.format("event-active", &[("event", event.name(&locale))]),
+    ));
+    current.0 = Some(ActiveEvent {
+        leaderboard: Leaderboard::load(&event),
+        event,
+        material,
+        spawn_timer: Timer::from_seconds(SPAWN_INTERVAL, true),
+        collected: 0,
+        seed: run.seed,
+    });
+}
+
+fn event_food_spawner(
+    mut commands: Commands,
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    state: Res<GameState>,
+    mut current: ResMut<CurrentEvent>,
+    mut run: ResMut<Run>,
+    mut event_food: Query<&EventFood>,
+) {
+    let active = match &mut current.0 {
+        Some(active) => active,
+        None => return,
+    };
+    if *state != GameState::Playing {
+        return;
+    }
+    active.spawn_timer.tick(time_scale.delta(&time));
+    if !active.spawn_timer.finished || event_food.iter().iter().next().is_some() {
+        return;
+    }
+    let position = Position {
+        x: run.rng.gen_range(0, ARENA_WIDTH as i32),
+        y: run.rng.gen_range(0, ARENA_HEIGHT as i32),
+    };
+    commands
+        .spawn(sized_sprite(active.material))
+        .with(EventFood)
+        .with(position)
+        .with(Size::square(0.8));
+}
+
+fn event_food_pickup(
+    mut commands: Commands,
+    locale: Res<Locale>,
+    mut current: ResMut<CurrentEvent>,
+    run: Res<Run>,
+    mut score_events: ResMut<Events<ScoreEvent>>,
+    mut announcements: ResMut<Events<Announcement>>,
+    mut heads: Query<(&SnakeHead, &Position)>,
+    mut event_food: Query<(Entity, &EventFood, &Position)>,
+) {
+    let active = match &mut current.0 {
+        Some(active) => active,
+        None => return,
+    };
+    if active.seed != run.seed {
+        active.seed = run.seed;
+        active.collected = 0;
+    }
+    for (_head, head_pos) in &mut heads.iter() {
+        for (ent, _food, pos) in &mut event_food.iter() {
+            if pos != head_pos {
+                continue;
+            }
+            commands.despawn(ent);
+            active.collected += 1;
+            score_events
+                .send(ScoreEvent::new(active.event.points, ScoreReason::EventFood).at(*pos));
+            announcements.send(Announcement::verbose(locale.format(
+                "event-collected",
+                &[
+                    ("event", active.event.name(&locale)),
+                    ("count", active.collected.to_string()),
+                ],
+            )));
+        }
+    }
+}
+
+/// Adds the finished run to the event leaderboard.
+fn record_event_run(
+    mut reader: Local<EventReader<GameEvent>>,
+    game_events: Res<Events<GameEvent>>,
+    locale: Res<Locale>,
+    profile: Res<ActiveProfile>,
+    mut current: ResMut<CurrentEvent>,
+    mut toasts: ResMut<Events<Toast>>,
+) {
+    let active = match &mut current.0 {
+        Some(active) => active,
+        None => return,
+    };
+    if reader
+        .iter(&game_events)
+        .find_map(GameEvent::game_over)
+        .is_none()
+        || active.collected == 0
+    {
+        return;
+    }
+    let name = profile
+        .0
+        .as_ref()
+        .map_or_else(String::new, |profile| profile.name.clone());
+    let collected = active.collected;
+    active.collected = 0;
+    active.leaderboard.record(name, collected);
+    active.leaderboard.save(&active.event);
+    toasts.send(Toast::new(locale.format(
+        "event-result",
+        &[
+            ("event", active.event.name(&locale)),
+            ("count", collected.to_string()),
+            ("best", active.leaderboard.best().to_string()),
+        ],
+    )));
+}
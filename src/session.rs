@@ -0,0 +1,204 @@
+use std::{thread, time::Duration};
+
+use bevy::{app::AppExit, prelude::*};
+use tracing::{info, warn};
+
+use crate::{
+    game_event::GameEvent,
+    locale::Locale,
+    net::post,
+    profile::ActiveProfile,
+    tasks::{Task, TaskPool},
+    Run, Score,
+};
+
+/// How long closing the game waits for the last summary to go out, in steps of
+/// `EXIT_WAIT_STEP`. Closing is how most sessions end, so it's worth a short hold.
+const EXIT_WAIT_STEPS: u32 = 30;
+
+const EXIT_WAIT_STEP: Duration = Duration::from_millis(50);
+
+/// Where the summary of each session is posted, set from `config.ron`. `None` sends nothing.
+#[derive(Default)]
+pub struct SessionWebhook(pub Option<String>);
+
+/// What's been played since the game started or the profile last changed.
+#[derive(Clone, Default)]
+struct Session {
+    player: Option<String>,
+    runs: u32,
+    best_score: u32,
+    /// Seconds of play, not counting pauses.
+    seconds_played: f32,
+}
+
+impl Session {
+    fn record(&mut self, run: &Run, score: &Score) {
+        self.runs += 1;
+        self.best_score = self.best_score.max(score.0);
+        self.seconds_played += run.elapsed;
+    }
+
+    /// The summary as JSON. Chat webhooks post `content` or `text`, whichever they read, and
+    /// anything else can use the numbers.
+    fn to_json(&self, locale: &Locale) -> String {
+        let minutes = (self.seconds_played / 60.0).round() as u32;
+        let player = self
+            .player
+            .clone()
+            .unwrap_or_else(|| locale.text("session-anonymous"));
+        let message = locale.format(
+            "session-summary",
+            &[
+                ("player", player.clone()),
+                ("runs", self.runs.to_string()),
+                ("best", self.best_score.to_string()),
+                ("minutes", minutes.to_string()),
+            ],
+        );
+        format!(
+            "{{\"content\":{message},\"text\":{message},\"player\":{},\"runs\":{},\
+             \"best_score\":{},\"seconds_played\":{:.0}}}",
+            json_string(&player),
+            self.runs,
+            self.best_score,
+            self.seconds_played,
+            message = json_string(&message),
+        )
+    }
+}
+
+/// `text` as a quoted JSON string.
+fn json_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// The session being played, and the summaries still being sent.
+#[derive(Default)]
+struct SessionState {
+    session: Session,
+    sending: Vec<Task<Result<(), String>>>,
+}
+
+impl SessionState {
+    /// Sends the session so far, if anything was played, and starts a new one for `player`.
+    fn finish(
+        &mut self,
+        webhook: &SessionWebhook,
+        locale: &Locale,
+        tasks: &TaskPool,
+        player: Option<String>,
+    ) {
+        let session = std::mem::replace(
+            &mut self.session,
+            Session {
+                player,
+                ..Session::default()
+            },
+        );
+        let url = match &webhook.0 {
+            Some(url) if session.runs > 0 => url.clone(),
+            _ => return,
+        };
+        let body = session.to_json(locale);
+        self.sending.push(tasks.spawn("session summary", move || {
+            post(&url, "application/json", &body)
+        }));
+    }
+
+    /// Drops the summaries that have gone out, logging how they went.
+    fn poll_sending(&mut self) {
+        self.sending.retain(|task| {
+            let result = match task.poll() {
+                Some(Ok(result)) => result,
+                Some(Err(_lost)) => Err("sending stopped".to_string()),
+                None => return true,
+            };
+            match result {
+                Ok(()) => info!("session summary sent"),
+                Err(err) => warn!("could not send the session summary: {}", err),
+            }
+            false
+        });
+    }
+}
+
+pub struct SessionPlugin;
+
+impl Plugin for SessionPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<SessionWebhook>()
+            .init_resource::<SessionState>()
+            .add_system(track_session.system())
+            // Last, so it sees the exit whichever system asked for it this frame.
+            .add_system_to_stage(stage::LAST, send_on_exit.system());
+    }
+}
+
+/// Adds up each finished run, and sends the session when the player switches profiles.
+fn track_session(
+    mut reader: Local<EventReader<GameEvent>>,
+    game_events: Res<Events<GameEvent>>,
+    webhook: Res<SessionWebhook>,
+    locale: Res<Locale>,
+    tasks: Res<TaskPool>,
+    profile: Res<ActiveProfile>,
+    run: Res<Run>,
+    score: Res<Score>,
+    mut state: ResMut<SessionState>,
+) {
+    state.poll_sending();
+    let player = profile.0.as_ref().map(|profile| profile.name.clone());
+    if player != state.session.player {
+        state.finish(&webhook, &locale, &tasks, player);
+    }
+    if reader
+        .iter(&game_events)
+        .find_map(GameEvent::game_over)
+        .is_some()
+    {
+        state.session.record(&run, &score);
+    }
+}
+
+/// Sends the session when the game closes, counting the run left unfinished, and holds the
+/// close up for at most a second and a half so the summary can get out.
+fn send_on_exit(
+    mut reader: Local<EventReader<AppExit>>,
+    exits: Res<Events<AppExit>>,
+    webhook: Res<SessionWebhook>,
+    locale: Res<Locale>,
+    tasks: Res<TaskPool>,
+    run: Res<Run>,
+    score: Res<Score>,
+    mut state: ResMut<SessionState>,
+) {
+    if reader.iter(&exits).next().is_none() {
+        return;
+    }
+    if run.tick > 0 {
+        state.session.record(&run, &score);
+    }
+    let player = state.session.player.clone();
+    state.finish(&webhook, &locale, &tasks, player);
+    for _ in 0..EXIT_WAIT_STEPS {
+        state.poll_sending();
+        if state.sending.is_empty() {
+            return;
+        }
+        thread::sleep(EXIT_WAIT_STEP);
+    }
+    warn!("closing without the session summary");
+}
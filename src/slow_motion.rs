@@ -0,0 +1,99 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::{
+    danger::Lookahead,
+    time_scale::{TimeScale, TimeSource},
+    GameState,
+};
+
+/// How hard and how long the game slows down when the snake is about to crash.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+pub struct SlowMotion {
+    /// Game speed while slowed, e.g. 0.25 for a quarter speed.
+    pub scale: f32,
+    /// How long the slowdown lasts, in real seconds.
+    pub seconds: f32,
+}
+
+impl Default for SlowMotion {
+    fn default() -> Self {
+        Self {
+            scale: 0.25,
+            seconds: 0.4,
+        }
+    }
+}
+
+impl SlowMotion {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.scale <= 0.0 || self.scale > 1.0 {
+            return Err(format!(
+                "slow_motion.scale must be above 0 and at most 1, got {}",
+                self.scale
+            ));
+        }
+        if self.seconds < 0.0 || self.seconds > 2.0 {
+            return Err(format!(
+                "slow_motion.seconds must be between 0 and 2, got {}",
+                self.seconds
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// The slowdown in use, or `None` when it's turned off.
+pub struct SlowMotionSettings(pub Option<SlowMotion>);
+
+impl Default for SlowMotionSettings {
+    fn default() -> Self {
+        Self(Some(SlowMotion::default()))
+    }
+}
+
+pub struct SlowMotionPlugin;
+
+impl Plugin for SlowMotionPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<SlowMotionSettings>()
+            .add_system(last_chance_slowdown.system());
+    }
+}
+
+/// Slows the game down for a moment when a head's next move becomes fatal, giving the player
+/// a last chance to react. It triggers once per danger, so heading into a wall doesn't keep
+/// the game slowed.
+fn last_chance_slowdown(
+    mut was_in_danger: Local<bool>,
+    mut remaining: Local<f32>,
+    time: Res<Time>,
+    state: Res<GameState>,
+    settings: Res<SlowMotionSettings>,
+    lookahead: Res<Lookahead>,
+    mut time_scale: ResMut<TimeScale>,
+) {
+    let in_danger = *state == GameState::Playing && !lookahead.fatal_cells.is_empty();
+    let slow_motion = match settings.0 {
+        Some(slow_motion) => slow_motion,
+        None => {
+            *remaining = 0.0;
+            time_scale.clear(TimeSource::LastChance);
+            return;
+        }
+    };
+    if in_danger && !*was_in_danger {
+        debug!(cells = ?lookahead.fatal_cells, "slowing down before a crash");
+        *remaining = slow_motion.seconds;
+    }
+    *was_in_danger = in_danger;
+
+    // Counted in real time, since game time is what's being slowed.
+    *remaining = (*remaining - time.delta_seconds).max(0.0);
+    if *remaining > 0.0 {
+        time_scale.set(TimeSource::LastChance, slow_motion.scale);
+    } else {
+        time_scale.clear(TimeSource::LastChance);
+    }
+}
@@ -0,0 +1,59 @@
+use bevy::prelude::*;
+
+use crate::{
+    effects::{ActiveEffect, EffectKind, Effects},
+    game_event::GameEvent,
+    rules::GameRules,
+    Run,
+};
+
+/// Food a run takes to get up to full speed.
+const RAMP_FOOD: u32 = 5;
+
+/// How fast a run starts, as a share of full speed.
+const START_SPEED: f32 = 0.6;
+
+/// Food eaten so far in the run with the given seed.
+#[derive(Default)]
+struct Ramp {
+    seed: u64,
+    eaten: u32,
+}
+
+pub struct SlowStartPlugin;
+
+impl Plugin for SlowStartPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(slow_start.system());
+    }
+}
+
+/// With the `slow_start` rule, runs start at `START_SPEED` and each of the first `RAMP_FOOD`
+/// food the player eats, not counting the allies', brings them closer to the usual speed. It's
+/// an effect like mud, so it combines with tiles and anything else that changes the speed.
+fn slow_start(
+    mut ramp: Local<Ramp>,
+    mut reader: Local<EventReader<GameEvent>>,
+    game_events: Res<Events<GameEvent>>,
+    rules: Res<GameRules>,
+    run: Res<Run>,
+    mut effects: ResMut<Effects>,
+) {
+    if ramp.seed != run.seed {
+        *ramp = Ramp {
+            seed: run.seed,
+            eaten: 0,
+        };
+    }
+    for event in reader.iter(&game_events) {
+        if let GameEvent::FoodEaten { by_ally: false, .. } = event {
+            ramp.eaten += 1;
+        }
+    }
+    if !rules.slow_start || ramp.eaten >= RAMP_FOOD {
+        return;
+    }
+    let speed = START_SPEED + (1.0 - START_SPEED) * ramp.eaten as f32 / RAMP_FOOD as f32;
+    // Only lasts the frame, so it's gone as soon as the rule's turned off.
+    effects.add(ActiveEffect::new(EffectKind::SlowStart, 0.0, 1.0 / speed));
+}
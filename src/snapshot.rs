@@ -0,0 +1,98 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    spawn_food, spawn_snake, split::Ally, Direction, Food, FoodMaterial, HeadMaterial, Position,
+    Run, Score, SegmentMaterial, SnakeHead, SnakeSegment,
+};
+
+/// Everything needed to put a run back on screen. The RNG state can't be captured, so a
+/// restored run keeps its seed but its food placement diverges from the original.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub seed: u64,
+    pub tick: u64,
+    pub score: u32,
+    pub direction: Direction,
+    pub head: Position,
+    /// From just behind the head to the tail.
+    pub segments: Vec<Position>,
+    /// Segments eaten but not grown yet. Missing from older snapshots.
+    #[serde(default)]
+    pub pending_growth: u32,
+    pub food: Vec<Position>,
+}
+
+impl Snapshot {
+    /// Captures the current run, or returns `None` if there is no complete snake to capture,
+    /// e.g. while the arena is being reset.
+    pub fn capture(
+        run: &Run,
+        score: &Score,
+        heads: &mut Query<Without<Ally, (&SnakeHead, &Position)>>,
+        segments: &Query<(&SnakeSegment, &Position)>,
+        food: &mut Query<(&Food, &Position)>,
+    ) -> Option<Self> {
+        let (direction, first_segment, head, pending_growth) = {
+            let mut heads = heads.iter();
+            let (head, head_pos) = (&mut heads).into_iter().next()?;
+            (
+                head.direction,
+                head.next_segment,
+                *head_pos,
+                head.pending_growth,
+            )
+        };
+
+        let mut segment_positions = Vec::new();
+        let mut next = Some(first_segment);
+        while let Some(ent) = next {
+            next = segments.get::<SnakeSegment>(ent).ok()?.next_segment;
+            segment_positions.push(*segments.get::<Position>(ent).ok()?);
+        }
+
+        let mut food_positions = Vec::new();
+        for (_food, position) in &mut food.iter() {
+            food_positions.push(*position);
+        }
+
+        Some(Self {
+            seed: run.seed,
+            tick: run.tick,
+            score: score.0,
+            direction,
+            head,
+            segments: segment_positions,
+            pending_growth,
+            food: food_positions,
+        })
+    }
+
+    /// Spawns the snapshot's snake and food and rewinds the run to it. The arena should be
+    /// empty beforehand.
+    pub fn restore(
+        &self,
+        commands: &mut Commands,
+        head_material: &HeadMaterial,
+        segment_material: &SegmentMaterial,
+        food_material: &FoodMaterial,
+        run: &mut Run,
+        score: &mut Score,
+    ) {
+        spawn_snake(
+            commands,
+            head_material.0,
+            segment_material.0,
+            self.head,
+            self.direction,
+            &self.segments,
+            self.pending_growth,
+        );
+        for &position in &self.food {
+            spawn_food(commands, food_material.0, position);
+        }
+        *run = Run::with_seed(self.seed);
+        run.tick = self.tick;
+        score.0 = self.score;
+    }
+}
@@ -0,0 +1,302 @@
+use std::collections::{HashSet, VecDeque};
+
+use bevy::prelude::*;
+use serde::Deserialize;
+use tracing::info;
+
+use crate::{
+    accessibility::Announcement,
+    body::BodyChange,
+    controller::{ControlFrame, Controlled, Controller, InputQueue},
+    game_event::GameEvent,
+    locale::Locale,
+    power_up::PowerUpKind,
+    scoring::{ScoreEvent, ScoreReason},
+    snake_length, spawn_snake,
+    time_scale::TimeScale,
+    toast::Toast,
+    Dead, Direction, GameState, Position, Run, SegmentMaterial, SnakeConfig, SnakeHead,
+    SnakeSegment, ARENA_HEIGHT, ARENA_WIDTH,
+};
+
+/// How long an ally sticks around, in seconds.
+const ALLY_DURATION: f32 = 15.0;
+
+/// Points awarded for every part of an ally when it expires.
+const ALLY_POINTS_PER_SEGMENT: u32 = 5;
+
+/// How many cells the open space check looks at before calling a move safe.
+const SPACE_LOOKAHEAD: usize = 24;
+
+/// How many cells ahead of the player's head a blocker aims for.
+const BLOCK_LEAD: i32 = 3;
+
+/// How computer-controlled snakes weigh up their moves.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Deserialize)]
+pub enum Personality {
+    /// Heads straight for the closest food.
+    Greedy,
+    /// Goes for food but won't move into a space it could get trapped in.
+    Defensive,
+    /// Tries to cut across the path of the player's head.
+    Blocker,
+}
+
+impl Default for Personality {
+    fn default() -> Self {
+        Self::Greedy
+    }
+}
+
+/// Heuristic weights behind a personality. A move's score is the weighted sum of its
+/// distances and open space, and the highest scoring move wins.
+struct Weights {
+    food: i32,
+    space: i32,
+    block: i32,
+}
+
+impl Personality {
+    fn weights(self) -> Weights {
+        match self {
+            Self::Greedy => Weights {
+                food: 4,
+                space: 0,
+                block: 0,
+            },
+            Self::Defensive => Weights {
+                food: 2,
+                space: 3,
+                block: 0,
+            },
+            Self::Blocker => Weights {
+                food: 1,
+                space: 1,
+                block: 4,
+            },
+        }
+    }
+}
+
+/// A computer-controlled snake split off from the player's. It collects food for the player
+/// until its timer runs out.
+pub struct Ally {
+    timer: Timer,
+}
+
+pub struct SplitPlugin;
+
+impl Plugin for SplitPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(expire_allies.system());
+    }
+}
+
+/// Cuts a snake in half, turning the rear half into an ally heading away from the cut and
+/// steered with `personality`. Snakes shorter than three segments can't be split and are left
+/// alone. Returns whether the snake was split.
+pub fn split_snake(
+    commands: &mut Commands,
+    body_changes: &mut Events<BodyChange>,
+    segment_material: &SegmentMaterial,
+    personality: Personality,
+    first_segment: Entity,
+    segments: &Query<(&SnakeSegment, &Position)>,
+) -> bool {
+    let mut chain = Vec::new();
+    let mut next = Some(first_segment);
+    while let Some(ent) = next {
+        match (
+            segments.get::<SnakeSegment>(ent),
+            segments.get::<Position>(ent),
+        ) {
+            (Ok(segment), Ok(position)) => {
+                chain.push((ent, *position));
+                next = segment.next_segment;
+            }
+            _ => return false,
+        }
+    }
+    if chain.len() < 3 {
+        return false;
+    }
+
+    let (front, rear) = chain.split_at(chain.len() / 2);
+    body_changes.send(BodyChange::CutBehind {
+        new_tail: front[front.len() - 1].0,
+        first_cut: rear[0].0,
+    });
+
+    // The old tail becomes the ally's head, so it moves off away from the player.
+    let (&(_, head), body) = rear.split_last().unwrap();
+    let behind = body[body.len() - 1].1;
+    let direction = match (head.x - behind.x, head.y - behind.y) {
+        (-1, 0) => Direction::Left,
+        (1, 0) => Direction::Right,
+        (0, -1) => Direction::Down,
+        _ => Direction::Up,
+    };
+    let body: Vec<Position> = body
+        .iter()
+        .rev()
+        .map(|&(_ent, position)| position)
+        .collect();
+    // Allies are drawn entirely in the segment color so they can't be mistaken for the player.
+    let ally = spawn_snake(
+        commands,
+        segment_material.0,
+        segment_material.0,
+        head,
+        direction,
+        &body,
+        0,
+    );
+    commands.insert_one(
+        ally,
+        Ally {
+            timer: Timer::from_seconds(ALLY_DURATION, false),
+        },
+    );
+    commands.insert_one(
+        ally,
+        Controlled(Box::new(AllyController {
+            weights: personality.weights(),
+        })),
+    );
+    commands.insert_one(ally, SnakeConfig::computer());
+    true
+}
+
+fn distance(a: Position, b: Position) -> i32 {
+    (a.x - b.x).abs() + (a.y - b.y).abs()
+}
+
+fn in_arena(position: Position) -> bool {
+    position.x >= 0
+        && position.y >= 0
+        && (position.x as u32) < ARENA_WIDTH
+        && (position.y as u32) < ARENA_HEIGHT
+}
+
+/// Counts the free cells reachable from `start`, up to `SPACE_LOOKAHEAD`.
+fn open_space(start: Position, blocked: &HashSet<Position>) -> usize {
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::new();
+    seen.insert(start);
+    queue.push_back(start);
+    while let Some(cell) = queue.pop_front() {
+        if seen.len() >= SPACE_LOOKAHEAD {
+            break;
+        }
+        for &direction in &[
+            Direction::Left,
+            Direction::Up,
+            Direction::Right,
+            Direction::Down,
+        ] {
+            let next = cell.moved(direction);
+            if in_arena(next) && !blocked.contains(&next) && seen.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+    seen.len()
+}
+
+/// Steers an ally by scoring the three moves open to it with the weights of its personality.
+/// Moves into a wall or a snake are never picked while there's another option.
+struct AllyController {
+    weights: Weights,
+}
+
+impl Controller for AllyController {
+    fn steer(&mut self, frame: &ControlFrame, queue: &mut InputQueue) {
+        let weights = &self.weights;
+        let head_pos = frame.head;
+        let target = frame
+            .food
+            .iter()
+            .copied()
+            .min_by_key(|&pos| distance(pos, head_pos));
+        // Aim a few cells ahead of the closest player head, where it's about to be.
+        let block_target = frame
+            .players
+            .iter()
+            .min_by_key(|(_dir, pos)| distance(*pos, head_pos))
+            .map(|&(direction, pos)| {
+                let mut ahead = pos;
+                for _ in 0..BLOCK_LEAD {
+                    ahead = ahead.moved(direction);
+                }
+                ahead
+            });
+
+        let mut best = None;
+        for &direction in &[
+            frame.direction,
+            frame.direction.clockwise(),
+            frame.direction.clockwise().opposite(),
+        ] {
+            let next = head_pos.moved(direction);
+            if !in_arena(next) || frame.blocked.contains(&next) {
+                continue;
+            }
+            let mut score = 0;
+            if let Some(target) = target {
+                score -= weights.food * distance(target, next);
+            }
+            if let Some(block_target) = block_target {
+                score -= weights.block * distance(block_target, next);
+            }
+            if weights.space > 0 {
+                score += weights.space * open_space(next, frame.blocked) as i32;
+            }
+            if best.map_or(true, |(_dir, best_score)| score > best_score) {
+                best = Some((direction, score));
+            }
+        }
+        if let Some((direction, _score)) = best {
+            queue.push(direction);
+        }
+    }
+}
+
+/// Despawns allies whose time is up and pays out their bonus.
+fn expire_allies(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    state: Res<GameState>,
+    locale: Res<Locale>,
+    run: Res<Run>,
+    mut score_events: ResMut<Events<ScoreEvent>>,
+    mut announcements: ResMut<Events<Announcement>>,
+    mut toasts: ResMut<Events<Toast>>,
+    mut game_events: ResMut<Events<GameEvent>>,
+    mut body_changes: ResMut<Events<BodyChange>>,
+    mut allies: Query<Without<Dead, (Entity, &mut Ally, &SnakeHead)>>,
+    segments: Query<&SnakeSegment>,
+) {
+    if *state != GameState::Playing {
+        return;
+    }
+    for (ent, mut ally, head) in &mut allies.iter() {
+        ally.timer.tick(time_scale.delta(&time));
+        if !ally.timer.finished {
+            continue;
+        }
+
+        let length = snake_length(head.next_segment, &segments);
+        let points = length * ALLY_POINTS_PER_SEGMENT;
+        score_events.send(ScoreEvent::new(points, ScoreReason::AllyExpired));
+        run.span()
+            .in_scope(|| info!(length, points, "ally expired"));
+        body_changes.send(BodyChange::Remove { head: ent });
+        game_events.send(GameEvent::PowerUpExpired {
+            kind: PowerUpKind::Split,
+        });
+
+        let text = locale.format("ally-expired", &[("points", points.to_string())]);
+        announcements.send(Announcement::essential(text.clone()));
+        toasts.send(Toast::new(text));
+    }
+}
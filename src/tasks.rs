@@ -0,0 +1,124 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::{mpsc::Sender, Arc};
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        mpsc::{self, Receiver, TryRecvError},
+        Mutex,
+    },
+};
+
+use bevy::prelude::*;
+use tracing::warn;
+
+/// Threads kept for background work. Few tasks run at once, and most of them wait on the disk
+/// or the network.
+#[cfg(not(target_arch = "wasm32"))]
+const WORKERS: usize = 2;
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Runs work too slow for a frame, like solving puzzles, saving replays and downloads, on
+/// worker threads. Browsers have no threads, so there it runs straight away instead.
+pub struct TaskPool {
+    #[cfg(not(target_arch = "wasm32"))]
+    jobs: Mutex<Sender<Job>>,
+}
+
+impl Default for TaskPool {
+    #[cfg(not(target_arch = "wasm32"))]
+    fn default() -> Self {
+        let (jobs, queue) = mpsc::channel::<Job>();
+        let queue = Arc::new(Mutex::new(queue));
+        for index in 0..WORKERS {
+            let queue = Arc::clone(&queue);
+            let spawned = std::thread::Builder::new()
+                .name(format!("worker-{}", index))
+                .spawn(move || loop {
+                    // Holding the lock only while waiting lets the other workers take the next
+                    // job as soon as this one starts. Workers stop once the pool is dropped.
+                    let job = match queue.lock().unwrap().recv() {
+                        Ok(job) => job,
+                        Err(_) => return,
+                    };
+                    job();
+                });
+            if let Err(err) = spawned {
+                warn!("could not start worker thread: {}", err);
+            }
+        }
+        Self {
+            jobs: Mutex::new(jobs),
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn default() -> Self {
+        Self {}
+    }
+}
+
+impl TaskPool {
+    /// Starts `work` in the background. `name` says what it was in the log if it panics.
+    pub fn spawn<T, F>(&self, name: &'static str, work: F) -> Task<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        let job: Job = Box::new(move || {
+            // A panic only loses this task's result, and the worker carries on.
+            match panic::catch_unwind(AssertUnwindSafe(work)) {
+                // Whoever asked may have stopped waiting, which is fine.
+                Ok(result) => {
+                    let _ = sender.send(result);
+                }
+                Err(_) => warn!("background task {} panicked", name),
+            }
+        });
+        self.run(job);
+        Task {
+            receiver: Mutex::new(receiver),
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn run(&self, job: Job) {
+        // With no workers left the job is dropped, and its task reports it was lost.
+        let _ = self.jobs.lock().unwrap().send(job);
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn run(&self, job: Job) {
+        job();
+    }
+}
+
+/// Work started on the task pool, checked on every frame until it's done. Dropping it lets the
+/// work finish with nobody waiting for it.
+pub struct Task<T> {
+    receiver: Mutex<Receiver<T>>,
+}
+
+/// A task that ended without a result, because it panicked.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TaskLost;
+
+impl<T> Task<T> {
+    /// The result once the work is done, or `None` until then.
+    pub fn poll(&self) -> Option<Result<T, TaskLost>> {
+        match self.receiver.lock().unwrap().try_recv() {
+            Ok(result) => Some(Ok(result)),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(TaskLost)),
+        }
+    }
+}
+
+pub struct TasksPlugin;
+
+impl Plugin for TasksPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<TaskPool>();
+    }
+}
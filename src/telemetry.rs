@@ -0,0 +1,318 @@
+use std::{collections::BTreeMap, fs};
+
+use bevy::prelude::*;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    config::{KidMode, ZenMode},
+    game_event::GameEvent,
+    input::InputSettings,
+    level::ActiveLevel,
+    locale::Locale,
+    net::post,
+    pack::ActiveCampaign,
+    rules::GameRules,
+    save_format::{self, Versioned},
+    tasks::{Task, TaskPool},
+    ui::UiFonts,
+    GameState, Run,
+};
+
+/// The totals so far. Only ever written while telemetry is on.
+const TELEMETRY_PATH: &str = "telemetry.ron";
+
+/// Runs between uploads, so a summary is never sent for just one or two of them.
+const UPLOAD_EVERY: u32 = 10;
+
+const VIEWER_FONT_SIZE: f32 = 22.0;
+
+/// Whether gameplay metrics are collected, and where the summary goes. Both are off unless
+/// `config.ron` or the options menu turns them on.
+#[derive(Default)]
+pub struct TelemetrySettings {
+    pub enabled: bool,
+    /// Without one the totals only stay on this computer.
+    pub upload_url: Option<String>,
+}
+
+/// Totals over every run played with telemetry on. Nothing in here says who played, or what
+/// their profile or scores are.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct Totals {
+    runs: u32,
+    seconds_played: f32,
+    /// Runs by play mode, or by the rules they were played with outside the special modes.
+    modes: BTreeMap<String, u32>,
+    /// Runs by how they ended.
+    deaths: BTreeMap<String, u32>,
+    /// Runs already in an uploaded summary.
+    uploaded_runs: u32,
+}
+
+/// The summary as it's uploaded, and as the viewer shows it.
+#[derive(Serialize)]
+struct Summary<'a> {
+    game_version: &'static str,
+    runs: u32,
+    average_run_seconds: f32,
+    modes: &'a BTreeMap<String, u32>,
+    deaths: &'a BTreeMap<String, u32>,
+}
+
+impl Versioned for Totals {
+    const KIND: &'static str = "telemetry";
+    const VERSION: u32 = 1;
+
+    fn migrate(version: u32, body: &str) -> Result<Self, String> {
+        match version {
+            // Totals from before the header have the same layout.
+            0 => ron::de::from_str(body).map_err(|err| err.to_string()),
+            _ => Err(format!("can't read telemetry version {}", version)),
+        }
+    }
+}
+
+impl Totals {
+    fn load() -> Self {
+        let text = match fs::read_to_string(TELEMETRY_PATH) {
+            Ok(text) => text,
+            Err(_) => return Self::default(),
+        };
+        save_format::decode(&text).unwrap_or_else(|err| {
+            warn!("ignoring {}: {}", TELEMETRY_PATH, err);
+            Self::default()
+        })
+    }
+
+    fn save(&self) {
+        let result = save_format::encode(self)
+            .and_then(|text| fs::write(TELEMETRY_PATH, text).map_err(|err| err.to_string()));
+        if let Err(err) = result {
+            warn!("could not save {}: {}", TELEMETRY_PATH, err);
+        }
+    }
+
+    fn record(&mut self, mode: String, seconds: f32, death: String) {
+        self.runs += 1;
+        self.seconds_played += seconds;
+        *self.modes.entry(mode).or_insert(0) += 1;
+        *self.deaths.entry(death).or_insert(0) += 1;
+    }
+
+    /// Exactly the text that's uploaded.
+    fn summary(&self) -> String {
+        let summary = Summary {
+            game_version: env!("CARGO_PKG_VERSION"),
+            runs: self.runs,
+            average_run_seconds: if self.runs == 0 {
+                0.0
+            } else {
+                self.seconds_played / self.runs as f32
+            },
+            modes: &self.modes,
+            deaths: &self.deaths,
+        };
+        ron::ser::to_string_pretty(&summary, PrettyConfig::default()).unwrap_or_else(|err| {
+            warn!("could not write the telemetry summary: {}", err);
+            String::new()
+        })
+    }
+}
+
+/// The totals, and the upload in flight, which reports how many runs it covered.
+#[derive(Default)]
+struct Telemetry {
+    totals: Totals,
+    upload: Option<Task<Result<u32, String>>>,
+}
+
+/// Shows what the summary would say, opened with T while paused.
+#[derive(Default)]
+struct TelemetryViewer {
+    open: bool,
+}
+
+struct ViewerLine;
+
+pub struct TelemetryPlugin;
+
+impl Plugin for TelemetryPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<TelemetrySettings>()
+            .init_resource::<TelemetryViewer>()
+            .add_resource(Telemetry {
+                totals: Totals::load(),
+                upload: None,
+            })
+            .add_system(record_runs.system())
+            .add_system(finish_upload.system())
+            .add_system(viewer_input.system())
+            .add_system(draw_viewer.system());
+    }
+}
+
+/// What a run was played as, for the mode counts.
+fn play_mode(
+    zen_mode: &ZenMode,
+    kid_mode: &KidMode,
+    input: &InputSettings,
+    level: &ActiveLevel,
+    campaign: &ActiveCampaign,
+    rules: &GameRules,
+) -> String {
+    let puzzle = level
+        .0
+        .as_ref()
+        .map_or(false, |level| level.puzzle.is_some());
+    let mode = if zen_mode.0 {
+        "zen"
+    } else if kid_mode.0 {
+        "kid"
+    } else if puzzle {
+        "puzzle"
+    } else if campaign.0.is_some() {
+        "campaign"
+    } else if level.0.is_some() {
+        "level"
+    } else if input.co_op {
+        "co-op"
+    } else {
+        return rules.summary();
+    };
+    mode.to_string()
+}
+
+/// Adds each finished run to the totals while telemetry is on, and uploads the summary every
+/// few runs if there's somewhere to send it.
+fn record_runs(
+    mut reader: Local<EventReader<GameEvent>>,
+    game_events: Res<Events<GameEvent>>,
+    settings: Res<TelemetrySettings>,
+    run: Res<Run>,
+    zen_mode: Res<ZenMode>,
+    kid_mode: Res<KidMode>,
+    input: Res<InputSettings>,
+    level: Res<ActiveLevel>,
+    campaign: Res<ActiveCampaign>,
+    rules: Res<GameRules>,
+    tasks: Res<TaskPool>,
+    mut telemetry: ResMut<Telemetry>,
+) {
+    let cause = match reader.iter(&game_events).find_map(GameEvent::game_over) {
+        Some(cause) => cause,
+        None => return,
+    };
+    if !settings.enabled {
+        return;
+    }
+    let mode = play_mode(&zen_mode, &kid_mode, &input, &level, &campaign, &rules);
+    telemetry
+        .totals
+        .record(mode, run.elapsed, format!("{:?}", cause));
+    telemetry.totals.save();
+
+    let url = match &settings.upload_url {
+        Some(url) => url.clone(),
+        None => return,
+    };
+    let totals = &telemetry.totals;
+    if telemetry.upload.is_some() || totals.runs < totals.uploaded_runs + UPLOAD_EVERY {
+        return;
+    }
+    let runs = totals.runs;
+    let body = totals.summary();
+    telemetry.upload = Some(tasks.spawn("telemetry upload", move || {
+        post(&url, "application/ron", &body).map(|()| runs)
+    }));
+}
+
+fn finish_upload(mut telemetry: ResMut<Telemetry>) {
+    let result = match telemetry.upload.as_ref().and_then(Task::poll) {
+        Some(Ok(result)) => result,
+        Some(Err(_lost)) => Err("upload stopped".to_string()),
+        None => return,
+    };
+    telemetry.upload = None;
+    match result {
+        Ok(runs) => {
+            info!(runs, "telemetry summary uploaded");
+            telemetry.totals.uploaded_runs = runs;
+            telemetry.totals.save();
+        }
+        // Tried again after the next run.
+        Err(err) => warn!("could not upload the telemetry summary: {}", err),
+    }
+}
+
+/// T while paused opens and closes the viewer. Resuming the game closes it.
+fn viewer_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    state: Res<GameState>,
+    mut viewer: ResMut<TelemetryViewer>,
+) {
+    if *state != GameState::Paused {
+        viewer.open = false;
+    } else if keyboard_input.just_pressed(KeyCode::T) {
+        viewer.open = !viewer.open;
+    }
+}
+
+/// Lists whether telemetry is on, where the summary goes, and the summary itself, line for
+/// line as it would be sent.
+fn draw_viewer(
+    mut drawn: Local<Vec<String>>,
+    mut commands: Commands,
+    fonts: Res<UiFonts>,
+    locale: Res<Locale>,
+    settings: Res<TelemetrySettings>,
+    telemetry: Res<Telemetry>,
+    viewer: Res<TelemetryViewer>,
+    mut lines: Query<(Entity, &ViewerLine)>,
+) {
+    let mut text = Vec::new();
+    if viewer.open {
+        text.push(locale.text("telemetry-title"));
+        text.push(locale.text(if settings.enabled {
+            "telemetry-on"
+        } else {
+            "telemetry-off"
+        }));
+        text.push(match &settings.upload_url {
+            Some(url) => locale.format(
+                "telemetry-destination",
+                &[("url", url.clone()), ("every", UPLOAD_EVERY.to_string())],
+            ),
+            None => locale.text("telemetry-local"),
+        });
+        text.extend(telemetry.totals.summary().lines().map(str::to_string));
+        text.push(locale.text("telemetry-hint"));
+    }
+    if *drawn == text {
+        return;
+    }
+    *drawn = text.clone();
+
+    for (ent, _line) in &mut lines.iter() {
+        commands.despawn(ent);
+    }
+    for (row, line) in text.into_iter().enumerate() {
+        commands
+            .spawn(TextComponents {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        left: Val::Px(40.0),
+                        top: Val::Px(120.0 + row as f32 * (VIEWER_FONT_SIZE + 6.0)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                text: fonts.text(line, VIEWER_FONT_SIZE),
+                ..Default::default()
+            })
+            .with(ViewerLine);
+    }
+}
@@ -0,0 +1,288 @@
+use std::{
+    collections::HashMap,
+    f32::consts::{FRAC_PI_2, FRAC_PI_4, PI},
+};
+
+use bevy::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{
+    accessibility::{AccessibilitySettings, Palette},
+    effects::{ActiveEffect, EffectKind},
+    level::ActiveLevel,
+    loading::LoadingAssets,
+    rules::GameRules,
+    sized_sprite, Direction, Position, Run, Size, ARENA_HEIGHT, ARENA_WIDTH, START_POSITION,
+};
+
+/// Tiles are drawn slightly behind everything else, which sits at z = 0.
+const TILE_DEPTH: f32 = -0.05;
+
+const CONVEYOR_TEXTURE: &str = "assets/textures/conveyor.png";
+
+const ICE_PATCHES: usize = 4;
+const SPEED_PAD_PATCHES: usize = 3;
+const MUD_PATCHES: usize = 3;
+const CONVEYOR_BELTS: usize = 4;
+
+/// Cells this close to the starting position are kept clear.
+const SPAWN_CLEARANCE: i32 = 3;
+
+/// Mixed into the run seed so tile layout doesn't share a random stream with food placement.
+const TILE_SEED_SALT: u64 = 0x7469_6c65;
+
+/// What a cell of the arena floor is made of. Cells without a tile behave normally.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub enum TileKind {
+    /// The snake can't turn while its head is on ice.
+    Ice,
+    /// Halves the move interval while the head is on it.
+    SpeedPad,
+    /// Doubles the move interval while the head is on it.
+    Mud,
+    /// Pushes the whole snake one extra cell in its direction after every move that ends on it.
+    Conveyor(Direction),
+    /// Takes segments off the tail of a head that crosses it, see `spike_damage`. Only placed
+    /// by levels.
+    Spikes,
+}
+
+impl TileKind {
+    /// The effect a head on this tile is under, lasting only as long as it stays there.
+    pub fn effect(self) -> Option<ActiveEffect> {
+        match self {
+            Self::SpeedPad => Some(ActiveEffect::new(EffectKind::Haste, 0.0, 0.5)),
+            Self::Mud => Some(ActiveEffect::new(EffectKind::Slow, 0.0, 2.0)),
+            Self::Ice | Self::Conveyor(_) | Self::Spikes => None,
+        }
+    }
+}
+
+/// The arena floor. Levels and the generator place tiles through this so the grid and the
+/// tile sprites stay in sync.
+#[derive(Default)]
+pub struct Tiles {
+    kinds: HashMap<Position, TileKind>,
+}
+
+impl Tiles {
+    pub fn get(&self, position: &Position) -> Option<TileKind> {
+        self.kinds.get(position).copied()
+    }
+
+    pub fn place(
+        &mut self,
+        commands: &mut Commands,
+        materials: &TileMaterials,
+        position: Position,
+        kind: TileKind,
+    ) {
+        if self.kinds.insert(position, kind).is_some() {
+            return;
+        }
+        // Speed pads are drawn as diamonds so they can be told apart from mud by shape.
+        // Conveyors show an arrow, which points up before rotating. Spikes are small squares.
+        let (material, rotation, size) = match kind {
+            TileKind::Ice => (materials.ice, 0.0, 1.0),
+            TileKind::SpeedPad => (materials.speed_pad, FRAC_PI_4, 0.7),
+            TileKind::Mud => (materials.mud, 0.0, 1.0),
+            TileKind::Conveyor(direction) => {
+                let rotation = match direction {
+                    Direction::Up => 0.0,
+                    Direction::Left => FRAC_PI_2,
+                    Direction::Down => PI,
+                    Direction::Right => -FRAC_PI_2,
+                };
+                (materials.conveyor, rotation, 1.0)
+            }
+            TileKind::Spikes => (materials.spikes, 0.0, 0.5),
+        };
+        let mut transform = Transform::from_rotation(Quat::from_rotation_z(rotation));
+        transform.set_translation(Vec3::new(0.0, 0.0, TILE_DEPTH));
+        commands
+            .spawn(SpriteComponents {
+                transform,
+                ..sized_sprite(material)
+            })
+            .with(Tile)
+            .with(position)
+            .with(Size::square(size));
+    }
+
+    /// Removes every tile and its sprite.
+    pub fn clear(&mut self, commands: &mut Commands, tiles: &mut Query<(Entity, &Tile)>) {
+        self.kinds.clear();
+        for (ent, _tile) in &mut tiles.iter() {
+            commands.despawn(ent);
+        }
+    }
+}
+
+pub struct Tile;
+
+pub struct TileMaterials {
+    ice: Handle<ColorMaterial>,
+    speed_pad: Handle<ColorMaterial>,
+    mud: Handle<ColorMaterial>,
+    conveyor: Handle<ColorMaterial>,
+    spikes: Handle<ColorMaterial>,
+}
+
+pub struct TilesPlugin;
+
+impl Plugin for TilesPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Tiles>()
+            .add_startup_system(setup_tiles.system())
+            .add_system(generate_tiles.system())
+            .add_system(apply_tile_palette.system());
+    }
+}
+
+fn setup_tiles(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<AccessibilitySettings>,
+    mut loading: ResMut<LoadingAssets>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let colors = settings.palette.colors();
+    let conveyor = match asset_server.load(CONVEYOR_TEXTURE) {
+        Ok(texture) => {
+            loading.track(CONVEYOR_TEXTURE, texture);
+            ColorMaterial::modulated_texture(texture, colors.conveyor)
+        }
+        Err(err) => {
+            warn!("could not load {}: {:?}", CONVEYOR_TEXTURE, err);
+            colors.conveyor.into()
+        }
+    };
+    commands.insert_resource(TileMaterials {
+        ice: materials.add(colors.ice.into()),
+        speed_pad: materials.add(colors.speed_pad.into()),
+        mud: materials.add(colors.mud.into()),
+        conveyor: materials.add(conveyor),
+        spikes: materials.add(colors.spikes.into()),
+    });
+}
+
+/// Lays out a fresh floor whenever a new run starts or the tile rules change. The layout is
+/// derived from the run seed, so a restored run gets the same floor back. A level brings its
+/// own floor instead, and the tile rules don't add to it.
+fn generate_tiles(
+    mut commands: Commands,
+    mut generated: Local<Option<(u64, GameRules)>>,
+    mut laid_level: Local<Option<Vec<(Position, TileKind)>>>,
+    run: Res<Run>,
+    rules: Res<GameRules>,
+    level: Res<ActiveLevel>,
+    materials: Res<TileMaterials>,
+    mut tiles: ResMut<Tiles>,
+    mut tile_entities: Query<(Entity, &Tile)>,
+) {
+    if let Some(level) = &level.0 {
+        if laid_level.as_ref() != Some(&level.tiles) {
+            *laid_level = Some(level.tiles.clone());
+            *generated = None;
+            tiles.clear(&mut commands, &mut tile_entities);
+            for &(position, kind) in &level.tiles {
+                tiles.place(&mut commands, &materials, position, kind);
+            }
+        }
+        return;
+    }
+    if let Some((seed, generated_rules)) = &*generated {
+        if *seed == run.seed && *generated_rules == *rules {
+            return;
+        }
+    }
+    *laid_level = None;
+    *generated = Some((run.seed, rules.clone()));
+    tiles.clear(&mut commands, &mut tile_entities);
+
+    let mut rng = StdRng::seed_from_u64(run.seed ^ TILE_SEED_SALT);
+    let mut scatter = |kind, patches| {
+        for _ in 0..patches {
+            let width = rng.gen_range(2, 6);
+            let height = rng.gen_range(2, 5);
+            let left = rng.gen_range(0, ARENA_WIDTH as i32 - width);
+            let bottom = rng.gen_range(0, ARENA_HEIGHT as i32 - height);
+            for x in left..left + width {
+                for y in bottom..bottom + height {
+                    let position = Position { x, y };
+                    if near_spawn(&position) {
+                        continue;
+                    }
+                    tiles.place(&mut commands, &materials, position, kind);
+                }
+            }
+        }
+    };
+    if rules.ice_tiles {
+        scatter(TileKind::Ice, ICE_PATCHES);
+    }
+    if rules.speed_tiles {
+        scatter(TileKind::SpeedPad, SPEED_PAD_PATCHES);
+        scatter(TileKind::Mud, MUD_PATCHES);
+    }
+    if rules.conveyor_tiles {
+        for _ in 0..CONVEYOR_BELTS {
+            let direction = match rng.gen_range(0, 4) {
+                0 => Direction::Left,
+                1 => Direction::Up,
+                2 => Direction::Right,
+                _ => Direction::Down,
+            };
+            let mut position = Position {
+                x: rng.gen_range(0, ARENA_WIDTH as i32),
+                y: rng.gen_range(0, ARENA_HEIGHT as i32),
+            };
+            for _ in 0..rng.gen_range(4, 9) {
+                if position.outside_arena() {
+                    break;
+                }
+                if !near_spawn(&position) {
+                    tiles.place(
+                        &mut commands,
+                        &materials,
+                        position,
+                        TileKind::Conveyor(direction),
+                    );
+                }
+                position = position.moved(direction);
+            }
+        }
+    }
+}
+
+fn near_spawn(position: &Position) -> bool {
+    (position.x - START_POSITION.x).abs() <= SPAWN_CLEARANCE
+        && (position.y - START_POSITION.y).abs() <= SPAWN_CLEARANCE
+}
+
+fn apply_tile_palette(
+    mut applied: Local<Option<Palette>>,
+    settings: Res<AccessibilitySettings>,
+    tile_materials: Res<TileMaterials>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if *applied == Some(settings.palette) {
+        return;
+    }
+    *applied = Some(settings.palette);
+
+    let colors = settings.palette.colors();
+    for (handle, color) in &[
+        (tile_materials.ice, colors.ice),
+        (tile_materials.speed_pad, colors.speed_pad),
+        (tile_materials.mud, colors.mud),
+        (tile_materials.conveyor, colors.conveyor),
+        (tile_materials.spikes, colors.spikes),
+    ] {
+        if let Some(material) = materials.get_mut(handle) {
+            material.color = *color;
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+
+use crate::GameState;
+
+/// What is bending game time away from real time. Each source sets its own factor, so one
+/// ending doesn't undo another.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub enum TimeSource {
+    /// Stops game time while paused.
+    Pause,
+    /// The slowdown just before a crash.
+    LastChance,
+    /// The speed a replay is being watched at.
+    Replay,
+    /// The speed set with the debug hotkeys during a run.
+    Debug,
+}
+
+/// Speeds the hotkeys step through, slowest first.
+pub const SPEEDS: [f32; 6] = [0.25, 0.5, 1.0, 2.0, 4.0, 8.0];
+
+/// The next speed up or down from `speed`, staying within `SPEEDS`.
+pub fn step_speed(speed: f32, faster: bool) -> f32 {
+    let current = SPEEDS
+        .iter()
+        .position(|&step| step >= speed)
+        .unwrap_or(SPEEDS.len() - 1);
+    let next = if faster {
+        (current + 1).min(SPEEDS.len() - 1)
+    } else {
+        current.saturating_sub(1)
+    };
+    SPEEDS[next]
+}
+
+/// How fast game time passes compared to real time. The factors of every source multiply, so a
+/// slowdown during a fast-forwarded replay is still slower than the replay. Gameplay timers and
+/// animations tick by `delta`; menus, toasts and the camera keep real time.
+#[derive(Default)]
+pub struct TimeScale {
+    factors: HashMap<TimeSource, f32>,
+}
+
+impl TimeScale {
+    pub fn set(&mut self, source: TimeSource, factor: f32) {
+        self.factors.insert(source, factor);
+    }
+
+    pub fn clear(&mut self, source: TimeSource) {
+        self.factors.remove(&source);
+    }
+
+    /// The factor one source has set, 1 if it hasn't set any.
+    pub fn factor_of(&self, source: TimeSource) -> f32 {
+        self.factors.get(&source).copied().unwrap_or(1.0)
+    }
+
+    pub fn factor(&self) -> f32 {
+        self.factors.values().product()
+    }
+
+    /// Game seconds passed this frame.
+    pub fn delta(&self, time: &Time) -> f32 {
+        time.delta_seconds * self.factor()
+    }
+}
+
+pub struct TimeScalePlugin;
+
+impl Plugin for TimeScalePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<TimeScale>()
+            .add_system(pause_time.system());
+        if cfg!(debug_assertions) {
+            app.add_system(debug_speed.system());
+        }
+    }
+}
+
+fn pause_time(state: Res<GameState>, mut time_scale: ResMut<TimeScale>) {
+    if *state == GameState::Paused {
+        time_scale.set(TimeSource::Pause, 0.0);
+    } else {
+        time_scale.clear(TimeSource::Pause);
+    }
+}
+
+/// Minus and Equals slow down and speed up a run in debug builds, for testing timing-heavy
+/// features without waiting on them. Leaving the run puts the speed back.
+fn debug_speed(
+    keyboard_input: Res<Input<KeyCode>>,
+    state: Res<GameState>,
+    mut time_scale: ResMut<TimeScale>,
+) {
+    if !matches!(*state, GameState::Playing | GameState::Paused) {
+        time_scale.clear(TimeSource::Debug);
+        return;
+    }
+    let speed = time_scale.factor_of(TimeSource::Debug);
+    let speed = if keyboard_input.just_pressed(KeyCode::Minus) {
+        step_speed(speed, false)
+    } else if keyboard_input.just_pressed(KeyCode::Equals) {
+        step_speed(speed, true)
+    } else {
+        return;
+    };
+    time_scale.set(TimeSource::Debug, speed);
+}
@@ -0,0 +1,86 @@
+use bevy::prelude::*;
+
+use crate::ui::UiFonts;
+
+/// How long a toast stays on screen, in seconds.
+const TOAST_DURATION: f32 = 3.0;
+
+/// Older toasts are dismissed early once more than this many are on screen.
+const MAX_TOASTS: usize = 5;
+
+const TOAST_FONT_SIZE: f32 = 24.0;
+
+/// A short message shown in the bottom-left corner for a few seconds. Any system can send one.
+pub struct Toast {
+    pub text: String,
+}
+
+impl Toast {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self { text: text.into() }
+    }
+}
+
+struct ToastTimer(Timer);
+
+pub struct ToastPlugin;
+
+impl Plugin for ToastPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<Toast>()
+            .add_system(spawn_toasts.system())
+            .add_system(dismiss_toasts.system());
+    }
+}
+
+fn spawn_toasts(
+    mut commands: Commands,
+    mut reader: Local<EventReader<Toast>>,
+    toasts: Res<Events<Toast>>,
+    fonts: Res<UiFonts>,
+) {
+    for toast in reader.iter(&toasts) {
+        commands
+            .spawn(TextComponents {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    ..Default::default()
+                },
+                text: fonts.text(toast.text.clone(), TOAST_FONT_SIZE),
+                ..Default::default()
+            })
+            .with(ToastTimer(Timer::from_seconds(TOAST_DURATION, false)));
+    }
+}
+
+/// Expires old toasts and stacks the rest upwards, newest at the bottom.
+fn dismiss_toasts(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut toasts: Query<(Entity, &mut ToastTimer, &mut Style)>,
+) {
+    let mut live = Vec::new();
+    for (ent, mut timer, _style) in &mut toasts.iter() {
+        timer.0.tick(time.delta_seconds);
+        if timer.0.finished {
+            commands.despawn(ent);
+        } else {
+            live.push((timer.0.elapsed, ent));
+        }
+    }
+
+    live.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    for (index, &(_elapsed, ent)) in live.iter().enumerate() {
+        if index >= MAX_TOASTS {
+            commands.despawn(ent);
+            continue;
+        }
+        if let Ok(mut style) = toasts.get_mut::<Style>(ent) {
+            style.position = Rect {
+                left: Val::Px(10.0),
+                bottom: Val::Px(10.0 + index as f32 * (TOAST_FONT_SIZE + 8.0)),
+                ..Default::default()
+            };
+        }
+    }
+}
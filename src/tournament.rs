@@ -0,0 +1,438 @@
+use bevy::{prelude::*, window::ReceivedCharacter};
+use tracing::info;
+
+use crate::{game_event::GameEvent, locale::Locale, ui::UiFonts, GameState, Score};
+
+const MIN_PLAYERS: usize = 3;
+
+/// Hot seat works with fewer players than a bracket does.
+const MIN_HOT_SEAT_PLAYERS: usize = 2;
+
+const MAX_PLAYERS: usize = 8;
+
+const MAX_NAME_LEN: usize = 16;
+
+const BRACKET_FONT_SIZE: f32 = 26.0;
+
+/// Two players taking turns at a run. A `None` player is a bye, which the other player wins
+/// without playing.
+struct Match {
+    players: [Option<usize>; 2],
+    scores: [Option<u32>; 2],
+}
+
+impl Match {
+    /// Index of the winner once the match is decided. Ties go to the first player.
+    fn winner(&self) -> Option<usize> {
+        match (self.players, self.scores) {
+            ([Some(a), None], _) => Some(a),
+            ([None, Some(b)], _) => Some(b),
+            ([Some(a), Some(b)], [Some(score_a), Some(score_b)]) => {
+                Some(if score_b > score_a { b } else { a })
+            }
+            _ => None,
+        }
+    }
+
+    /// Which side plays next, if the match still needs a run.
+    fn next_side(&self) -> Option<usize> {
+        if self.winner().is_some() {
+            return None;
+        }
+        self.scores.iter().position(Option::is_none)
+    }
+}
+
+/// A single-elimination bracket played hot-seat, one run per player per match.
+struct Bracket {
+    names: Vec<String>,
+    rounds: Vec<Vec<Match>>,
+}
+
+impl Bracket {
+    /// Seeds the first round so byes are spread out and never meet each other.
+    fn new(names: Vec<String>) -> Self {
+        let half = names.len().next_power_of_two() / 2;
+        let first_round = (0..half)
+            .map(|i| Match {
+                players: [Some(i), Some(i + half).filter(|&j| j < names.len())],
+                scores: [None, None],
+            })
+            .collect();
+        Self {
+            names,
+            rounds: vec![first_round],
+        }
+    }
+
+    /// The match and side that play next.
+    fn next_turn(&self) -> Option<(usize, usize)> {
+        let round = self.rounds.last()?;
+        round
+            .iter()
+            .enumerate()
+            .find_map(|(i, m)| m.next_side().map(|side| (i, side)))
+    }
+
+    fn next_player(&self) -> Option<&str> {
+        let (i, side) = self.next_turn()?;
+        let player = self.rounds.last()?[i].players[side]?;
+        Some(&self.names[player])
+    }
+
+    fn record(&mut self, score: u32) {
+        if let Some((i, side)) = self.next_turn() {
+            self.rounds.last_mut().unwrap()[i].scores[side] = Some(score);
+        }
+        self.advance();
+    }
+
+    /// Starts the next round once every match of the current one is decided.
+    fn advance(&mut self) {
+        let winners: Option<Vec<usize>> = self
+            .rounds
+            .last()
+            .unwrap()
+            .iter()
+            .map(Match::winner)
+            .collect();
+        let winners = match winners {
+            Some(winners) if winners.len() > 1 => winners,
+            _ => return,
+        };
+        let next_round = winners
+            .chunks(2)
+            .map(|pair| Match {
+                players: [pair.get(0).copied(), pair.get(1).copied()],
+                scores: [None, None],
+            })
+            .collect();
+        self.rounds.push(next_round);
+    }
+
+    fn champion(&self) -> Option<&str> {
+        // There are always at least two first-round matches, so a lone match is the final.
+        match self.rounds.last()?.as_slice() {
+            [last] => last.winner().map(|winner| self.names[winner].as_str()),
+            _ => None,
+        }
+    }
+}
+
+/// Players taking turns at runs for as long as they like, keeping score across the session.
+struct HotSeat {
+    names: Vec<String>,
+    /// Every run's score, per player.
+    scores: Vec<Vec<u32>>,
+    next: usize,
+}
+
+impl HotSeat {
+    fn new(names: Vec<String>) -> Self {
+        Self {
+            scores: vec![Vec::new(); names.len()],
+            names,
+            next: 0,
+        }
+    }
+
+    fn record(&mut self, score: u32) {
+        self.scores[self.next].push(score);
+        self.next = (self.next + 1) % self.names.len();
+    }
+}
+
+enum Stage {
+    /// Names are being typed in.
+    Entry {
+        names: Vec<String>,
+        typing: String,
+        hot_seat: bool,
+    },
+    Bracket(Bracket),
+    HotSeat(HotSeat),
+}
+
+/// The tournament or hot-seat session being set up or played, if any.
+#[derive(Default)]
+pub struct Tournament {
+    stage: Option<Stage>,
+    /// Set when the tournament screen needs to be redrawn.
+    dirty: bool,
+}
+
+struct BracketLine;
+
+pub struct TournamentPlugin;
+
+impl Plugin for TournamentPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Tournament>()
+            .add_system(tournament_input.system())
+            .add_system(record_tournament_run.system())
+            .add_system(draw_tournament.system());
+    }
+}
+
+fn valid_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == ' ' || c == '-' || c == '_'
+}
+
+/// Tab on the profile picker opens the name entry, where Tab picks between a knockout bracket
+/// and hot seat. Enter adds a name, or starts once enough names are in, and then starts each
+/// player's run in turn. Esc ends it at any point.
+fn tournament_input(
+    mut char_reader: Local<EventReader<ReceivedCharacter>>,
+    characters: Res<Events<ReceivedCharacter>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut tournament: ResMut<Tournament>,
+    mut state: ResMut<GameState>,
+) {
+    let typed: Vec<char> = char_reader
+        .iter(&characters)
+        .map(|event| event.char)
+        .collect();
+    if *state == GameState::ProfileSelect && keyboard_input.just_pressed(KeyCode::Tab) {
+        tournament.stage = Some(Stage::Entry {
+            names: Vec::new(),
+            typing: String::new(),
+            hot_seat: false,
+        });
+        tournament.dirty = true;
+        *state = GameState::Tournament;
+        return;
+    }
+    if *state != GameState::Tournament {
+        return;
+    }
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        tournament.stage = None;
+        tournament.dirty = true;
+        *state = GameState::ProfileSelect;
+        return;
+    }
+
+    let enter = keyboard_input.just_pressed(KeyCode::Return);
+    let back = keyboard_input.just_pressed(KeyCode::Back);
+    let tab = keyboard_input.just_pressed(KeyCode::Tab);
+    if !enter && !back && !tab && typed.is_empty() {
+        return;
+    }
+    let mut start = None;
+    let mut finished = false;
+    match &mut tournament.stage {
+        Some(Stage::Entry {
+            names,
+            typing,
+            hot_seat,
+        }) => {
+            if tab {
+                *hot_seat = !*hot_seat;
+            }
+            let min_players = if *hot_seat {
+                MIN_HOT_SEAT_PLAYERS
+            } else {
+                MIN_PLAYERS
+            };
+            for c in typed {
+                if valid_name_char(c) && typing.chars().count() < MAX_NAME_LEN {
+                    typing.push(c);
+                }
+            }
+            if back {
+                typing.pop();
+            }
+            if enter {
+                let name = typing.trim().to_string();
+                typing.clear();
+                if !name.is_empty() && names.len() < MAX_PLAYERS && !names.contains(&name) {
+                    names.push(name);
+                } else if name.is_empty() && names.len() >= min_players {
+                    start = Some((std::mem::take(names), *hot_seat));
+                }
+            }
+        }
+        Some(Stage::Bracket(bracket)) => {
+            if enter {
+                finished = bracket.champion().is_some();
+                if !finished {
+                    *state = GameState::Playing;
+                }
+            }
+        }
+        Some(Stage::HotSeat(_session)) => {
+            if enter {
+                *state = GameState::Playing;
+            }
+        }
+        None => finished = true,
+    }
+    if finished {
+        tournament.stage = None;
+        *state = GameState::ProfileSelect;
+    }
+    match start {
+        Some((names, true)) => {
+            info!(players = names.len(), "hot seat started");
+            tournament.stage = Some(Stage::HotSeat(HotSeat::new(names)));
+        }
+        Some((names, false)) => {
+            info!(players = names.len(), "tournament started");
+            tournament.stage = Some(Stage::Bracket(Bracket::new(names)));
+        }
+        None => {}
+    }
+    tournament.dirty = true;
+}
+
+/// Scores the finished run for whoever was up and goes back to the bracket. This runs before
+/// the game over resets the score.
+fn record_tournament_run(
+    mut reader: Local<EventReader<GameEvent>>,
+    game_events: Res<Events<GameEvent>>,
+    score: Res<Score>,
+    mut tournament: ResMut<Tournament>,
+    mut state: ResMut<GameState>,
+) {
+    if reader
+        .iter(&game_events)
+        .find_map(GameEvent::game_over)
+        .is_none()
+    {
+        return;
+    }
+    match &mut tournament.stage {
+        Some(Stage::Bracket(bracket)) => {
+            bracket.record(score.0);
+            if let Some(champion) = bracket.champion() {
+                info!(champion, "tournament won");
+            }
+        }
+        Some(Stage::HotSeat(session)) => session.record(score.0),
+        _ => return,
+    }
+    tournament.dirty = true;
+    *state = GameState::Tournament;
+}
+
+fn draw_tournament(
+    mut commands: Commands,
+    state: Res<GameState>,
+    fonts: Res<UiFonts>,
+    locale: Res<Locale>,
+    mut tournament: ResMut<Tournament>,
+    mut lines: Query<(Entity, &BracketLine)>,
+) {
+    if !tournament.dirty {
+        return;
+    }
+    tournament.dirty = false;
+
+    for (ent, _line) in &mut lines.iter() {
+        commands.despawn(ent);
+    }
+    if *state != GameState::Tournament {
+        return;
+    }
+
+    let title = match &tournament.stage {
+        Some(Stage::HotSeat(_session)) => "hot-seat-title",
+        _ => "tournament-title",
+    };
+    let mut text = vec![locale.text(title)];
+    match &tournament.stage {
+        Some(Stage::Entry {
+            names,
+            typing,
+            hot_seat,
+        }) => {
+            let (format_key, min_players) = if *hot_seat {
+                ("tournament-format-hot-seat", MIN_HOT_SEAT_PLAYERS)
+            } else {
+                ("tournament-format-knockout", MIN_PLAYERS)
+            };
+            text.push(locale.text(format_key));
+            for name in names {
+                text.push(format!("  {}", name));
+            }
+            text.push(locale.format("tournament-new-player", &[("name", typing.clone())]));
+            text.push(locale.format(
+                "tournament-entry-hint",
+                &[
+                    ("min", min_players.to_string()),
+                    ("max", MAX_PLAYERS.to_string()),
+                ],
+            ));
+        }
+        Some(Stage::HotSeat(session)) => {
+            for (name, scores) in session.names.iter().zip(&session.scores) {
+                let best = scores.iter().max().copied().unwrap_or(0);
+                let total: u32 = scores.iter().sum();
+                text.push(locale.format(
+                    "hot-seat-entry",
+                    &[
+                        ("name", name.clone()),
+                        ("runs", scores.len().to_string()),
+                        ("best", best.to_string()),
+                        ("total", total.to_string()),
+                    ],
+                ));
+            }
+            text.push(locale.format(
+                "tournament-next",
+                &[("name", session.names[session.next].clone())],
+            ));
+            text.push(locale.text("hot-seat-hint"));
+        }
+        Some(Stage::Bracket(bracket)) => {
+            let name = |player: Option<usize>| {
+                player.map_or_else(
+                    || locale.text("tournament-bye"),
+                    |i| bracket.names[i].clone(),
+                )
+            };
+            for (round, matches) in bracket.rounds.iter().enumerate() {
+                text.push(locale.format("tournament-round", &[("round", (round + 1).to_string())]));
+                for m in matches {
+                    let score = |side: usize| {
+                        m.scores[side].map_or_else(String::new, |score| format!(" ({})", score))
+                    };
+                    text.push(format!(
+                        "  {}{} - {}{}",
+                        name(m.players[0]),
+                        score(0),
+                        name(m.players[1]),
+                        score(1)
+                    ));
+                }
+            }
+            match (bracket.champion(), bracket.next_player()) {
+                (Some(champion), _) => text
+                    .push(locale.format("tournament-champion", &[("name", champion.to_string())])),
+                (None, Some(player)) => {
+                    text.push(locale.format("tournament-next", &[("name", player.to_string())]))
+                }
+                (None, None) => {}
+            }
+        }
+        None => {}
+    }
+
+    for (row, line) in text.into_iter().enumerate() {
+        commands
+            .spawn(TextComponents {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        left: Val::Px(40.0),
+                        bottom: Val::Px(900.0 - row as f32 * (BRACKET_FONT_SIZE + 8.0)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                text: fonts.text(line, BRACKET_FONT_SIZE),
+                ..Default::default()
+            })
+            .with(BracketLine);
+    }
+}
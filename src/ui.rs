@@ -0,0 +1,354 @@
+use std::{f32::consts::PI, path::Path};
+
+use bevy::prelude::*;
+use tracing::warn;
+
+use crate::{
+    accessibility::AccessibilitySettings,
+    adaptive::DifficultyLabel,
+    celebration::NewBest,
+    config::ZenMode,
+    hud_layout::{HudLayout, HudWidget},
+    loading::LoadingAssets,
+    locale::Locale,
+    objectives::Objectives,
+    puzzle::MovesLabel,
+    scoring::DangerBonus,
+    time_scale::{TimeScale, TimeSource},
+    zone::Zone,
+    GameState, Score,
+};
+
+const HUD_FONT_SIZE: f32 = 28.0;
+
+const UI_FONT: &str = "assets/fonts/DejaVuSans-Bold.ttf";
+
+/// Compiled-in copy of the UI font, used when the assets folder doesn't have it.
+const FALLBACK_FONT: &[u8] = include_bytes!("../assets/fonts/DejaVuSans-Bold.ttf");
+
+/// Fonts shared by every piece of UI text. Build text through `UiFonts::text` rather than
+/// loading fonts directly so everything stays consistent.
+pub struct UiFonts {
+    pub main: Handle<Font>,
+}
+
+impl UiFonts {
+    pub fn text(&self, value: impl Into<String>, font_size: f32) -> Text {
+        Text {
+            value: value.into(),
+            font: self.main,
+            style: TextStyle {
+                font_size,
+                color: Color::WHITE,
+            },
+        }
+    }
+}
+
+struct PauseLabel;
+
+struct ScoreLabel;
+
+/// Lights up while the player is earning the danger bonus.
+struct DangerLabel;
+
+struct ObjectiveLabel;
+
+/// Counts down to the next king-of-the-hill zone move.
+struct ZoneLabel;
+
+/// Shown for the rest of a run once it beats the high score.
+struct BestLabel;
+
+/// The speed set with the debug hotkeys, when it isn't normal speed.
+struct SpeedLabel;
+
+/// How fast the "new best" badge pulses, in pulses per second.
+const BEST_PULSE_RATE: f32 = 1.5;
+
+pub struct UiPlugin;
+
+impl Plugin for UiPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_startup_system(setup_ui.system())
+            .add_system(pause_label.system())
+            .add_system(hud_labels.system())
+            .add_system(hud_edit_labels.system())
+            .add_system(pulse_best_label.system())
+            .add_system(window_title.system());
+    }
+}
+
+fn load_ui_font(
+    asset_server: &AssetServer,
+    loading: &mut LoadingAssets,
+    fonts: &mut Assets<Font>,
+) -> Handle<Font> {
+    if Path::new(UI_FONT).exists() {
+        match asset_server.load(UI_FONT) {
+            Ok(font) => {
+                loading.track(UI_FONT, font);
+                return font;
+            }
+            Err(err) => warn!("could not load {}: {:?}", UI_FONT, err),
+        }
+    } else {
+        warn!("{} is missing, using the built-in font", UI_FONT);
+    }
+    fonts.add(Font::try_from_bytes(FALLBACK_FONT.to_vec()).unwrap())
+}
+
+fn setup_ui(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut loading: ResMut<LoadingAssets>,
+    mut fonts: ResMut<Assets<Font>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let ui_fonts = UiFonts {
+        main: load_ui_font(&asset_server, &mut loading, &mut fonts),
+    };
+
+    commands
+        .spawn(NodeComponents {
+            style: Style {
+                position_type: PositionType::Absolute,
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.0).into()),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn(TextComponents {
+                    text: ui_fonts.text("", 60.0),
+                    draw: Draw {
+                        is_visible: false,
+                        is_transparent: true,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .with(PauseLabel);
+        });
+
+    // Widgets are placed by the HUD layout.
+    let hud_text = || TextComponents {
+        style: Style {
+            position_type: PositionType::Absolute,
+            ..Default::default()
+        },
+        text: ui_fonts.text("", HUD_FONT_SIZE),
+        ..Default::default()
+    };
+    commands
+        .spawn(hud_text())
+        .with(ScoreLabel)
+        .with(HudWidget::Score)
+        .spawn(hud_text())
+        .with(DangerLabel)
+        .with(HudWidget::Danger)
+        .spawn(hud_text())
+        .with(ObjectiveLabel)
+        .with(HudWidget::Objective)
+        .spawn(hud_text())
+        .with(ZoneLabel)
+        .with(HudWidget::Zone)
+        .spawn(hud_text())
+        .with(BestLabel)
+        .with(HudWidget::Best)
+        .spawn(hud_text())
+        .with(SpeedLabel)
+        .with(HudWidget::Speed)
+        .spawn(hud_text())
+        .with(MovesLabel)
+        .with(HudWidget::Moves)
+        .spawn(hud_text())
+        .with(DifficultyLabel)
+        .with(HudWidget::Difficulty);
+
+    commands.insert_resource(ui_fonts);
+}
+
+fn hud_labels(
+    state: Res<GameState>,
+    locale: Res<Locale>,
+    score: Res<Score>,
+    danger: Res<DangerBonus>,
+    objectives: Res<Objectives>,
+    zone: Res<Zone>,
+    new_best: Res<NewBest>,
+    zen_mode: Res<ZenMode>,
+    layout: Res<HudLayout>,
+    time_scale: Res<TimeScale>,
+    mut score_labels: Query<(&ScoreLabel, &mut Text)>,
+    mut danger_labels: Query<(&DangerLabel, &mut Draw, &mut Text)>,
+    mut objective_labels: Query<(&ObjectiveLabel, &mut Text)>,
+    mut zone_labels: Query<(&ZoneLabel, &mut Text)>,
+    mut best_labels: Query<(&BestLabel, &mut Text)>,
+    mut speed_labels: Query<(&SpeedLabel, &mut Text)>,
+) {
+    // Zen mode has no HUD at all.
+    let playing = matches!(*state, GameState::Playing | GameState::Paused) && !zen_mode.0;
+    // While the layout is edited every widget shows its name instead, see `hud_edit_labels`.
+    if layout.editing {
+        return;
+    }
+    let shows = |widget| playing && layout.shows(widget);
+    for (_label, mut text) in &mut score_labels.iter() {
+        let value = if shows(HudWidget::Score) {
+            locale.format("score", &[("score", score.0.to_string())])
+        } else {
+            String::new()
+        };
+        if text.value != value {
+            text.value = value;
+        }
+    }
+    for (_label, mut draw, mut text) in &mut danger_labels.iter() {
+        draw.is_visible = shows(HudWidget::Danger) && danger.active;
+        let value = locale.text("danger-bonus");
+        if text.value != value {
+            text.value = value;
+        }
+    }
+    for (_label, mut text) in &mut objective_labels.iter() {
+        let value = if shows(HudWidget::Objective) {
+            objectives.hud_text(&locale).unwrap_or_default()
+        } else {
+            String::new()
+        };
+        if text.value != value {
+            text.value = value;
+        }
+    }
+    for (_label, mut text) in &mut zone_labels.iter() {
+        let value = match zone.seconds_left() {
+            Some(seconds) if shows(HudWidget::Zone) => {
+                locale.format("zone-timer", &[("seconds", seconds.to_string())])
+            }
+            _ => String::new(),
+        };
+        if text.value != value {
+            text.value = value;
+        }
+    }
+    for (_label, mut text) in &mut best_labels.iter() {
+        let value = if shows(HudWidget::Best) && new_best.0 {
+            locale.text("new-best-badge")
+        } else {
+            String::new()
+        };
+        if text.value != value {
+            text.value = value;
+        }
+    }
+    let speed = time_scale.factor_of(TimeSource::Debug);
+    for (_label, mut text) in &mut speed_labels.iter() {
+        let value = if shows(HudWidget::Speed) && (speed - 1.0).abs() > f32::EPSILON {
+            locale.format("speed", &[("speed", speed.to_string())])
+        } else {
+            String::new()
+        };
+        if text.value != value {
+            text.value = value;
+        }
+    }
+}
+
+/// Fades the "new best" badge in and out, or holds it steady with reduced motion.
+fn pulse_best_label(
+    time: Res<Time>,
+    settings: Res<AccessibilitySettings>,
+    mut labels: Query<(&BestLabel, &mut Text)>,
+) {
+    let alpha = if settings.reduced_motion {
+        1.0
+    } else {
+        let phase = time.seconds_since_startup as f32 * BEST_PULSE_RATE * 2.0 * PI;
+        0.65 + 0.35 * phase.sin()
+    };
+    for (_label, mut text) in &mut labels.iter() {
+        text.style.color = Color::rgba(1.0, 0.85, 0.2, alpha);
+    }
+}
+
+/// Labels every widget with its name and number key while the HUD layout is edited, so even
+/// hidden or empty widgets can be found.
+fn hud_edit_labels(
+    locale: Res<Locale>,
+    layout: Res<HudLayout>,
+    mut widgets: Query<(&HudWidget, &mut Draw, &mut Text)>,
+) {
+    if !layout.editing {
+        return;
+    }
+    for (&widget, mut draw, mut text) in &mut widgets.iter() {
+        draw.is_visible = true;
+        let key = HudWidget::ALL.iter().position(|&w| w == widget).unwrap() + 1;
+        let name = locale.text(widget.name_key());
+        let value = if layout.shows(widget) {
+            format!("[{}] {}", key, name)
+        } else {
+            locale.format("hud-hidden", &[("key", key.to_string()), ("widget", name)])
+        };
+        if text.value != value {
+            text.value = value;
+        }
+    }
+}
+
+fn pause_label(
+    state: Res<GameState>,
+    locale: Res<Locale>,
+    mut labels: Query<(&PauseLabel, &mut Draw, &mut Text)>,
+) {
+    for (_label, mut draw, mut text) in &mut labels.iter() {
+        draw.is_visible = *state == GameState::Paused;
+        let value = locale.text("paused");
+        if text.value != value {
+            text.value = value;
+        }
+    }
+}
+
+/// Keeps the window title showing the score and whether the game is paused, so it can be
+/// followed from the taskbar. Menus get the plain title.
+fn window_title(
+    mut shown: Local<String>,
+    state: Res<GameState>,
+    locale: Res<Locale>,
+    score: Res<Score>,
+    zen_mode: Res<ZenMode>,
+    mut windows: ResMut<Windows>,
+) {
+    let title = locale.text("window-title");
+    let score = score.0.to_string();
+    let title = match *state {
+        _ if zen_mode.0 => title,
+        GameState::Playing => locale.format(
+            "window-title-playing",
+            &[("title", title), ("score", score)],
+        ),
+        GameState::Paused => {
+            locale.format("window-title-paused", &[("title", title), ("score", score)])
+        }
+        GameState::Loading
+        | GameState::ProfileSelect
+        | GameState::Tournament
+        | GameState::Replays
+        | GameState::Editor
+        | GameState::Levels
+        | GameState::Campaign => title,
+    };
+    if *shown == title {
+        return;
+    }
+    if let Some(window) = windows.get_primary_mut() {
+        window.set_title(title.clone());
+        *shown = title;
+    }
+}
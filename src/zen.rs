@@ -0,0 +1,48 @@
+use std::f32::consts::PI;
+
+use bevy::{prelude::*, render::pass::ClearColor};
+
+use crate::{accessibility::AccessibilitySettings, config::ZenMode};
+
+/// Seconds for the background to drift through all its colors once.
+const BACKGROUND_CYCLE: f32 = 90.0;
+
+/// How far the background strays from its base color on each channel.
+const BACKGROUND_SWING: f32 = 0.05;
+
+const BACKGROUND_BASE: f32 = 0.1;
+
+pub struct ZenPlugin;
+
+impl Plugin for ZenPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(ambient_background.system());
+    }
+}
+
+/// Drifts the background through soft colors in zen mode, holding still with reduced motion,
+/// and hands the background back to the palette when zen mode ends.
+fn ambient_background(
+    mut was_zen: Local<bool>,
+    time: Res<Time>,
+    zen_mode: Res<ZenMode>,
+    settings: Res<AccessibilitySettings>,
+    mut clear_color: ResMut<ClearColor>,
+) {
+    if !zen_mode.0 {
+        if *was_zen {
+            *was_zen = false;
+            clear_color.0 = settings.palette.colors().background;
+        }
+        return;
+    }
+    *was_zen = true;
+
+    let phase = if settings.reduced_motion {
+        0.0
+    } else {
+        time.seconds_since_startup as f32 / BACKGROUND_CYCLE * 2.0 * PI
+    };
+    let channel = |offset: f32| BACKGROUND_BASE + BACKGROUND_SWING * (phase + offset).sin();
+    clear_color.0 = Color::rgb(channel(0.0), channel(2.1), channel(4.2));
+}
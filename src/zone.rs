@@ -0,0 +1,176 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::{
+    accessibility::{AccessibilitySettings, Palette},
+    rules::GameRules,
+    scoring::{ScoreEvent, ScoreReason},
+    sized_sprite,
+    split::Ally,
+    time_scale::TimeScale,
+    Dead, GameState, Position, Run, Size, SnakeHead, ARENA_HEIGHT, ARENA_WIDTH,
+};
+
+/// How long the zone stays put, in seconds.
+const ZONE_DURATION: f32 = 20.0;
+
+/// Cells from the center of the zone to its edge. The zone is a square of `2 * r + 1` cells.
+const ZONE_RADIUS: i32 = 2;
+
+/// Points for every move a head ends inside the zone.
+const ZONE_POINTS: u32 = 2;
+
+/// Above the floor tiles but under the night overlay.
+const ZONE_DEPTH: f32 = -0.04;
+
+/// The king-of-the-hill zone. Heads inside it earn points every move.
+pub struct Zone {
+    center: Option<Position>,
+    timer: Timer,
+    seed: u64,
+}
+
+impl Default for Zone {
+    fn default() -> Self {
+        Self {
+            center: None,
+            timer: Timer::from_seconds(ZONE_DURATION, true),
+            seed: 0,
+        }
+    }
+}
+
+impl Zone {
+    fn contains(&self, position: &Position) -> bool {
+        self.center.map_or(false, |center| {
+            (position.x - center.x).abs() <= ZONE_RADIUS
+                && (position.y - center.y).abs() <= ZONE_RADIUS
+        })
+    }
+
+    /// Whole seconds until the zone moves, if there is one.
+    pub fn seconds_left(&self) -> Option<u32> {
+        self.center
+            .map(|_| (ZONE_DURATION - self.timer.elapsed).ceil() as u32)
+    }
+
+    fn relocate(&mut self, run: &mut Run) {
+        self.center = Some(Position {
+            x: run
+                .rng
+                .gen_range(ZONE_RADIUS, ARENA_WIDTH as i32 - ZONE_RADIUS),
+            y: run
+                .rng
+                .gen_range(ZONE_RADIUS, ARENA_HEIGHT as i32 - ZONE_RADIUS),
+        });
+    }
+}
+
+struct ZoneMarker;
+
+struct ZoneMaterial(Handle<ColorMaterial>);
+
+pub struct ZonePlugin;
+
+impl Plugin for ZonePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Zone>()
+            .add_startup_system(setup_zone.system())
+            .add_system(move_zone.system())
+            .add_system(zone_points.system())
+            .add_system(apply_zone_palette.system());
+    }
+}
+
+fn setup_zone(
+    mut commands: Commands,
+    settings: Res<AccessibilitySettings>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let material = materials.add(settings.palette.colors().zone.into());
+    commands
+        .spawn(SpriteComponents {
+            transform: Transform::from_translation(Vec3::new(0.0, 0.0, ZONE_DEPTH)),
+            draw: Draw {
+                is_visible: false,
+                is_transparent: true,
+                ..Default::default()
+            },
+            ..sized_sprite(material)
+        })
+        .with(ZoneMarker)
+        .with(Position { x: 0, y: 0 })
+        .with(Size::square((2 * ZONE_RADIUS + 1) as f32));
+    commands.insert_resource(ZoneMaterial(material));
+}
+
+/// Places the zone at the start of each run with the `king_of_the_hill` rule and moves it
+/// every `ZONE_DURATION` seconds of play.
+fn move_zone(
+    time: Res<Time>,
+    time_scale: Res<TimeScale>,
+    state: Res<GameState>,
+    rules: Res<GameRules>,
+    mut run: ResMut<Run>,
+    mut zone: ResMut<Zone>,
+    mut markers: Query<(&ZoneMarker, &mut Position, &mut Draw)>,
+) {
+    if zone.seed != run.seed || rules.king_of_the_hill != zone.center.is_some() {
+        zone.seed = run.seed;
+        zone.timer.reset();
+        zone.center = None;
+        if rules.king_of_the_hill {
+            zone.relocate(&mut run);
+        }
+    }
+    if zone.center.is_some() && *state == GameState::Playing {
+        zone.timer.tick(time_scale.delta(&time));
+        if zone.timer.finished {
+            zone.relocate(&mut run);
+        }
+    }
+
+    for (_marker, mut position, mut draw) in &mut markers.iter() {
+        draw.is_visible = zone.center.is_some();
+        if let Some(center) = zone.center {
+            if *position != center {
+                *position = center;
+            }
+        }
+    }
+}
+
+fn zone_points(
+    mut last_tick: Local<u64>,
+    run: Res<Run>,
+    zone: Res<Zone>,
+    mut score_events: ResMut<Events<ScoreEvent>>,
+    mut heads: Query<Without<Ally, Without<Dead, (&SnakeHead, &Position)>>>,
+) {
+    if run.tick == *last_tick {
+        return;
+    }
+    *last_tick = run.tick;
+
+    for (_head, position) in &mut heads.iter() {
+        if zone.contains(position) {
+            score_events.send(ScoreEvent::new(ZONE_POINTS, ScoreReason::Zone));
+        }
+    }
+}
+
+fn apply_zone_palette(
+    mut applied: Local<Option<Palette>>,
+    settings: Res<AccessibilitySettings>,
+    zone_material: Res<ZoneMaterial>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if *applied == Some(settings.palette) {
+        return;
+    }
+    *applied = Some(settings.palette);
+
+    if let Some(material) = materials.get_mut(&zone_material.0) {
+        material.color = settings.palette.colors().zone;
+    }
+}